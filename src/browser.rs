@@ -1,33 +1,52 @@
+mod closed;
+pub mod config;
+mod export;
 pub mod fonts;
+mod history;
 mod network;
 mod parsers;
+mod selection;
+pub mod settings;
+#[cfg(feature = "tts")]
+mod speech;
 mod tab;
+pub mod theme;
 pub mod widgets;
 
+mod browser_test;
+
 use std::path::PathBuf;
 
-use eframe::{egui::{self, global_theme_preference_buttons, gui_zoom::zoom_menu_buttons, Button, CentralPanel, Checkbox, Frame, Key, KeyboardShortcut, Label, MenuBar, Modifiers, TopBottomPanel, ViewportBuilder}, App, NativeOptions};
+use eframe::{egui::{self, gui_zoom::zoom_menu_buttons, Button, CentralPanel, Checkbox, Frame, Key, KeyboardShortcut, Label, MenuBar, Modifiers, ScrollArea, TopBottomPanel, ViewportBuilder, Window}, App, NativeOptions};
 use egui_extras::install_image_loaders;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use crate::{browser::{fonts::load_fonts, tab::Tab}, gemtext_widget::{self}};
+use crate::{browser::{closed::{RecentlyClosed, ABOUT_URL as RECENTLY_CLOSED_URL}, config::ConfigFile, history::{History, ABOUT_URL as HISTORY_URL}, network::{MultiLoader, SCow}, settings::{Palette, Settings}, tab::Tab}};
+
+/// Used by eframe to name our window and data directory.
+pub const APP_ID: &str = "egemi";
 
-pub fn main(url: String) -> eframe::Result {
+/// Reopens the tabs from last time, and `url` in a new tab. The user's home page if there's neither.
+pub fn main(url: Option<String>, config: Option<ConfigFile>) -> eframe::Result {
     env_logger::init();
 
     let opts = NativeOptions {
         persist_window: true,
         ..Default::default()
     };
-    let url = try_file_url(url);
+    let url = url.map(try_file_url);
 
     eframe::run_native(
-        "egemi",
+        APP_ID,
         opts,
         Box::new(move |c| {
-            let mut app = Browser::new(c);
-            app.goto_url(url);
+            let mut app = Browser::new(c, config);
+            match url {
+                Some(url) => app.new_tab().goto_url(url.into()),
+                None if app.tabs.is_empty() => app.open_home_page(),
+                None => {},
+            }
             let app = Box::new(app);
             Ok(app)
         }),
@@ -55,14 +74,56 @@ fn try_file_url(url: String) -> String {
 }
 
 /// The main browser window.
-/// May eventually support multiple tabs. For now, there's only ever one, which fills the whole screen.
+/// Shows one [`Tab`] at a time, with a strip to switch between them.
 #[derive(Serialize, Deserialize, Debug, Default)]
 struct Browser {
-    tab: Tab,
+    tabs: Vec<Tab>,
+
+    /// Index into [`Self::tabs`] of the tab we're showing.
+    active: usize,
 
     // Allows us to toggle menu on/off
     show_menu: bool,
-    
+
+    /// Saved separately. See [`Settings::load`].
+    #[serde(skip)]
+    settings: Settings,
+
+    #[serde(skip)]
+    show_settings: bool,
+
+    /// If set, we also save [`Self::settings`] here, and it takes precedence over what eframe saved.
+    #[serde(skip)]
+    config: Option<ConfigFile>,
+
+    /// Shared by all tabs, so that they share a cache and logins.
+    #[serde(skip)]
+    loader: MultiLoader,
+
+    /// Saved separately. See [`RecentlyClosed::load`].
+    #[serde(skip)]
+    recently_closed: RecentlyClosed,
+
+    /// Saved separately. See [`History::load`].
+    #[serde(skip)]
+    history: History,
+
+    /// Shown in the status bar. Set by the previous frame, since the bar is laid out before the tab.
+    #[serde(skip)]
+    hovered_link: Option<String>,
+
+    /// What we last set the window's title to.
+    #[serde(skip)]
+    window_title: String,
+
+    /// The palette, font size, and [`Settings::reduce_motion`] we last applied. See [`theme::apply`].
+    #[serde(skip)]
+    applied_style: Option<(Palette, f32, bool)>,
+
+    /// The proportional and monospace fonts we last applied. See [`Settings::proportional_font`].
+    #[serde(skip)]
+    applied_fonts: Option<(String, String)>,
+
     #[serde(skip)]
     debug_menu: bool,
     #[serde(skip)]
@@ -72,18 +133,245 @@ struct Browser {
 }
 
 impl Browser {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    fn new(cc: &eframe::CreationContext<'_>, mut config: Option<ConfigFile>) -> Self {
         install_image_loaders(&cc.egui_ctx);
-        load_fonts(cc);
 
-        // TODO: Better themes:
-        gemtext_widget::Style::config(&cc.egui_ctx);
+        let mut browser = Self {
+            settings: config.as_mut()
+                .and_then(ConfigFile::take_settings)
+                .unwrap_or_else(|| Settings::load(cc.storage)),
+            config,
+            recently_closed: RecentlyClosed::load(cc.storage),
+            history: History::load(cc.storage),
+            ..Self::default()
+        };
+        browser.loader.configure(&browser.settings);
+        // Before the first frame, so that it isn't drawn with egui's default fonts:
+        browser.apply_fonts(&cc.egui_ctx);
+        if let Some(saved) = cc.storage.and_then(|it| eframe::get_value::<Browser>(it, eframe::APP_KEY)) {
+            browser.restore_tabs(saved.tabs, saved.active);
+        }
+        browser
+    }
+
+    /// Use the user's choice of fonts, if they've changed.
+    fn apply_fonts(&mut self, ctx: &egui::Context) {
+        let fonts = (self.settings.proportional_font.clone(), self.settings.monospace_font.clone());
+        if self.applied_fonts.as_ref() == Some(&fonts) {
+            return;
+        }
+        ctx.set_fonts(fonts::definitions(&fonts.0, &fonts.1));
+        self.applied_fonts = Some(fonts);
+    }
 
-        Self::default()
+    fn tab(&mut self) -> &mut Tab {
+        if self.tabs.is_empty() {
+            self.new_tab();
+        }
+        self.active = self.active.min(self.tabs.len() - 1);
+        &mut self.tabs[self.active]
+    }
+
+    /// Add an empty tab after the active one, and returns its index. Doesn't switch to it.
+    fn add_tab(&mut self) -> usize {
+        let mut tab = Tab::default();
+        tab.set_settings(&self.settings);
+        tab.set_loader(self.loader.clone());
+        tab.set_dynamic_page(RECENTLY_CLOSED_URL, self.recently_closed.to_gemtext());
+        tab.set_dynamic_page(HISTORY_URL, self.history.to_gemtext());
+        let index = if self.tabs.is_empty() { 0 } else { self.active + 1 };
+        self.tabs.insert(index, tab);
+        index
+    }
+
+    /// Open the home page in a new tab.
+    fn open_home_page(&mut self) {
+        let url = self.settings.home_page().to_string();
+        self.new_tab().goto_url(url.into());
+    }
+
+    /// Names the window after the active tab's page.
+    fn update_window_title(&mut self, ctx: &egui::Context) {
+        let title = match self.tab().page_title() {
+            Some(page) => format!("{page} — egemi"),
+            None => "egemi".to_string(),
+        };
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+
+    /// Ask where to save the active tab's document as HTML, and save it there.
+    fn export_html(&mut self) {
+        let Some((name, html)) = self.tab().export_html() else {
+            return;
+        };
+        let dialog = rfd::FileDialog::new().add_filter("HTML", &["html", "htm"]).set_file_name(name);
+        if let Some(path) = dialog.save_file() {
+            export::write(path, html);
+        }
+    }
+
+    /// Open a new tab, and switch to it.
+    fn new_tab(&mut self) -> &mut Tab {
+        self.active = self.add_tab();
+        &mut self.tabs[self.active]
+    }
+
+    /// Open `url` in a new tab, without switching to it.
+    fn open_in_new_tab(&mut self, url: SCow) {
+        let index = self.add_tab();
+        self.tabs[index].goto_url(url);
+    }
+
+    /// Reopen the tabs from a previous session, pinned ones first. Otherwise, they keep their order.
+    /// `active` is the index of the tab that was active then.
+    fn restore_tabs(&mut self, saved: Vec<Tab>, active: usize) {
+        let mut saved: Vec<(usize, Tab)> = saved.into_iter().enumerate().collect();
+        saved.sort_by_key(|(_, tab)| !tab.is_pinned());
+        for (_, saved) in &saved {
+            let tab = self.new_tab();
+            tab.restore(saved.closed());
+            tab.set_pinned(saved.is_pinned());
+        }
+        if let Some(index) = saved.iter().position(|(index, _)| *index == active) {
+            self.active = index;
+        }
+    }
+
+    /// Reload every tab, except pinned ones.
+    fn reload_unpinned(&mut self) {
+        for tab in self.tabs.iter_mut().filter(|it| !it.is_pinned()) {
+            tab.reload();
+        }
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        let tab = self.tabs.remove(index);
+        self.recently_closed.push(tab.closed());
+        self.recently_closed_changed();
+        if index < self.active || self.active >= self.tabs.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        if self.tabs.is_empty() {
+            self.open_home_page();
+        }
+    }
+
+    /// Reopen a recently closed tab, in a new tab. (0 is the most recently closed.)
+    fn reopen_tab(&mut self, index: usize) {
+        let Some(closed) = self.recently_closed.take(index) else {
+            return;
+        };
+        self.recently_closed_changed();
+        self.new_tab().restore(closed);
+    }
+
+    fn recently_closed_changed(&mut self) {
+        let text = self.recently_closed.to_gemtext();
+        for tab in &mut self.tabs {
+            tab.set_dynamic_page(RECENTLY_CLOSED_URL, text.clone());
+        }
+    }
+
+    fn history_changed(&mut self) {
+        let text = self.history.to_gemtext();
+        for tab in &mut self.tabs {
+            tab.set_dynamic_page(HISTORY_URL, text.clone());
+        }
+    }
+
+    fn settings_changed(&mut self) {
+        self.loader.configure(&self.settings);
+        for tab in &mut self.tabs {
+            tab.set_settings(&self.settings);
+            tab.set_loader(self.loader.clone());
+        }
+        self.history.trim(self.settings.max_history);
+        self.history_changed();
+
+        if let Some(config) = &self.config
+            && let Err(err) = config.save(&self.settings)
+        {
+            log::warn!("{err}");
+        }
+    }
+
+    fn recently_closed_menu(&mut self, ui: &mut egui::Ui) {
+        let mut reopen = None;
+        for (index, closed) in self.recently_closed.iter().enumerate() {
+            let url = closed.url().unwrap_or_default();
+            if ui.button(url).clicked() {
+                reopen = Some(index);
+            }
+        }
+        if self.recently_closed.is_empty() {
+            ui.label("Nothing closed yet.");
+        }
+        ui.separator();
+        if ui.button("Show all").clicked() {
+            self.goto_url(RECENTLY_CLOSED_URL.into());
+        }
+        if let Some(index) = reopen {
+            self.reopen_tab(index);
+        }
     }
     
     fn goto_url(&mut self, url: String) {
-        self.tab.goto_url(url.into());
+        self.tab().goto_url(url.into());
+    }
+
+    fn tab_strip(&mut self, ui: &mut egui::Ui) {
+        let mut close = None;
+        let mut reload_all = false;
+        ui.horizontal_wrapped(|ui| {
+            for (index, tab) in self.tabs.iter_mut().enumerate() {
+                let title = if tab.is_pinned() { format!("📌 {}", tab.title()) } else { tab.title() };
+                let label = ui.selectable_label(index == self.active, title);
+                if label.clicked() {
+                    self.active = index;
+                }
+                label.context_menu(|ui| {
+                    let pin = if tab.is_pinned() { "Unpin tab" } else { "Pin tab" };
+                    if ui.button(pin).on_hover_text("Pinned tabs aren't reloaded with the others").clicked() {
+                        tab.set_pinned(!tab.is_pinned());
+                        ui.close();
+                    }
+                    if ui.button("Reload all tabs").on_hover_text("Except pinned tabs").clicked() {
+                        reload_all = true;
+                        ui.close();
+                    }
+                    if ui.button("Close tab").clicked() {
+                        close = Some(index);
+                        ui.close();
+                    }
+                });
+                if ui.small_button("✖").on_hover_text("Close tab").clicked() {
+                    close = Some(index);
+                }
+                ui.separator();
+            }
+            let new_tab = Button::new("➕").small();
+            if ui.add(new_tab).on_hover_text(format!("New tab ({})", ui.ctx().format_shortcut(&NEW_TAB))).clicked() {
+                self.open_home_page();
+            }
+        });
+        if reload_all {
+            self.reload_unpinned();
+        }
+        if let Some(index) = close {
+            self.close_tab(index);
+        }
+    }
+
+    /// Always laid out, even when no link is hovered, so that the page doesn't jump as the mouse moves.
+    fn status_bar(&self, ui: &mut egui::Ui) {
+        let text = self.hovered_link.as_deref().unwrap_or(" ");
+        ui.add(Label::new(egui::RichText::new(text).monospace().weak()).truncate());
     }
 
     fn menu_bar(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
@@ -93,8 +381,35 @@ impl Browser {
                     self.goto_url("about:egemi".into());
                 }
 
-                // TODO: A better place to put this?
-                global_theme_preference_buttons(ui);
+                let theme = self.settings.theme;
+                self.settings.theme.radio_buttons(ui);
+                if self.settings.theme != theme {
+                    self.settings_changed();
+                }
+
+                if ui.button("Settings…").clicked() {
+                    self.show_settings = true;
+                }
+
+                let new_tab = Button::new("New Tab").shortcut_text(ctx.format_shortcut(&NEW_TAB));
+                if ui.add(new_tab).clicked() {
+                    self.open_home_page();
+                }
+                let close = Button::new("Close Tab").shortcut_text(ctx.format_shortcut(&CLOSE_TAB));
+                if ui.add(close).clicked() {
+                    self.close_tab(self.active);
+                }
+                ui.menu_button("Recently Closed", |ui| self.recently_closed_menu(ui));
+                if ui.button("Export as HTML…").clicked() {
+                    self.export_html();
+                }
+                if ui.button("History").clicked() {
+                    self.goto_url(HISTORY_URL.into());
+                }
+                if ui.add_enabled(!self.history.is_empty(), Button::new("Clear History")).clicked() {
+                    self.history.clear();
+                    self.history_changed();
+                }
 
                 ui.checkbox(&mut self.debug_menu, "Debug");
 
@@ -111,6 +426,23 @@ impl Browser {
             //     ctx.settings_ui(ui);
             // });
 
+            ui.menu_button("View", |ui| {
+                if ui.checkbox(&mut self.settings.inline_images, "Inline Images").changed() {
+                    self.settings_changed();
+                }
+                if self.settings.content_width_ui(ui) {
+                    self.settings_changed();
+                }
+                if ui.checkbox(&mut self.settings.reduce_motion, "Reduce Motion").changed() {
+                    self.settings_changed();
+                }
+                ui.menu_button("Colors", |ui| {
+                    if self.settings.palette.radio_buttons(ui) {
+                        self.settings_changed();
+                    }
+                });
+            });
+
             ui.menu_button("Zoom", |ui| {
                 zoom_menu_buttons(ui);
             });
@@ -134,15 +466,64 @@ impl Browser {
 
         }    
     }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        Window::new("Settings")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(config) = &self.config {
+                    ui.weak(format!("Also saved to {}", config.path().display()));
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    if self.settings.ui(ui) {
+                        self.settings_changed();
+                    }
+                });
+            });
+        self.show_settings = open;
+    }
 }
 
+const NEW_TAB: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::T);
+const CLOSE_TAB: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::W);
+const REOPEN_TAB: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::T);
+
 impl App for Browser {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Check the more specific shortcut first, since Cmd+T would also match Cmd+Shift+T:
+        if ctx.input_mut(|i| i.consume_shortcut(&REOPEN_TAB)) {
+            self.reopen_tab(0);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&NEW_TAB)) {
+            self.open_home_page();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&CLOSE_TAB)) {
+            self.close_tab(self.active);
+        }
+
+        if ctx.options(|it| it.theme_preference) != self.settings.theme {
+            ctx.set_theme(self.settings.theme);
+        }
+        let style = (self.settings.palette, self.settings.font_size, self.settings.reduce_motion);
+        if self.applied_style != Some(style) {
+            theme::apply(ctx, style.0, style.1, style.2);
+            self.applied_style = Some(style);
+        }
+        self.apply_fonts(ctx);
+
         TopBottomPanel::top("top panel")
             .show_animated(ctx, self.show_menu, |ui| {
                 self.menu_bar(ctx, ui)
             });
 
+        TopBottomPanel::top("tab strip").show(ctx, |ui| self.tab_strip(ui));
+
+        self.settings_window(ctx);
+
+        TopBottomPanel::bottom("status bar")
+            .show_animated(ctx, self.settings.status_bar, |ui| self.status_bar(ui));
+
         let frame = Frame::new()
             .outer_margin(0.0)
             .inner_margin(0.0)
@@ -151,15 +532,40 @@ impl App for Browser {
         CentralPanel::default()
             .frame(frame)
             .show(ctx, |ui| {
-                let tab = self.tab.show(ui);
+                let tab = self.tab().show(ui);
                 if tab.toggle_menu {
                     self.show_menu = !self.show_menu;
                 }
+                if tab.open_settings {
+                    self.show_settings = true;
+                }
+                if let Some(settings) = tab.settings {
+                    self.settings = settings;
+                    self.settings_changed();
+                }
+                if let Some(url) = tab.open_in_new_tab {
+                    self.open_in_new_tab(url);
+                }
+                let mut visited = false;
+                for visit in tab.visits {
+                    visited |= self.history.record(visit, self.settings.max_history);
+                }
+                if visited {
+                    self.history_changed();
+                }
+                if tab.hovered_link != self.hovered_link {
+                    self.hovered_link = tab.hovered_link;
+                    ctx.request_repaint();
+                }
 
             });
+        self.update_window_title(ctx);
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
+        self.settings.save(storage);
+        self.recently_closed.save(storage);
+        self.history.save(storage);
     }
 }
\ No newline at end of file
@@ -0,0 +1,40 @@
+#![cfg(test)]
+
+use crate::browser::Browser;
+use pretty_assertions::assert_eq;
+
+/// The URL each tab is showing.
+fn urls(browser: &Browser) -> Vec<String> {
+    browser.tabs.iter().map(|it| it.closed().url().unwrap_or_default().to_string()).collect()
+}
+
+#[test]
+fn pinned_tabs() {
+    let mut browser = Browser::default();
+    browser.new_tab().goto_url("about:egemi".into());
+    browser.new_tab().goto_url("about:changelog".into());
+    browser.new_tab().goto_url("about:settings".into());
+    browser.new_tab().goto_url("about:history".into());
+    browser.tabs[2].set_pinned(true);
+
+    // Pinned tabs close like any other:
+    browser.close_tab(3);
+    assert_eq!(urls(&browser), ["about:egemi", "about:changelog", "about:settings"]);
+
+    // All tabs are reopened, pinned ones first:
+    let mut restored = Browser::default();
+    restored.restore_tabs(std::mem::take(&mut browser.tabs), 1);
+    assert_eq!(urls(&restored), ["about:settings", "about:egemi", "about:changelog"]);
+    assert_eq!(restored.tabs.iter().map(|it| it.is_pinned()).collect::<Vec<_>>(), [true, false, false]);
+    // The same tab is still active:
+    assert_eq!(restored.active, 2);
+}
+
+#[test]
+fn closing_the_last_tab_opens_the_home_page() {
+    let mut browser = Browser::default();
+    browser.settings.home_page = "about:changelog".into();
+    browser.new_tab().goto_url("about:settings".into());
+    browser.close_tab(0);
+    assert_eq!(urls(&browser), ["about:changelog"]);
+}
@@ -0,0 +1,86 @@
+//! Remembers recently closed tabs, so that they can be reopened.
+//! Persisted separately from the rest of the browser state, like [`Settings`](super::settings::Settings).
+
+use std::{collections::VecDeque, fmt::Write as _};
+
+use serde::{Deserialize, Serialize};
+
+use crate::browser::network::SCow;
+
+mod closed_test;
+
+/// The storage key we persist [`RecentlyClosed`] under.
+pub const STORAGE_KEY: &str = "egemi-recently-closed";
+
+/// The builtin page that lists recently closed tabs.
+pub const ABOUT_URL: &str = "about:recently-closed";
+
+/// How many closed tabs we remember.
+const MAX_CLOSED: usize = 25;
+
+/// A tab that was closed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClosedTab {
+    /// The tab's history. The last entry is the page that was open.
+    pub history: Vec<SCow>,
+}
+
+impl ClosedTab {
+    pub fn url(&self) -> Option<&str> {
+        self.history.last().map(AsRef::as_ref)
+    }
+}
+
+/// Recently closed tabs, newest first.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RecentlyClosed {
+    tabs: VecDeque<ClosedTab>,
+}
+
+impl RecentlyClosed {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+
+    /// Remember a closed tab. Tabs that never loaded anything aren't worth remembering.
+    pub fn push(&mut self, tab: ClosedTab) {
+        if tab.history.is_empty() {
+            return;
+        }
+        self.tabs.push_front(tab);
+        self.tabs.truncate(MAX_CLOSED);
+    }
+
+    /// Remove & return the closed tab at `index`. (0 is the most recently closed.)
+    pub fn take(&mut self, index: usize) -> Option<ClosedTab> {
+        self.tabs.remove(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ClosedTab> {
+        self.tabs.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    /// The contents of [`ABOUT_URL`].
+    pub fn to_gemtext(&self) -> String {
+        let mut out = String::from("# Recently Closed\n\n");
+        if self.tabs.is_empty() {
+            out.push_str("You haven't closed any tabs yet.\n");
+            return out;
+        }
+        out.push_str("Use File → Recently Closed (or Ctrl+Shift+T) to also restore a tab's history.\n\n");
+        for url in self.tabs.iter().filter_map(ClosedTab::url) {
+            writeln!(out, "=> {}", url.replace(' ', "%20")).expect("writing to string");
+        }
+        out
+    }
+}
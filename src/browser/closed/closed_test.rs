@@ -0,0 +1,27 @@
+#![cfg(test)]
+
+use crate::browser::closed::{ClosedTab, RecentlyClosed};
+use pretty_assertions::assert_eq;
+
+fn closed(url: &str) -> ClosedTab {
+    ClosedTab { history: vec!["about:egemi".into(), url.to_string().into()] }
+}
+
+#[test]
+fn newest_first_and_bounded() {
+    let mut recent = RecentlyClosed::default();
+    recent.push(ClosedTab { history: vec![] });
+    assert!(recent.is_empty());
+
+    for i in 0..30 {
+        recent.push(closed(&format!("gemini://example.com/{i}")));
+    }
+    let urls: Vec<_> = recent.iter().filter_map(ClosedTab::url).collect();
+    assert_eq!(urls.len(), 25);
+    assert_eq!(urls[0], "gemini://example.com/29");
+    assert_eq!(urls[24], "gemini://example.com/5");
+
+    let reopened = recent.take(0).unwrap();
+    assert_eq!(reopened.url(), Some("gemini://example.com/29"));
+    assert_eq!(recent.iter().next().and_then(ClosedTab::url), Some("gemini://example.com/28"));
+}
@@ -0,0 +1,93 @@
+//! An optional TOML config file, for users who'd rather manage their [`Settings`] in one file
+//! than through eframe's persisted state.
+
+use std::{io::ErrorKind, path::{Path, PathBuf}};
+
+use crate::browser::{settings::Settings, APP_ID};
+
+mod config_test;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Couldn't access config file {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+
+    #[error("Invalid config file {path}: {source}")]
+    Parse { path: PathBuf, source: toml::de::Error },
+
+    #[error("Couldn't write config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Where we read [`Settings`] from at startup, and write them back to when they change.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    path: PathBuf,
+
+    /// What we read from [`Self::path`]. None if the file doesn't exist (yet).
+    settings: Option<Settings>,
+}
+
+impl ConfigFile {
+    /// Reads the config at `path`. It's OK if it doesn't exist yet. We'll create it when settings change.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(Self { path, settings: None });
+            },
+            Err(source) => return Err(Error::Io { path, source }),
+        };
+        match parse(&text) {
+            Ok(settings) => Ok(Self { path, settings: Some(settings) }),
+            Err(source) => Err(Error::Parse { path, source }),
+        }
+    }
+
+    /// `egemi.toml` in the platform's config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|it| it.join(format!("{APP_ID}.toml")))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The settings from the file, if it existed.
+    pub fn take_settings(&mut self) -> Option<Settings> {
+        self.settings.take()
+    }
+
+    pub fn save(&self, settings: &Settings) -> Result<()> {
+        let text = toml::to_string_pretty(settings)?;
+        let io_err = |source| Error::Io { path: self.path.clone(), source };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        std::fs::write(&self.path, text).map_err(io_err)
+    }
+}
+
+/// Settings missing from `text` keep their defaults. Numbers are [clamped](Settings::clamp).
+pub fn parse(text: &str) -> Result<Settings, toml::de::Error> {
+    let mut settings: Settings = toml::from_str(text)?;
+    settings.clamp();
+    Ok(settings)
+}
+
+fn config_dir() -> Option<PathBuf> {
+    use eframe::egui::os::OperatingSystem as OS;
+    use std::env::var_os;
+    match OS::from_target_os() {
+        OS::Nix => var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|it| it.is_absolute())
+            .or_else(|| home::home_dir().map(|it| it.join(".config")))
+            .map(|it| it.join(APP_ID)),
+        OS::Mac => home::home_dir().map(|it| it.join("Library").join("Application Support").join(APP_ID)),
+        OS::Windows => var_os("APPDATA").map(PathBuf::from).map(|it| it.join(APP_ID)),
+        OS::Unknown | OS::Android | OS::IOS => None,
+    }
+}
@@ -0,0 +1,43 @@
+#![cfg(test)]
+
+use crate::browser::{config::{parse, ConfigFile}, settings::Settings};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn missing_settings_are_defaults() {
+    let settings = parse("inline_images = true\nblocklist = [\"example.com\"]\n").unwrap();
+    assert_eq!(settings, Settings {
+        inline_images: true,
+        blocklist: vec!["example.com".into()],
+        ..Settings::default()
+    });
+    assert_eq!(parse("").unwrap(), Settings::default());
+    assert!(parse("inline_images = \"yes\"").is_err());
+}
+
+#[test]
+fn numbers_are_clamped() {
+    let settings = parse("request_timeout_secs = inf\nretry_backoff_secs = nan\nbackground_after_secs = 1e20\nlink_preview_secs = -5.0\n").unwrap();
+    assert_eq!(settings.request_timeout_secs, 600.0);
+    assert_eq!(settings.retry_backoff_secs, Settings::default().retry_backoff_secs);
+    assert_eq!(settings.background_after_secs, 120.0);
+    assert_eq!(settings.link_preview_secs, 0.0);
+
+    let settings = parse("cache_megabytes = 9223372036854775807\ncache_entries = 1000000\n").unwrap();
+    assert_eq!(settings.cache_megabytes, 4096);
+    assert_eq!(settings.cache_entries, 10_000);
+}
+
+#[test]
+fn round_trip() {
+    let path = std::env::temp_dir().join(format!("egemi-config-test-{}", std::process::id())).join("egemi.toml");
+    let mut config = ConfigFile::load(path.clone()).unwrap();
+    assert_eq!(config.take_settings(), None);
+
+    let settings = Settings { max_history: 7, ..Settings::default() };
+    config.save(&settings).unwrap();
+    let mut config = ConfigFile::load(path.clone()).unwrap();
+    assert_eq!(config.take_settings(), Some(settings));
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,54 @@
+//! Saves the current document as a standalone HTML file, so that its cleaned-up content can be shared.
+
+use std::path::PathBuf;
+
+use log::{debug, warn};
+
+use crate::{browser::network::rt, util::escape_html};
+
+mod export_test;
+
+/// Wraps an HTML fragment from [`DocWidget::to_html`](crate::browser::widgets::DocWidget::to_html) in a
+/// complete page, with a little styling for readability.
+pub fn standalone(title: &str, body: &str) -> String {
+    let title = escape_html(title);
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="generator" content="egemi">
+<title>{title}</title>
+<style>
+body {{ max-width: 45em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; }}
+pre {{ overflow-x: auto; }}
+blockquote {{ border-left: 3px solid #888; margin-left: 0; padding-left: 1em; }}
+</style>
+</head>
+<body>
+{body}</body>
+</html>
+"#)
+}
+
+/// A file name to suggest for a page titled `title`.
+pub fn file_name(title: &str) -> String {
+    let name: String = title.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+    let name = if name.is_empty() { "page".to_string() } else { name.chars().take(60).collect() };
+    format!("{name}.html")
+}
+
+/// Writes `html` in the background.
+pub fn write(path: PathBuf, html: String) {
+    rt().spawn(async move {
+        match tokio::fs::write(&path, html).await {
+            Ok(()) => debug!("Exported {}", path.display()),
+            Err(err) => warn!("Couldn't export {}: {err}", path.display()),
+        }
+    });
+}
@@ -0,0 +1,19 @@
+#![cfg(test)]
+
+use crate::browser::export::{file_name, standalone};
+
+#[test]
+fn standalone_pages() {
+    let html = standalone("Tom & Jerry", "<p>Hi</p>\n");
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Tom &amp; Jerry</title>"));
+    assert!(html.contains("<body>\n<p>Hi</p>\n</body>"));
+}
+
+#[test]
+fn file_names() {
+    assert_eq!(file_name("My Capsule: Home"), "My-Capsule-Home.html");
+    assert_eq!(file_name("example.com/path/"), "example-com-path.html");
+    assert_eq!(file_name("日本語"), "日本語.html");
+    assert_eq!(file_name(" / "), "page.html");
+}
@@ -0,0 +1,21 @@
+#![cfg(test)]
+
+use eframe::egui::FontFamily;
+
+use crate::browser::fonts::{definitions, priority, strict_mono, NOTO_SANS, NOTO_SANS_MONO};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn chosen_font_comes_first() {
+    assert_eq!(priority("Noto Sans JP", NOTO_SANS_MONO), ["Noto Sans JP", "Noto Sans", "Noto Emoji", "Noto Sans KR", "Noto Sans SC", "Noto Sans TC"]);
+
+    // Unknown fonts (ex: from an older config) are skipped:
+    assert_eq!(priority("Comic Sans", NOTO_SANS_MONO), ["Noto Sans", "Noto Emoji", "Noto Sans JP", "Noto Sans KR", "Noto Sans SC", "Noto Sans TC"]);
+
+    let fonts = definitions(NOTO_SANS, "Noto Sans KR");
+    assert_eq!(fonts.families[&FontFamily::Monospace][..2], ["Noto Sans KR", "Noto Sans Mono"]);
+    assert_eq!(fonts.families[&strict_mono()], ["Noto Sans Mono"]);
+    for family in fonts.families.values() {
+        assert!(family.iter().all(|it| fonts.font_data.contains_key(it)));
+    }
+}
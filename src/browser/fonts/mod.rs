@@ -1,98 +1,69 @@
-use eframe::{egui::{self, FontData, FontDefinitions, FontFamily}, epaint::text::{FontInsert, InsertFontFamily}};
+use std::sync::Arc;
 
-pub fn load_fonts(cc: &eframe::CreationContext) {
-    cc.egui_ctx.set_fonts(FontDefinitions::empty());
-    noto_sans(cc);
-    noto_sans_mono(cc);
-    noto_emoji(cc);
+use eframe::egui::{self, FontData, FontDefinitions, FontFamily};
 
-    add_prop(cc, "NotoSansJP", include_bytes!("NotoSansJP-VariableFont_wght.ttf"));
-    add_prop(cc, "NotoSansKR", include_bytes!("NotoSansKR-VariableFont_wght.ttf"));
-    add_prop(cc, "NotoSansSC", include_bytes!("NotoSansSC-VariableFont_wght.ttf"));
-    add_prop(cc, "NotoSansTC", include_bytes!("NotoSansTC-VariableFont_wght.ttf"));
-}
+mod fonts_test;
+
+pub const NOTO_SANS: &str = "Noto Sans";
+pub const NOTO_SANS_MONO: &str = "Noto Sans Mono";
+const NOTO_EMOJI: &str = "Noto Emoji";
+
+/// The fonts bundled with egemi, by name.
+pub const FONTS: &[(&str, &[u8])] = &[
+    (NOTO_SANS, include_bytes!("NotoSans-Variable.ttf")),
+    (NOTO_SANS_MONO, include_bytes!("NotoSansMono-Variable.ttf")),
+    // Sadly, egui doesn't support color fonts yet:
+    (NOTO_EMOJI, include_bytes!("noto-emoji/NotoEmoji-Variable.ttf")),
+    ("Noto Sans JP", include_bytes!("NotoSansJP-VariableFont_wght.ttf")),
+    ("Noto Sans KR", include_bytes!("NotoSansKR-VariableFont_wght.ttf")),
+    ("Noto Sans SC", include_bytes!("NotoSansSC-VariableFont_wght.ttf")),
+    ("Noto Sans TC", include_bytes!("NotoSansTC-VariableFont_wght.ttf")),
+];
 
-fn add_prop(cc: &eframe::CreationContext, name: &str, bytes: &'static [u8]){
-    cc.egui_ctx.add_font(FontInsert{
-        name: name.into(),
-        data: FontData {
-            font: bytes.into(),
-            index: 0,
-            tweak: Default::default(),
-        },
-        families: vec![
-            InsertFontFamily{
-                family: FontFamily::Proportional,
-                priority: egui::epaint::text::FontPriority::Lowest,
-            },
-            InsertFontFamily{
-                family: FontFamily::Monospace,
-                priority: egui::epaint::text::FontPriority::Lowest,
-            },
-        ]
-    })
+/// A monospace family with *only* our mono font, with no proportional fallback fonts.
+/// Missing glyphs render as a (monospace-width) placeholder box, which keeps ASCII art aligned.
+pub fn strict_mono() -> FontFamily {
+    FontFamily::Name("strict-mono".into())
 }
 
+/// Load our fonts, with the defaults first.
+pub fn load_fonts(cc: &eframe::CreationContext) {
+    cc.egui_ctx.set_fonts(definitions(NOTO_SANS, NOTO_SANS_MONO));
+}
 
-fn noto_sans(cc: &eframe::CreationContext) {
-    let bytes = include_bytes!("NotoSans-Variable.ttf");
-    let name = "Noto Sans";
-    cc.egui_ctx.add_font(FontInsert{
-        name: name.into(),
-        data: FontData {
-            font: bytes.into(),
-            index: 0,
-            tweak: Default::default(),
-        },
-        families: vec![
-            InsertFontFamily{
-                family: FontFamily::Proportional,
-                priority: egui::epaint::text::FontPriority::Highest,
-            },
-        ]
-    })
+/// All of our fonts, with `proportional` and `monospace` (names from [`FONTS`]) tried first for those families.
+/// Glyphs they don't have fall back to the other fonts, in the order of [`FONTS`].
+pub fn definitions(proportional: &str, monospace: &str) -> FontDefinitions {
+    let mut fonts = FontDefinitions::empty();
+    for (name, bytes) in FONTS {
+        fonts.font_data.insert(name.to_string(), Arc::new(FontData::from_static(bytes)));
+    }
+    fonts.families.insert(FontFamily::Proportional, priority(proportional, NOTO_SANS_MONO));
+    // (I don't think the emoji font is technically monospace, but it's better than no emoji?)
+    fonts.families.insert(FontFamily::Monospace, priority(monospace, NOTO_SANS));
+    fonts.families.insert(strict_mono(), vec![NOTO_SANS_MONO.into()]);
+    fonts
 }
 
-fn noto_sans_mono(cc: &eframe::CreationContext) {
-    let bytes = include_bytes!("NotoSansMono-Variable.ttf");
-    let name = "Noto Sans Mono";
-    cc.egui_ctx.add_font(FontInsert{
-        name: name.into(),
-        data: FontData {
-            font: bytes.into(),
-            index: 0,
-            tweak: Default::default(),
-        },
-        families: vec![
-            InsertFontFamily{
-                family: FontFamily::Monospace,
-                priority: egui::epaint::text::FontPriority::Highest,
-            },
-        ]
-    })
+/// `first`, if we have it, then our other fonts. Except for `excluded`, which would look out of place.
+pub fn priority(first: &str, excluded: &str) -> Vec<String> {
+    let known = FONTS.iter().any(|(name, _)| *name == first);
+    known.then_some(first)
+        .into_iter()
+        .chain(FONTS.iter().map(|(name, _)| *name).filter(|it| *it != first && *it != excluded))
+        .map(String::from)
+        .collect()
 }
 
-// Sadly, egui doesn't support color fonts yet:
-fn noto_emoji(cc: &eframe::CreationContext) {
-    let bytes = include_bytes!("noto-emoji/NotoEmoji-Variable.ttf");
-    let name = "NotoEmoji";
-    cc.egui_ctx.add_font(FontInsert{
-        name: name.into(),
-        data: FontData {
-            font: bytes.into(),
-            index: 0,
-            tweak: Default::default(),
-        },
-        families: vec![
-            InsertFontFamily{
-                family: FontFamily::Proportional,
-                priority: egui::epaint::text::FontPriority::Lowest,
-            },
-            InsertFontFamily{
-                // I don't think this is technically monospace, but it's better than no emoji?
-                family: FontFamily::Monospace,
-                priority: egui::epaint::text::FontPriority::Lowest,
-            },
-        ]
-    })
-}
\ No newline at end of file
+/// A menu to pick one of our fonts, by name.
+pub fn font_combo_box(ui: &mut egui::Ui, id: &str, selected: &mut String) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected.as_str())
+        .show_ui(ui, |ui| {
+            for (name, _) in FONTS {
+                changed |= ui.selectable_value(selected, name.to_string(), *name).changed();
+            }
+        });
+    changed
+}
@@ -0,0 +1,139 @@
+//! A global log of visited pages, across all tabs.
+//! Persisted separately from the rest of the browser state, like [`RecentlyClosed`](super::closed::RecentlyClosed).
+
+use std::{fmt::Write as _, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{browser::network::SCow, util::{collapse_whitespace, normalize_url, push_bounded, trim_oldest}};
+
+mod history_test;
+
+/// The storage key we persist [`History`] under.
+pub const STORAGE_KEY: &str = "egemi-history";
+
+/// The builtin page that lists visited pages.
+pub const ABOUT_URL: &str = "about:history";
+
+/// A visit to a page.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub url: SCow,
+
+    /// The document's title, if it had one. Filled in once the page has loaded.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+}
+
+impl Visit {
+    pub fn new(url: SCow) -> Self {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|it| it.as_secs())
+            .unwrap_or_default();
+        Self { url, title: None, time }
+    }
+
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        self.title = title;
+        self
+    }
+}
+
+/// Visited pages, oldest first.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct History {
+    visits: Vec<Visit>,
+}
+
+impl History {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+
+    /// Record a visit, keeping at most `max` visits.
+    /// Visiting the same URL again right away (ex: reloading) just updates the last visit.
+    /// Returns whether anything changed.
+    pub fn record(&mut self, visit: Visit, max: usize) -> bool {
+        // Builtin pages aren't worth remembering, and about:history would record itself.
+        if visit.url.starts_with("about:") {
+            return false;
+        }
+        if let Some(last) = self.visits.last_mut() && normalize_url(&last.url) == normalize_url(&visit.url) {
+            let title = visit.title.or_else(|| last.title.take());
+            let changed = last.title != title || last.time != visit.time;
+            last.title = title;
+            last.time = visit.time;
+            return changed;
+        }
+        push_bounded(&mut self.visits, visit, max);
+        true
+    }
+
+    /// Drop the oldest visits so that at most `max` remain.
+    pub fn trim(&mut self, max: usize) {
+        trim_oldest(&mut self.visits, max);
+    }
+
+    pub fn clear(&mut self) {
+        self.visits.clear();
+    }
+
+    /// Visits, newest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Visit> {
+        self.visits.iter().rev()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visits.is_empty()
+    }
+
+    /// The contents of [`ABOUT_URL`]: visits, newest first, grouped by day.
+    pub fn to_gemtext(&self) -> String {
+        let mut out = String::from("# History\n\n");
+        if self.visits.is_empty() {
+            out.push_str("You haven't visited any pages yet.\n");
+            return out;
+        }
+        out.push_str("Use File → Clear History to forget these.\n");
+        let mut day = None;
+        for visit in self.iter() {
+            let date = utc_date(visit.time);
+            if day.as_ref() != Some(&date) {
+                writeln!(out, "\n## {date}").expect("writing to string");
+                day = Some(date);
+            }
+            let url = visit.url.replace(' ', "%20");
+            // Titles come from the pages, so keep them from breaking out of the link line:
+            match visit.title.as_deref().map(collapse_whitespace).filter(|it| !it.is_empty()) {
+                Some(title) => writeln!(out, "=> {url} {title}"),
+                None => writeln!(out, "=> {url}"),
+            }.expect("writing to string");
+        }
+        out
+    }
+}
+
+/// Formats seconds since the Unix epoch as a YYYY-MM-DD date, in UTC.
+fn utc_date(secs: u64) -> String {
+    // See: http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,57 @@
+#![cfg(test)]
+
+use crate::browser::history::{utc_date, History, Visit};
+use pretty_assertions::assert_eq;
+
+fn visit(url: &str, time: u64) -> Visit {
+    Visit { url: url.to_string().into(), title: None, time }
+}
+
+#[test]
+fn dedups_consecutive_visits() {
+    let mut history = History::default();
+    assert!(history.record(visit("gemini://example.com/", 1), 10));
+    assert!(history.record(visit("gemini://example.com/", 2).with_title(Some("Example".into())), 10));
+    // A later visit without a title (yet) keeps the old one:
+    assert!(history.record(visit("gemini://example.com/", 3), 10));
+    assert!(!history.record(visit("about:egemi", 4), 10));
+    assert!(history.record(visit("gemini://example.com/other", 5), 10));
+
+    let visits: Vec<_> = history.iter().cloned().collect();
+    assert_eq!(visits, vec![
+        visit("gemini://example.com/other", 5),
+        visit("gemini://example.com/", 3).with_title(Some("Example".into())),
+    ]);
+}
+
+#[test]
+fn bounded() {
+    let mut history = History::default();
+    for i in 0..30 {
+        history.record(visit(&format!("gemini://example.com/{i}"), i), 25);
+    }
+    let urls: Vec<_> = history.iter().map(|it| it.url.as_ref()).collect();
+    assert_eq!(urls.len(), 25);
+    assert_eq!(urls[0], "gemini://example.com/29");
+    assert_eq!(urls[24], "gemini://example.com/5");
+}
+
+#[test]
+fn gemtext_newest_first() {
+    let mut history = History::default();
+    history.record(visit("gemini://example.com/old", 0), 10);
+    history.record(visit("gemini://example.com/new", 86_400).with_title(Some("New\n=> evil:/ Page ".into())), 10);
+    assert_eq!(history.to_gemtext(), "# History\n\n\
+        Use File → Clear History to forget these.\n\
+        \n## 1970-01-02\n\
+        => gemini://example.com/new New => evil:/ Page\n\
+        \n## 1970-01-01\n\
+        => gemini://example.com/old\n");
+}
+
+#[test]
+fn dates() {
+    assert_eq!(utc_date(0), "1970-01-01");
+    assert_eq!(utc_date(951_782_400), "2000-02-29");
+    assert_eq!(utc_date(1_791_590_400), "2026-10-10");
+}
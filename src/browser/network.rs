@@ -3,15 +3,23 @@
 pub mod http;
 pub mod file;
 pub mod gemini;
+pub mod cache;
+pub mod blocklist;
+pub mod certs;
+pub mod data;
+pub mod dns;
+pub mod known_hosts;
+pub mod titan;
 
-use std::{borrow::Cow, fmt::Display, io, sync::{Arc, LazyLock}};
+use std::{borrow::Cow, fmt::Display, io, path::PathBuf, sync::{mpsc, Arc, LazyLock, Mutex}, time::Duration};
 
+use log::debug;
 use mime::Mime;
 use reqwest::header::ToStrError;
-use tokio::{runtime::Runtime, task::JoinHandle};
+use tokio::{io::{AsyncRead, AsyncReadExt as _}, runtime::Runtime, task::{AbortHandle, JoinHandle}};
 use url::Url;
 
-use crate::browser::network::{file::FileStatus, gemini::GeminiLoader, http::HttpLoader};
+use crate::browser::{network::{blocklist::Blocklist, cache::{Cache, CacheControl}, certs::ClientCerts, dns::Dns, file::FileStatus, gemini::{GeminiLoader, Retrying}, http::{auth::{Credentials, HostCredentials}, HttpLoader}, titan::{TitanLoader, Upload}}, parsers::feed, settings::Settings};
 
 // A global runtime to execute async tasks on.
 // The big benefit of async here is that tokio Tasks can be aborted at any time.
@@ -31,33 +39,248 @@ pub fn rt() -> Arc<Runtime> {
     RT.clone()
 }
 
-#[derive(Default, Debug)]
+/// How much of a page [`MultiLoader::fetch_preview`] reads. Titles are almost always near the top.
+pub const PREVIEW_BYTES: u64 = 8 * 1024;
+
+/// Max number of redirects we'll follow for a single request.
+pub const MAX_REDIRECTS: usize = 5;
+
+/// Clones share the cache, credentials, and retry state, so that every tab can use the same ones.
+#[derive(Default, Debug, Clone)]
 pub struct MultiLoader {
     http: Arc<HttpLoader>,
     gemini: Arc<GeminiLoader>,
+    titan: Arc<TitanLoader>,
     file: Arc<file::FileLoader>,
+    blocklist: Blocklist,
+
+    /// Shared with in-flight requests, so that they can add to it when they finish.
+    cache: Arc<Mutex<Cache>>,
+
+    /// Shared with in-flight Gemini requests, so that they can report that they're retrying.
+    retrying: Retrying,
+
+    /// How long a request may take before we give up on it. None waits forever.
+    timeout: Option<Duration>,
+
+    /// HTTP basic auth credentials the user has given us this session.
+    credentials: HostCredentials,
 }
 
 impl MultiLoader {
+    /// Apply user settings to the loaders.
+    /// In-flight requests will continue to use the old settings.
+    pub fn configure(&mut self, settings: &Settings) {
+        let dns = Dns::new(settings.dns_resolver, &settings.host_overrides);
+        self.blocklist = Blocklist::new(settings.blocklist.iter().map(String::as_str));
+        self.http = Arc::new(
+            HttpLoader::default()
+                .with_accept_types(&settings.accept_types)
+                .with_host_headers(settings.host_headers.clone())
+                .with_network(&settings.http_proxy, dns.clone())
+                .with_referer_policy(settings.referer_policy)
+                .with_follow_redirects(settings.follow_redirects)
+                .with_blocklist(self.blocklist.clone())
+                .with_credentials(self.credentials.clone())
+        );
+        self.file = Arc::new(
+            file::FileLoader::default().with_root(settings.file_root())
+        );
+        let certs = ClientCerts::new(settings.cert_dir(), settings.client_certs.clone());
+        self.gemini = Arc::new(
+            GeminiLoader::default()
+                .with_certs(certs.clone())
+                .with_retries(
+                    settings.gemini_retries,
+                    Duration::try_from_secs_f32(settings.retry_backoff_secs).unwrap_or_default(),
+                    self.retrying.clone(),
+                )
+                .with_blocklist(self.blocklist.clone())
+                .with_dns(dns.clone())
+        );
+        self.titan = Arc::new(
+            TitanLoader::default()
+                .with_certs(certs)
+                .with_dns(dns)
+                .with_gemini(self.gemini.clone())
+                .with_blocklist(self.blocklist.clone())
+        );
+        self.timeout = Duration::try_from_secs_f32(settings.request_timeout_secs).ok().filter(|it| !it.is_zero());
+        self.cache().set_limits(settings.cache_entries, settings.cache_megabytes.saturating_mul(1024 * 1024));
+    }
+
+    /// If an in-flight request for `url` is waiting to retry, which attempt it's on.
+    pub fn retry_attempt(&self, url: &str) -> Option<u32> {
+        self.retrying.attempt(url)
+    }
+
+    /// Send `credentials` with HTTP requests to `url`'s host, for the rest of the session.
+    pub fn set_credentials(&self, url: &str, credentials: Credentials) {
+        if let Ok(url) = Url::parse(url) {
+            self.credentials.set(&url, credentials);
+        }
+    }
+
+    /// Forget any cached copy of `url`, so that the next fetch goes to the network.
+    pub fn invalidate(&self, url: &str) {
+        self.cache().remove(url);
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, Cache> {
+        // The cache is only ever changed in small synchronous steps, so it's still usable after a panic:
+        self.cache.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
     pub fn fetch(&self, url: SCow) -> JoinHandle<Result<LoadedResource>> {
+        self.fetch_checked(url, true, None, None)
+    }
+
+    /// Like [`Self::fetch`], for a page to show in a tab.
+    /// * `check_blocklist` is false when the user chose to "allow once".
+    /// * `referrer` is the page whose link we're following. Whether servers are told about it is up to [`Settings::referer_policy`].
+    /// * Gemini responses are also sent to `chunks` as they arrive, so that they can be shown before they finish.
+    ///   Other responses (including cached ones) aren't, so the returned handle is still the whole response.
+    pub fn fetch_page(&self, url: SCow, check_blocklist: bool, referrer: Option<SCow>, chunks: Chunks) -> JoinHandle<Result<LoadedResource>> {
+        self.fetch_checked(url, check_blocklist, referrer, Some(chunks))
+    }
+
+    /// Upload to a titan:// `url`. Uploads are never cached, and the page they change is dropped from the cache.
+    pub fn upload(&self, url: SCow, upload: Upload) -> JoinHandle<Result<LoadedResource>> {
+        let parsed = match Url::parse(&url) {
+            Ok(ok) if ok.scheme() == "titan" => ok,
+            _ => return async_err(Error::InvalidUrl(url)),
+        };
+        if let Some(pattern) = self.blocklist.blocked_by(&parsed) {
+            let pattern = pattern.as_str().to_string();
+            return async_err(Error::Blocked { url, pattern });
+        }
+        self.invalidate(titan::gemini_url(&parsed).as_str());
+        let handle = self.titan.upload(parsed, upload);
+        self.with_timeout(url, handle)
+    }
+
+    fn fetch_checked(&self, url: SCow, check_blocklist: bool, referrer: Option<SCow>, chunks: Option<Chunks>) -> JoinHandle<Result<LoadedResource>> {
         let parsed = match Url::parse(&url) {
             Ok(ok) => ok,
             Err(_) => {
                 return async_err(Error::InvalidUrl(url))
             },
         };
+        if check_blocklist && let Some(pattern) = self.blocklist.blocked_by(&parsed) {
+            let pattern = pattern.as_str().to_string();
+            return async_err(Error::Blocked { url, pattern });
+        }
+
+        if let Some(cached) = self.cache().get(&url) {
+            debug!("Loaded {url} from cache");
+            return rt().spawn(async move { Ok(cached) });
+        }
+        if !is_cacheable(&parsed) {
+            return self.fetch_uncached(url, parsed, referrer, chunks);
+        }
+        let handle = self.fetch_uncached(url.clone(), parsed, referrer, chunks);
+        let cache = self.cache.clone();
+        rt().spawn(async move {
+            // If the caller aborts us, stop the actual request too:
+            let _abort = AbortOnDrop(handle.abort_handle());
+            let loaded = handle.await.map_err(|err| Error::Unknown(err.to_string()))??;
+            cache.lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .insert(url.into_owned(), loaded.clone());
+            Ok(loaded)
+        })
+    }
+
+    /// Just the start of `url` (up to [`PREVIEW_BYTES`]), for previewing its title.
+    /// Unlike [`Self::fetch`], this sends no client certificates, credentials, or user-configured headers,
+    /// doesn't follow redirects, and isn't cached, since it's usually incomplete.
+    pub fn fetch_preview(&self, url: SCow) -> JoinHandle<Result<LoadedResource>> {
+        let parsed = match Url::parse(&url) {
+            Ok(ok) => ok,
+            Err(_) => return async_err(Error::InvalidUrl(url)),
+        };
+        if let Some(pattern) = self.blocklist.blocked_by(&parsed) {
+            let pattern = pattern.as_str().to_string();
+            return async_err(Error::Blocked { url, pattern });
+        }
+        if let Some(cached) = self.cache().get(&url) {
+            return rt().spawn(async move { Ok(cached) });
+        }
+        let handle = match parsed.scheme() {
+            "gemini" => self.gemini.fetch_preview(parsed, PREVIEW_BYTES),
+            "http" | "https" => self.http.fetch_preview(&url, PREVIEW_BYTES),
+            _ => return async_err(Error::UnsupportedUrlScheme(parsed)),
+        };
+        self.with_timeout(url, handle)
+    }
+
+    fn fetch_uncached(&self, url: SCow, parsed: Url, referrer: Option<SCow>, chunks: Option<Chunks>) -> JoinHandle<Result<LoadedResource>> {
+        let handle = self.fetch_scheme(url.clone(), parsed, referrer, chunks);
+        self.with_timeout(url, handle)
+    }
+
+    /// Gives up on `handle` after the user's timeout.
+    fn with_timeout(&self, url: SCow, handle: JoinHandle<Result<LoadedResource>>) -> JoinHandle<Result<LoadedResource>> {
+        let Some(timeout) = self.timeout else {
+            return handle;
+        };
+        rt().spawn(async move {
+            // Stop the actual request if it times out, or if the caller aborts us:
+            let _abort = AbortOnDrop(handle.abort_handle());
+            match tokio::time::timeout(timeout, handle).await {
+                Ok(joined) => joined.map_err(|err| Error::Unknown(err.to_string()))?,
+                Err(_) => Err(Error::Timeout { url, after: timeout }),
+            }
+        })
+    }
+
+    fn fetch_scheme(&self, url: SCow, parsed: Url, referrer: Option<SCow>, chunks: Option<Chunks>) -> JoinHandle<Result<LoadedResource>> {
         if parsed.scheme() == "gemini" {
-            self.gemini.fetch(parsed)
+            self.gemini.fetch_streaming(parsed, chunks)
         } else if parsed.scheme() == "http" || parsed.scheme() == "https" {
-            self.http.fetch(&url)
+            self.http.fetch(&url, referrer.as_deref())
         } else if parsed.scheme() == "file" {
             self.file.fetch(parsed)
+        } else if parsed.scheme() == "titan" {
+            // There's nothing to fetch. Ask the user what to upload. See: [`Self::upload`]
+            let loaded = LoadedResource {
+                status: Status::TitanUpload,
+                body: Body::Text("".into()),
+                content_type: None,
+                length: None,
+                cache_control: None,
+                timings: None,
+                url,
+            };
+            rt().spawn(async move { Ok(loaded) })
+        } else if parsed.scheme() == "data" {
+            let loaded = data::load(url);
+            rt().spawn(async move { loaded })
         } else {
             async_err(Error::UnsupportedUrlScheme(parsed))
         }
     }
 }
 
+/// Local files are cheap to load, and may have changed. Gemini queries are usually user input,
+/// which may be sensitive, and whose responses are usually dynamic.
+fn is_cacheable(url: &Url) -> bool {
+    match url.scheme() {
+        "http" | "https" => true,
+        "gemini" => url.query().is_none(),
+        _ => false,
+    }
+}
+
+/// Aborts a task when it's dropped. (ex: To stop a request when the task waiting on it is aborted.)
+pub struct AbortOnDrop(pub AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 fn async_err(err: Error) -> JoinHandle<Result<LoadedResource>> {
     rt().spawn( async move {
         Err(err)
@@ -72,7 +295,7 @@ pub type SCow = Cow<'static, str>;
 pub type BCow = Cow<'static, [u8]>;
 
 /// Resource that has been completely loaded and is ready for synchronous use.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LoadedResource {
     pub url: SCow,
 
@@ -82,6 +305,12 @@ pub struct LoadedResource {
     pub length: Option<u64>,
     pub content_type: Option<Arc<Mime>>,
 
+    /// How this resource may be cached. Only HTTP(S) responses have this.
+    pub cache_control: Option<CacheControl>,
+
+    /// How long the request took. Only network requests have this.
+    pub timings: Option<Timings>,
+
     // TODO: 
     pub body: Body
 
@@ -89,14 +318,85 @@ pub struct LoadedResource {
 }
 
 
+/// Part of a response, sent while it's still loading. See [`MultiLoader::fetch_page`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chunk {
+    /// A successful response from `url` started. (Which may differ from the requested URL, after redirects.)
+    /// [`Chunk::Body`]s that follow are its body.
+    Start { url: SCow, content_type: Option<Arc<Mime>> },
+
+    Body(Vec<u8>),
+}
+
+pub type Chunks = mpsc::Sender<Chunk>;
+
+/// How long a network request took.
+/// reqwest doesn't expose DNS/connect/TLS timings, so HTTP(S) only has the ones we can measure from the outside.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Timings {
+    /// Looking up the server's address.
+    pub dns: Option<Duration>,
+
+    /// Opening a TCP connection to the server.
+    pub connect: Option<Duration>,
+
+    /// The TLS handshake.
+    pub tls: Option<Duration>,
+
+    /// Until we got the start of the response.
+    pub first_byte: Option<Duration>,
+
+    /// Until we finished reading the body.
+    pub total: Duration,
+}
+
+impl Display for Timings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let steps = [("DNS", self.dns), ("connect", self.connect), ("TLS", self.tls), ("first byte", self.first_byte)];
+        for (name, time) in steps {
+            if let Some(time) = time {
+                write!(f, "{name}: {} ms, ", time.as_millis())?;
+            }
+        }
+        write!(f, "total: {} ms", self.total.as_millis())
+    }
+}
+
 /// Like an HTTP status, but might apply to not-HTTP.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Status {
     HttpStatus {
         code: u16,
     },
 
     FileStatus(FileStatus),
+
+    /// HTTP 401, with a `WWW-Authenticate` header that offers basic auth.
+    /// The user should be prompted for credentials.
+    HttpAuthRequired {
+        realm: String,
+
+        /// We already sent credentials, which the server didn't accept.
+        rejected: bool,
+    },
+
+    /// Gemini status 10 (INPUT) or 11 (SENSITIVE INPUT).
+    /// The user should be prompted, and their input sent as the URL's query.
+    GeminiInput {
+        /// Status 11. Input should be masked, and must not be saved.
+        sensitive: bool,
+        prompt: String,
+    },
+
+    /// A titan:// URL. The user should be asked what to upload to it.
+    TitanUpload,
+
+    /// Gemini status 60 (certificate required), 61 (not authorized), or 62 (not valid).
+    GeminiCertRequired {
+        code: u8,
+        /// The server's explanation, if any.
+        message: String,
+    },
 }
 
 impl Display for Status {
@@ -105,7 +405,14 @@ impl Display for Status {
             Status::HttpStatus { code } => {
                 write!(f, "HTTP {code}")
             },
+            Status::HttpAuthRequired { .. } => write!(f, "HTTP 401 (Authentication Required)"),
             Status::FileStatus(stat) => write!(f, "{stat:?}"),
+            Status::GeminiInput { sensitive: false, .. } => write!(f, "Gemini 10 (Input)"),
+            Status::GeminiInput { sensitive: true, .. } => write!(f, "Gemini 11 (Sensitive Input)"),
+            Status::TitanUpload => write!(f, "Titan (Upload)"),
+            Status::GeminiCertRequired { code: 61, .. } => write!(f, "Gemini 61 (Certificate Not Authorized)"),
+            Status::GeminiCertRequired { code: 62, .. } => write!(f, "Gemini 62 (Certificate Not Valid)"),
+            Status::GeminiCertRequired { code, .. } => write!(f, "Gemini {code} (Client Certificate Required)"),
         }
     }
 }
@@ -115,17 +422,49 @@ impl Status {
         use Status::*;
         match self {
             HttpStatus { code } => { 200 <= *code && *code < 300 },
+            HttpAuthRequired { .. } => false,
             FileStatus(stat) => { stat == &file::FileStatus::Ok },
+            GeminiInput { .. } => false,
+            GeminiCertRequired { .. } => false,
+            TitanUpload => false,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Body {
     Bytes(BCow),
     Text(SCow)
 }
 
+/// Fails if a response of `length` bytes is bigger than we allow.
+pub fn check_size(length: u64, max_length: u64) -> Result<()> {
+    if length > max_length {
+        return Err(Error::ResponseTooBig { content_length: length, max_length });
+    }
+    Ok(())
+}
+
+/// Reads all of `reader`, but stops as soon as it's more than `max_length` bytes, so that a huge
+/// (or endless) response can't use up all of our memory.
+/// Read errors are returned along with whatever was read before them.
+pub async fn read_capped(reader: impl AsyncRead + Unpin, max_length: u64, data: &mut Vec<u8>) -> Result<()> {
+    reader.take(max_length.saturating_add(1)).read_to_end(data).await?;
+    check_size(data.len() as u64, max_length)
+}
+
+/// Whether we should keep a body of this type as bytes instead of decoding it as text.
+/// That's anything we can't render as a document, so that images can be shown and other files saved.
+pub fn is_binary(content_type: Option<&Mime>) -> bool {
+    // Without a type, assume text.
+    content_type.is_some_and(|it| it.type_() != mime::TEXT && !feed::is_feed(it) && !is_json(it))
+}
+
+/// `application/json`, or a type based on it. (ex: `application/activity+json`)
+pub fn is_json(content_type: &Mime) -> bool {
+    content_type.subtype() == mime::JSON || content_type.suffix() == Some(mime::JSON)
+}
+
 
 
 #[derive(thiserror::Error, Debug)]
@@ -133,6 +472,10 @@ pub enum Error {
     #[error("Unknown error {0}")]
     Unknown(String),
 
+    /// Couldn't connect to the server, or the connection failed. These may be worth retrying.
+    #[error("Connection error: {0}")]
+    Connection(String),
+
     #[error("Unsupported URL scheme: {0}")]
     UnsupportedUrlScheme(Url),
 
@@ -140,9 +483,6 @@ pub enum Error {
     #[error("Unrequested Content-Type: {0}")]
     UnrequestedContentType(Mime),
 
-    #[error("Unsupported Content-Type: {0}")]
-    UnsupportedContentType(Mime),
-
 
     #[error("Missing Content-Type")]
     MissingContentType,
@@ -156,11 +496,30 @@ pub enum Error {
     #[error("I/O Error: {0}")]
     IoError(#[from] io::Error),
 
+    /// A file:// URL outside of the configured root directory.
+    #[error("{} is outside of the allowed directory: {}", path.display(), root.display())]
+    OutsideRoot { path: PathBuf, root: PathBuf },
+
     #[error("Max allowed response size was {max_length} bytes, but got {content_length}")]
     ResponseTooBig { content_length: u64, max_length: u64 },
 
     #[error("Redirect")]
-    Redirect{ destination: String, temporary: bool }
+    Redirect{ destination: String, temporary: bool },
+
+    #[error("Client certificate {name}: {message}")]
+    ClientCert { name: String, message: String },
+
+    /// The URL matched a pattern in the user's blocklist.
+    #[error("{url} is blocked by: {pattern}")]
+    Blocked { url: SCow, pattern: String },
+
+    /// `hops` are the URLs we were redirected from, in order. `url` is where the last one pointed.
+    #[error("Too many redirects. Last redirected to: {url}")]
+    TooManyRedirects { url: SCow, hops: Vec<SCow> },
+
+    /// The request took longer than the user's configured timeout.
+    #[error("Timed out after {after:?} loading {url}")]
+    Timeout { url: SCow, after: Duration },
     
 }
 
@@ -0,0 +1,89 @@
+//! Refuse to load URLs that the user has blocked.
+
+use url::Url;
+
+use super::{Error, Result};
+
+mod blocklist_test;
+
+/// A list of [`BlockPattern`]s. Matching any of them blocks a URL.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Blocklist {
+    patterns: Vec<BlockPattern>,
+}
+
+impl Blocklist {
+    /// Parse patterns, one per item. Empty items are ignored.
+    pub fn new<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Self {
+        let patterns = patterns.into_iter()
+            .filter_map(BlockPattern::parse)
+            .collect();
+        Self { patterns }
+    }
+
+    /// The first pattern that blocks `url`, if any.
+    pub fn blocked_by(&self, url: &Url) -> Option<&BlockPattern> {
+        self.patterns.iter().find(|it| it.matches(url))
+    }
+
+    /// [`Error::Blocked`] if `url` is blocked. For checking where redirects lead.
+    pub fn check(&self, url: &Url) -> Result<()> {
+        match self.blocked_by(url) {
+            Some(pattern) => Err(Error::Blocked { url: url.to_string().into(), pattern: pattern.as_str().to_string() }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A host, and optional path prefix. (ex: `example.com` or `example.com/news/`)
+///
+/// The host also matches its subdomains, and the pattern applies to any URL scheme.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockPattern {
+    /// Lowercase, without a trailing dot.
+    host: String,
+
+    /// Starts with `/`.
+    path_prefix: String,
+
+    /// As the user wrote it, for error messages.
+    source: String,
+}
+
+impl BlockPattern {
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let source = pattern.trim();
+        // Allow pasting whole URLs:
+        let pattern = source.split_once("://").map(|(_scheme, rest)| rest).unwrap_or(source);
+        let (host, path) = match pattern.find('/') {
+            Some(index) => pattern.split_at(index),
+            None => (pattern, "/"),
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self {
+            host,
+            path_prefix: path.to_string(),
+            source: source.to_string(),
+        })
+    }
+
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let host_matches = match host.strip_suffix(&self.host) {
+            Some("") => true,
+            Some(subdomain) => subdomain.ends_with('.'),
+            None => false,
+        };
+        host_matches && url.path().starts_with(&self.path_prefix)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
@@ -0,0 +1,35 @@
+#![cfg(test)]
+
+use url::Url;
+
+use crate::browser::network::blocklist::Blocklist;
+
+fn blocked(patterns: &[&str], url: &str) -> bool {
+    let url = Url::parse(url).unwrap();
+    Blocklist::new(patterns.iter().copied()).blocked_by(&url).is_some()
+}
+
+#[test]
+fn host_suffix() {
+    let list = ["example.com"];
+    assert!(blocked(&list, "https://example.com/"));
+    assert!(blocked(&list, "gemini://Example.COM/foo"));
+    assert!(blocked(&list, "https://www.example.com/"));
+    assert!(!blocked(&list, "https://notexample.com/"));
+    assert!(!blocked(&list, "https://example.com.au/"));
+    assert!(!blocked(&list, "file:///example.com"));
+}
+
+#[test]
+fn path_prefix() {
+    let list = ["example.com/news/", "https://other.org/feed"];
+    assert!(blocked(&list, "https://example.com/news/today"));
+    assert!(!blocked(&list, "https://example.com/about"));
+    assert!(!blocked(&list, "https://example.com/news"));
+    assert!(blocked(&list, "gemini://other.org/feed.xml"));
+}
+
+#[test]
+fn empty_patterns() {
+    assert!(!blocked(&["", "  ", "/path"], "https://example.com/path"));
+}
@@ -0,0 +1,194 @@
+//! Caching of loaded resources.
+//! HTTP responses tell us how they may be cached with the `Cache-Control` header.
+//! See: https://developer.mozilla.org/en-US/docs/Web/HTTP/Reference/Headers/Cache-Control
+
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use crate::{browser::network::{Body, LoadedResource}, util::normalize_url};
+
+mod cache_test;
+
+/// The `Cache-Control` directives we care about, from a response.
+/// Unknown directives are ignored.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CacheControl {
+    /// Must never be stored.
+    pub no_store: bool,
+
+    /// Only for the user's own (private) cache. We're a single-user browser, but
+    /// we don't want to keep things like account pages around, so we treat this like `no-store`.
+    pub private: bool,
+
+    /// May be stored, but must be revalidated before each use.
+    pub no_cache: bool,
+
+    /// How long the response stays fresh after it was fetched.
+    pub max_age: Option<Duration>,
+}
+
+impl CacheControl {
+    /// Parse the value of a `Cache-Control` header. ex: `public, max-age=3600`
+    pub fn parse(value: &str) -> Self {
+        let mut out = Self::default();
+        for directive in value.split(',') {
+            let (name, arg) = match directive.split_once('=') {
+                Some((name, arg)) => (name, Some(arg.trim().trim_matches('"'))),
+                None => (directive, None),
+            };
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" => out.no_store = true,
+                "private" => out.private = true,
+                "no-cache" => out.no_cache = true,
+                "max-age" => {
+                    // Per RFC 9111, an invalid max-age means the response is stale:
+                    let seconds = arg.and_then(|it| it.parse().ok()).unwrap_or(0);
+                    out.max_age = Some(Duration::from_secs(seconds));
+                },
+                _ => {},
+            }
+        }
+        out
+    }
+
+    /// Whether we may keep this response in our cache at all.
+    pub fn is_storable(&self) -> bool {
+        !self.no_store && !self.private
+    }
+
+    /// Whether a cached response that is `age` old may be used without revalidating it.
+    /// Without a `max-age`, responses never go stale by themselves.
+    pub fn is_fresh(&self, age: Duration) -> bool {
+        if self.no_cache {
+            return false;
+        }
+        match self.max_age {
+            Some(max_age) => age < max_age,
+            None => true,
+        }
+    }
+}
+
+/// Default for [`Cache::new`]'s `max_entries`.
+pub const DEFAULT_MAX_ENTRIES: usize = 200;
+
+/// Default for [`Cache::new`]'s `max_bytes`.
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// An in-memory cache of loaded resources, keyed by URL.
+/// When it's full, the least recently used entries are evicted first.
+#[derive(Debug)]
+pub struct Cache {
+    max_entries: usize,
+    max_bytes: usize,
+    /// Keyed by [`normalize_url`], so that different spellings of a URL share an entry.
+    entries: HashMap<String, Entry>,
+
+    /// The total size of all entries.
+    bytes: usize,
+
+    /// Incremented on each use, to find the least recently used entry.
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    resource: LoadedResource,
+    fetched: Instant,
+    last_used: u64,
+    size: usize,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES, DEFAULT_MAX_BYTES)
+    }
+}
+
+impl Cache {
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            max_entries,
+            max_bytes,
+            entries: HashMap::new(),
+            bytes: 0,
+            tick: 0,
+        }
+    }
+
+    /// Change the limits, evicting entries if we're now over them.
+    pub fn set_limits(&mut self, max_entries: usize, max_bytes: usize) {
+        self.max_entries = max_entries;
+        self.max_bytes = max_bytes;
+        self.evict(0);
+    }
+
+    /// A copy of the cached resource for `url`, if we have a fresh one.
+    pub fn get(&mut self, url: &str) -> Option<LoadedResource> {
+        let url = &normalize_url(url);
+        let entry = self.entries.get(url)?;
+        let fresh = match &entry.resource.cache_control {
+            Some(control) => control.is_fresh(entry.fetched.elapsed()),
+            None => true,
+        };
+        if !fresh {
+            self.remove(url);
+            return None;
+        }
+
+        self.tick += 1;
+        let entry = self.entries.get_mut(url)?;
+        entry.last_used = self.tick;
+        Some(entry.resource.clone())
+    }
+
+    /// Cache `resource`, if it was successful and may be stored.
+    pub fn insert(&mut self, url: String, resource: LoadedResource) {
+        let url = normalize_url(&url);
+        let storable = resource.cache_control.as_ref().is_none_or(CacheControl::is_storable);
+        if !storable || !resource.status.ok() {
+            return;
+        }
+        let size = url.len() + match &resource.body {
+            Body::Bytes(bytes) => bytes.len(),
+            Body::Text(text) => text.len(),
+        };
+        if size > self.max_bytes || self.max_entries == 0 {
+            return;
+        }
+
+        self.remove(&url);
+        self.evict(size);
+        self.tick += 1;
+        self.bytes += size;
+        self.entries.insert(url, Entry {
+            resource,
+            fetched: Instant::now(),
+            last_used: self.tick,
+            size,
+        });
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        if let Some(entry) = self.entries.remove(&normalize_url(url)) {
+            self.bytes -= entry.size;
+        }
+    }
+
+    /// Evict least recently used entries until there's room for a new entry of `size` bytes.
+    /// (Or, with a `size` of 0, until we're within our limits.)
+    fn evict(&mut self, size: usize) {
+        let full = |cache: &Self| {
+            let new_entries = if size > 0 { 1 } else { 0 };
+            cache.entries.len() + new_entries > cache.max_entries || cache.bytes + size > cache.max_bytes
+        };
+        while full(self) {
+            let oldest = self.entries.iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(url, _)| url.clone());
+            let Some(oldest) = oldest else {
+                return;
+            };
+            self.remove(&oldest);
+        }
+    }
+}
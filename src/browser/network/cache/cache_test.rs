@@ -0,0 +1,124 @@
+#![cfg(test)]
+
+use std::time::Duration;
+
+use crate::browser::network::{cache::{Cache, CacheControl}, Body, LoadedResource, Status};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn no_store_is_never_cached() {
+    assert!(!CacheControl::parse("no-store").is_storable());
+    assert!(!CacheControl::parse("No-Store, max-age=3600").is_storable());
+    assert!(!CacheControl::parse("private, max-age=60").is_storable());
+    assert!(CacheControl::parse("public, max-age=60").is_storable());
+    assert!(CacheControl::default().is_storable());
+}
+
+#[test]
+fn max_age_zero_always_revalidates() {
+    let control = CacheControl::parse("max-age=0");
+    assert_eq!(control.max_age, Some(Duration::ZERO));
+    assert!(control.is_storable());
+    assert!(!control.is_fresh(Duration::ZERO));
+
+    let control = CacheControl::parse("max-age=\"60\"");
+    assert!(control.is_fresh(Duration::from_secs(59)));
+    assert!(!control.is_fresh(Duration::from_secs(60)));
+
+    assert!(!CacheControl::parse("no-cache").is_fresh(Duration::ZERO));
+}
+
+fn page(text: &str) -> LoadedResource {
+    LoadedResource {
+        url: "gemini://example.com/".into(),
+        status: Status::HttpStatus { code: 200 },
+        length: None,
+        content_type: None,
+        cache_control: None,
+        timings: None,
+        body: Body::Text(text.to_string().into()),
+    }
+}
+
+fn cached_text(cache: &mut Cache, url: &str) -> Option<String> {
+    match cache.get(url)?.body {
+        Body::Text(text) => Some(text.into_owned()),
+        Body::Bytes(_) => None,
+    }
+}
+
+#[test]
+fn evicts_least_recently_used() {
+    let mut cache = Cache::new(2, 1000);
+    cache.insert("a".into(), page("A"));
+    cache.insert("b".into(), page("B"));
+    // Using "a" makes "b" the oldest:
+    assert_eq!(cached_text(&mut cache, "a"), Some("A".into()));
+    cache.insert("c".into(), page("C"));
+
+    assert_eq!(cache.entries.len(), 2);
+    assert_eq!(cached_text(&mut cache, "b"), None);
+    assert_eq!(cached_text(&mut cache, "a"), Some("A".into()));
+    assert_eq!(cached_text(&mut cache, "c"), Some("C".into()));
+}
+
+#[test]
+fn evicts_to_byte_budget() {
+    // Sizes include the URL:
+    let mut cache = Cache::new(100, 20);
+    cache.insert("a".into(), page("123456789"));
+    cache.insert("b".into(), page("123456789"));
+    assert_eq!(cache.entries.len(), 2);
+    cache.insert("c".into(), page("1234"));
+    assert_eq!(cache.entries.len(), 2);
+    assert_eq!(cached_text(&mut cache, "a"), None);
+
+    // Too big to ever fit:
+    cache.insert("d".into(), page(&"x".repeat(20)));
+    assert_eq!(cached_text(&mut cache, "d"), None);
+    assert_eq!(cache.entries.len(), 2);
+
+    cache.set_limits(1, 20);
+    assert_eq!(cache.entries.len(), 1);
+    assert_eq!(cached_text(&mut cache, "c"), Some("1234".into()));
+}
+
+#[test]
+fn only_caches_what_it_may() {
+    let mut cache = Cache::default();
+    let mut failed = page("Oops");
+    failed.status = Status::HttpStatus { code: 500 };
+    cache.insert("failed".into(), failed);
+
+    for header in ["no-store", "private, max-age=60"] {
+        let mut secret = page("Secret");
+        secret.cache_control = Some(CacheControl::parse(header));
+        cache.insert("secret".into(), secret);
+    }
+    assert_eq!(cache.entries.len(), 0);
+
+    // Stored, since it may be, but never fresh enough to use:
+    let mut stale = page("Stale");
+    stale.cache_control = Some(CacheControl::parse("max-age=0"));
+    cache.insert("stale".into(), stale);
+    assert_eq!(cache.entries.len(), 1);
+
+    assert_eq!(cached_text(&mut cache, "failed"), None);
+    assert_eq!(cached_text(&mut cache, "secret"), None);
+    assert_eq!(cached_text(&mut cache, "stale"), None);
+    assert_eq!(cache.entries.len(), 0);
+
+    cache.insert("page".into(), page("Page"));
+    cache.remove("page");
+    assert_eq!(cached_text(&mut cache, "page"), None);
+}
+
+#[test]
+fn equivalent_urls_share_entries() {
+    let mut cache = Cache::new(10, 1000);
+    cache.insert("gemini://Example.com:1965/a/../page#top".into(), page("Page"));
+    assert_eq!(cached_text(&mut cache, "gemini://example.com/page"), Some("Page".into()));
+
+    cache.remove("gemini://EXAMPLE.com/page");
+    assert_eq!(cache.entries.len(), 0);
+}
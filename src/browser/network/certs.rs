@@ -0,0 +1,141 @@
+//! Client certificates, for Gemini capsules that use them to identify users.
+//! See: https://geminiprotocol.net/docs/protocol-specification.gmi#client-certificates
+//!
+//! Each certificate is a PEM `<name>.crt` and `<name>.key` pair in the certificate directory.
+//! [`ClientCertMapping`]s (in [`Settings`](crate::browser::settings::Settings)) pick which one to
+//! present to which capsule.
+
+use std::{fs, io, path::{Path, PathBuf}, process::Command};
+
+use rustls::{Certificate, PrivateKey};
+use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use url::Url;
+
+use crate::browser::{network::{known_hosts::KnownHosts, Error, Result}, settings::ClientCertMapping};
+
+mod certs_test;
+
+/// The certificates available to a loader, and where to use them.
+#[derive(Debug, Default, Clone)]
+pub struct ClientCerts {
+    dir: Option<PathBuf>,
+    mappings: Vec<ClientCertMapping>,
+}
+
+impl ClientCerts {
+    pub fn new(dir: Option<PathBuf>, mappings: Vec<ClientCertMapping>) -> Self {
+        Self { dir, mappings }
+    }
+
+    /// The certificate to present when requesting `url`. The longest matching prefix wins.
+    pub fn for_url(&self, url: &Url) -> Option<ClientCert> {
+        let dir = self.dir.as_ref()?;
+        let mapping = self.mappings.iter()
+            .filter(|it| it.matches(url))
+            .max_by_key(|it| it.prefix.trim().len())?;
+        Some(ClientCert::new(dir, mapping.name.trim()))
+    }
+
+    /// Names of the certificates in our directory that have both a `.crt` and a `.key`.
+    pub fn available(&self) -> Vec<String> {
+        let Some(entries) = self.dir.as_ref().and_then(|it| fs::read_dir(it).ok()) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|it| it.ok())
+            .map(|it| it.path())
+            .filter(|it| it.extension().is_some_and(|ext| ext == "crt") && it.with_extension("key").is_file())
+            .filter_map(|it| it.file_stem()?.to_str().map(String::from))
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Generate a new self-signed certificate with `openssl`, named after `name`.
+    /// Returns the name it was saved under, which is unique within our directory.
+    pub fn generate(&self, name: &str) -> Result<String> {
+        let Some(dir) = &self.dir else {
+            return Err(Error::ClientCert { name: name.into(), message: "No certificate directory is configured.".into() });
+        };
+        fs::create_dir_all(dir)?;
+
+        let base: String = name.chars()
+            .map(|it| if it.is_ascii_alphanumeric() || it == '.' || it == '-' { it } else { '-' })
+            .collect();
+        let name = (1..)
+            .map(|n| if n == 1 { base.clone() } else { format!("{base}-{n}") })
+            .find(|it| !ClientCert::new(dir, it).cert.exists())
+            .expect("some unused name");
+        let cert = ClientCert::new(dir, &name);
+
+        // Gemini certs are self-signed, and servers identify you by their fingerprint, so they
+        // should last a long time.
+        let output = Command::new("openssl")
+            .args(["req", "-x509", "-newkey", "ec", "-pkeyopt", "ec_paramgen_curve:prime256v1", "-nodes", "-days", "3650"])
+            .arg("-subj").arg(format!("/CN={name}"))
+            .arg("-keyout").arg(&cert.key)
+            .arg("-out").arg(&cert.cert)
+            .output()
+            .map_err(|err| cert.error(format!("Couldn't run openssl: {err}")))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(cert.error(format!("openssl failed: {}", stderr.trim())));
+        }
+        Ok(name)
+    }
+}
+
+/// A PEM certificate and private key pair.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientCert {
+    pub name: String,
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl ClientCert {
+    fn new(dir: &Path, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            cert: dir.join(format!("{name}.crt")),
+            key: dir.join(format!("{name}.key")),
+        }
+    }
+
+    /// The server certificates this was sent to. Shared by all certificates in the same directory.
+    pub fn known_hosts(&self) -> KnownHosts {
+        KnownHosts::new(self.cert.with_file_name("known_hosts"))
+    }
+
+    /// Read the certificate chain and key, in the form rustls wants them.
+    pub fn load(&self) -> Result<(Vec<Certificate>, PrivateKey)> {
+        let chain = CertificateDer::pem_file_iter(&self.cert)
+            .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+            .map_err(|err| self.pem_error(&self.cert, err))?;
+        if chain.is_empty() {
+            return Err(self.error(format!("No certificates in {}", self.cert.display())));
+        }
+        let key = PrivateKeyDer::from_pem_file(&self.key)
+            .map_err(|err| self.pem_error(&self.key, err))?;
+
+        let chain = chain.into_iter().map(|it| Certificate(it.to_vec())).collect();
+        Ok((chain, PrivateKey(key.secret_der().to_vec())))
+    }
+
+    fn pem_error(&self, path: &Path, err: rustls_pki_types::pem::Error) -> Error {
+        match err {
+            rustls_pki_types::pem::Error::Io(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.error(format!("{} does not exist", path.display()))
+            },
+            err => self.error(format!("Error reading {}: {err}", path.display())),
+        }
+    }
+
+    pub(super) fn error(&self, message: String) -> Error {
+        Error::ClientCert { name: self.name.clone(), message }
+    }
+}
@@ -0,0 +1,48 @@
+#![cfg(test)]
+
+use url::Url;
+
+use crate::browser::{network::certs::ClientCerts, settings::ClientCertMapping};
+
+fn mapping(prefix: &str, name: &str) -> ClientCertMapping {
+    ClientCertMapping { prefix: prefix.into(), name: name.into() }
+}
+
+fn cert_for(certs: &ClientCerts, url: &str) -> Option<String> {
+    certs.for_url(&Url::parse(url).unwrap()).map(|it| it.name)
+}
+
+#[test]
+fn longest_prefix_wins() {
+    let certs = ClientCerts::new(Some("/certs".into()), vec![
+        mapping("example.com", "main"),
+        mapping("gemini://example.com/admin/", "admin"),
+    ]);
+    assert_eq!(cert_for(&certs, "gemini://example.com/"), Some("main".into()));
+    assert_eq!(cert_for(&certs, "gemini://EXAMPLE.com/admin/users"), Some("admin".into()));
+    assert_eq!(cert_for(&certs, "gemini://example.com/administrator"), Some("main".into()));
+}
+
+#[test]
+fn certs_stay_on_their_capsule() {
+    let certs = ClientCerts::new(Some("/certs".into()), vec![
+        mapping("example.com/", "main"),
+        mapping("", "empty"),
+    ]);
+    assert_eq!(cert_for(&certs, "gemini://www.example.com/"), None);
+    assert_eq!(cert_for(&certs, "gemini://example.org/"), None);
+    assert_eq!(cert_for(&certs, "https://example.com/"), None);
+}
+
+#[test]
+fn available_needs_cert_and_key() {
+    let dir = std::env::temp_dir().join(format!("egemi-certs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for file in ["b.crt", "b.key", "a.crt", "a.key", "no-key.crt", "notes.txt"] {
+        std::fs::write(dir.join(file), "").unwrap();
+    }
+
+    let certs = ClientCerts::new(Some(dir), Vec::new());
+    assert_eq!(certs.available(), vec!["a", "b"]);
+}
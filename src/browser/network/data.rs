@@ -0,0 +1,59 @@
+//! Decodes `data:` URLs, which embed their content in the URL itself. No network required.
+//! See: https://developer.mozilla.org/en-US/docs/Web/URI/Reference/Schemes/data
+
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use mime::Mime;
+use percent_encoding::percent_decode_str;
+
+use crate::browser::network::{is_binary, Body, Error, LoadedResource, Result, SCow, Status};
+
+mod data_test;
+
+/// The content type of a `data:` URL that doesn't specify one.
+const DEFAULT_TYPE: &str = "text/plain;charset=US-ASCII";
+
+pub fn load(url: SCow) -> Result<LoadedResource> {
+    let invalid = || Error::InvalidUrl(url.clone());
+    let rest = url.strip_prefix("data:").ok_or_else(invalid)?;
+    let (header, data) = rest.split_once(',').ok_or_else(invalid)?;
+
+    let header = header.trim();
+    let (media_type, is_base64) = match header.strip_suffix(";base64") {
+        Some(media_type) => (media_type, true),
+        None => (header, false),
+    };
+    let media_type = percent_decode_str(media_type).decode_utf8_lossy();
+    let content_type: Mime = match media_type.trim() {
+        "" => DEFAULT_TYPE.parse()?,
+        // Only parameters, like `;charset=utf-8`:
+        params if params.starts_with(';') => format!("text/plain{params}").parse()?,
+        media_type => media_type.parse()?,
+    };
+
+    let bytes: Vec<u8> = percent_decode_str(data).collect();
+    let bytes = if is_base64 {
+        // Whitespace is allowed within base64 data, ex: from line wrapping.
+        let encoded: Vec<u8> = bytes.into_iter().filter(|it| !it.is_ascii_whitespace()).collect();
+        STANDARD.decode(encoded).map_err(|err| Error::Unknown(format!("Invalid base64 in data: URL: {err}")))?
+    } else {
+        bytes
+    };
+
+    let length = bytes.len() as u64;
+    let body = if is_binary(Some(&content_type)) {
+        Body::Bytes(bytes.into())
+    } else {
+        Body::Text(String::from_utf8_lossy(&bytes).into_owned().into())
+    };
+    Ok(LoadedResource {
+        url,
+        status: Status::HttpStatus { code: 200 },
+        length: Some(length),
+        content_type: Some(Arc::new(content_type)),
+        cache_control: None,
+        timings: None,
+        body,
+    })
+}
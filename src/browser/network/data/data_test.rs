@@ -0,0 +1,36 @@
+#![cfg(test)]
+
+use crate::browser::network::{data::load, Body, Error};
+use pretty_assertions::assert_eq;
+
+fn text(url: &str) -> (String, String) {
+    let loaded = load(url.to_string().into()).unwrap();
+    let Body::Text(body) = loaded.body else {
+        panic!("Expected text for {url}");
+    };
+    (loaded.content_type.unwrap().to_string(), body.into_owned())
+}
+
+#[test]
+fn percent_encoded() {
+    assert_eq!(text("data:,Hello%2C%20World%21"), ("text/plain;charset=us-ascii".into(), "Hello, World!".into()));
+    assert_eq!(text("data:text/gemini,%23 Title"), ("text/gemini".into(), "# Title".into()));
+    assert_eq!(text("data:;charset=utf-8,caf%C3%A9").1, "café");
+    // Types we render as documents are text, too:
+    assert_eq!(text("data:application/rss+xml,%3Crss%2F%3E").1, "<rss/>");
+    assert_eq!(text("data:application/json,%7B%7D").1, "{}");
+}
+
+#[test]
+fn base64() {
+    assert_eq!(text("data:text/plain;base64,SGVsbG8s IFdvcmxkIQ=="), ("text/plain".into(), "Hello, World!".into()));
+
+    let loaded = load("data:image/png;base64,iVBORw0K".into()).unwrap();
+    assert!(matches!(loaded.body, Body::Bytes(bytes) if bytes.starts_with(b"\x89PNG")));
+}
+
+#[test]
+fn invalid() {
+    assert!(matches!(load("data:text/plain".into()), Err(Error::InvalidUrl(_))));
+    assert!(matches!(load("data:;base64,!!!".into()), Err(Error::Unknown(_))));
+}
@@ -0,0 +1,114 @@
+//! Looks up host names for HTTP(S) and Gemini connections: with the system's resolver, or privately with DNS over
+//! HTTPS or TLS. User-defined host overrides (like a hosts file) take precedence over either.
+
+use std::{collections::HashMap, fmt::Debug, io, net::{IpAddr, SocketAddr}, sync::Arc};
+
+use hickory_resolver::{config::{ResolverConfig, ResolverOpts}, TokioAsyncResolver};
+use log::warn;
+use tokio::net::TcpStream;
+
+use crate::browser::settings::DnsResolver;
+
+mod dns_test;
+
+#[derive(Default, Clone)]
+pub struct Dns {
+    /// Host (lowercase) to the address to use instead of looking it up.
+    overrides: Arc<HashMap<String, IpAddr>>,
+
+    /// None uses the system's resolver.
+    resolver: Option<Arc<TokioAsyncResolver>>,
+}
+
+impl Debug for Dns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dns")
+            .field("overrides", &self.overrides)
+            .field("private", &self.resolver.is_some())
+            .finish()
+    }
+}
+
+impl Dns {
+    /// `overrides` are lines like in a hosts file: `<ip> <host> [<host>...]`. `#` starts a comment.
+    pub fn new(resolver: DnsResolver, overrides: &[String]) -> Self {
+        let config = match resolver {
+            DnsResolver::System => None,
+            DnsResolver::CloudflareHttps => Some(ResolverConfig::cloudflare_https()),
+            DnsResolver::CloudflareTls => Some(ResolverConfig::cloudflare_tls()),
+            DnsResolver::GoogleHttps => Some(ResolverConfig::google_https()),
+            DnsResolver::GoogleTls => Some(ResolverConfig::google_tls()),
+            DnsResolver::Quad9Https => Some(ResolverConfig::quad9_https()),
+            DnsResolver::Quad9Tls => Some(ResolverConfig::quad9_tls()),
+        };
+        Self {
+            overrides: Arc::new(parse_overrides(overrides)),
+            resolver: config.map(|it| Arc::new(TokioAsyncResolver::tokio(it, ResolverOpts::default()))),
+        }
+    }
+
+    /// Whether this looks up hosts just like the system would. (So libraries can do it themselves.)
+    pub fn is_system(&self) -> bool {
+        self.overrides.is_empty() && self.resolver.is_none()
+    }
+
+    pub async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        // URLs put brackets around IPv6 addresses:
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if let Some(ip) = self.overrides.get(&host.to_ascii_lowercase()) {
+            return Ok(vec![SocketAddr::new(*ip, port)]);
+        }
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        let Some(resolver) = &self.resolver else {
+            return Ok(tokio::net::lookup_host((host, port)).await?.collect());
+        };
+        let ips = resolver.lookup_ip(host).await.map_err(io::Error::other)?;
+        Ok(ips.iter().map(|ip| SocketAddr::new(ip, port)).collect())
+    }
+
+}
+
+/// Connects to the first of `host`'s addresses (from [`Dns::lookup`]) that accepts.
+pub async fn connect(host: &str, addrs: Vec<SocketAddr>) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in addrs {
+        match TcpStream::connect(addr).await {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No addresses found for {host}"))))
+}
+
+impl reqwest::dns::Resolve for Dns {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let dns = self.clone();
+        Box::pin(async move {
+            // reqwest replaces the port with the URL's:
+            let addrs = dns.lookup(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Invalid lines are skipped.
+pub fn parse_overrides(lines: &[String]) -> HashMap<String, IpAddr> {
+    let mut overrides = HashMap::new();
+    for line in lines {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(ip) = words.next() else {
+            continue;
+        };
+        let Ok(ip) = ip.parse::<IpAddr>() else {
+            warn!("Skipping host override with invalid IP address: {line:?}");
+            continue;
+        };
+        for host in words {
+            overrides.insert(host.to_ascii_lowercase(), ip);
+        }
+    }
+    overrides
+}
@@ -0,0 +1,30 @@
+#![cfg(test)]
+
+use std::net::{IpAddr, SocketAddr};
+
+use pretty_assertions::assert_eq;
+
+use crate::browser::{network::{dns::{parse_overrides, Dns}, rt}, settings::DnsResolver};
+
+#[test]
+fn overrides() {
+    let lines = [
+        "# Staging",
+        "203.0.113.5  staging.example.com  Beta.Example.com # both",
+        "::1 local.test",
+        "not-an-ip example.org",
+    ].map(String::from);
+    let overrides = parse_overrides(&lines);
+    assert_eq!(overrides.len(), 3);
+    assert_eq!(overrides["beta.example.com"], "203.0.113.5".parse::<IpAddr>().unwrap());
+    assert!(!overrides.contains_key("example.org"));
+
+    let dns = Dns::new(DnsResolver::System, &lines);
+    assert!(!dns.is_system());
+    let addrs = rt().block_on(dns.lookup("STAGING.example.com", 1965)).unwrap();
+    assert_eq!(addrs, vec!["203.0.113.5:1965".parse::<SocketAddr>().unwrap()]);
+    let addrs = rt().block_on(dns.lookup("[::1]", 80)).unwrap();
+    assert_eq!(addrs, vec!["[::1]:80".parse::<SocketAddr>().unwrap()]);
+
+    assert!(Dns::new(DnsResolver::System, &[]).is_system());
+}
@@ -2,18 +2,31 @@
 //! This loader loads files from disk. 
 //! If the path is a directory, it will return a gemtext directory listing.
 
-use std::{io::ErrorKind, path::PathBuf, sync::Arc};
+use std::{io::ErrorKind, path::{Path, PathBuf}, sync::Arc};
 
 use tokio::task::JoinHandle;
 use url::Url;
 
-use crate::browser::network::{rt, text_gemini, Body, Error, LoadedResource, Result, Status};
+use crate::browser::{network::{check_size, is_binary, read_capped, rt, text_gemini, Body, Error, LoadedResource, Result, Status}};
+
+mod file_test;
+
+/// Max size of a file we'll load, in bytes.
+const MAX_SIZE: u64 = 1024 * 1024 * 30; // 30 MiB
 
 #[derive(Debug, Default)]
-pub struct FileLoader;
+pub struct FileLoader {
+    /// "Sandbox mode": If set, only files inside this directory may be loaded.
+    root: Option<PathBuf>,
+}
 
 
 impl FileLoader {
+    pub fn with_root(mut self, root: Option<PathBuf>) -> Self {
+        self.root = root;
+        self
+    }
+
     pub fn fetch(self: &Arc<Self>, url: Url) -> JoinHandle<Result<LoadedResource>> {
         let fut = self.clone()._fetch(url);
         let rt = rt();
@@ -24,9 +37,16 @@ impl FileLoader {
         if url.scheme() != "file" {
             return Err(Error::InvalidUrl(String::from(url).into()));
         }
-        let Ok(path) = url.to_file_path() else {
+        let Ok(mut path) = url.to_file_path() else {
             return Err(Error::InvalidUrl(String::from(url).into()));
         };
+        if let Some(root) = &self.root {
+            // Read the path we checked, so that a symlink can't be swapped in between checking and reading:
+            path = match within_root(&path, root).await {
+                Err(Error::IoError(err)) if err.kind() == ErrorKind::NotFound => return Ok(not_found(url)),
+                result => result?,
+            };
+        }
 
         let stat = tokio::fs::metadata(&path).await;
         use ErrorKind::*;
@@ -43,13 +63,9 @@ impl FileLoader {
             return gemtext_dir_list(url, path).await;
         }
 
-        let mebibyte: u64 = 1024 * 1024;
-
         if stat.is_file() {
-            let bytes = stat.len();
-            if bytes > 30 * mebibyte {
-                return Err(Error::Unknown(format!("File too large: {bytes} bytes")));
-            }
+            // Fail fast, but the file could grow before we read it. load_file() checks again:
+            check_size(stat.len(), MAX_SIZE)?;
             return load_file(url, path).await;
         }
 
@@ -59,28 +75,45 @@ impl FileLoader {
     }
 }
 
+/// Make sure that `path` is inside `root`, after resolving any `..` and symlinks.
+/// Returns the canonical path.
+async fn within_root(path: &Path, root: &Path) -> Result<PathBuf> {
+    let root = tokio::fs::canonicalize(root).await?;
+    let canonical = tokio::fs::canonicalize(path).await?;
+    // Note: Path::starts_with compares whole components, so `/docs2` is not inside `/docs`.
+    if !canonical.starts_with(&root) {
+        return Err(Error::OutsideRoot { path: path.into(), root });
+    }
+    Ok(canonical)
+}
+
 async fn load_file(url: Url, path: PathBuf) -> std::result::Result<LoadedResource, Error> {
     let content_type = mime_guess::from_path(&path).first();
     let Some(content_type) = content_type else {
         return Err(Error::MissingContentType);
     };
 
-    if content_type.type_() != "text" {
-        return Err(Error::UnsupportedContentType(content_type))
+    let mut bytes = Vec::new();
+    read_capped(tokio::fs::File::open(path).await?, MAX_SIZE, &mut bytes).await?;
+    let body = if is_binary(Some(&content_type)) {
+        Body::Bytes(bytes.into())
+    } else {
+        let text = String::from_utf8(bytes).map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))?;
+        Body::Text(text.into())
     };
 
-    let text = tokio::fs::read_to_string(path).await?;
-
     Ok(LoadedResource {
-        body: Body::Text(text.into()),
+        body,
         content_type: Some(content_type.into()),
         length: None,
+        cache_control: None,
+        timings: None,
         status: FileStatus::Ok.into(),
         url: String::from(url).into(),
     })
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum FileStatus {
     // File found, all good:
     Ok,
@@ -142,6 +175,8 @@ async fn gemtext_dir_list(url: Url, path: PathBuf) -> Result<LoadedResource> {
         body: Body::Text(out.into()),
         content_type: Some(text_gemini()),
         length: None,
+        cache_control: None,
+        timings: None,
         status: FileStatus::Ok.into(),
         url: String::from(url).into(),
     };
@@ -155,6 +190,8 @@ fn not_found(url: Url) -> LoadedResource {
         body: Body::Text("No such file".into()),
         content_type: Some(mime::TEXT_PLAIN.into()),
         length: None,
+        cache_control: None,
+        timings: None,
         status: FileStatus::NotFound.into(),
         url: String::from(url).into()
     }
@@ -175,6 +212,8 @@ fn dir_needs_slash(url: Url) -> Result<LoadedResource> {
         body: Body::Text(out.into()),
         content_type: Some(text_gemini()),
         length: None,
+        cache_control: None,
+        timings: None,
         status: FileStatus::DirNeedsSlash.into(),
         url: String::from(url).into()
     })
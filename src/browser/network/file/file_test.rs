@@ -0,0 +1,94 @@
+#![cfg(test)]
+
+use std::{path::PathBuf, sync::Arc};
+
+use url::Url;
+
+use crate::browser::network::{file::FileLoader, rt, Body, Error, LoadedResource, Result};
+
+/// A fresh directory containing `root/inside.gmi` and `outside.gmi`.
+fn sandbox(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("egemi-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("root")).unwrap();
+    std::fs::write(dir.join("root/inside.gmi"), "# Inside\n").unwrap();
+    std::fs::write(dir.join("outside.gmi"), "# Secret\n").unwrap();
+    dir
+}
+
+fn fetch(loader: &Arc<FileLoader>, path: PathBuf) -> Result<LoadedResource> {
+    let url = Url::from_file_path(path).unwrap();
+    rt().block_on(loader.fetch(url)).unwrap()
+}
+
+#[test]
+fn root_allows_files_inside() {
+    let dir = sandbox("root-allows");
+    let loader = Arc::new(FileLoader::default().with_root(Some(dir.join("root"))));
+
+    let loaded = fetch(&loader, dir.join("root/inside.gmi")).unwrap();
+    assert!(matches!(loaded.body, Body::Text(text) if text == "# Inside\n"));
+
+    // Symlinks that stay inside are read through their target, which is what was checked:
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(dir.join("root/inside.gmi"), dir.join("root/link.gmi")).unwrap();
+        let loaded = fetch(&loader, dir.join("root/link.gmi")).unwrap();
+        assert!(matches!(loaded.body, Body::Text(text) if text == "# Inside\n"));
+        assert_eq!(loaded.url, Url::from_file_path(dir.join("root/link.gmi")).unwrap().as_str());
+    }
+}
+
+#[test]
+fn root_blocks_traversal() {
+    let dir = sandbox("root-blocks");
+    let loader = Arc::new(FileLoader::default().with_root(Some(dir.join("root"))));
+
+    // `..` in the path:
+    let result = fetch(&loader, dir.join("root/../outside.gmi"));
+    assert!(matches!(result, Err(Error::OutsideRoot{..})), "{result:?}");
+
+    // Absolute paths elsewhere:
+    let result = fetch(&loader, dir.join("outside.gmi"));
+    assert!(matches!(result, Err(Error::OutsideRoot{..})), "{result:?}");
+
+    // A sibling that merely shares a prefix:
+    std::fs::create_dir_all(dir.join("root2")).unwrap();
+    std::fs::write(dir.join("root2/other.gmi"), "# Other\n").unwrap();
+    let result = fetch(&loader, dir.join("root2/other.gmi"));
+    assert!(matches!(result, Err(Error::OutsideRoot{..})), "{result:?}");
+
+    // Symlinks that escape:
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(dir.join("outside.gmi"), dir.join("root/link.gmi")).unwrap();
+        let result = fetch(&loader, dir.join("root/link.gmi"));
+        assert!(matches!(result, Err(Error::OutsideRoot{..})), "{result:?}");
+    }
+
+    // Without a root, anything goes:
+    let loader = Arc::new(FileLoader::default());
+    assert!(fetch(&loader, dir.join("outside.gmi")).is_ok());
+}
+
+/// Local files should get the content type that picks the right document widget.
+#[test]
+fn local_content_types() {
+    let dir = sandbox("content-types");
+    let loader = Arc::new(FileLoader::default());
+    let cases = [
+        ("page.gmi", "text/gemini"),
+        ("page.md", "text/markdown"),
+        ("page.markdown", "text/markdown"),
+        ("page.html", "text/html"),
+        ("page.htm", "text/html"),
+        ("page.txt", "text/plain"),
+    ];
+    for (name, expected) in cases {
+        std::fs::write(dir.join(name), "# Hello\n").unwrap();
+        let loaded = fetch(&loader, dir.join(name)).unwrap();
+        let content_type = loaded.content_type.as_ref().map(|it| it.essence_str().to_string());
+        assert_eq!(content_type.as_deref(), Some(expected), "{name}");
+        assert!(matches!(loaded.body, Body::Text(_)), "{name}");
+    }
+}
@@ -1,46 +1,442 @@
-use std::sync::Arc;
+use std::{collections::HashMap, io, sync::{Arc, Mutex}, time::{Duration, Instant, SystemTime}};
+
+use log::debug;
 
 use mime::Mime;
-use tokio::task::JoinHandle;
-use germ::request::non_blocking::request as germ_request;
+use rustls::{client::{ServerCertVerified, ServerCertVerifier}, Certificate, ServerName};
+use tokio::{io::{AsyncRead, AsyncReadExt as _, AsyncWriteExt}, net::TcpStream, task::JoinHandle};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+use url::Url;
 
-use crate::browser::network::{rt, Body};
+use crate::browser::network::{blocklist::Blocklist, certs::{ClientCert, ClientCerts}, check_size, dns::{self, Dns}, is_binary, known_hosts::{KnownHosts, Trust}, rt, Body, Chunk, Chunks, SCow, Timings, MAX_REDIRECTS};
 
 use super::{LoadedResource, Result, Error};
 
+mod gemini_test;
+
+
 
+/// Max size of a response body, in bytes.
+const MAX_SIZE: u64 = 1024 * 1024 * 100; // 100 MiB
 
 #[derive(Default, Debug)]
 pub struct GeminiLoader {
+    certs: ClientCerts,
 
+    /// How many times to retry connection errors.
+    retries: u32,
+    /// How long to wait before the first retry. Doubles after each one.
+    backoff: Duration,
+    retrying: Retrying,
+
+    /// Redirects to blocked URLs are refused.
+    blocklist: Blocklist,
+
+    /// Overrides or a private resolver.
+    dns: Dns,
 }
 
 impl GeminiLoader {
+    pub fn with_certs(mut self, certs: ClientCerts) -> Self {
+        self.certs = certs;
+        self
+    }
+
+    /// Retry connection errors up to `retries` times, and report when we do to `retrying`.
+    pub fn with_retries(mut self, retries: u32, backoff: Duration, retrying: Retrying) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self.retrying = retrying;
+        self
+    }
+
+    pub fn with_blocklist(mut self, blocklist: Blocklist) -> Self {
+        self.blocklist = blocklist;
+        self
+    }
+
+    pub fn with_dns(mut self, dns: Dns) -> Self {
+        self.dns = dns;
+        self
+    }
+
     pub fn fetch(self: &Arc<Self>, url: url::Url) -> JoinHandle<Result<LoadedResource>> {
-        rt().spawn(self.clone()._fetch(url))
+        rt().spawn(self.clone()._fetch(url, None))
+    }
+
+    /// Like [`Self::fetch`], but also sends the successful response to `chunks` as it arrives.
+    pub fn fetch_streaming(self: &Arc<Self>, url: url::Url, chunks: Option<Chunks>) -> JoinHandle<Result<LoadedResource>> {
+        rt().spawn(self.clone()._fetch(url, chunks))
+    }
+
+    async fn _fetch(self: Arc<Self>, mut url: url::Url, chunks: Option<Chunks>) -> Result<LoadedResource> {
+        let start = Instant::now();
+        let mut hops: Vec<SCow> = Vec::new();
+        let response = loop {
+            let response = self.request_retrying(&url, chunks.as_ref()).await?;
+            if !matches!(response.status, 30 | 31) {
+                break response;
+            }
+
+            // The meta is the new URL, which may be relative:
+            let meta = response.meta.trim();
+            let Ok(destination) = url.join(meta) else {
+                return Err(Error::InvalidUrl(meta.to_string().into()));
+            };
+            if destination.scheme() != "gemini" {
+                // Don't silently leave Geminispace. Let the user decide:
+                return Err(Error::Redirect {
+                    destination: destination.into(),
+                    temporary: response.status == 30,
+                });
+            }
+            self.blocklist.check(&destination)?;
+            hops.push(url.to_string().into());
+            if hops.len() > MAX_REDIRECTS {
+                return Err(Error::TooManyRedirects { url: destination.to_string().into(), hops });
+            }
+            debug!("Following redirect from {url} to {destination}");
+            url = destination;
+        };
+        let timings = response.timings(start);
+        debug!("Loaded {url} ({timings})");
+        response.loaded(&url, timings)
+    }
+
+    /// Just the first `max` bytes of `url`'s response, without a client certificate. See [`super::MultiLoader::fetch_preview`].
+    pub fn fetch_preview(self: &Arc<Self>, url: url::Url, max: u64) -> JoinHandle<Result<LoadedResource>> {
+        rt().spawn(self.clone()._fetch_preview(url, max))
+    }
+
+    async fn _fetch_preview(self: Arc<Self>, url: url::Url, max: u64) -> Result<LoadedResource> {
+        let start = Instant::now();
+        let (mut tls, connected) = connect(&url, None, &self.dns).await?;
+        tls.write_all(format!("{url}\r\n").as_bytes()).await?;
+        let mut data = Vec::new();
+        match (&mut tls).take(max).read_to_end(&mut data).await {
+            Ok(_) => {},
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && !data.is_empty() => {},
+            Err(err) => Err(err)?,
+        }
+        let timings = Timings { total: start.elapsed(), ..connected };
+        Response::parse(&data)?.loaded(&url, timings)
+    }
+
+    /// Like [`Self::request`], but retries connection errors with exponential backoff.
+    /// Aborting the task cancels any retries, too.
+    async fn request_retrying(&self, url: &Url, chunks: Option<&Chunks>) -> Result<Response> {
+        let _clear = self.retrying.clear_on_drop(url);
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            match self.request(url, chunks).await {
+                Err(err) if attempt < self.retries && is_transient(&err) => {
+                    attempt += 1;
+                    debug!("Retrying {url} in {delay:?} (attempt {attempt}): {err}");
+                    self.retrying.set(url, attempt);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn request(&self, url: &Url, chunks: Option<&Chunks>) -> Result<Response> {
+        let cert = self.certs.for_url(url);
+        if let Some(cert) = &cert {
+            debug!("Using client certificate {} for {url}", cert.name);
+        }
+        let response = request_direct(url, cert.as_ref(), &self.dns, &[], chunks).await?;
+        if response.status == 20 && response.body.is_empty() {
+            debug!("Empty body from {url}: meta {:?}", response.meta);
+        }
+        Ok(response)
+    }
+}
+
+/// Connection errors may succeed if we try again. Other errors (ex: bad certificates, or malformed responses) won't.
+fn is_transient(err: &Error) -> bool {
+    matches!(err, Error::Connection(_) | Error::IoError(_))
+}
+
+/// Which in-flight requests are waiting to retry, and on which attempt.
+#[derive(Default, Debug, Clone)]
+pub struct Retrying(Arc<Mutex<HashMap<String, u32>>>);
+
+impl Retrying {
+    pub fn attempt(&self, url: &str) -> Option<u32> {
+        self.lock().get(url).copied()
+    }
+
+    fn set(&self, url: &Url, attempt: u32) {
+        self.lock().insert(url.to_string(), attempt);
+    }
+
+    /// Forget about `url` when the returned guard is dropped. (Including when the request is aborted.)
+    fn clear_on_drop(&self, url: &Url) -> ClearOnDrop {
+        ClearOnDrop { retrying: self.clone(), url: url.to_string() }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, u32>> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+struct ClearOnDrop {
+    retrying: Retrying,
+    url: String,
+}
+
+impl Drop for ClearOnDrop {
+    fn drop(&mut self) {
+        self.retrying.lock().remove(&self.url);
+    }
+}
+
+/// A complete Gemini response.
+#[derive(Debug)]
+pub(super) struct Response {
+    pub status: u8,
+    pub meta: String,
+    body: Vec<u8>,
+
+    /// When we finished reading the header.
+    header_read: Option<Instant>,
+
+    /// How long connecting took. See [`connect`].
+    connected: Timings,
+}
+
+impl Response {
+    /// Parse a response header (`<status> <meta>\r\n`) and body.
+    fn parse(data: &[u8]) -> Result<Self> {
+        let header_end = data.iter().position(|it| *it == b'\n')
+            .ok_or_else(|| Error::Unknown("Missing Gemini response header".into()))?;
+        let header = String::from_utf8_lossy(&data[..header_end]);
+        let header = header.trim_end_matches('\r');
+        let (status, meta) = header.split_once(' ').unwrap_or((header, ""));
+        let status = status.parse()
+            .map_err(|_| Error::Unknown(format!("Invalid Gemini response header: {header}")))?;
+        Ok(Self {
+            status,
+            meta: meta.trim().to_string(),
+            body: data[header_end + 1..].to_vec(),
+            header_read: None,
+            connected: Timings::default(),
+        })
+    }
+
+    /// How long this response took, for a request that started at `start`. (Which may have been redirected here.)
+    pub(super) fn timings(&self, start: Instant) -> Timings {
+        Timings {
+            first_byte: self.header_read.map(|it| it.duration_since(start)),
+            total: start.elapsed(),
+            ..self.connected
+        }
     }
 
-    async fn _fetch(self: Arc<Self>, url: url::Url) -> Result<LoadedResource> {
-        let response = match germ_request(&url).await {
-            Ok(ok) => ok,
-            Err(err) => Err(Error::Unknown(format!("{err:#?}")))?
+    /// A non-redirect response, from `url`, as a resource we can show.
+    pub(super) fn loaded(self, url: &Url, timings: Timings) -> Result<LoadedResource> {
+        // For these, the meta is a prompt or message, not a content type:
+        let special = match self.status {
+            10 | 11 => Some(super::Status::GeminiInput {
+                sensitive: self.status == 11,
+                prompt: self.meta.clone(),
+            }),
+            code @ 60..=69 => Some(super::Status::GeminiCertRequired { code, message: self.meta.clone() }),
+            _ => None,
         };
+        if let Some(status) = special {
+            return Ok(LoadedResource {
+                status,
+                body: Body::Text("".into()),
+                content_type: None,
+                length: None,
+                cache_control: None,
+                timings: Some(timings),
+                url: url.to_string().into(),
+            });
+        }
 
         let status = super::Status::HttpStatus {
-            code: if *response.status() == germ::request::Status::Success {
+            code: if self.status == 20 {
                 200
             } else { 500 } // TODO: better mapping here.
         };
 
-        let ctype: Mime = response.meta().parse()?;
+        let ctype: Mime = self.meta.parse()?;
+
+        let length = self.body.len() as u64;
+        let body = if is_binary(Some(&ctype)) {
+            Body::Bytes(self.body.into())
+        } else {
+            Body::Text(String::from_utf8_lossy(&self.body).into_owned().into())
+        };
 
         Ok(LoadedResource {
             status,
-            body: Body::Text(response.content().unwrap_or_else(String::new).into()),
+            body,
             content_type: Some(Arc::new(ctype)),
-            length: Some(*response.size() as u64),
+            length: Some(length),
+            cache_control: None,
+            timings: Some(timings),
             url: url.to_string().into()
         })
     }
+}
+
+/// Sends a request to `url`, and reads its response, up to [`MAX_SIZE`] bytes.
+/// `body` is sent after the request line. Only Titan uploads have one.
+pub(super) async fn request_direct(url: &Url, cert: Option<&ClientCert>, dns: &Dns, body: &[u8], chunks: Option<&Chunks>) -> Result<Response> {
+    let (mut tls, connected) = connect(url, cert, dns).await?;
+    tls.write_all(format!("{url}\r\n").as_bytes()).await?;
+    tls.write_all(body).await?;
+
+    let mut data = Vec::new();
+    let mut header_read = None;
+    match read_response(&mut tls, url, chunks, &mut data, &mut header_read).await {
+        Ok(()) => {},
+        // Many servers just close the connection without a TLS close_notify:
+        Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof && !data.is_empty() => {},
+        Err(err) => Err(err)?,
+    }
+    Ok(Response { header_read, connected, ..Response::parse(&data)? })
+}
+
+/// A TLS connection to `url`'s server, identifying ourselves with `cert`, if given.
+/// Also returns how long the DNS, connect, and TLS steps took.
+async fn connect(url: &Url, cert: Option<&ClientCert>, dns: &Dns) -> Result<(TlsStream<TcpStream>, Timings)> {
+    let host = url.host_str().unwrap_or_default();
+    let port = url.port().unwrap_or(1965);
+    let known_server = cert.map(|cert| Arc::new(KnownServerCert::new(cert, format!("{host}:{port}"))));
+    let verifier: Arc<dyn ServerCertVerifier> = match &known_server {
+        Some(it) => it.clone(),
+        None => Arc::new(AnyServerCert),
+    };
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(verifier);
+    let config = match cert {
+        None => config.with_no_client_auth(),
+        Some(cert) => {
+            let (chain, key) = cert.load()?;
+            config.with_client_auth_cert(chain, key)
+                .map_err(|err| Error::ClientCert { name: cert.name.clone(), message: err.to_string() })?
+        },
+    };
+
+    let server_name = ServerName::try_from(host.trim_start_matches('[').trim_end_matches(']'))
+        .map_err(|_| Error::InvalidUrl(url.to_string().into()))?;
+    let connection_error = |err: io::Error| Error::Connection(err.to_string());
+    let mut step = Instant::now();
+    let mut elapsed = || {
+        let now = Instant::now();
+        Some(now - std::mem::replace(&mut step, now))
+    };
+    let addrs = dns.lookup(host, port).await.map_err(connection_error)?;
+    let looked_up = elapsed();
+    let stream = dns::connect(host, addrs).await.map_err(connection_error)?;
+    let connected = elapsed();
+    let tls = TlsConnector::from(Arc::new(config)).connect(server_name, stream).await
+        .map_err(|err| known_server.and_then(|it| it.refused()).unwrap_or_else(|| connection_error(err)))?;
+    Ok((tls, Timings { dns: looked_up, connect: connected, tls: elapsed(), ..Timings::default() }))
+}
 
-}
\ No newline at end of file
+/// Like [`read_capped`](super::read_capped), but sets `header_read` to when the response header arrived.
+/// If there are `chunks`, a successful response's body is also sent there as it arrives.
+async fn read_response(mut reader: impl AsyncRead + Unpin, url: &Url, chunks: Option<&Chunks>, data: &mut Vec<u8>, header_read: &mut Option<Instant>) -> Result<()> {
+    let mut buf = vec![0; 16 * 1024];
+    // How much of `data` we've sent. None until we know we're streaming it.
+    let mut sent = None;
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        data.extend_from_slice(&buf[..read]);
+        check_size(data.len() as u64, MAX_SIZE)?;
+
+        if header_read.is_none() && let Some(header_end) = data.iter().position(|it| *it == b'\n') {
+            *header_read = Some(Instant::now());
+            let header = Response::parse(&data[..=header_end])?;
+            if header.status == 20 && let Some(chunks) = chunks {
+                // (The receiver may have gone away, but we still want the whole response.)
+                let _ = chunks.send(Chunk::Start { url: url.to_string().into(), content_type: header.meta.parse().ok().map(Arc::new) });
+                sent = Some(header_end + 1);
+            }
+        }
+        if let Some(start) = sent && start < data.len() && let Some(chunks) = chunks {
+            let _ = chunks.send(Chunk::Body(data[start..].to_vec()));
+            sent = Some(data.len());
+        }
+    }
+}
+
+/// Without a client certificate, we accept any server certificate.
+struct AnyServerCert;
+
+impl ServerCertVerifier for AnyServerCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Like [`AnyServerCert`], but a client certificate is only sent to the server certificate it was
+/// first sent to. See [`known_hosts`](super::known_hosts).
+struct KnownServerCert {
+    cert: ClientCert,
+    known_hosts: KnownHosts,
+
+    /// `<host>:<port>`
+    host: String,
+
+    /// Why we refused the server's certificate, if we did.
+    refused: Mutex<Option<Error>>,
+}
+
+impl KnownServerCert {
+    fn new(cert: &ClientCert, host: String) -> Self {
+        Self { cert: cert.clone(), known_hosts: cert.known_hosts(), host, refused: Mutex::default() }
+    }
+
+    fn refused(&self) -> Option<Error> {
+        self.refused.lock().expect("refused lock").take()
+    }
+
+    fn refuse(&self, message: String) -> rustls::Error {
+        let error = rustls::Error::General(message.clone());
+        *self.refused.lock().expect("refused lock") = Some(self.cert.error(message));
+        error
+    }
+}
+
+impl ServerCertVerifier for KnownServerCert {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let (host, file) = (&self.host, self.known_hosts.file().display());
+        match self.known_hosts.check(host, &end_entity.0) {
+            Ok(Trust::New | Trust::Known) => Ok(ServerCertVerified::assertion()),
+            Ok(Trust::Changed) => Err(self.refuse(format!(
+                "Not sent, because {host}'s server certificate has changed since it was first sent there. \
+                If you expected the change, remove {host} from {file}."
+            ))),
+            Err(err) => Err(self.refuse(format!("Not sent, because {host}'s server certificate couldn't be checked against {file}: {err}"))),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+#![cfg(test)]
+
+use std::{net::TcpListener, sync::{mpsc, Arc}, time::Duration};
+
+use crate::browser::{network::{gemini::{read_response, GeminiLoader, Retrying}, read_capped, rt, Chunk, Error, MultiLoader}, settings::Settings};
+
+#[test]
+fn retries_connection_errors() {
+    // Find a port that nothing is listening on:
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let url = url::Url::parse(&format!("gemini://127.0.0.1:{port}/")).unwrap();
+
+    let retrying = Retrying::default();
+    let loader = Arc::new(GeminiLoader::default().with_retries(2, Duration::from_millis(1), retrying.clone()));
+    let result = rt().block_on(loader.fetch(url.clone())).unwrap();
+
+    assert!(matches!(result, Err(Error::Connection(_))), "{result:?}");
+    assert_eq!(retrying.attempt(url.as_str()), None);
+}
+
+#[test]
+fn responses_are_capped() {
+    let mut data = Vec::new();
+    rt().block_on(read_capped(&b"20 text/gemini\r\nhi"[..], 18, &mut data)).unwrap();
+    assert_eq!(data, b"20 text/gemini\r\nhi");
+
+    // Stops reading just past the limit:
+    let mut data = Vec::new();
+    let result = rt().block_on(read_capped(&[0u8; 1000][..], 10, &mut data));
+    assert!(matches!(result, Err(Error::ResponseTooBig { content_length: 11, max_length: 10 })), "{result:?}");
+}
+
+#[test]
+fn slow_servers_time_out() {
+    // Accepts connections (via the OS backlog), but never responds:
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let mut loader = MultiLoader::default();
+    loader.configure(&Settings { request_timeout_secs: 0.2, gemini_retries: 0, ..Settings::default() });
+    let result = rt().block_on(loader.fetch(format!("gemini://127.0.0.1:{port}/").into())).unwrap();
+
+    assert!(matches!(result, Err(Error::Timeout { after, .. }) if after == Duration::from_secs_f32(0.2)), "{result:?}");
+    drop(listener);
+}
+
+#[test]
+fn responses_note_when_the_header_arrived() {
+    let url = url::Url::parse("gemini://example.com/").unwrap();
+    let response = b"20 text/gemini\r\n# Hi";
+    let mut data = Vec::new();
+    let mut header_read = None;
+    rt().block_on(read_response(&response[..], &url, None, &mut data, &mut header_read)).unwrap();
+    assert!(header_read.is_some());
+    assert_eq!(data, response);
+
+    // And when streaming:
+    let (chunks, received) = mpsc::channel();
+    let mut data = Vec::new();
+    let mut header_read = None;
+    rt().block_on(read_response(&response[..], &url, Some(&chunks), &mut data, &mut header_read)).unwrap();
+
+    assert!(header_read.is_some());
+    assert!(matches!(received.recv().unwrap(), Chunk::Start { .. }));
+    assert_eq!(received.recv().unwrap(), Chunk::Body(b"# Hi".to_vec()));
+}
@@ -1,12 +1,18 @@
-use std::{sync::Arc, time::Duration};
+use std::{sync::Arc, time::{Duration, Instant}};
 
+use log::{debug, warn};
 use mime::Mime;
-use reqwest::redirect::Policy;
+use reqwest::{header::{HeaderName, HeaderValue}, redirect::Policy, RequestBuilder};
 use tokio::task::JoinHandle;
+use url::Url;
 
 use super::{Result, Error};
+use auth::HostCredentials;
 
-use crate::{browser::network::{rt, Body, LoadedResource, Status}, util::DisplayJoin as _};
+use crate::{browser::{network::{blocklist::Blocklist, cache::CacheControl, check_size, dns::Dns, is_binary, rt, Body, LoadedResource, SCow, Status, Timings, MAX_REDIRECTS}, settings::{self, HostHeader, RefererPolicy}}, util::DisplayJoin as _};
+
+pub mod auth;
+mod http_test;
 
 
 
@@ -21,27 +27,35 @@ pub struct HttpLoader {
 
     // Which content types to request. If we don't get one of these back, then error out fast.
     accept_content_types: Vec<Mime>,
+
+    // User-configured headers. Only sent to the matching host.
+    host_headers: Vec<HostHeader>,
+
+    referer_policy: RefererPolicy,
+
+    /// Off by default, so that the user sees where they're being sent and can choose to go.
+    follow_redirects: bool,
+
+    /// Redirects to blocked URLs are refused.
+    blocklist: Blocklist,
+
+    /// Basic auth credentials. Shared with the [`super::MultiLoader`], so that they outlive settings changes.
+    credentials: HostCredentials,
 }
 
 impl Default for HttpLoader {
     fn default() -> Self {
         Self { 
             max_size: Some(1024 * 1024 * 100), // 100 MiB
-            client: reqwest::Client::builder()
-                .connect_timeout(Duration::from_secs(10))
-                .user_agent(USER_AGENT)
-                // Let the user see that redirects are happening and opt in:
-                .redirect(Policy::none())
-                .build()
-                .expect("Building reqwest client"),
-            accept_content_types: [
-                // See: https://developer.mozilla.org/en-US/docs/Glossary/Quality_values
-                "text/gemini; q=1",
-                "text/markdown; q=0.9",
-                "text/plain; q=0.8",
-                "text/*; q=0.7",
-                "*/*; q=0.1"
-            ].into_iter().map(|it| it.parse().expect("parsing mime")).collect(),
+            client: client_builder().build().expect("Building reqwest client"),
+            accept_content_types: settings::PREFER_GEMTEXT.iter()
+                .map(|it| it.parse().expect("parsing mime"))
+                .collect(),
+            host_headers: Vec::new(),
+            referer_policy: RefererPolicy::None,
+            follow_redirects: false,
+            blocklist: Blocklist::default(),
+            credentials: HostCredentials::default(),
         }
     }
 }
@@ -50,33 +64,128 @@ const USER_AGENT: &str = concat!(
     "eGemi v", env!("CARGO_PKG_VERSION")
 );
 
+fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .user_agent(USER_AGENT)
+        // We follow redirects ourselves, so that we can tell where we ended up, and stay within http(s):
+        .redirect(Policy::none())
+}
+
 impl HttpLoader {
-    pub fn fetch(self: &Arc<Self>, url: &str) -> JoinHandle<Result<LoadedResource>> {
+    /// Set the content types we request, in order of preference. Invalid ones are skipped.
+    pub fn with_accept_types(mut self, types: &[String]) -> Self {
+        let parsed: Vec<Mime> = types.iter()
+            .map(|it| it.trim())
+            .filter(|it| !it.is_empty())
+            .filter_map(|it| match it.parse() {
+                Ok(mime) => Some(mime),
+                Err(err) => {
+                    warn!("Skipping invalid Accept type {it:?}: {err}");
+                    None
+                },
+            })
+            .collect();
+        if !parsed.is_empty() {
+            self.accept_content_types = parsed;
+        }
+        self
+    }
+
+    /// Send all HTTP(S) requests through `proxy`. If it's empty or invalid, we use the system's proxy settings.
+    /// (The Gemini loaders don't support proxies.)
+    /// Look up hosts with `dns`.
+    pub fn with_network(mut self, proxy: &str, dns: Dns) -> Self {
+        let mut builder = client_builder();
+        if !dns.is_system() {
+            builder = builder.dns_resolver(Arc::new(dns));
+        }
+        let proxy = proxy.trim();
+        if !proxy.is_empty() {
+            match reqwest::Proxy::all(proxy) {
+                Ok(proxy) => builder = builder.proxy(proxy),
+                Err(err) => warn!("Ignoring invalid proxy {proxy:?}: {err}"),
+            }
+        }
+        match builder.build() {
+            Ok(client) => self.client = client,
+            Err(err) => warn!("Couldn't configure HTTP client: {err}"),
+        }
+        self
+    }
+
+    pub fn with_host_headers(mut self, host_headers: Vec<HostHeader>) -> Self {
+        self.host_headers = host_headers;
+        self
+    }
+
+    pub fn with_referer_policy(mut self, policy: RefererPolicy) -> Self {
+        self.referer_policy = policy;
+        self
+    }
+
+    /// Follow redirects to other http(s) URLs, instead of returning [`Error::Redirect`].
+    pub fn with_follow_redirects(mut self, follow: bool) -> Self {
+        self.follow_redirects = follow;
+        self
+    }
+
+    pub fn with_blocklist(mut self, blocklist: Blocklist) -> Self {
+        self.blocklist = blocklist;
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: HostCredentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Load `url`. `referrer` is the page that linked to it, if any.
+    pub fn fetch(self: &Arc<Self>, url: &str, referrer: Option<&str>) -> JoinHandle<Result<LoadedResource>> {
         let url = url.to_string();
-        let fut = self.clone()._fetch(url);
+        let referrer = referrer.map(str::to_string);
+        let fut = self.clone()._fetch(url, referrer);
         let rt = rt();
         rt.spawn(fut)
     }
 
-    async fn _fetch(self: Arc<Self>, url: String) -> Result<LoadedResource> {
-        let response = self.client.get(&url)
-            .header("Accept", self.accept_content_types.iter().join(","))
-            .send()
-            .await?;
-
-        let ctype = match response.headers().get("content-type") {
-            Some(header) => match header.to_str() {
-                Ok(str) => Some(str.to_owned()),
-                Err(err) => Err(err)?,
-            },
-            None => None,
-        };
-        let ctype = match ctype {
-            None => None,
-            Some(ctype) => {
-                Some(ctype.parse::<Mime>()?)
+    async fn _fetch(self: Arc<Self>, mut url: String, referrer: Option<String>) -> Result<LoadedResource> {
+        let start = Instant::now();
+        if let Ok(mut parsed) = Url::parse(&url) && let Some(credentials) = auth::take_userinfo(&mut parsed) {
+            // Remember them for the rest of the site, and keep the password out of the location bar:
+            self.credentials.set(&parsed, credentials);
+            url = parsed.into();
+        }
+        let mut hops: Vec<SCow> = Vec::new();
+        let response = loop {
+            let response = self.send(&url, referrer.as_deref()).await?;
+            if !response.status().is_redirection() {
+                break response;
             }
+            let Some(Ok(location)) = response.headers().get("location").map(|it| it.to_str()) else {
+                break response;
+            };
+            let Ok(destination) = Url::parse(&url).and_then(|it| it.join(location)) else {
+                return Err(Error::InvalidUrl(location.to_string().into()));
+            };
+            let web = matches!(destination.scheme(), "http" | "https");
+            if !self.follow_redirects || !web {
+                return Err(Error::Redirect {
+                    destination: destination.into(),
+                    temporary: !matches!(response.status().as_u16(), 301 | 308),
+                });
+            }
+            self.blocklist.check(&destination)?;
+            hops.push(url.into());
+            if hops.len() > MAX_REDIRECTS {
+                return Err(Error::TooManyRedirects { url: destination.to_string().into(), hops });
+            }
+            debug!("Following redirect to {destination}");
+            url = destination.into();
         };
+        let first_byte = start.elapsed();
+
+        let ctype = content_type(&response)?;
 
         // !!! NOT THIS
         // let length = response.content_length(); // LIES
@@ -86,38 +195,152 @@ impl HttpLoader {
             .map(|it| it.parse::<u64>().ok()).flatten()
         ;
         if let (Some(length), Some(max_len)) = (length, self.max_size) {
-            if length > max_len {
-                return Err(Error::ResponseTooBig{ content_length: length, max_length: max_len })
-            }
+            check_size(length, max_len)?;
         }
 
+        let cache_control = response.headers()
+            .get("cache-control")
+            .and_then(|it| it.to_str().ok())
+            .map(CacheControl::parse);
+
         let code = response.status().as_u16();
-        if response.status().is_redirection() {
-            if let Some(Ok(dest)) = response.headers().get("location").map(|it| it.to_str()) {
-                return Err(Error::Redirect { 
-                    destination: dest.into(), 
-                    temporary: code != 301
-                });
-            }
-        }
-        // TODO: binary.
+        let realm = response.headers()
+            .get_all("www-authenticate")
+            .iter()
+            .filter_map(|it| it.to_str().ok())
+            .find_map(auth::basic_realm);
         // TODO: Some things report application/octet-stream when they don't know the mime type.
         // Could try to second-guess the type from the file extension.
-        let status = Status::HttpStatus { 
-            code
+        let status = match realm {
+            Some(realm) if code == 401 => Status::HttpAuthRequired {
+                realm,
+                rejected: Url::parse(&url).ok().and_then(|it| self.credentials.get(&it)).is_some(),
+            },
+            _ => Status::HttpStatus { code },
         };
         
-        let text = response.text().await?;
-
+        let bytes = self.read_body(response).await?;
+        let body = if is_binary(ctype.as_ref()) {
+            Body::Bytes(bytes.into())
+        } else {
+            Body::Text(decode_text(&bytes, ctype.as_ref()).into())
+        };
+        let timings = Timings { first_byte: Some(first_byte), total: start.elapsed(), ..Timings::default() };
+        debug!("Loaded {url} ({timings})");
 
         let resource = LoadedResource {
-            body: Body::Text(text.into()), 
+            body, 
             content_type: ctype.map(Into::into),
             length,
+            cache_control,
+            timings: Some(timings),
             status,
             url: url.into(),
         };
 
         Ok(resource)
     }
-}
\ No newline at end of file
+
+    /// Just the first `max` bytes of `url`. See [`super::MultiLoader::fetch_preview`].
+    pub fn fetch_preview(self: &Arc<Self>, url: &str, max: u64) -> JoinHandle<Result<LoadedResource>> {
+        rt().spawn(self.clone()._fetch_preview(url.to_string(), max))
+    }
+
+    async fn _fetch_preview(self: Arc<Self>, url: String, max: u64) -> Result<LoadedResource> {
+        let start = Instant::now();
+        // Without credentials or host headers:
+        let mut response = self.client.get(&url)
+            .header("Accept", self.accept_content_types.iter().join(","))
+            .send()
+            .await?;
+        let ctype = content_type(&response)?;
+        let code = response.status().as_u16();
+        let max = usize::try_from(max).unwrap_or(usize::MAX);
+        let mut bytes = Vec::new();
+        while bytes.len() < max && let Some(chunk) = response.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+        }
+        bytes.truncate(max);
+        let body = if is_binary(ctype.as_ref()) {
+            Body::Bytes(bytes.into())
+        } else {
+            Body::Text(decode_text(&bytes, ctype.as_ref()).into())
+        };
+        Ok(LoadedResource {
+            body,
+            content_type: ctype.map(Into::into),
+            length: None,
+            cache_control: None,
+            timings: Some(Timings { total: start.elapsed(), ..Timings::default() }),
+            status: Status::HttpStatus { code },
+            url: url.into(),
+        })
+    }
+
+    /// Reads the whole body, but gives up once it's bigger than [`Self::max_size`], in case the
+    /// server didn't send a `content-length`.
+    async fn read_body(&self, mut response: reqwest::Response) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+            if let Some(max_len) = self.max_size {
+                check_size(bytes.len() as u64, max_len)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn send(&self, url: &str, referrer: Option<&str>) -> impl Future<Output = reqwest::Result<reqwest::Response>> {
+        let mut request = self.client.get(url)
+            .header("Accept", self.accept_content_types.iter().join(","));
+        if let Some(referer) = referrer.and_then(|it| self.referer(it, url)) {
+            request = request.header("Referer", referer);
+        }
+        if let Some(credentials) = Url::parse(url).ok().and_then(|it| self.credentials.get(&it)) {
+            request = request.basic_auth(credentials.user, Some(credentials.password));
+        }
+        self.add_host_headers(request, url).send()
+    }
+
+    /// The `Referer` header to send to `url`, when following a link from `referrer`.
+    fn referer(&self, referrer: &str, url: &str) -> Option<String> {
+        let from = Url::parse(referrer).ok()?;
+        let to = Url::parse(url).ok()?;
+        self.referer_policy.referer(&from, &to)
+    }
+
+    fn add_host_headers(&self, mut request: RequestBuilder, url: &str) -> RequestBuilder {
+        let Some(host) = Url::parse(url).ok().and_then(|it| it.host_str().map(str::to_owned)) else {
+            return request;
+        };
+
+        for header in self.host_headers.iter().filter(|it| it.matches_host(&host)) {
+            let name = HeaderName::from_bytes(header.name.trim().as_bytes());
+            let value = HeaderValue::from_str(&header.value);
+            let (Ok(name), Ok(value)) = (name, value) else {
+                warn!("Skipping invalid header {:?} for host {host}", header.name);
+                continue;
+            };
+            request = request.header(name, value);
+        }
+        request
+    }
+}
+
+/// The response's `Content-Type`, if it has one.
+fn content_type(response: &reqwest::Response) -> Result<Option<Mime>> {
+    let Some(header) = response.headers().get("content-type") else {
+        return Ok(None);
+    };
+    Ok(Some(header.to_str()?.parse::<Mime>()?))
+}
+
+/// Decodes `bytes` using the content type's charset, like [`reqwest::Response::text`] does. Defaults to UTF-8.
+fn decode_text(bytes: &[u8], content_type: Option<&Mime>) -> String {
+    let encoding = content_type
+        .and_then(|it| it.get_param(mime::CHARSET))
+        .and_then(|it| encoding_rs::Encoding::for_label(it.as_str().as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
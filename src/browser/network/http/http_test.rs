@@ -0,0 +1,155 @@
+#![cfg(test)]
+
+use std::{io::{BufRead as _, BufReader, Write as _}, net::TcpListener, sync::Arc, thread};
+
+use mime::Mime;
+use pretty_assertions::assert_eq;
+
+use crate::browser::{network::{blocklist::Blocklist, http::{auth::{Credentials, HostCredentials}, decode_text, HttpLoader}, rt, Error, Status, MAX_REDIRECTS}, settings::HostHeader};
+
+#[test]
+fn decodes_charsets() {
+    let latin1: Mime = "text/plain; charset=ISO-8859-1".parse().unwrap();
+    assert_eq!(decode_text(b"caf\xe9", Some(&latin1)), "café");
+
+    // UTF-8 by default:
+    assert_eq!(decode_text("café".as_bytes(), None), "café");
+    let unknown: Mime = "text/plain; charset=nonsense".parse().unwrap();
+    assert_eq!(decode_text("café".as_bytes(), Some(&unknown)), "café");
+
+    // Invalid bytes are replaced, not an error:
+    assert_eq!(decode_text(b"caf\xff", None), "caf\u{FFFD}");
+}
+
+/// Serves `/` with "done", and redirects `/<n>` to `/<n - 1>`. Returns the server's base URL.
+fn redirecting_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { return };
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(&stream);
+            reader.read_line(&mut request_line).unwrap();
+            // Skip the headers:
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let path = request_line.split(' ').nth(1).unwrap_or("/");
+            let response = match path.trim_start_matches('/').parse::<u32>() {
+                Ok(n) if n > 0 => format!("HTTP/1.1 302 Found\r\nLocation: /{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", n - 1),
+                _ => "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 4\r\nConnection: close\r\n\r\ndone".to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    base
+}
+
+#[test]
+fn follows_redirects() {
+    let base = redirecting_server();
+    let loader = Arc::new(HttpLoader::default().with_follow_redirects(true));
+
+    let loaded = rt().block_on(loader.fetch(&format!("{base}/2"), None)).unwrap().unwrap();
+    assert_eq!(loaded.url, format!("{base}/0"));
+
+    let too_many = MAX_REDIRECTS + 1;
+    let result = rt().block_on(loader.fetch(&format!("{base}/{too_many}"), None)).unwrap();
+    let Err(Error::TooManyRedirects { url, hops }) = result else {
+        panic!("{result:?}");
+    };
+    assert_eq!(url, format!("{base}/0"));
+    assert_eq!(hops.len(), too_many);
+    assert_eq!(hops[0], format!("{base}/{too_many}"));
+
+    // Or let the user decide:
+    let loader = Arc::new(HttpLoader::default());
+    let result = rt().block_on(loader.fetch(&format!("{base}/1"), None)).unwrap();
+    assert!(matches!(&result, Err(Error::Redirect { destination, temporary: true }) if *destination == format!("{base}/0")), "{result:?}");
+}
+
+#[test]
+fn redirects_are_blocked() {
+    let base = redirecting_server();
+    let blocked = format!("{base}/0");
+    // (Patterns don't include ports.)
+    let loader = Arc::new(HttpLoader::default().with_follow_redirects(true).with_blocklist(Blocklist::new(["127.0.0.1/0"])));
+
+    // The first URL is checked by the MultiLoader, but every hop after it is checked here:
+    let result = rt().block_on(loader.fetch(&format!("{base}/2"), None)).unwrap();
+    assert!(matches!(&result, Err(Error::Blocked { url, .. }) if *url == blocked), "{result:?}");
+}
+
+/// Serves "secret" to requests that log in as `me:pass`, and asks everyone else to log in.
+fn auth_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { return };
+            let mut reader = BufReader::new(&stream);
+            let mut authorized = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                // base64("me:pass"):
+                authorized |= line.trim().eq_ignore_ascii_case("authorization: Basic bWU6cGFzcw==");
+                line.clear();
+            }
+            let response = if authorized {
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nConnection: close\r\n\r\nsecret"
+            } else {
+                "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"Test\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    base
+}
+
+#[test]
+fn basic_auth() {
+    let base = auth_server();
+    let credentials = HostCredentials::default();
+    let loader = Arc::new(HttpLoader::default().with_credentials(credentials.clone()));
+    let fetch = |url: String| rt().block_on(loader.fetch(&url, None)).unwrap().unwrap();
+
+    let loaded = fetch(format!("{base}/"));
+    assert!(matches!(&loaded.status, Status::HttpAuthRequired { realm, rejected: false } if realm == "Test"), "{:?}", loaded.status);
+
+    // From the URL, which is then forgotten. But the login is remembered for the host:
+    let loaded = fetch(base.replace("://", "://me:pass@") + "/");
+    assert!(loaded.status.ok(), "{:?}", loaded.status);
+    assert_eq!(loaded.url, format!("{base}/"));
+    assert!(fetch(format!("{base}/other")).status.ok());
+
+    let url = url::Url::parse(&base).unwrap();
+    credentials.set(&url, Credentials { user: "me".into(), password: "wrong".into() });
+    let loaded = fetch(format!("{base}/"));
+    assert!(matches!(&loaded.status, Status::HttpAuthRequired { rejected: true, .. }), "{:?}", loaded.status);
+}
+
+#[test]
+fn host_headers() {
+    let header = |host: &str, name: &str, value: &str| HostHeader { host: host.into(), name: name.into(), value: value.into() };
+    let loader = HttpLoader::default().with_host_headers(vec![
+        header("example.com", "X-Token", "secret"),
+        header("example.com", "Bad Name", "skipped"),
+        header("example.com", "X-Bad-Value", "line\nbreak"),
+        header("other.com", "X-Other", "other"),
+    ]);
+    let headers = |url: &str| {
+        let request = loader.add_host_headers(loader.client.get(url), url).build().unwrap();
+        let mut headers: Vec<(String, String)> = request.headers().iter()
+            .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_string()))
+            .collect();
+        headers.sort();
+        headers
+    };
+
+    // Invalid headers are skipped, rather than failing the request:
+    assert_eq!(headers("https://example.com/page"), vec![("x-token".to_string(), "secret".to_string())]);
+    assert_eq!(headers("https://other.com/"), vec![("x-other".to_string(), "other".to_string())]);
+    assert_eq!(headers("https://sub.example.com/"), vec![]);
+}
@@ -0,0 +1,75 @@
+//! Server certificates of the capsules we've sent client certificates to, so that we don't send them
+//! to an impostor later. Gemini servers usually have self-signed certificates, so rather than checking
+//! them against certificate authorities, we trust the first one we see for each host.
+//! See: https://geminiprotocol.net/docs/protocol-specification.gmi#tls-server-certificate-validation
+//!
+//! Saved as `<host>:<port> <SHA-256 fingerprint>` lines in a `known_hosts` file in the certificate
+//! directory. Removing a host's line trusts whichever certificate it presents next.
+
+use std::{collections::HashMap, fs, io::{self, Write as _}, path::{Path, PathBuf}};
+
+use ring::digest::{digest, SHA256};
+
+mod known_hosts_test;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownHosts {
+    file: PathBuf,
+}
+
+/// How a server's certificate compares to the one we saw first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trust {
+    /// We hadn't seen this host before. Its certificate is now the one we expect.
+    New,
+    Known,
+    Changed,
+}
+
+impl KnownHosts {
+    pub fn new(file: PathBuf) -> Self {
+        Self { file }
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Check `cert` (DER) against the certificate we first saw for `host` (ex: `example.com:1965`).
+    /// Remembers it, if it's the first.
+    pub fn check(&self, host: &str, cert: &[u8]) -> io::Result<Trust> {
+        let fingerprint = fingerprint(cert);
+        match self.load()?.get(host) {
+            Some(known) if *known == fingerprint => Ok(Trust::Known),
+            Some(_) => Ok(Trust::Changed),
+            None => {
+                self.save(host, &fingerprint)?;
+                Ok(Trust::New)
+            },
+        }
+    }
+
+    fn load(&self) -> io::Result<HashMap<String, String>> {
+        let text = match fs::read_to_string(&self.file) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => return Err(err),
+        };
+        Ok(text.lines()
+            .filter_map(|line| line.trim().split_once(' '))
+            .map(|(host, fingerprint)| (host.to_string(), fingerprint.trim().to_string()))
+            .collect())
+    }
+
+    fn save(&self, host: &str, fingerprint: &str) -> io::Result<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&self.file)?;
+        writeln!(file, "{host} {fingerprint}")
+    }
+}
+
+fn fingerprint(cert: &[u8]) -> String {
+    digest(&SHA256, cert).as_ref().iter().map(|it| format!("{it:02x}")).collect()
+}
@@ -0,0 +1,25 @@
+#![cfg(test)]
+
+use pretty_assertions::assert_eq;
+
+use crate::browser::network::known_hosts::{KnownHosts, Trust};
+
+#[test]
+fn trusts_the_first_certificate() {
+    let dir = std::env::temp_dir().join(format!("egemi-known-hosts-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let known = KnownHosts::new(dir.join("known_hosts"));
+
+    assert_eq!(known.check("example.com:1965", b"first").unwrap(), Trust::New);
+    assert_eq!(known.check("example.com:1965", b"first").unwrap(), Trust::Known);
+    assert_eq!(known.check("example.com:1965", b"second").unwrap(), Trust::Changed);
+    // Other hosts (and ports) are pinned separately:
+    assert_eq!(known.check("example.com:1966", b"second").unwrap(), Trust::New);
+
+    // Saved, and a changed certificate doesn't replace the first one:
+    let reloaded = KnownHosts::new(dir.join("known_hosts"));
+    assert_eq!(reloaded.check("example.com:1965", b"first").unwrap(), Trust::Known);
+    assert_eq!(reloaded.check("example.com:1966", b"first").unwrap(), Trust::Changed);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,142 @@
+//! Titan, Gemini's companion protocol for uploading. (ex: to edit a wiki page, or post to a pastebin.)
+//! See: https://transjovian.org/titan/page/The%20Titan%20Specification
+//!
+//! Responses are Gemini responses, and usually redirect to the gemini:// page that was changed.
+
+use std::{fmt::Debug, sync::Arc, time::Instant};
+
+use log::debug;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use tokio::task::JoinHandle;
+use url::Url;
+
+use crate::browser::network::{blocklist::Blocklist, certs::ClientCerts, dns::Dns, gemini::{request_direct, GeminiLoader}, rt, Error, LoadedResource, Result};
+
+mod titan_test;
+
+/// Characters that would end a parameter (or the URL) early.
+const PARAM: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'%')
+    .add(b';')
+    .add(b'=')
+    .add(b'?')
+    .add(b'#');
+
+/// What the user wants to upload.
+#[derive(Clone, PartialEq)]
+pub struct Upload {
+    pub body: Vec<u8>,
+    pub mime: String,
+
+    /// Some servers only accept uploads that include a token (ex: a password) they gave out.
+    pub token: Option<String>,
+}
+
+// Manual impl, so that we never log tokens.
+impl Debug for Upload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Upload")
+            .field("size", &self.body.len())
+            .field("mime", &self.mime)
+            .field("token", &self.token.as_ref().map(|_| "…"))
+            .finish()
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct TitanLoader {
+    certs: ClientCerts,
+    dns: Dns,
+
+    /// Loads the gemini:// page that an upload redirects to.
+    gemini: Arc<GeminiLoader>,
+
+    /// A redirect to a blocked URL is refused.
+    blocklist: Blocklist,
+}
+
+impl TitanLoader {
+    /// Certificates are matched against the upload's URL, and then against the same URL as gemini://,
+    /// so that the certificate used for reading a capsule is also used for editing it.
+    pub fn with_certs(mut self, certs: ClientCerts) -> Self {
+        self.certs = certs;
+        self
+    }
+
+    pub fn with_dns(mut self, dns: Dns) -> Self {
+        self.dns = dns;
+        self
+    }
+
+    pub fn with_gemini(mut self, gemini: Arc<GeminiLoader>) -> Self {
+        self.gemini = gemini;
+        self
+    }
+
+    pub fn with_blocklist(mut self, blocklist: Blocklist) -> Self {
+        self.blocklist = blocklist;
+        self
+    }
+
+    pub fn upload(self: &Arc<Self>, url: Url, upload: Upload) -> JoinHandle<Result<LoadedResource>> {
+        rt().spawn(self.clone()._upload(url, upload))
+    }
+
+    async fn _upload(self: Arc<Self>, url: Url, upload: Upload) -> Result<LoadedResource> {
+        let start = Instant::now();
+        let cert = self.certs.for_url(&url).or_else(|| self.certs.for_url(&gemini_url(&url)));
+        if let Some(cert) = &cert {
+            debug!("Using client certificate {} for {url}", cert.name);
+        }
+        let request = request_url(&url, &upload);
+        let response = request_direct(&request, cert.as_ref(), &self.dns, &upload.body, None).await?;
+        let timings = response.timings(start);
+        debug!("Uploaded {} bytes to {url} ({timings})", upload.body.len());
+
+        if !matches!(response.status, 30 | 31) {
+            return response.loaded(&url, timings);
+        }
+        let meta = response.meta.trim();
+        let Ok(destination) = url.join(meta) else {
+            return Err(Error::InvalidUrl(meta.to_string().into()));
+        };
+        if destination.scheme() != "gemini" {
+            return Err(Error::Redirect {
+                destination: destination.into(),
+                temporary: response.status == 30,
+            });
+        }
+        self.blocklist.check(&destination)?;
+        debug!("Following redirect from {url} to {destination}");
+        self.gemini.fetch(destination).await.map_err(|err| Error::Unknown(err.to_string()))?
+    }
+}
+
+/// `url`, with the upload's parameters added to its path. (ex: `titan://example.com/page;mime=text/gemini;size=12`)
+/// Any parameters `url` already had are replaced.
+pub fn request_url(url: &Url, upload: &Upload) -> Url {
+    let mut request = url.clone();
+    let path = url.path().split(';').next().unwrap_or_default();
+    let mut params = format!(";mime={};size={}", encode(&upload.mime), upload.body.len());
+    if let Some(token) = &upload.token {
+        params += &format!(";token={}", encode(token));
+    }
+    let path = if path.is_empty() { "/" } else { path };
+    request.set_path(&format!("{path}{params}"));
+    request
+}
+
+/// The gemini:// URL that a titan:// URL uploads to.
+pub fn gemini_url(url: &Url) -> Url {
+    let mut gemini = url.clone();
+    let path = url.path().split(';').next().unwrap_or_default().to_string();
+    gemini.set_path(&path);
+    // Both are "non-special" schemes, which url allows switching between:
+    let _ = gemini.set_scheme("gemini");
+    gemini
+}
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, PARAM).to_string()
+}
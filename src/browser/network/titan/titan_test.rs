@@ -0,0 +1,37 @@
+#![cfg(test)]
+
+use url::Url;
+
+use crate::browser::{network::{rt, titan::{gemini_url, request_url, Upload}, MultiLoader, Status}, settings::Settings};
+
+#[test]
+fn request_parameters() {
+    let upload = |token: Option<&str>| Upload { body: b"# Hello".to_vec(), mime: "text/gemini".into(), token: token.map(String::from) };
+    let request = |url: &str, upload: &Upload| request_url(&Url::parse(url).unwrap(), upload).to_string();
+
+    assert_eq!(request("titan://example.com/wiki/page", &upload(None)), "titan://example.com/wiki/page;mime=text/gemini;size=7");
+    assert_eq!(request("titan://example.com", &upload(None)), "titan://example.com/;mime=text/gemini;size=7");
+    assert_eq!(request("titan://example.com/page", &upload(Some("hunter2"))), "titan://example.com/page;mime=text/gemini;size=7;token=hunter2");
+
+    // Parameters that were already in the link are replaced, and ours are escaped:
+    assert_eq!(request("titan://example.com/page;size=1;token=old", &upload(Some("a;b=c"))), "titan://example.com/page;mime=text/gemini;size=7;token=a%3Bb%3Dc");
+
+    // Tokens aren't logged:
+    assert!(!format!("{:?}", upload(Some("hunter2"))).contains("hunter2"));
+}
+
+#[test]
+fn gemini_urls() {
+    let gemini = |url: &str| gemini_url(&Url::parse(url).unwrap()).to_string();
+    assert_eq!(gemini("titan://example.com/wiki/page"), "gemini://example.com/wiki/page");
+    assert_eq!(gemini("titan://example.com:1966/page;size=3;mime=text/plain"), "gemini://example.com:1966/page");
+}
+
+#[test]
+fn titan_links_ask_what_to_upload() {
+    let mut loader = MultiLoader::default();
+    loader.configure(&Settings::default());
+    let loaded = rt().block_on(loader.fetch("titan://example.com/page".into())).unwrap().unwrap();
+    assert!(matches!(loaded.status, Status::TitanUpload), "{:?}", loaded.status);
+    assert_eq!(loaded.url, "titan://example.com/page");
+}
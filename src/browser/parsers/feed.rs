@@ -0,0 +1,193 @@
+//! A tiny Atom/RSS parser.
+//! We don't need all of the metadata, just enough to show a readable list of entries.
+//! So we render feeds to gemtext.
+
+use std::{fmt::Write as _, sync::LazyLock};
+
+use mime::Mime;
+use regex::Regex;
+use roxmltree::{Document, Node};
+
+use crate::util::collapse_whitespace;
+
+mod feed_test;
+
+/// Max length (in chars) of an entry summary.
+const SUMMARY_LENGTH: usize = 280;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Feed {
+    pub title: String,
+    pub description: String,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Entry {
+    pub title: String,
+    pub link: String,
+    pub date: String,
+    pub summary: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("XML error: {0}")]
+    Xml(#[from] roxmltree::Error),
+
+    #[error("Not an Atom or RSS feed. Root element: <{0}>")]
+    UnknownRoot(String),
+}
+
+/// Content types we know how to render as feeds.
+pub fn is_feed(content_type: &Mime) -> bool {
+    matches!(content_type.essence_str(), "application/atom+xml" | "application/rss+xml")
+}
+
+pub fn parse(xml: &str) -> Result<Feed, Error> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+    match root.tag_name().name() {
+        "feed" => Ok(parse_atom(root)),
+        // RSS 0.9x/2.0, and RSS 1.0, which is RDF:
+        "rss" | "RDF" => Ok(parse_rss(root)),
+        other => Err(Error::UnknownRoot(other.into())),
+    }
+}
+
+fn parse_atom(feed: Node) -> Feed {
+    let entries = children(feed, "entry").map(|entry| {
+        let date = child_text(entry, "published");
+        Entry {
+            title: child_text(entry, "title"),
+            link: atom_link(entry),
+            date: if date.is_empty() { child_text(entry, "updated") } else { date },
+            summary: summarize(&first_text(entry, &["summary", "content"])),
+        }
+    }).collect();
+
+    Feed {
+        title: child_text(feed, "title"),
+        description: summarize(&child_text(feed, "subtitle")),
+        entries,
+    }
+}
+
+/// Atom entries can have several links. Prefer the "alternate" one, which is the default `rel`.
+fn atom_link(entry: Node) -> String {
+    let mut links = children(entry, "link");
+    let alternate = links.find(|it| matches!(it.attribute("rel"), None | Some("alternate")));
+    let link = alternate.or_else(|| children(entry, "link").next());
+    link.and_then(|it| it.attribute("href")).unwrap_or_default().trim().into()
+}
+
+fn parse_rss(root: Node) -> Feed {
+    let channel = children(root, "channel").next().unwrap_or(root);
+
+    // In RSS 2.0, items are inside the channel. In RSS 1.0, they're siblings of it.
+    let entries = root.descendants()
+        .filter(|it| it.tag_name().name() == "item")
+        .map(|item| Entry {
+            title: child_text(item, "title"),
+            link: first_text(item, &["link", "guid"]),
+            date: first_text(item, &["pubDate", "date"]),
+            summary: summarize(&child_text(item, "description")),
+        })
+        .collect();
+
+    Feed {
+        title: child_text(channel, "title"),
+        description: summarize(&child_text(channel, "description")),
+        entries,
+    }
+}
+
+/// Child elements with a given local name. (Ignores namespaces.)
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |it| it.is_element() && it.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &'static str) -> String {
+    let Some(child) = children(node, name).next() else {
+        return String::new();
+    };
+    let text: String = child.descendants()
+        .filter(|it| it.is_text())
+        .filter_map(|it| it.text())
+        .collect();
+    collapse_whitespace(&text)
+}
+
+/// The text of the first child (by name) that has some.
+fn first_text(node: Node, names: &[&'static str]) -> String {
+    names.iter()
+        .map(|name| child_text(node, name))
+        .find(|it| !it.is_empty())
+        .unwrap_or_default()
+}
+
+/// Summaries are often (escaped) HTML. Strip that down to a short plaintext snippet.
+fn summarize(text: &str) -> String {
+    static TAGS: LazyLock<Regex> = LazyLock::new(|| Regex::new("<[^>]*>").unwrap());
+    let text = collapse_whitespace(&TAGS.replace_all(text, " "));
+
+    if text.chars().count() <= SUMMARY_LENGTH {
+        return text;
+    }
+    let mut short: String = text.chars().take(SUMMARY_LENGTH).collect();
+    short.push('…');
+    short
+}
+
+/// Feed dates come in several formats. Shorten the common ISO 8601 ones to just the date.
+fn short_date(date: &str) -> &str {
+    static ISO_DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}").unwrap());
+    match ISO_DATE.find(date) {
+        Some(found) => found.as_str(),
+        None => date,
+    }
+}
+
+/// Quotes a line of text (which has no newlines) from the feed.
+/// Quoted lines are parsed as gemtext too, so text that starts like a link, heading, list, or code fence gets a
+/// zero-width space in front of it, to keep it plain text.
+fn quote(text: &str) -> String {
+    let starts_with_markup = text.starts_with(|c: char| matches!(c, '=' | '#' | '*' | '>' | '`') || c.is_ascii_digit());
+    if starts_with_markup {
+        format!("> \u{200b}{text}")
+    } else {
+        format!("> {text}")
+    }
+}
+
+impl Feed {
+    pub fn to_gemtext(&self) -> String {
+        let mut out = String::new();
+        let title = if self.title.is_empty() { "Untitled Feed" } else { &self.title };
+        writeln!(out, "# {title}").expect("writing to string");
+        if !self.description.is_empty() {
+            writeln!(out, "{}", quote(&self.description)).expect("writing to string");
+        }
+        if self.entries.is_empty() {
+            out.push_str("\nThis feed has no entries.\n");
+        }
+
+        for entry in &self.entries {
+            out.push('\n');
+            let title = if entry.title.is_empty() { "(untitled)" } else { &entry.title };
+            let date = short_date(&entry.date);
+            let text = if date.is_empty() { title.to_string() } else { format!("{date} {title}") };
+            if entry.link.is_empty() {
+                writeln!(out, "### {text}").expect("writing to string");
+            } else {
+                let link = entry.link.replace(' ', "%20");
+                writeln!(out, "=> {link} {text}").expect("writing to string");
+            }
+            if !entry.summary.is_empty() {
+                writeln!(out, "{}", quote(&entry.summary)).expect("writing to string");
+            }
+        }
+
+        out
+    }
+}
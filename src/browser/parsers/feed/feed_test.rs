@@ -0,0 +1,124 @@
+#![cfg(test)]
+
+use crate::browser::parsers::feed::{self, Entry};
+use crate::gemtext::{self, Block};
+use indoc::indoc;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn atom() {
+    let xml = indoc! { r#"
+        <?xml version="1.0" encoding="utf-8"?>
+        <feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Example Feed</title>
+            <subtitle>A subtitle.</subtitle>
+            <entry>
+                <title>First   Post</title>
+                <link rel="edit" href="https://example.com/edit/1"/>
+                <link href="https://example.com/posts/1"/>
+                <updated>2024-07-27T18:30:02Z</updated>
+                <summary type="html">&lt;p&gt;Some &lt;b&gt;bold&lt;/b&gt; text.&lt;/p&gt;</summary>
+            </entry>
+        </feed>
+    "# };
+
+    let feed = feed::parse(xml).expect("parse");
+    assert_eq!(feed.title, "Example Feed");
+    assert_eq!(feed.entries, vec![Entry {
+        title: "First Post".into(),
+        link: "https://example.com/posts/1".into(),
+        date: "2024-07-27T18:30:02Z".into(),
+        summary: "Some bold text.".into(),
+    }]);
+
+    assert_eq!(feed.to_gemtext(), indoc! { "
+        # Example Feed
+        > A subtitle.
+
+        => https://example.com/posts/1 2024-07-27 First Post
+        > Some bold text.
+    "});
+}
+
+#[test]
+fn rss() {
+    let xml = indoc! { r#"
+        <rss version="2.0">
+        <channel>
+            <title>RSS Example</title>
+            <description>Described.</description>
+            <item>
+                <title>An item</title>
+                <link>https://example.com/item</link>
+                <pubDate>Sat, 27 Jul 2024 18:30:02 GMT</pubDate>
+            </item>
+            <item>
+                <description>No title or link.</description>
+            </item>
+        </channel>
+        </rss>
+    "# };
+
+    let feed = feed::parse(xml).expect("parse");
+    assert_eq!(feed.to_gemtext(), indoc! { "
+        # RSS Example
+        > Described.
+
+        => https://example.com/item Sat, 27 Jul 2024 18:30:02 GMT An item
+
+        ### (untitled)
+        > No title or link.
+    "});
+}
+
+#[test]
+fn no_gemtext_injection() {
+    let xml = indoc! { r#"
+        <rss version="2.0">
+        <channel>
+            <title>Title
+=> gemini://evil.example/ Click me</title>
+            <description>```
+Everything after this would be preformatted.</description>
+            <item>
+                <title>Item
+# Heading</title>
+                <description>Line one
+=> gemini://evil.example/ Link</description>
+            </item>
+            <item>
+                <title>Another</title>
+                <description>=> gemini://evil.example/ Link</description>
+            </item>
+            <item>
+                <title>And another</title>
+                <description># Heading</description>
+            </item>
+        </channel>
+        </rss>
+    "# };
+
+    let feed = feed::parse(xml).expect("parse");
+    let blocks = gemtext::Options::default().parse(&feed.to_gemtext()).expect("parse gemtext");
+    let mut headings = 0;
+    for block in &blocks {
+        match block {
+            Block::Heading { .. } => headings += 1,
+            Block::Text(_) => {},
+            Block::BlockQuote { lines } => {
+                for line in lines {
+                    assert!(matches!(line, Block::Text(_)), "feed text was parsed as gemtext: {line:?}");
+                }
+            },
+            other => panic!("feed text was parsed as gemtext: {other:?}"),
+        }
+    }
+    // Just the feed title, and the items, which have no links:
+    assert_eq!(headings, 4);
+}
+
+#[test]
+fn not_a_feed() {
+    let result = feed::parse("<html><body>Hi</body></html>");
+    assert!(matches!(result, Err(feed::Error::UnknownRoot(root)) if root == "html"));
+}
@@ -2,32 +2,124 @@
 //! And I want to render Markdown too.
 //! So let's just convert to markdown and then show that.
 
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, rc::Rc};
 
 use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory};
-use log::debug;
+use log::{debug, warn};
+use tl::{HTMLTag, NodeHandle, Parser, ParserOptions};
+
+use crate::util::{strip_bom, DisplayJoin as _};
 
 mod html_test;
 
+/// Tags we skip entirely, including their contents.
+/// `<head>` is also skipped, but that's expected, so isn't reported in [`Diagnostics`].
+const SKIPPED_TAGS: &[&str] = &["script"];
+
+/// Tags that have no markdown equivalent. Their text (if any) is kept, but their structure/function is lost.
+const UNHANDLED_TAGS: &[&str] = &[
+    "audio", "button", "canvas", "embed", "form", "input", "math", "noscript",
+    "object", "select", "svg", "textarea", "video",
+];
+
 
-pub fn to_md(html: &str) -> String {
+/// Convert HTML to markdown.
+/// Also reports what content was dropped or couldn't be converted.
+pub fn to_md(html: &str) -> (String, Diagnostics) {
+    let diagnostics = Rc::new(RefCell::new(Diagnostics::default()));
     let mut tag_map: HashMap<String, Box<dyn TagHandlerFactory>> = HashMap::new();
 
-    let skip_tag = Box::new(SkipTagFactory);
-    tag_map.insert("head".into(), skip_tag.clone());
-    tag_map.insert("script".into(), skip_tag);
+    tag_map.insert("head".into(), Box::new(SkipTagFactory { diagnostics: None }));
+    for tag in SKIPPED_TAGS {
+        tag_map.insert(tag.to_string(), Box::new(SkipTagFactory { diagnostics: Some(diagnostics.clone()) }));
+    }
+    tag_map.insert("details".into(), Box::new(DetailsTagFactory));
+    tag_map.insert("summary".into(), Box::new(DetailsTagFactory));
+    for tag in UNHANDLED_TAGS {
+        tag_map.insert(tag.to_string(), Box::new(UnhandledTagFactory { diagnostics: diagnostics.clone() }));
+    }
 
-    let out = html2md::parse_html_custom(html, &tag_map);
+    let out = html2md::parse_html_custom(strip_bom(html), &tag_map);
+    drop(tag_map);
 
-    out
+    let diagnostics = diagnostics.take();
+    (out, diagnostics)
+}
+
+/// Non-fatal problems found while converting HTML to markdown.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Diagnostics {
+    /// Tags that were skipped along with their contents. (ex: `<script>`)
+    pub skipped: Vec<TagLocation>,
+
+    /// Tags that we can't represent in markdown. (ex: `<form>`)
+    pub unhandled: Vec<TagLocation>,
+}
+
+/// Where we found a tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagLocation {
+    pub tag: String,
+    /// The chain of parent elements, ex: `html > body > div`.
+    pub path: String,
+}
+
+impl TagLocation {
+    fn new(tag: &Handle, printer: &StructuredPrinter) -> Self {
+        let tag = match &tag.data {
+            html2md::NodeData::Element { name, .. } => name.local.to_string(),
+            other => format!("{other:?}"),
+        };
+        Self {
+            tag,
+            path: printer.parent_chain.iter().filter(|it| !it.is_empty()).join(" > "),
+        }
+    }
+}
+
+impl Diagnostics {
+    pub fn is_empty(&self) -> bool {
+        self.skipped.is_empty() && self.unhandled.is_empty()
+    }
+
+    /// A one-line summary, ex: "Skipped 2 tags (script ×2). 1 tag unsupported (form)."
+    pub fn summary(&self) -> String {
+        let mut parts = vec![];
+        if !self.skipped.is_empty() {
+            parts.push(format!("Skipped {} ({}).", plural(self.skipped.len(), "tag"), tag_counts(&self.skipped)));
+        }
+        if !self.unhandled.is_empty() {
+            parts.push(format!("{} unsupported ({}).", plural(self.unhandled.len(), "tag"), tag_counts(&self.unhandled)));
+        }
+        parts.join(" ")
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 { format!("1 {noun}") } else { format!("{count} {noun}s") }
+}
+
+fn tag_counts(locations: &[TagLocation]) -> String {
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for location in locations {
+        *counts.entry(&location.tag).or_default() += 1;
+    }
+    counts.into_iter()
+        .map(|(tag, count)| if count == 1 { tag.to_string() } else { format!("{tag} ×{count}") })
+        .join(", ")
 }
 
 /// By default, html2md will parse & show <head> and <title> tags, but we usually just want to show the document.
-struct SkipTag;
+struct SkipTag {
+    diagnostics: Option<Rc<RefCell<Diagnostics>>>,
+}
 
 impl TagHandler for SkipTag {
-    fn handle(&mut self, tag: &Handle, _printer: &mut StructuredPrinter) { 
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
         debug!("Skipping tag: {:#?}", tag.data);
+        if let Some(diagnostics) = &self.diagnostics {
+            diagnostics.borrow_mut().skipped.push(TagLocation::new(tag, printer));
+        }
     }
 
     fn after_handle(&mut self, _printer: &mut StructuredPrinter) { }
@@ -36,14 +128,240 @@ impl TagHandler for SkipTag {
 }
 
 #[derive(Clone, Debug)]
-struct SkipTagFactory;
+struct SkipTagFactory {
+    diagnostics: Option<Rc<RefCell<Diagnostics>>>,
+}
 
 impl TagHandlerFactory for SkipTagFactory {
     fn instantiate(&self) -> Box<dyn html2md::TagHandler> {
-        Box::new(SkipTag)
+        Box::new(SkipTag { diagnostics: self.diagnostics.clone() })
+    }
+}
+
+/// Passes through its contents like html2md's default handler, but records that we saw the tag.
+struct UnhandledTag {
+    diagnostics: Rc<RefCell<Diagnostics>>,
+}
+
+impl TagHandler for UnhandledTag {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        debug!("Unhandled tag: {:#?}", tag.data);
+        self.diagnostics.borrow_mut().unhandled.push(TagLocation::new(tag, printer));
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) { }
+}
+
+#[derive(Clone, Debug)]
+struct UnhandledTagFactory {
+    diagnostics: Rc<RefCell<Diagnostics>>,
+}
+
+impl TagHandlerFactory for UnhandledTagFactory {
+    fn instantiate(&self) -> Box<dyn html2md::TagHandler> {
+        Box::new(UnhandledTag { diagnostics: self.diagnostics.clone() })
+    }
+}
+
+/// Keeps `<details>` and `<summary>` as HTML blocks, which the markdown parser turns into
+/// [`Block::Collapsible`](crate::browser::widgets::markdown::tree::Block::Collapsible).
+/// html2md keeps them too, but inline, where they'd run into the surrounding text.
+#[derive(Default)]
+struct DetailsTag {
+    name: String,
+}
+
+impl TagHandler for DetailsTag {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let html2md::NodeData::Element { name, attrs, .. } = &tag.data else {
+            return;
+        };
+        self.name = name.local.to_string();
+        if self.name == "details" {
+            let open = attrs.borrow().iter().any(|it| &*it.name.local == "open");
+            printer.insert_newline();
+            printer.insert_newline();
+            printer.append_str(if open { "<details open>" } else { "<details>" });
+        } else {
+            printer.append_str("<summary>");
+        }
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        match self.name.as_str() {
+            "details" => {
+                printer.insert_newline();
+                printer.insert_newline();
+                printer.append_str("</details>");
+            },
+            "summary" => printer.append_str("</summary>"),
+            _ => return,
+        }
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+}
+
+struct DetailsTagFactory;
+
+impl TagHandlerFactory for DetailsTagFactory {
+    fn instantiate(&self) -> Box<dyn html2md::TagHandler> {
+        Box::new(DetailsTag::default())
+    }
+}
+
+/// "Article mode": Find the element that most likely contains the main content of the page
+/// (as opposed to navigation, sidebars, comments, etc.) and return just its HTML.
+///
+/// This is a simplified version of the heuristics used by browsers' "reader mode":
+///  * Each paragraph adds to the score of its parent (and half that to its grandparent).
+///  * Longer paragraphs with more commas score higher.
+///  * Class names/ids like "content" or "article" boost a score, "sidebar" or "comment" reduce it.
+///  * The final score is reduced by how much of the element's text is links.
+///
+/// Returns the original HTML if we can't find a likely candidate.
+pub fn extract_main(html: &str) -> String {
+    let dom = match tl::parse(html, ParserOptions::default()) {
+        Ok(dom) => dom,
+        Err(err) => {
+            warn!("Couldn't parse HTML for article mode: {err:?}");
+            return html.into();
+        }
+    };
+    let parser = dom.parser();
+
+    let mut scorer = ArticleScorer { parser, scores: HashMap::new() };
+    let mut ancestors = vec![];
+    for child in dom.children() {
+        scorer.walk(*child, &mut ancestors);
+    }
+
+    let best = scorer.scores.iter()
+        .filter_map(|(handle, score)| {
+            let tag = handle.get(parser)?.as_tag()?;
+            Some((tag, score * (1.0 - link_density(tag, parser))))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    match best {
+        Some((tag, _)) => tag.outer_html(parser),
+        None => html.into(),
+    }
+}
+
+/// Elements that we never consider as part of the main content.
+const NOT_CONTENT: &[&str] = &[
+    "aside", "footer", "form", "header", "nav", "noscript", "script", "style",
+];
+
+/// Paragraphs shorter than this probably aren't content. (ex: bylines, "Read more", etc.)
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+
+struct ArticleScorer<'p, 'buf> {
+    parser: &'p Parser<'buf>,
+    scores: HashMap<NodeHandle, f64>,
+}
+
+impl ArticleScorer<'_, '_> {
+    fn walk(&mut self, handle: NodeHandle, ancestors: &mut Vec<NodeHandle>) {
+        let Some(tag) = handle.get(self.parser).and_then(|it| it.as_tag()) else {
+            return;
+        };
+        let name = tag_name(tag);
+        if NOT_CONTENT.contains(&name.as_str()) {
+            return;
+        }
+
+        if name == "p" || name == "pre" {
+            self.score_paragraph(tag, ancestors);
+            // Don't bother descending. Content containers should be above paragraphs.
+            return;
+        }
+
+        ancestors.push(handle);
+        for child in tag.children().top().iter() {
+            self.walk(*child, ancestors);
+        }
+        ancestors.pop();
+    }
+
+    fn score_paragraph(&mut self, paragraph: &HTMLTag, ancestors: &[NodeHandle]) {
+        let text = paragraph.inner_text(self.parser);
+        let length = text.trim().chars().count();
+        if length < MIN_PARAGRAPH_LENGTH {
+            return;
+        }
+        let commas = text.matches(',').count();
+        let score = 1.0 + commas as f64 + (length as f64 / 100.0).min(3.0);
+
+        let mut parents = ancestors.iter().rev();
+        if let Some(parent) = parents.next() {
+            *self.candidate(*parent) += score;
+        }
+        if let Some(grandparent) = parents.next() {
+            *self.candidate(*grandparent) += score / 2.0;
+        }
+    }
+
+    /// Get the score for a candidate element, initializing it from the element itself if necessary.
+    fn candidate(&mut self, handle: NodeHandle) -> &mut f64 {
+        let parser = self.parser;
+        self.scores.entry(handle).or_insert_with(|| {
+            let Some(tag) = handle.get(parser).and_then(|it| it.as_tag()) else {
+                return 0.0;
+            };
+            let tag_score = match tag_name(tag).as_str() {
+                "article" | "main" => 10.0,
+                "div" => 5.0,
+                "pre" | "td" | "blockquote" => 3.0,
+                "ol" | "ul" | "dl" | "li" | "address" => -3.0,
+                _ => 0.0,
+            };
+            tag_score + class_weight(tag)
+        })
+    }
+}
+
+fn tag_name(tag: &HTMLTag) -> String {
+    tag.name().as_utf8_str().to_ascii_lowercase()
+}
+
+/// Use class names and ids as hints about whether something is the main content.
+fn class_weight(tag: &HTMLTag) -> f64 {
+    const POSITIVE: &[&str] = &["article", "body", "content", "entry", "main", "post", "story", "text"];
+    const NEGATIVE: &[&str] = &[
+        "ad-", "comment", "footer", "menu", "nav", "promo", "related", "share", "sidebar", "social", "widget",
+    ];
+
+    let attributes = tag.attributes();
+    let mut weight = 0.0;
+    for value in [attributes.class(), attributes.id()].into_iter().flatten() {
+        let value = value.as_utf8_str().to_ascii_lowercase();
+        if NEGATIVE.iter().any(|it| value.contains(it)) {
+            weight -= 25.0;
+        }
+        if POSITIVE.iter().any(|it| value.contains(it)) {
+            weight += 25.0;
+        }
+    }
+    weight
+}
+
+/// What fraction of an element's text is inside links.
+fn link_density(tag: &HTMLTag, parser: &Parser) -> f64 {
+    let total = tag.inner_text(parser).chars().count();
+    if total == 0 {
+        return 1.0;
     }
+    let links: usize = tag.query_selector(parser, "a")
+        .into_iter()
+        .flatten()
+        .filter_map(|it| it.get(parser))
+        .map(|it| it.inner_text(parser).chars().count())
+        .sum();
+    (links as f64 / total as f64).min(1.0)
 }
 
 
 // TODO: Investigate better html -> markdown processing:
-// https://docs.rs/htmd/latest/
\ No newline at end of file
+// https://docs.rs/htmd/latest/
@@ -52,7 +52,7 @@ fn simple_example() {
         </html>
     "# };
 
-    let out = parse_html::to_md(example);
+    let (out, _) = parse_html::to_md(example);
     assert_eq!(out, indoc! { r#"
         A bit of this & that
         ==========
@@ -73,4 +73,83 @@ fn simple_example() {
         an h2
         ----------"#
     });
-}
\ No newline at end of file
+}
+
+#[test]
+fn diagnostics() {
+    let example = indoc! { r#"
+        <html>
+        <head><title>Not reported</title></head>
+        <body>
+            <script>alert("skipped");</script>
+            <form><input name="q"> Search</form>
+            <script>alert("also skipped");</script>
+        </body>
+        </html>
+    "# };
+
+    let (out, diagnostics) = parse_html::to_md(example);
+    assert_eq!(out.trim(), "Search");
+    assert_eq!(diagnostics.summary(), "Skipped 2 tags (script ×2). 2 tags unsupported (form, input).");
+    assert_eq!(diagnostics.unhandled[1].path, "html > body > form");
+}
+
+
+/// A typical blog layout: nav, sidebar, footer, and a post in a plain <div>.
+#[test]
+fn extract_main_blog() {
+    let example = indoc! { r#"
+        <html><body>
+        <div id="nav"><a href="/">Home</a> <a href="/about">About</a> <a href="/archive">Archive, by year</a></div>
+        <div class="wrapper">
+            <div class="sidebar">
+                <p><a href="/tags/a">A tag with a long name, for testing</a> <a href="/tags/b">Another tag, also long</a></p>
+            </div>
+            <div class="post">
+                <h1>Post title</h1>
+                <p>This is the first paragraph of the post, which has plenty of text, and some commas.</p>
+                <p>This is the second paragraph. It <a href="/x">links</a> to something, but is mostly text.</p>
+            </div>
+        </div>
+        <footer><p>Copyright, all rights reserved, etc. etc. etc.</p></footer>
+        </body></html>
+    "# };
+
+    let main = parse_html::extract_main(example);
+    assert!(main.starts_with(r#"<div class="post">"#), "Got: {main}");
+    assert!(main.contains("Post title"));
+    assert!(!main.contains("Archive"));
+    assert!(!main.contains("Copyright"));
+}
+
+/// A news layout with an <article> and a comment section that has more paragraphs than the article.
+#[test]
+fn extract_main_news() {
+    let example = indoc! { r#"
+        <html><body>
+        <main>
+            <article>
+                <h1>Headline</h1>
+                <p>The story starts here, and it goes on for a while, with lots of details.</p>
+                <p>The story continues with more details, quotes, and so on, and so forth.</p>
+            </article>
+            <section id="comments">
+                <p>First! This comment is long enough to count as a paragraph.</p>
+                <p>Another comment, which is also long enough to count as a paragraph.</p>
+                <p>Yet another comment, which is also long enough to count as a paragraph.</p>
+            </section>
+        </main>
+        </body></html>
+    "# };
+
+    let main = parse_html::extract_main(example);
+    assert!(main.starts_with("<article>"), "Got: {main}");
+    assert!(!main.contains("First!"));
+}
+
+/// If there aren't any paragraphs, we don't have anything to go on, so leave the page alone.
+#[test]
+fn extract_main_no_candidates() {
+    let example = "<html><body><ul><li><a href='/a'>A</a></li></ul></body></html>";
+    assert_eq!(parse_html::extract_main(example), example);
+}
@@ -1 +1,2 @@
+pub mod feed;
 pub mod html;
\ No newline at end of file
@@ -0,0 +1,24 @@
+//! The X11 "primary selection": whatever text was last selected, which Linux users paste with a middle click.
+//! egui doesn't expose it, so we read it ourselves. Other platforms don't have one.
+
+/// Whether this platform has a primary selection.
+pub const AVAILABLE: bool = cfg!(target_os = "linux");
+
+#[cfg(target_os = "linux")]
+pub fn primary() -> Option<String> {
+    use arboard::{Clipboard, GetExtLinux as _, LinuxClipboardKind};
+
+    let result = Clipboard::new().and_then(|mut it| it.get().clipboard(LinuxClipboardKind::Primary).text());
+    match result {
+        Ok(text) => Some(text),
+        Err(err) => {
+            log::debug!("Couldn't read the primary selection: {err}");
+            None
+        },
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn primary() -> Option<String> {
+    None
+}
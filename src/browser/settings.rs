@@ -0,0 +1,1076 @@
+//! User-configurable settings.
+//! These are persisted separately from the rest of the browser state, so that
+//! we can restore them on startup.
+
+use std::{ops::RangeInclusive, path::PathBuf};
+
+use eframe::egui::{self, vec2, Checkbox, ComboBox, ThemePreference, DragValue, FontFamily, Grid, RichText, Slider, TextEdit, Ui, UiBuilder};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::browser::{fonts, network::cache, selection, APP_ID};
+
+mod settings_test;
+
+/// The storage key we persist [`Settings`] under.
+pub const STORAGE_KEY: &str = "egemi-settings";
+
+pub const DEFAULT_HOME_PAGE: &str = "about:egemi";
+
+/// Schemes offered for [`Settings::default_scheme`].
+const DEFAULT_SCHEMES: &[&str] = &["gemini", "https", "http"];
+
+/// [`Settings::accept_types`] presets.
+pub const PREFER_GEMTEXT: &[&str] = &[
+    // See: https://developer.mozilla.org/en-US/docs/Glossary/Quality_values
+    "text/gemini; q=1",
+    "text/markdown; q=0.9",
+    "text/plain; q=0.8",
+    "text/*; q=0.7",
+    "*/*; q=0.1",
+];
+pub const PREFER_HTML: &[&str] = &[
+    "text/html; q=1",
+    "text/gemini; q=0.9",
+    "text/markdown; q=0.8",
+    "text/plain; q=0.7",
+    "text/*; q=0.6",
+    "*/*; q=0.1",
+];
+
+/// Default for [`Settings::max_history`].
+pub const DEFAULT_MAX_HISTORY: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Light, dark, or follow the system.
+    pub theme: ThemePreference,
+
+    /// Colors to use on top of the light or dark [`Self::theme`].
+    pub palette: Palette,
+
+    /// Of body text throughout the app, in points. Headings and other text are sized relative to it.
+    pub font_size: f32,
+
+    /// The first choice for proportional text, by name. Other fonts fill in glyphs it doesn't have. (ex: CJK)
+    pub proportional_font: String,
+
+    /// The first choice for monospace text. Preformatted art always uses [`fonts::NOTO_SANS_MONO`], to stay aligned.
+    pub monospace_font: String,
+
+    /// Opened on startup (unless we're given a URL) and in new tabs.
+    pub home_page: String,
+
+    /// Added to addresses typed into the location bar without a scheme. (ex: `example.com`)
+    pub default_scheme: String,
+
+    /// Scales the text of documents that don't have a rendering profile.
+    pub text_scale: f32,
+
+    /// Keep documents that don't have a rendering profile at most [`Self::max_content_width`] wide,
+    /// since long lines are hard to read on wide windows.
+    pub limit_width: bool,
+
+    /// In points.
+    pub max_content_width: f32,
+
+    /// Extra request headers, scoped by host.
+    pub host_headers: Vec<HostHeader>,
+
+    /// Content types to request from HTTP(S) servers, in order of preference. Sent as the `Accept` header.
+    pub accept_types: Vec<String>,
+
+    /// Remove tracking query parameters from http(s) URLs before navigating to them.
+    pub strip_tracking_params: bool,
+
+    /// Query parameters to remove. A trailing `*` matches any suffix.
+    pub tracking_params: Vec<String>,
+
+    /// Only show the main content of HTML pages. See [`extract_main`](crate::browser::parsers::html::extract_main).
+    pub article_mode: bool,
+
+    /// Center the first H1 of markdown/HTML pages as a title, like gemtext.
+    pub center_markdown_title: bool,
+
+    /// Show runs of gemtext links (like a capsule's footer) as a compact, wrapped row.
+    pub compact_link_menus: bool,
+
+    /// End gemtext code fences that are never closed before the first link after them,
+    /// instead of showing the rest of the page as code.
+    pub close_unclosed_fences: bool,
+
+    /// Show runs of blank gemtext lines as a single blank line.
+    pub collapse_blank_lines: bool,
+
+    /// Parse `1.`-style lines in gemtext as numbered list items, which Gemtext doesn't have.
+    pub numbered_lists: bool,
+
+    /// Collapse gemtext preformatted blocks that have alt text (ex: ASCII art), showing just the alt text.
+    pub collapse_captioned_blocks: bool,
+
+    /// Show images linked from gemtext, and embedded in markdown and HTML pages, within the page instead of as links.
+    /// Off by default, since they're loaded (often from other hosts) without the user asking.
+    pub inline_images: bool,
+
+    /// Open http(s) links in the system web browser, instead of egemi's (limited) HTML renderer.
+    pub open_web_links_externally: bool,
+
+    /// Offer to keep loading slow pages in the background, so that the user can keep browsing.
+    pub offer_background_loads: bool,
+
+    /// How long a page must be loading before we offer that.
+    pub background_after_secs: f32,
+
+    /// Middle-clicking the document or location bar opens the URL in the primary selection. (Linux only.)
+    pub middle_click_paste: bool,
+
+    /// Show a bar at the bottom of the window with the URL of the hovered link.
+    pub status_bar: bool,
+
+    /// Only render this many blocks (or lines) of a document at first, with a button to show more.
+    /// Keeps huge pages responsive. 0 renders everything.
+    pub max_blocks: usize,
+
+    /// Multiplies how far the document scrolls per mouse wheel notch.
+    pub scroll_multiplier: f32,
+
+    /// Animate scrolling, instead of jumping straight to the new position.
+    pub smooth_scroll: bool,
+
+    /// Turn off all animations, including [`Self::smooth_scroll`], for users who find them distracting or nauseating.
+    pub reduce_motion: bool,
+
+    /// Fetch the title of a link's target when hovering over it, if it uses the same protocol as the current page.
+    /// Off by default, since it contacts servers the user hasn't chosen to visit.
+    pub link_previews: bool,
+
+    /// How long to hover over a link before previewing it.
+    pub link_preview_secs: f32,
+
+    /// "Sandbox mode": Only allow file:// URLs inside this directory. Empty allows any file.
+    pub file_root: String,
+
+    /// When to tell HTTP(S) servers which page linked to them.
+    pub referer_policy: RefererPolicy,
+
+    /// Hosts (and their subdomains) or host/path prefixes to refuse to load. (ex: `example.com/news/`)
+    pub blocklist: Vec<String>,
+
+    /// Where Gemini client certificates are kept. Empty uses egemi's data directory.
+    pub cert_dir: String,
+
+    /// Which client certificate to present to which Gemini capsule.
+    /// Each is only sent to the server certificate that capsule had when it was first sent there.
+    /// (See [`known_hosts`](crate::browser::network::known_hosts).)
+    pub client_certs: Vec<ClientCertMapping>,
+
+    /// Named sets of rendering overrides. See [`Self::host_profiles`].
+    pub render_profiles: Vec<RenderProfile>,
+
+    /// Which rendering profile to use for which host.
+    pub host_profiles: Vec<HostProfile>,
+
+    /// Send HTTP(S) requests through this proxy. (ex: `http://localhost:8080`) Empty uses the
+    /// system's proxy environment variables. Gemini (and Titan) requests always connect directly.
+    pub http_proxy: String,
+
+    /// How to look up host names, for HTTP(S) and Gemini.
+    pub dns_resolver: DnsResolver,
+
+    /// Addresses to use for hosts instead of looking them up, like a hosts file: `<ip> <host> [<host>...]`.
+    /// Useful for testing staging servers.
+    pub host_overrides: Vec<String>,
+
+    /// Follow HTTP(S) redirects automatically. Otherwise, show a link to where the server redirects.
+    /// (Gemini redirects within Geminispace are always followed.)
+    pub follow_redirects: bool,
+
+    /// Give up on requests that take longer than this, including any retries. 0 waits forever.
+    pub request_timeout_secs: f32,
+
+    /// How many times to retry Gemini requests that fail to connect.
+    pub gemini_retries: u32,
+
+    /// How long to wait before the first retry. Each retry waits twice as long as the last.
+    pub retry_backoff_secs: f32,
+
+    /// Max number of entries to keep in each history list. The oldest entries are dropped first.
+    pub max_history: usize,
+
+    /// Max number of pages and images to keep in the in-memory cache.
+    pub cache_entries: usize,
+
+    /// Max total size of the in-memory cache.
+    pub cache_megabytes: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: ThemePreference::System,
+            palette: Palette::default(),
+            font_size: DEFAULT_FONT_SIZE,
+            proportional_font: fonts::NOTO_SANS.into(),
+            monospace_font: fonts::NOTO_SANS_MONO.into(),
+            home_page: DEFAULT_HOME_PAGE.into(),
+            default_scheme: "gemini".into(),
+            text_scale: 1.0,
+            limit_width: false,
+            max_content_width: 700.0,
+            host_headers: Vec::new(),
+            accept_types: PREFER_GEMTEXT.iter().map(|it| it.to_string()).collect(),
+            strip_tracking_params: false,
+            tracking_params: [
+                "utm_*",
+                "fbclid",
+                "gclid",
+                "dclid",
+                "msclkid",
+                "mc_cid",
+                "mc_eid",
+                "igshid",
+                "_hsenc",
+                "_hsmi",
+                "yclid",
+            ].into_iter().map(String::from).collect(),
+            article_mode: false,
+            center_markdown_title: false,
+            compact_link_menus: false,
+            close_unclosed_fences: false,
+            collapse_blank_lines: false,
+            numbered_lists: false,
+            collapse_captioned_blocks: false,
+            inline_images: false,
+            open_web_links_externally: false,
+            offer_background_loads: true,
+            background_after_secs: 5.0,
+            middle_click_paste: true,
+            status_bar: true,
+            max_blocks: 5000,
+            scroll_multiplier: 1.0,
+            smooth_scroll: true,
+            reduce_motion: false,
+            link_previews: false,
+            link_preview_secs: 0.75,
+            file_root: String::new(),
+            referer_policy: RefererPolicy::None,
+            blocklist: Vec::new(),
+            cert_dir: String::new(),
+            client_certs: Vec::new(),
+            render_profiles: Vec::new(),
+            host_profiles: Vec::new(),
+            http_proxy: String::new(),
+            dns_resolver: DnsResolver::System,
+            host_overrides: Vec::new(),
+            follow_redirects: false,
+            request_timeout_secs: 30.0,
+            gemini_retries: 2,
+            retry_backoff_secs: 0.5,
+            max_history: DEFAULT_MAX_HISTORY,
+            cache_entries: cache::DEFAULT_MAX_ENTRIES,
+            cache_megabytes: cache::DEFAULT_MAX_BYTES / 1024 / 1024,
+        }
+    }
+}
+
+/// A header that will be sent with every request to a single host.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct HostHeader {
+    /// Matched exactly (ignoring case) against the request URL's host. No wildcards, so that
+    /// secrets don't leak to subdomains or other origins.
+    pub host: String,
+    pub name: String,
+    pub value: String,
+}
+
+impl HostHeader {
+    pub fn matches_host(&self, host: &str) -> bool {
+        self.host.trim().eq_ignore_ascii_case(host)
+    }
+}
+
+/// Whether to send a `Referer` header when following a link to an HTTP(S) page.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RefererPolicy {
+    /// Never send one.
+    #[default]
+    None,
+
+    /// Only to the origin (scheme, host, and port) of the linking page.
+    SameOrigin,
+
+    /// To any HTTP(S) page, except from an HTTPS page to an HTTP one.
+    Always,
+}
+
+impl RefererPolicy {
+    pub const ALL: &[Self] = &[Self::None, Self::SameOrigin, Self::Always];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "Never",
+            Self::SameOrigin => "Same site only",
+            Self::Always => "Always",
+        }
+    }
+
+    /// The `Referer` header to send when following a link from `from` to `to`, if any.
+    /// Only web pages are sent, without their fragment or any user name and password.
+    pub fn referer(self, from: &Url, to: &Url) -> Option<String> {
+        let web = |url: &Url| matches!(url.scheme(), "http" | "https");
+        if !web(from) || !web(to) {
+            return None;
+        }
+        let allowed = match self {
+            Self::None => false,
+            Self::SameOrigin => from.origin() == to.origin(),
+            Self::Always => !(from.scheme() == "https" && to.scheme() == "http"),
+        };
+        if !allowed {
+            return None;
+        }
+        let mut referer = from.clone();
+        referer.set_fragment(None);
+        let _ = referer.set_username("");
+        let _ = referer.set_password(None);
+        Some(referer.into())
+    }
+}
+
+/// egui's default size for body text.
+pub const DEFAULT_FONT_SIZE: f32 = 12.5;
+
+/// Colors for the whole app. Each has a light and a dark variant. See [`crate::browser::theme`].
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    /// egui's own colors.
+    #[default]
+    Default,
+    /// Warm, low-contrast colors, like a printed page.
+    Sepia,
+    HighContrast,
+}
+
+impl Palette {
+    pub const ALL: &[Self] = &[Self::Default, Self::Sepia, Self::HighContrast];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::Sepia => "Sepia",
+            Self::HighContrast => "High contrast",
+        }
+    }
+
+    /// Radio buttons to pick a palette. Returns whether it changed.
+    pub fn radio_buttons(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        for palette in Self::ALL {
+            changed |= ui.radio_value(self, *palette, palette.label()).changed();
+        }
+        changed
+    }
+}
+
+/// Which DNS resolver to look up hosts with. Besides the system's, these are encrypted, so that the network can't
+/// see (or tamper with) which hosts you visit.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DnsResolver {
+    #[default]
+    System,
+    CloudflareHttps,
+    CloudflareTls,
+    GoogleHttps,
+    GoogleTls,
+    Quad9Https,
+    Quad9Tls,
+}
+
+impl DnsResolver {
+    pub const ALL: &[Self] = &[
+        Self::System,
+        Self::CloudflareHttps, Self::CloudflareTls,
+        Self::GoogleHttps, Self::GoogleTls,
+        Self::Quad9Https, Self::Quad9Tls,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::System => "System",
+            Self::CloudflareHttps => "Cloudflare (DNS over HTTPS)",
+            Self::CloudflareTls => "Cloudflare (DNS over TLS)",
+            Self::GoogleHttps => "Google (DNS over HTTPS)",
+            Self::GoogleTls => "Google (DNS over TLS)",
+            Self::Quad9Https => "Quad9 (DNS over HTTPS)",
+            Self::Quad9Tls => "Quad9 (DNS over TLS)",
+        }
+    }
+}
+
+/// Present the client certificate `name` to Gemini URLs starting with `prefix`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct ClientCertMapping {
+    /// A host, and optional path prefix. (ex: `station.martinrue.com/`)
+    /// Unlike the blocklist, this does NOT match subdomains, so that we don't identify
+    /// ourselves to capsules the user didn't choose.
+    pub prefix: String,
+    pub name: String,
+}
+
+impl ClientCertMapping {
+    pub fn matches(&self, url: &Url) -> bool {
+        let prefix = self.prefix.trim();
+        let prefix = prefix.strip_prefix("gemini://").unwrap_or(prefix);
+        let (host, path) = match prefix.find('/') {
+            Some(index) => prefix.split_at(index),
+            None => (prefix, "/"),
+        };
+        url.scheme() == "gemini"
+            && !host.is_empty()
+            && url.host_str().is_some_and(|it| it.eq_ignore_ascii_case(host))
+            && url.path().starts_with(path)
+    }
+}
+
+/// Rendering overrides for capsules with particular quirks. (ex: tiny text, or very wide tables.)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RenderProfile {
+    pub name: String,
+
+    /// Multiplies the size of all text.
+    pub text_scale: f32,
+
+    /// Max width of the document, in points. 0 means no limit.
+    pub max_width: f32,
+
+    /// Use a monospace font for all text.
+    pub monospace: bool,
+
+    /// Show links to images, instead of the images themselves.
+    pub hide_images: bool,
+}
+
+impl Default for RenderProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            text_scale: 1.0,
+            max_width: 0.0,
+            monospace: false,
+            hide_images: false,
+        }
+    }
+}
+
+impl RenderProfile {
+    /// Show `add_contents` with this profile's text style and width.
+    pub fn show<R>(&self, ui: &mut Ui, add_contents: impl FnOnce(&mut Ui) -> R) -> R {
+        let mut rect = ui.available_rect_before_wrap();
+        let margin = (rect.width() - self.max_width) / 2.0;
+        if self.max_width > 0.0 && margin > 0.0 {
+            rect = rect.shrink2(vec2(margin, 0.0));
+        }
+        ui.scope_builder(UiBuilder::new().max_rect(rect), |ui| {
+            let style = ui.style_mut();
+            for font in style.text_styles.values_mut() {
+                font.size *= self.text_scale.clamp(0.25, 5.0);
+                if self.monospace {
+                    font.family = FontFamily::Monospace;
+                }
+            }
+            add_contents(ui)
+        }).inner
+    }
+}
+
+/// Use the [`RenderProfile`] named `profile` for `host`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct HostProfile {
+    /// Also matches subdomains, like the blocklist.
+    pub host: String,
+    pub profile: String,
+}
+
+impl HostProfile {
+    pub fn matches_host(&self, host: &str) -> bool {
+        let pattern = self.host.trim().trim_start_matches('.');
+        if pattern.is_empty() {
+            return false;
+        }
+        let host = host.to_ascii_lowercase();
+        let pattern = pattern.to_ascii_lowercase();
+        host == pattern || host.strip_suffix(&pattern).is_some_and(|it| it.ends_with('.'))
+    }
+}
+
+/// `value`, within `range`. `default` if it's NaN.
+fn clamp(value: &mut f32, default: f32, range: RangeInclusive<f32>) {
+    *value = if value.is_nan() { default } else { value.clamp(*range.start(), *range.end()) };
+}
+
+impl Settings {
+    /// Load settings from eframe's storage, if there are any.
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+
+    /// Keep numbers within the ranges the settings UI allows, since a hand-edited config file can have anything.
+    /// (ex: `inf`, which can't be a [`Duration`](std::time::Duration).) Ones that aren't numbers get their defaults.
+    pub fn clamp(&mut self) {
+        let defaults = Settings::default();
+        clamp(&mut self.font_size, defaults.font_size, 8.0..=32.0);
+        clamp(&mut self.text_scale, defaults.text_scale, 0.5..=3.0);
+        clamp(&mut self.max_content_width, defaults.max_content_width, 200.0..=4000.0);
+        clamp(&mut self.scroll_multiplier, defaults.scroll_multiplier, 0.25..=5.0);
+        clamp(&mut self.background_after_secs, defaults.background_after_secs, 0.0..=120.0);
+        clamp(&mut self.link_preview_secs, defaults.link_preview_secs, 0.0..=10.0);
+        clamp(&mut self.request_timeout_secs, defaults.request_timeout_secs, 0.0..=600.0);
+        clamp(&mut self.retry_backoff_secs, defaults.retry_backoff_secs, 0.0..=30.0);
+        self.cache_entries = self.cache_entries.min(10_000);
+        self.cache_megabytes = self.cache_megabytes.min(4096);
+        // With no history at all, each visit would be trimmed as soon as it's added:
+        self.max_history = self.max_history.clamp(1, 100_000);
+        self.gemini_retries = self.gemini_retries.min(10);
+        let profile_defaults = RenderProfile::default();
+        for profile in &mut self.render_profiles {
+            clamp(&mut profile.text_scale, profile_defaults.text_scale, 0.25..=5.0);
+            clamp(&mut profile.max_width, profile_defaults.max_width, 0.0..=10_000.0);
+        }
+    }
+
+    pub fn file_root(&self) -> Option<PathBuf> {
+        let root = self.file_root.trim();
+        (!root.is_empty()).then(|| root.into())
+    }
+
+    pub fn cert_dir(&self) -> Option<PathBuf> {
+        let dir = self.cert_dir.trim();
+        if !dir.is_empty() {
+            return Some(dir.into());
+        }
+        eframe::storage_dir(APP_ID).map(|it| it.join("certs"))
+    }
+
+    /// The page to open on startup and in new tabs.
+    pub fn home_page(&self) -> &str {
+        match self.home_page.trim() {
+            "" => DEFAULT_HOME_PAGE,
+            page => page,
+        }
+    }
+
+    /// For pages without a rendering profile.
+    pub fn default_profile(&self) -> RenderProfile {
+        RenderProfile {
+            text_scale: self.text_scale,
+            max_width: if self.limit_width { self.max_content_width } else { 0.0 },
+            ..RenderProfile::default()
+        }
+    }
+
+    /// Also shown in the View menu.
+    pub fn content_width_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui.checkbox(&mut self.limit_width, "Limit content width to")
+                .on_hover_text("Rendering profiles set their own max width.")
+                .changed();
+            let width = DragValue::new(&mut self.max_content_width).range(200.0..=4000.0).speed(10).suffix(" pt");
+            changed |= ui.add_enabled(self.limit_width, width).changed();
+        });
+        changed
+    }
+
+    /// The rendering profile to use for `url`, if the user has assigned one to its host.
+    pub fn render_profile(&self, url: &Url) -> Option<&RenderProfile> {
+        let host = url.host_str()?;
+        let mapping = self.host_profiles.iter().find(|it| it.matches_host(host))?;
+        self.render_profiles.iter().find(|it| it.name == mapping.profile)
+    }
+
+    /// Whether `param` is a tracking query parameter we should strip.
+    pub fn is_tracking_param(&self, param: &str) -> bool {
+        self.tracking_params.iter()
+            .map(|it| it.trim())
+            .filter(|it| !it.is_empty())
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => param.starts_with(prefix),
+                None => param == pattern,
+            })
+    }
+
+    /// Show an editor for the settings.
+    /// Returns true if anything changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        changed |= self.general_ui(ui);
+        ui.separator();
+        changed |= self.reading_ui(ui);
+        ui.separator();
+        changed |= self.profiles_ui(ui);
+        ui.separator();
+        changed |= self.privacy_ui(ui);
+        ui.separator();
+        changed |= self.history_ui(ui);
+        ui.separator();
+        changed |= self.network_ui(ui);
+        ui.separator();
+        changed |= self.accept_ui(ui);
+        ui.separator();
+        changed |= self.headers_ui(ui);
+        ui.separator();
+        changed |= self.certs_ui(ui);
+        changed
+    }
+
+    fn general_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("General");
+        let theme = self.theme;
+        self.theme.radio_buttons(ui);
+        changed |= self.theme != theme;
+
+        ui.horizontal(|ui| {
+            ui.label("Colors:");
+            ComboBox::from_id_salt("palette")
+                .selected_text(self.palette.label())
+                .show_ui(ui, |ui| {
+                    for palette in Palette::ALL {
+                        changed |= ui.selectable_value(&mut self.palette, *palette, palette.label()).changed();
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Font size:")
+                .on_hover_text("Of the whole app. Documents are also scaled by their text size, below.");
+            let size = Slider::new(&mut self.font_size, 8.0..=32.0).step_by(0.5).suffix(" pt");
+            changed |= ui.add(size).changed();
+            if ui.add_enabled(self.font_size != DEFAULT_FONT_SIZE, egui::Button::new("Reset").small()).clicked() {
+                self.font_size = DEFAULT_FONT_SIZE;
+                changed = true;
+            }
+        });
+
+        Grid::new("fonts").num_columns(2).show(ui, |ui| {
+            ui.label("Font:")
+                .on_hover_text("Tried first for text. Characters it doesn't have come from the other fonts.");
+            changed |= fonts::font_combo_box(ui, "proportional font", &mut self.proportional_font);
+            ui.end_row();
+
+            ui.label("Monospace font:");
+            changed |= fonts::font_combo_box(ui, "monospace font", &mut self.monospace_font);
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Home page:");
+            changed |= ui.add(TextEdit::singleline(&mut self.home_page).hint_text(DEFAULT_HOME_PAGE)).changed();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Default scheme:")
+                .on_hover_text("Used for addresses typed into the location bar without one. (ex: example.com)");
+            ComboBox::from_id_salt("default scheme")
+                .selected_text(&self.default_scheme)
+                .show_ui(ui, |ui| {
+                    for scheme in DEFAULT_SCHEMES {
+                        changed |= ui.selectable_value(&mut self.default_scheme, scheme.to_string(), *scheme).changed();
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Text size:")
+                .on_hover_text("Rendering profiles set their own text size.");
+            let scale = Slider::new(&mut self.text_scale, 0.5..=3.0).suffix("×");
+            changed |= ui.add(scale).changed();
+        });
+        changed |= self.content_width_ui(ui);
+
+        changed
+    }
+
+    fn reading_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("Reading");
+        changed |= ui.checkbox(&mut self.article_mode, "Article mode")
+            .on_hover_text("Show only the main content of HTML pages, without navigation, sidebars, etc.\nTakes effect on the next page load.")
+            .changed();
+
+        changed |= ui.checkbox(&mut self.compact_link_menus, "Compact link menus")
+            .on_hover_text("Show 3 or more consecutive gemtext links as a wrapped row, instead of one per line.\nTakes effect on the next page load.")
+            .changed();
+
+        changed |= ui.checkbox(&mut self.close_unclosed_fences, "Close unclosed code fences")
+            .on_hover_text("If a gemtext page opens a code block (```) and never closes it, end it before the first link after it, instead of showing the rest of the page as code.\nTakes effect on the next page load.")
+            .changed();
+
+        changed |= ui.checkbox(&mut self.collapse_blank_lines, "Collapse blank lines")
+            .on_hover_text("Show several blank lines in a row in gemtext pages as just one.\nTakes effect on the next page load.")
+            .changed();
+
+        changed |= ui.checkbox(&mut self.numbered_lists, "Numbered lists")
+            .on_hover_text("Show gemtext lines like \"1. text\" as numbered list items. Gemtext only defines \"*\" lists, so this isn't spec-compliant.\nTakes effect on the next page load.")
+            .changed();
+
+        changed |= ui.checkbox(&mut self.collapse_captioned_blocks, "Collapse captioned preformatted blocks")
+            .on_hover_text("Show gemtext preformatted blocks that have alt text (like ASCII art) collapsed, under their alt text.\nTakes effect on the next page load.")
+            .changed();
+
+        changed |= ui.checkbox(&mut self.center_markdown_title, "Center markdown and HTML titles")
+            .on_hover_text("Show the first heading of markdown and HTML pages as a centered title, like gemtext.")
+            .changed();
+
+        changed |= ui.checkbox(&mut self.inline_images, "Inline images")
+            .on_hover_text("Show images linked from gemtext pages, and embedded in markdown and HTML pages, within the page.\nAlso in the View menu.")
+            .changed();
+
+        if selection::AVAILABLE {
+            changed |= ui.checkbox(&mut self.middle_click_paste, "Middle-click to open selected URLs")
+                .on_hover_text("Middle-click the page or location bar to go to the URL you last selected, in any app.")
+                .changed();
+        }
+
+        changed |= ui.checkbox(&mut self.status_bar, "Status bar")
+            .on_hover_text("Show the URL of the link under the mouse at the bottom of the window.")
+            .changed();
+
+        changed |= ui.checkbox(&mut self.open_web_links_externally, "Open web links in your browser")
+            .on_hover_text("Open http(s) links in your system's web browser.\nHold Alt while clicking to open them in egemi instead.")
+            .changed();
+
+        ui.horizontal(|ui| {
+            ui.label("Render at most:")
+                .on_hover_text("Very long pages show a button to render more.\n0 always renders everything.\nTakes effect on the next page load.");
+            changed |= ui.add(DragValue::new(&mut self.max_blocks).range(0..=1_000_000).speed(50).suffix(" blocks")).changed();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Scroll speed:");
+            let speed = Slider::new(&mut self.scroll_multiplier, 0.25..=5.0)
+                .logarithmic(true)
+                .suffix("×");
+            changed |= ui.add(speed).changed();
+        });
+        changed |= ui.add_enabled(!self.reduce_motion, Checkbox::new(&mut self.smooth_scroll, "Smooth scrolling")).changed();
+        changed |= ui.checkbox(&mut self.reduce_motion, "Reduce motion")
+            .on_hover_text("Don't animate scrolling, menus, or panels.")
+            .changed();
+
+        ui.horizontal(|ui| {
+            changed |= ui.checkbox(&mut self.offer_background_loads, "Offer to load slow pages in the background after")
+                .changed();
+            let secs = DragValue::new(&mut self.background_after_secs).range(0.0..=120.0).suffix(" s");
+            changed |= ui.add_enabled(self.offer_background_loads, secs).changed();
+        });
+
+        changed
+    }
+
+    fn profiles_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("Rendering Profiles");
+        ui.label("Overrides for capsules that are hard to read with the usual settings.");
+
+        let mut remove = None;
+        Grid::new("render profiles").num_columns(5).striped(true).show(ui, |ui| {
+            ui.strong("Name");
+            ui.strong("Text size");
+            ui.strong("Max width");
+            ui.end_row();
+
+            for (index, profile) in self.render_profiles.iter_mut().enumerate() {
+                changed |= ui.add(TextEdit::singleline(&mut profile.name).hint_text("Tiny text")).changed();
+                changed |= ui.add(DragValue::new(&mut profile.text_scale).range(0.25..=5.0).speed(0.05).suffix("×")).changed();
+                let width = DragValue::new(&mut profile.max_width)
+                    .range(0.0..=10_000.0)
+                    .custom_formatter(|it, _| if it <= 0.0 { "None".into() } else { format!("{it}") });
+                changed |= ui.add(width).changed();
+                ui.horizontal(|ui| {
+                    changed |= ui.checkbox(&mut profile.monospace, "Monospace").changed();
+                    changed |= ui.checkbox(&mut profile.hide_images, "Hide images").changed();
+                });
+                if ui.button("🗑").on_hover_text("Remove").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = remove {
+            self.render_profiles.remove(index);
+            changed = true;
+        }
+        if ui.button("Add profile").clicked() {
+            self.render_profiles.push(RenderProfile::default());
+            changed = true;
+        }
+
+        ui.label("Hosts (and their subdomains) to use them for:");
+        let mut remove = None;
+        Grid::new("host profiles").num_columns(3).striped(true).show(ui, |ui| {
+            for (index, mapping) in self.host_profiles.iter_mut().enumerate() {
+                changed |= ui.add(TextEdit::singleline(&mut mapping.host).hint_text("example.com")).changed();
+                ComboBox::from_id_salt(("host profile", index))
+                    .selected_text(mapping.profile.as_str())
+                    .show_ui(ui, |ui| {
+                        for profile in &self.render_profiles {
+                            changed |= ui.selectable_value(&mut mapping.profile, profile.name.clone(), profile.name.as_str()).changed();
+                        }
+                    });
+                if ui.button("🗑").on_hover_text("Remove").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = remove {
+            self.host_profiles.remove(index);
+            changed = true;
+        }
+        if ui.button("Add host").clicked() {
+            self.host_profiles.push(HostProfile::default());
+            changed = true;
+        }
+
+        changed
+    }
+
+    fn privacy_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("Privacy");
+        changed |= ui.checkbox(&mut self.strip_tracking_params, "Strip tracking parameters from HTTP(S) URLs").changed();
+        ui.add_enabled_ui(self.strip_tracking_params, |ui| {
+            ui.label("Parameters to strip, one per line. A trailing * matches any suffix:");
+            let mut text = self.tracking_params.join("\n");
+            let edit = TextEdit::multiline(&mut text).desired_rows(4).code_editor();
+            if ui.add(edit).changed() {
+                self.tracking_params = text.split('\n').map(String::from).collect();
+                changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            changed |= ui.checkbox(&mut self.link_previews, "Preview link titles after hovering for")
+                .on_hover_text("Loads the link's page to show its title.\nOnly for links that use the same protocol as the current page.")
+                .changed();
+            let secs = DragValue::new(&mut self.link_preview_secs).range(0.0..=10.0).speed(0.05).suffix(" s");
+            changed |= ui.add_enabled(self.link_previews, secs).changed();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Tell websites which page linked to them:")
+                .on_hover_text("Sends the Referer header when following a link to an HTTP(S) page.");
+            ComboBox::from_id_salt("referer policy")
+                .selected_text(self.referer_policy.label())
+                .show_ui(ui, |ui| {
+                    for policy in RefererPolicy::ALL {
+                        changed |= ui.selectable_value(&mut self.referer_policy, *policy, policy.label()).changed();
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Only allow local files in:")
+                .on_hover_text("file:// URLs outside of this directory will be blocked. Leave empty to allow any file.");
+            changed |= ui.add(TextEdit::singleline(&mut self.file_root).hint_text("Any directory")).changed();
+        });
+
+        ui.label("Blocked hosts or URL prefixes, one per line. Hosts also block their subdomains:");
+        let mut text = self.blocklist.join("\n");
+        let edit = TextEdit::multiline(&mut text).desired_rows(4).code_editor().hint_text("example.com/news/");
+        if ui.add(edit).changed() {
+            self.blocklist = text.split('\n').map(String::from).collect();
+            changed = true;
+        }
+
+        changed
+    }
+
+    fn history_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("History");
+        ui.horizontal(|ui| {
+            ui.label("Max history entries:");
+            changed |= ui.add(DragValue::new(&mut self.max_history).range(1..=100_000)).changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Cache up to:")
+                .on_hover_text("Recently loaded pages and images are kept in memory, so going back and forward is instant.");
+            changed |= ui.add(DragValue::new(&mut self.cache_entries).range(0..=10_000).suffix(" pages")).changed();
+            ui.label("or");
+            changed |= ui.add(DragValue::new(&mut self.cache_megabytes).range(0..=4096).suffix(" MB")).changed();
+        });
+
+        changed
+    }
+
+    fn network_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("Network");
+        ui.horizontal(|ui| {
+            ui.label("HTTP(S) proxy:")
+                .on_hover_text("Gemini requests don't use the proxy.\nLeave empty to use the HTTP_PROXY/HTTPS_PROXY environment variables.");
+            let edit = TextEdit::singleline(&mut self.http_proxy).hint_text("http://localhost:8080");
+            changed |= ui.add(edit).changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("DNS resolver:")
+                .on_hover_text("Encrypted DNS keeps the network from seeing which hosts you look up.\nThe proxy, if any, looks up HTTP(S) hosts itself.");
+            ComboBox::from_id_salt("dns resolver")
+                .selected_text(self.dns_resolver.label())
+                .show_ui(ui, |ui| {
+                    for resolver in DnsResolver::ALL {
+                        changed |= ui.selectable_value(&mut self.dns_resolver, *resolver, resolver.label()).changed();
+                    }
+                });
+        });
+        ui.label("Host overrides, one per line, like a hosts file:")
+            .on_hover_text("Connect to these addresses instead of looking the hosts up. Useful for testing staging servers.");
+        let mut text = self.host_overrides.join("\n");
+        let edit = TextEdit::multiline(&mut text).desired_rows(2).code_editor().hint_text("203.0.113.5 staging.example.com");
+        if ui.add(edit).changed() {
+            self.host_overrides = text.split('\n').map(String::from).collect();
+            changed = true;
+        }
+        changed |= ui.checkbox(&mut self.follow_redirects, "Follow HTTP redirects automatically")
+            .on_hover_text("Only to other http(s) URLs. Redirects elsewhere always ask first.\nGemini redirects within Geminispace are always followed.")
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Give up on pages after:")
+                .on_hover_text("Applies to all requests, including retries. 0 waits forever.");
+            let timeout = DragValue::new(&mut self.request_timeout_secs).range(0.0..=600.0).speed(0.5).suffix(" s");
+            changed |= ui.add(timeout).changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Retry Gemini connection errors:")
+                .on_hover_text("Flaky networks can make connections fail. Errors from the server itself aren't retried.");
+            changed |= ui.add(DragValue::new(&mut self.gemini_retries).range(0..=10).suffix(" times")).changed();
+            ui.label("waiting");
+            let backoff = DragValue::new(&mut self.retry_backoff_secs).range(0.0..=30.0).speed(0.1).suffix(" s");
+            changed |= ui.add_enabled(self.gemini_retries > 0, backoff)
+                .on_hover_text("Doubles after each retry.")
+                .changed();
+        });
+
+        changed
+    }
+
+    fn accept_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("Content Types");
+        ui.label("Content types to ask HTTP(S) servers for, one per line. `q` is the relative preference:");
+        ui.horizontal(|ui| {
+            for (name, preset) in [("Prefer gemtext", PREFER_GEMTEXT), ("Prefer HTML", PREFER_HTML)] {
+                if ui.button(name).clicked() {
+                    self.accept_types = preset.iter().map(|it| it.to_string()).collect();
+                    changed = true;
+                }
+            }
+        });
+        let mut text = self.accept_types.join("\n");
+        let edit = TextEdit::multiline(&mut text).desired_rows(4).code_editor();
+        if ui.add(edit).changed() {
+            self.accept_types = text.split('\n').map(String::from).collect();
+            changed = true;
+        }
+
+        changed
+    }
+
+    fn headers_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("Request Headers");
+        ui.label("Headers sent with every HTTP(S) request to a specific host.");
+        ui.label(
+            RichText::new("⚠ Values are saved unencrypted and sent to that host. Only add secrets for hosts you trust.")
+                .color(ui.visuals().warn_fg_color)
+        );
+
+        let mut remove = None;
+        Grid::new("host headers").num_columns(4).striped(true).show(ui, |ui| {
+            ui.strong("Host");
+            ui.strong("Header");
+            ui.strong("Value");
+            ui.end_row();
+
+            for (index, header) in self.host_headers.iter_mut().enumerate() {
+                changed |= ui.add(TextEdit::singleline(&mut header.host).hint_text("example.com")).changed();
+                changed |= ui.add(TextEdit::singleline(&mut header.name).hint_text("Authorization")).changed();
+                changed |= ui.add(TextEdit::singleline(&mut header.value).password(true)).changed();
+                if ui.button("🗑").on_hover_text("Remove").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(index) = remove {
+            self.host_headers.remove(index);
+            changed = true;
+        }
+
+        if ui.button("Add header").clicked() {
+            self.host_headers.push(HostHeader::default());
+            changed = true;
+        }
+
+        changed
+    }
+
+    fn certs_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.heading("Client Certificates");
+        ui.label("Some Gemini capsules use certificates to identify you. Each is a <name>.crt and <name>.key file.");
+        ui.horizontal(|ui| {
+            ui.label("Certificate directory:");
+            let default = self.cert_dir().map(|it| it.display().to_string()).unwrap_or_default();
+            changed |= ui.add(TextEdit::singleline(&mut self.cert_dir).hint_text(default)).changed();
+        });
+
+        let mut remove = None;
+        Grid::new("client certs").num_columns(3).striped(true).show(ui, |ui| {
+            ui.strong("URL prefix");
+            ui.strong("Certificate name");
+            ui.end_row();
+
+            for (index, mapping) in self.client_certs.iter_mut().enumerate() {
+                changed |= ui.add(TextEdit::singleline(&mut mapping.prefix).hint_text("example.com/")).changed();
+                changed |= ui.add(TextEdit::singleline(&mut mapping.name).hint_text("example.com")).changed();
+                if ui.button("🗑").on_hover_text("Remove").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(index) = remove {
+            self.client_certs.remove(index);
+            changed = true;
+        }
+
+        if ui.button("Add certificate").clicked() {
+            self.client_certs.push(ClientCertMapping::default());
+            changed = true;
+        }
+
+        changed
+    }
+}
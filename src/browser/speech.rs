@@ -0,0 +1,113 @@
+//! Reads documents aloud with the system's text-to-speech.
+//! Only built with the `tts` feature, since that needs extra system libraries.
+
+use std::{fmt::Debug, sync::{atomic::{AtomicUsize, Ordering}, Arc}};
+
+use log::warn;
+use tts::Tts;
+
+/// Reads a list of texts (usually one per document block) in order.
+pub struct Reader {
+    tts: Tts,
+    texts: Vec<String>,
+    state: State,
+
+    /// Index in `texts` of the first text we queued up.
+    queued_from: usize,
+
+    /// How many queued texts have started being spoken. Updated from TTS callbacks, if supported.
+    started: Arc<AtomicUsize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Stopped,
+    Playing,
+    /// Paused, and will resume from this index.
+    Paused(usize),
+}
+
+// Tts isn't Debug.
+impl Debug for Reader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reader")
+            .field("texts", &self.texts.len())
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Reader {
+    pub fn new() -> Result<Self, tts::Error> {
+        let tts = Tts::default()?;
+        let started = Arc::new(AtomicUsize::new(0));
+        if tts.supported_features().utterance_callbacks {
+            let started = started.clone();
+            tts.on_utterance_begin(Some(Box::new(move |_| {
+                started.fetch_add(1, Ordering::SeqCst);
+            })))?;
+        }
+        Ok(Self { tts, texts: Vec::new(), state: State::Stopped, queued_from: 0, started })
+    }
+
+    /// Replace what we're reading. Stops any current speech.
+    pub fn set_texts(&mut self, texts: Vec<String>) {
+        self.stop();
+        self.texts = texts;
+    }
+
+    pub fn has_texts(&self) -> bool {
+        !self.texts.is_empty()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        if self.state != State::Playing {
+            return false;
+        }
+        // Without is_speaking support, we can't tell when we've finished, so assume we haven't.
+        if !self.tts.supported_features().is_speaking {
+            return true;
+        }
+        self.tts.is_speaking().unwrap_or(true)
+    }
+
+    /// Start reading from the beginning, or resume where we were paused.
+    pub fn play(&mut self) {
+        let from = match self.state {
+            State::Paused(index) => index,
+            State::Stopped | State::Playing => 0,
+        };
+        self.silence();
+        self.queued_from = from;
+        self.started.store(0, Ordering::SeqCst);
+        for text in &self.texts[from.min(self.texts.len())..] {
+            if let Err(err) = self.tts.speak(text.as_str(), false) {
+                warn!("Couldn't queue speech: {err}");
+                break;
+            }
+        }
+        self.state = State::Playing;
+    }
+
+    /// Stop speaking, but remember where we were.
+    /// Resumes at the start of the text we were reading, since TTS can't resume mid-sentence.
+    pub fn pause(&mut self) {
+        if self.state != State::Playing {
+            return;
+        }
+        let current = self.queued_from + self.started.load(Ordering::SeqCst).saturating_sub(1);
+        self.silence();
+        self.state = State::Paused(current);
+    }
+
+    pub fn stop(&mut self) {
+        self.silence();
+        self.state = State::Stopped;
+    }
+
+    fn silence(&mut self) {
+        if let Err(err) = self.tts.stop() {
+            warn!("Couldn't stop speech: {err}");
+        }
+    }
+}
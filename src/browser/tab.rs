@@ -1,14 +1,35 @@
 
-use std::time::{Instant, SystemTime};
+use std::{collections::{HashMap, HashSet}, time::{Duration, Instant, SystemTime}};
 
-use eframe::egui::{self, style::ScrollAnimation, vec2, Button, Color32, Frame, Image, Key, Modifiers, OpenUrl, ScrollArea, Shadow, Stroke, TextBuffer, Ui, Vec2};
+use eframe::egui::{self, os::OperatingSystem, style::ScrollAnimation, UserAttentionType, ViewportCommand, vec2, Button, Color32, Frame, Image, Key, KeyboardShortcut, Modifiers, OpenUrl, PointerButton, Rect, ScrollArea, Shadow, SidePanel, Stroke, TextBuffer, Ui, Vec2};
 use egui_flex::{item, FlexAlignContent};
 use log::{debug, warn};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use tokio::task::JoinHandle;
 
-use crate::{browser::{network::{self, file::{self}, rt, LoadedResource, MultiLoader, SCow}, widgets::{markdown, DocWidget}}, gemtext::{self, Block}, gemtext_widget::GemtextWidget, svg::{self, menu}, widgets::textbox::TextBox};
+use crate::{browser::{closed::ClosedTab, export, history::Visit, network::{self, certs::ClientCerts, file::{self}, http::auth::{self, Credentials}, rt, titan::Upload, LoadedResource, MultiLoader, SCow, Timings}, selection, settings::{ClientCertMapping, Settings}, widgets::{link_menu::LinkAction, DocWidget, ImageData}}, svg::{self, menu}, util::{default_port, normalize_url, push_bounded, trim_oldest}, widgets::textbox::TextBox};
+
+use download::Download;
+use entered::EnteredUrls;
+use find::FindBar;
+use hints::{HintAction, LinkHints};
+use input::{InputPrompt, LoginPrompt, UploadPrompt};
+use preview::Previewer;
+use stream::{PageStream, Update};
+#[cfg(feature = "tts")]
+use crate::browser::speech;
+
+mod content;
+mod download;
+mod entered;
+mod find;
+mod hints;
+mod input;
+mod preview;
+mod stream;
+mod tab_test;
 
 /// A single tab in the browser.
 /// Each tab has its own history and URL.
@@ -21,15 +42,161 @@ pub struct Tab {
     history: Vec<SCow>, 
     forward_history: Vec<SCow>,
 
+    /// Pinned tabs are left alone when reloading all tabs, and come first when tabs are restored on startup.
+    /// See [`Self::set_pinned`].
+    #[serde(default)]
+    pinned: bool,
+
+    /// URLs the user typed into the location bar, for Up/Down.
+    #[serde(default)]
+    entered: EnteredUrls,
+
     #[serde(skip)]
     document: Option<Box<dyn DocWidget>>,
 
+    /// Find-in-page, if it's open.
+    #[serde(skip)]
+    find: Option<FindBar>,
+
+    /// Keyboard link hints, while they're shown.
+    #[serde(skip)]
+    hints: Option<LinkHints>,
+
+    /// Shown instead of the document when a Gemini server asks for input.
+    #[serde(skip)]
+    input: Option<InputPrompt>,
+
+    /// Shown instead of the document when an HTTP server asks for basic auth.
+    #[serde(skip)]
+    login: Option<LoginPrompt>,
+
+    /// Shown instead of the document for titan:// URLs, to write what to upload.
+    #[serde(skip)]
+    upload: Option<UploadPrompt>,
+
+    /// The URL we're currently loading. (Which may differ from [`Self::location`]. See [`Self::load`].)
+    #[serde(skip)]
+    requested: SCow,
+
+    /// The last URL that the blocklist refused to load.
+    #[serde(skip)]
+    blocked: Option<SCow>,
+
+    /// Skip the blocklist for the next load of this URL.
+    #[serde(skip)]
+    allow_once: Option<SCow>,
+
+    /// The page whose link we're following, for the next load. See [`Settings::referer_policy`].
+    #[serde(skip)]
+    referrer: Option<SCow>,
+
+    /// The last URL that asked for a client certificate.
+    #[serde(skip)]
+    cert_wanted: Option<SCow>,
+
+    /// The user asked to open this link in a new tab.
+    #[serde(skip)]
+    open_in_new_tab: Option<SCow>,
+
+    /// Pages visited since the browser last asked. See [`TabResponse::visits`].
+    #[serde(skip)]
+    visits: Vec<Visit>,
+
+    /// Titles of documents we've shown, for the back button's menu. Keyed by [`normalize_url`].
+    #[serde(skip)]
+    titles: HashMap<String, String>,
+
+    /// How far the user had scrolled pages they've left, so that going back (or forward) returns there.
+    /// Keyed by [`normalize_url`]. Only kept for pages that are still in the history.
+    #[serde(skip)]
+    scroll_offsets: HashMap<String, f32>,
+
+    /// The URL of the shown document, and how far it's scrolled.
+    #[serde(skip)]
+    scroll: Option<(SCow, f32)>,
+
+    /// Restore the next page's scroll offset from [`Self::scroll_offsets`]. Fresh links start at the top.
+    #[serde(skip)]
+    restore_scroll: bool,
+
+    /// The offset to give the next document, once it's loaded.
+    #[serde(skip)]
+    pending_scroll: Option<f32>,
+
+    /// Scroll the current document to this offset on the next frame.
+    #[serde(skip)]
+    scroll_to: Option<f32>,
+
+    /// The body of the current page, if we couldn't show it, so that the user can save it.
+    #[serde(skip)]
+    download: Option<Download>,
+
+    /// The user browsed to `about:settings`.
+    #[serde(skip)]
+    open_settings: bool,
+
+    /// We changed [`Self::settings`], and the browser should save them.
+    #[serde(skip)]
+    settings_changed: bool,
+
+    /// Reload once the browser gives us a loader that uses our changed settings. See [`Self::use_cert`].
+    #[serde(skip)]
+    reload_with_loader: bool,
+
     #[serde(skip)]
     loading: Option<JoinHandle<network::Result<LoadedResource>>>,
 
+    #[serde(skip)]
+    loading_since: Option<Instant>,
+
+    /// Shows the page that's [`Self::loading`] as it arrives, if it's gemtext.
+    #[serde(skip)]
+    stream: Option<PageStream>,
+
+    /// Slow pages the user chose to keep loading while they browse.
+    #[serde(skip)]
+    background: Vec<BackgroundLoad>,
+
+    /// Background loads that are ready to view.
+    #[serde(skip)]
+    finished: Vec<FinishedLoad>,
+
+    /// Shared with the browser's other tabs. See [`Self::set_loader`].
     #[serde(skip)]
     loader: MultiLoader,
 
+    #[serde(skip)]
+    settings: Settings,
+
+    /// Details about the current document, for the page info popup.
+    #[serde(skip)]
+    page_info: Option<PageInfo>,
+
+    /// Reads the document aloud.
+    #[cfg(feature = "tts")]
+    #[serde(skip)]
+    reader: Option<speech::Reader>,
+
+    /// Show image-heavy documents as a gallery, when they support it.
+    #[serde(skip)]
+    gallery: bool,
+
+    /// Show the document's headings in a sidebar.
+    #[serde(skip)]
+    outline: bool,
+
+    /// Builtin pages whose contents are generated by the browser. Keyed by URL.
+    #[serde(skip)]
+    dynamic_pages: HashMap<&'static str, String>,
+
+    /// Fetches titles for links the user hovers over.
+    #[serde(skip)]
+    previewer: Previewer,
+
+    /// Images the current document asked for.
+    #[serde(skip)]
+    image_loads: Vec<ImageLoad>,
+
     #[serde(skip)]
     shortcuts: Shortcuts,
 
@@ -44,9 +211,14 @@ pub struct Tab {
 
 impl Tab {
     pub fn show(&mut self, ui: &mut egui::Ui) -> TabResponse {
+        self.check_stream();
         self.check_tasks();
+        self.check_image_tasks(ui.ctx());
+        self.check_preview_tasks(ui.ctx());
+        self.check_background_tasks(ui.ctx());
 
         self.location_bar_ui(ui);
+        self.find_bar_ui(ui);
 
         let frame = Frame::new()
             .fill(ui.style().visuals.extreme_bg_color)
@@ -54,24 +226,152 @@ impl Tab {
             .outer_margin(0.0)
         ;
 
+        self.outline_ui(ui);
+
+        let mut hovered_link = None;
         frame.show(ui, |ui| {
             ui.push_id(self.doc_id, |ui| {
-                ScrollArea::vertical().show(ui, |ui| {
+                let mut scroll = ScrollArea::vertical()
+                    .wheel_scroll_multiplier(Vec2::splat(self.settings.scroll_multiplier))
+                    .animated(self.settings.smooth_scroll && !self.settings.reduce_motion);
+                if self.shortcuts.scroll_to_top(ui) {
+                    self.scroll_to_top();
+                }
+                if let Some(offset) = self.scroll_to.take() {
+                    scroll = scroll.vertical_scroll_offset(offset);
+                }
+                let profile = Url::parse(&self.location).ok()
+                    .and_then(|url| self.settings.render_profile(&url).cloned())
+                    .unwrap_or_else(|| self.settings.default_profile());
+                let output = scroll.show(ui, |ui| {
                     ui.expand_to_include_rect(ui.available_rect_before_wrap());
-                    let Some(document) = self.document.as_mut()  else {
-                        return;
-                    };
-                    let doc_ref = document.as_mut();
-                    let response = doc_ref.ui(ui);
-                    if let Some(url) = response.link_clicked {
-                        self.link_clicked(ui, url);
+                    profile.show(ui, |ui| {
+                        if let Some(input) = self.input.as_mut() {
+                            if let Some(url) = input.ui(ui) {
+                                self.submit_input(url);
+                            }
+                            return;
+                        }
+                        if let Some(login) = self.login.as_mut() {
+                            if let Some(credentials) = login.ui(ui) {
+                                self.submit_login(credentials);
+                            }
+                            return;
+                        }
+                        if let Some(prompt) = self.upload.as_mut() {
+                            if let Some(upload) = prompt.ui(ui) {
+                                self.submit_upload(upload);
+                            }
+                            return;
+                        }
+                        let Some(document) = self.document.as_mut()  else {
+                            return;
+                        };
+                        let doc_ref = document.as_mut();
+                        doc_ref.set_gallery(self.gallery && !profile.hide_images);
+                        doc_ref.set_inline_images(self.settings.inline_images && !profile.hide_images);
+                        let response = doc_ref.ui(ui);
+                        if let Some(url) = response.link_clicked {
+                            self.link_clicked(ui, url);
+                        }
+                        if let Some((action, link)) = response.link_action {
+                            self.link_action(ui, action, link);
+                        }
+                        self.link_hints(ui, response.links);
+                        hovered_link = response.link_hovered.as_deref().map(|link| {
+                            url_join(&self.location, link).map_or_else(|_| link.to_string(), String::from)
+                        });
+                        if self.settings.link_previews {
+                            let delay = Duration::try_from_secs_f32(self.settings.link_preview_secs).unwrap_or_default();
+                            if let Some(link) = self.previewer.hover(response.link_hovered, delay, ui.ctx()) {
+                                self.preview_link(link);
+                            }
+                        }
+                        if let Some(link) = response.load_image {
+                            self.load_image(link);
+                        }
+                    });
+                });
+                if let Some((_, offset)) = &mut self.scroll {
+                    *offset = output.state.offset.y;
+                }
+                // Once the top of the page is a screen or more away:
+                if output.state.offset.y > output.inner_rect.height() {
+                    let size = vec2(64.0, 24.0);
+                    let rect = Rect::from_min_size(output.inner_rect.right_bottom() - size - vec2(16.0, 8.0), size);
+                    let hover = format!("Scroll to the top ({})", ui.ctx().format_shortcut(&TOP));
+                    if ui.put(rect, Button::new("⬆ Top")).on_hover_text(hover).clicked() {
+                        self.scroll_to_top();
                     }
+                }
+                let middle_clicked = ui.input(|i| {
+                    i.pointer.button_clicked(PointerButton::Middle)
+                        && i.pointer.interact_pos().is_some_and(|pos| output.inner_rect.contains(pos))
                 });
+                if middle_clicked {
+                    self.open_selection();
+                }
             });
         });
 
         TabResponse {
             toggle_menu: { let tm = self.toggle_menu; self.toggle_menu = false; tm },
+            settings: std::mem::take(&mut self.settings_changed).then(|| self.settings.clone()),
+            open_in_new_tab: self.open_in_new_tab.take(),
+            open_settings: std::mem::take(&mut self.open_settings),
+            visits: std::mem::take(&mut self.visits),
+            hovered_link,
+        }
+    }
+
+    /// A sidebar listing the document's headings. Clicking one scrolls to it.
+    fn outline_ui(&mut self, ui: &mut egui::Ui) {
+        if self.shortcuts.outline(ui) {
+            self.outline = !self.outline;
+        }
+        let Some(document) = self.document.as_mut() else {
+            return;
+        };
+        let headings = document.outline();
+        if !self.outline || headings.is_empty() {
+            return;
+        }
+        let top_level = headings.iter().map(|it| it.level).min().unwrap_or(1);
+        SidePanel::left("outline")
+            .resizable(true)
+            .default_width(200.0)
+            .show_inside(ui, |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (index, heading) in headings.iter().enumerate() {
+                        let indent = f32::from(heading.level - top_level) * ui.spacing().indent;
+                        ui.horizontal(|ui| {
+                            ui.add_space(indent);
+                            let text = if heading.text.is_empty() { "(untitled)" } else { &heading.text };
+                            if ui.add(Button::new(text).frame(false).truncate()).clicked() {
+                                document.scroll_to_heading(index);
+                            }
+                        });
+                    }
+                });
+            });
+    }
+
+    fn find_bar_ui(&mut self, ui: &mut egui::Ui) {
+        if self.shortcuts.find(ui) {
+            if self.find.take().is_some() {
+                // Clear highlights:
+                if let Some(document) = self.document.as_mut() {
+                    document.find("", false);
+                }
+            } else {
+                self.find = Some(FindBar::new());
+            }
+        }
+        let Some(find) = self.find.as_mut() else {
+            return;
+        };
+        if find.ui(ui, self.document.as_mut(), self.doc_id) {
+            self.find = None;
         }
     }
 
@@ -100,13 +400,14 @@ impl Tab {
 
                 let back_enabled = self.history.len() > 1;
                 let back = ui.add_widget(item().enabled(back_enabled), svg::back());
-                if back.inner.clicked() {
+                if back.inner.clicked() || (back_enabled && self.shortcuts.back(ui.ui())) {
                     self.go_back();
                 }
+                back.inner.context_menu(|ui| self.back_menu_ui(ui));
 
                 let fw_enabled = !self.forward_history.is_empty();
                 let fw = ui.add_widget(item().enabled(fw_enabled), svg::forward());
-                if fw.inner.clicked() {
+                if fw.inner.clicked() || (fw_enabled && self.shortcuts.forward(ui.ui())) {
                     self.go_forward();
                 }
 
@@ -115,12 +416,32 @@ impl Tab {
                     self.reload();
                 }
 
+                let parent = self.history.last().and_then(|it| parent_url(it));
+                let up = ui.add_ui(item().enabled(parent.is_some()), |ui| {
+                    let hover = format!("Parent directory ({})", ui.ctx().format_shortcut(&PARENT));
+                    ui.button("⬆").on_hover_text(hover)
+                });
+                if let Some(parent) = parent && (up.inner.clicked() || self.shortcuts.parent(ui.ui())) {
+                    self.goto_url(parent.into());
+                }
+
                 let mut textbox = TextBox::new(self.location.to_mut())
                     .enabled(!is_loading);
                 ui.add_widget(item().grow(1.0).shrink(), &mut textbox);
-                if textbox.enter_pressed(ui.ui()) {
-                    self.goto_url(self.location.clone());
+                if let Some(key) = textbox.arrow_pressed(ui.ui()) {
+                    let text = match key {
+                        Key::ArrowUp => self.entered.older(textbox.text()).map(str::to_string),
+                        _ => self.entered.newer(),
+                    };
+                    if let Some(text) = text {
+                        textbox.set_text(ui.ui(), &text);
+                    }
+                } else if textbox.enter_pressed(ui.ui()) {
+                    self.submit_location();
+                } else if textbox.middle_clicked() {
+                    self.open_selection();
                 } else if textbox.lost_focus() {
+                    self.entered.reset();
                     if let Some(url) = self.history.last().map(Clone::clone) {
                         // !!! I'm surprised I can do this while textbox still has location.to_mut()!?!?
                         self.location = url;
@@ -132,62 +453,645 @@ impl Tab {
 
                 if is_loading {
                     ui.add_ui(item(), |ui| ui.spinner() );
+                    if let Some(attempt) = self.loader.retry_attempt(&self.requested) {
+                        ui.add_ui(item(), |ui| {
+                            ui.weak(format!("retrying… ({attempt})")).on_hover_text("The connection failed. Trying again.")
+                        });
+                    }
+                }
+                if self.is_slow() {
+                    ui.add_ui(item(), |ui| {
+                        let button = ui.button("⏳ Background")
+                            .on_hover_text("This page is slow. Keep loading it in the background, and get notified when it's done.");
+                        if button.clicked() {
+                            self.load_in_background();
+                        }
+                    });
+                }
+                if !self.finished.is_empty() {
+                    ui.add_ui(item(), |ui| {
+                        ui.menu_button(format!("🔔 {}", self.finished.len()), |ui| self.finished_ui(ui))
+                            .response
+                            .on_hover_text("Pages that finished loading in the background");
+                    });
+                }
+
+                #[cfg(feature = "tts")]
+                ui.add_ui(item(), |ui| {
+                    ui.menu_button("🔊", |ui| self.speech_ui(ui))
+                        .response
+                        .on_hover_text("Read aloud");
+                });
+
+                if self.document.as_ref().is_some_and(|it| !it.outline().is_empty()) {
+                    ui.add_ui(item(), |ui| {
+                        let hover = format!("Outline ({})", ui.ctx().format_shortcut(&OUTLINE));
+                        ui.toggle_value(&mut self.outline, "☰").on_hover_text(hover);
+                    });
+                }
+
+                if self.document.as_ref().is_some_and(|it| it.gallery_available()) {
+                    ui.add_ui(item(), |ui| {
+                        ui.toggle_value(&mut self.gallery, "🖼").on_hover_text("Show images as a gallery");
+                    });
+                }
+
+                ui.add_ui(item(), |ui| {
+                    ui.menu_button("ℹ", |ui| page_info_ui(ui, &self.location, self.page_info.as_ref()))
+                        .response
+                        .on_hover_text("Page info");
+                });
+
+                let toggle_menu = ui.add_widget(item(), menu());
+                if toggle_menu.inner.clicked() {
+                    self.toggle_menu = true;
+                }
+            });
+        });
+
+        ui.style_mut().spacing.item_spacing = old_spacing;    
+    }
+
+    #[cfg(feature = "tts")]
+    fn speech_ui(&mut self, ui: &mut Ui) {
+        if self.reader.is_none() {
+            match speech::Reader::new() {
+                Ok(reader) => self.reader = Some(reader),
+                Err(err) => {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("Text-to-speech unavailable: {err}"));
+                    return;
+                }
+            }
+        }
+        let Some(reader) = &mut self.reader else {
+            return;
+        };
+
+        if reader.is_playing() {
+            if ui.button("⏸ Pause").clicked() {
+                reader.pause();
+            }
+        } else if ui.button("▶ Play").clicked() {
+            if !reader.has_texts() {
+                let texts = self.document.as_ref().map(|it| it.readable_text()).unwrap_or_default();
+                reader.set_texts(texts);
+            }
+            reader.play();
+        }
+        if ui.button("⏹ Stop").clicked() {
+            reader.stop();
+        }
+    }
+
+    /// Set the contents of a builtin page. If it's currently open, it's updated immediately.
+    pub fn set_dynamic_page(&mut self, url: &'static str, text: String) {
+        if self.location == url && !self.is_loading() {
+            self.set_gemtext(&text);
+        }
+        self.dynamic_pages.insert(url, text);
+    }
+
+    /// The current document's title, if it has one.
+    pub fn page_title(&self) -> Option<String> {
+        self.document.as_ref()?.title().filter(|it| !it.is_empty())
+    }
+
+    /// A short name for the tab strip: the page's title, or else its URL.
+    pub fn title(&self) -> String {
+        let Some(url) = self.history.last() else {
+            return "New Tab".into();
+        };
+        let title = match self.page_title() {
+            Some(title) => title,
+            None => url_title(url),
+        };
+        if title.chars().count() <= MAX_TITLE_CHARS {
+            return title;
+        }
+        let mut title: String = title.chars().take(MAX_TITLE_CHARS - 1).collect();
+        title.push('…');
+        title
+    }
+
+    /// The current document as a standalone HTML page, and a file name for it. None if it can't be exported.
+    pub fn export_html(&self) -> Option<(String, String)> {
+        let document = self.document.as_ref()?;
+        let body = document.to_html()?;
+        let title = document.title().unwrap_or_else(|| self.title());
+        Some((export::file_name(&title), export::standalone(&title, &body)))
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+
+    /// What to remember about this tab when it's closed.
+    pub fn closed(&self) -> ClosedTab {
+        ClosedTab { history: self.history.clone() }
+    }
+
+    /// Restore a previously closed tab's history, and reload its page.
+    pub fn restore(&mut self, closed: ClosedTab) {
+        self.history = closed.history;
+        self.forward_history.clear();
+        if let Some(url) = self.history.pop() {
+            self.load_url(url);
+        }
+    }
+
+    /// Use the browser's `loader`, which it configures with its settings.
+    pub fn set_loader(&mut self, loader: MultiLoader) {
+        self.loader = loader;
+        if std::mem::take(&mut self.reload_with_loader) {
+            self.reload();
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &Settings) {
+        self.settings = settings.clone();
+
+        // History may have been restored from before the limit was lowered:
+        trim_oldest(&mut self.history, settings.max_history);
+        trim_oldest(&mut self.forward_history, settings.max_history);
+    }
+
+    /// Go to the address the user typed into the location bar.
+    fn submit_location(&mut self) {
+        let url = self.without_userinfo(entered_url(&self.location, &self.settings));
+        self.entered.record(&url);
+        self.goto_url(url);
+    }
+
+    // Full URL entered in location bar, or set by app.
+    pub fn goto_url(&mut self, url: SCow) {
+        let url = self.without_userinfo(url);
+        let url = clean_url(url, &self.settings);
+        let fw_history_matches = self.forward_history.last().is_some_and(|it| normalize_url(it) == normalize_url(&url));
+        if fw_history_matches {
+            self.forward_history.pop();
+        } else {
+            self.forward_history.clear();
+        }
+
+        self.load_url(url);
+    }
+
+    /// `url`, without any `user:password@`. The loader keeps them for the site, so that they're still sent
+    /// without being saved in the location bar, history, or entered URLs.
+    fn without_userinfo(&self, url: SCow) -> SCow {
+        let Ok(mut parsed) = Url::parse(&url) else {
+            return url;
+        };
+        let Some(credentials) = auth::take_userinfo(&mut parsed) else {
+            return url;
+        };
+        self.loader.set_credentials(parsed.as_str(), credentials);
+        String::from(parsed).into()
+    }
+
+    /// Like goto_url(), but does NOT clear the forward_history.
+    /// You should prefer goto_url() for most cases.
+    fn load_url(&mut self, url: SCow) {
+        self.load(url.clone(), url);
+    }
+
+    /// Load `url`, but show & save `history_url` in the location bar and history.
+    /// These only differ when `url` must not be saved. (ex: it contains sensitive input.)
+    fn load(&mut self, url: SCow, history_url: SCow) {
+        let allowed = self.allow_once.take().is_some_and(|it| normalize_url(&it) == normalize_url(&url));
+        let referrer = self.referrer.take();
+        self.start_page(history_url);
+
+        // TODO: Move the builtin loading to its own network/ loader module.
+        if url == BuiltinUrl::SETTINGS.url {
+            self.open_settings = true;
+        }
+        for builtin in BuiltinUrl::ALL {
+            if builtin.url == url.as_ref() {
+                self.set_gemtext(builtin.text);
+                return;
+            }
+        }
+        if let Some(text) = self.dynamic_pages.get(url.as_ref()).cloned() {
+            self.set_gemtext(&text);
+            return;
+        }
+        
+        self.requested = url.clone();
+        let (chunks, stream) = PageStream::new(content::gemtext_options(&self.settings));
+        self.stream = Some(stream);
+        self.loading = Some(self.loader.fetch_page(url, !allowed, referrer, chunks));
+        self.loading_since = Some(Instant::now());
+    }
+
+    /// Stop loading the current page and its images, and record `history_url` as the new page.
+    fn start_page(&mut self, history_url: SCow) {
+        if let Some((url, offset)) = self.scroll.take() {
+            self.scroll_offsets.insert(normalize_url(&url), offset);
+        }
+        self.pending_scroll = std::mem::take(&mut self.restore_scroll)
+            .then(|| self.scroll_offsets.get(&normalize_url(&history_url)).copied())
+            .flatten();
+        if let Some(loading) = self.loading.take() {
+            loading.abort();
+            // (drop)
+        }
+        self.stream = None;
+        for image in self.image_loads.drain(..) {
+            image.handle.abort();
+        }
+        self.previewer.clear();
+        self.hints = None;
+        self.input = None;
+        self.login = None;
+        self.upload = None;
+        self.download = None;
+        self.page_info = None;
+        self.blocked = None;
+        self.cert_wanted = None;
+        #[cfg(feature = "tts")]
+        if let Some(reader) = &mut self.reader {
+            // New document, new texts:
+            reader.set_texts(Vec::new());
+        }
+
+        push_bounded(&mut self.history, history_url.clone(), self.settings.max_history);
+        if !self.scroll_offsets.is_empty() {
+            let kept: HashSet<String> = self.history.iter().chain(&self.forward_history).map(|it| normalize_url(it)).collect();
+            self.scroll_offsets.retain(|url, _| kept.contains(url));
+        }
+        self.visits.push(Visit::new(history_url.clone()));
+        self.location = history_url;
+    }
+
+    /// Whether the current page has been loading long enough to offer loading it in the background.
+    fn is_slow(&self) -> bool {
+        let threshold = Duration::try_from_secs_f32(self.settings.background_after_secs).unwrap_or_default();
+        self.settings.offer_background_loads
+            && self.is_loading()
+            && self.loading_since.is_some_and(|it| it.elapsed() >= threshold)
+    }
+
+    /// Keep loading the current page, but let the user go back to browsing.
+    fn load_in_background(&mut self) {
+        let Some(handle) = self.loading.take() else {
+            return;
+        };
+        // Its chunks have nowhere to go, but we'll show the whole page when it's done:
+        self.stream = None;
+        let history_url = self.history.last().cloned().unwrap_or_else(|| self.requested.clone());
+        self.background.push(BackgroundLoad { url: self.requested.clone(), history_url: history_url.clone(), handle });
+
+        if self.history.len() > 1 {
+            self.go_back();
+        } else {
+            self.set_gemtext(&format!("Loading in the background:\n```\n{history_url}\n```\nYou'll be notified when it's done."));
+        }
+    }
+
+    /// Show a page that finished loading in the background.
+    fn view_background(&mut self, index: usize) {
+        if index >= self.finished.len() {
+            return;
+        }
+        let done = self.finished.remove(index);
+        self.forward_history.clear();
+        self.start_page(done.history_url);
+        self.requested = done.url;
+        self.show_result(done.result);
+    }
+
+    /// Move finished background loads to [`Self::finished`], and let the user know about them.
+    fn check_background_tasks(&mut self, ctx: &egui::Context) {
+        if self.background.is_empty() {
+            return;
+        }
+        let (done, pending) = std::mem::take(&mut self.background)
+            .into_iter()
+            .partition::<Vec<_>, _>(|it| it.handle.is_finished());
+        self.background = pending;
+        if !self.background.is_empty() {
+            // Nothing else will wake us up when these finish:
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+        if done.is_empty() {
+            return;
+        }
+
+        for BackgroundLoad { url, history_url, handle } in done {
+            // We expect this not to block (long) because the task is finished already:
+            let result = match rt().block_on(handle) {
+                Ok(result) => result,
+                Err(err) => Err(network::Error::Unknown(err.to_string())),
+            };
+            self.finished.push(FinishedLoad { url, history_url, result });
+        }
+        ctx.send_viewport_cmd(ViewportCommand::RequestUserAttention(UserAttentionType::Informational));
+    }
+
+    fn finished_ui(&mut self, ui: &mut Ui) {
+        let mut view = None;
+        for (index, done) in self.finished.iter().enumerate() {
+            let icon = if done.result.is_ok() { "✅" } else { "⚠" };
+            if ui.button(format!("{icon} {}", done.history_url)).clicked() {
+                view = Some(index);
+                ui.close();
+            }
+        }
+        ui.separator();
+        if ui.button("Clear").clicked() {
+            self.finished.clear();
+            ui.close();
+        }
+        if let Some(index) = view {
+            self.view_background(index);
+        }
+    }
+
+    /// The user answered a Gemini input prompt. `url` includes their input as its query.
+    fn submit_input(&mut self, url: SCow) {
+        let Some(input) = self.input.take() else {
+            return;
+        };
+        if !input.sensitive {
+            self.goto_url(url);
+            return;
+        }
+
+        // The URL contains a secret. Load it, but keep it out of history and the location bar.
+        // We replace the prompt's history entry, so that back/reload return to the prompt.
+        if self.history.last() == Some(&input.url) {
+            self.history.pop();
+        }
+        self.forward_history.clear();
+        self.load(url, input.url);
+    }
+
+    /// The user logged in to an HTTP site. Try again with their credentials.
+    fn submit_login(&mut self, credentials: Credentials) {
+        let Some(login) = self.login.take() else {
+            return;
+        };
+        self.loader.set_credentials(&login.url, credentials);
+        self.reload();
+    }
+
+    /// The user wrote something to upload to a titan:// URL. Show the server's response, like any other page.
+    fn submit_upload(&mut self, upload: Upload) {
+        let Some(prompt) = self.upload.take() else {
+            return;
+        };
+        if let Some(loading) = self.loading.take() {
+            loading.abort();
+        }
+        self.stream = None;
+        self.requested = prompt.url.clone();
+        self.loading = Some(self.loader.upload(prompt.url, upload));
+        self.loading_since = Some(Instant::now());
+    }
+
+    pub fn link_clicked(&mut self, ui: &egui::Ui, url: String) {
+        let url = match url_join(&self.location, &url) {
+            Ok(joined) => joined.to_string(),
+            Err(_) => url,
+        };
+
+        if url == ALLOW_ONCE_URL {
+            self.allow_blocked();
+            return;
+        }
+        if url == NEW_CERT_URL {
+            self.new_cert();
+            return;
+        }
+        if url == SAVE_URL {
+            self.save_download();
+            return;
+        }
+        if url == RELOAD_URL {
+            self.reload();
+            return;
+        }
+        if let Some(name) = url.strip_prefix(USE_CERT_URL).and_then(|it| it.strip_prefix('?')) {
+            self.use_cert(percent_decode_str(name).decode_utf8_lossy().into_owned());
+            return;
+        }
+
+        if ui.input(|i| i.modifiers.command) {
+            // A new egemi tab, even for links that would otherwise open in the system browser:
+            let url = url.strip_prefix(EXTERNAL_PREFIX).unwrap_or(&url);
+            self.open_in_new_tab = Some(url.to_string().into());
+            return;
+        }
+
+        let open_in_app = ui.input(|i| i.modifiers.alt);
+        if let Some(external) = external_url(&url, &self.settings, open_in_app) {
+            ui.ctx().open_url(OpenUrl::new_tab(external));
+            return;
+        }
+
+        self.referrer = Some(self.location.clone());
+        self.goto_url(url.into());
+    }
+
+    /// Does what the user picked from a link's context menu.
+    fn link_action(&mut self, ui: &egui::Ui, action: LinkAction, link: String) {
+        let url = url_join(&self.location, &link).map_or(link, String::from);
+        let url = url.strip_prefix(EXTERNAL_PREFIX).unwrap_or(&url);
+        match action {
+            LinkAction::CopyAddress => ui.ctx().copy_text(url.to_string()),
+            LinkAction::OpenInBrowser => ui.ctx().open_url(OpenUrl::new_tab(url)),
+            LinkAction::OpenInNewTab => self.open_in_new_tab = Some(url.to_string().into()),
+        }
+    }
+
+    fn scroll_to_top(&mut self) {
+        self.scroll_to = Some(0.0);
+    }
+
+    /// Go to the URL in the primary selection, if the user has middle-click paste on, and there is one.
+    fn open_selection(&mut self) {
+        if !self.settings.middle_click_paste {
+            return;
+        }
+        let url = selection::primary().and_then(|it| selection_url(&it, &self.settings));
+        if let Some(url) = url {
+            self.goto_url(url);
+        }
+    }
+
+    /// Shows keyboard link hints over `links` when the user asks for them, and follows the one they type.
+    fn link_hints(&mut self, ui: &egui::Ui, links: Vec<(egui::Rect, String)>) {
+        let Some(hints) = self.hints.as_mut() else {
+            if self.shortcuts.link_hints(ui) {
+                self.hints = LinkHints::new(links);
+                ui.ctx().request_repaint();
+            }
+            return;
+        };
+        hints.update(links);
+        match hints.input(ui.ctx()) {
+            Some(HintAction::Follow(url)) => {
+                self.hints = None;
+                self.link_clicked(ui, url);
+            },
+            Some(HintAction::Cancel) => self.hints = None,
+            None => hints.paint(ui.ctx()),
+        }
+    }
+
+    /// Ask where to save the current page's body, and save it there.
+    fn save_download(&mut self) {
+        let Some(download) = &self.download else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new().set_file_name(download.file_name()).save_file() else {
+            // Cancelled.
+            return;
+        };
+        let text = match download.save(&path) {
+            Ok(()) => {
+                let mut text = format!("## Saved\n\nSaved {} bytes to:\n```\n{}\n```\n", download.bytes.len(), path.display());
+                if let Some(dir) = path.parent().and_then(|it| Url::from_directory_path(it).ok()) {
+                    text.push_str(&format!("\n=> {dir} Show folder\n"));
                 }
+                text
+            },
+            Err(err) => format!("## Couldn't save\n\n```\n{}\n{err}\n```\n", path.display()),
+        };
+        self.set_gemtext(&format!("{text}=> {SAVE_URL} 💾 Save to another file\n"));
+    }
 
-                let toggle_menu = ui.add_widget(item(), menu());
-                if toggle_menu.inner.clicked() {
-                    self.toggle_menu = true;
-                }
-            });
-        });
+    /// Load the page that was just blocked, ignoring the blocklist this one time.
+    fn allow_blocked(&mut self) {
+        let Some(url) = self.blocked.take() else {
+            return;
+        };
+        // Replace the blocked page's history entry:
+        if self.history.last() == Some(&url) {
+            self.history.pop();
+        }
+        self.allow_once = Some(url.clone());
+        self.load_url(url);
+    }
 
-        ui.style_mut().spacing.item_spacing = old_spacing;    
+    fn client_certs(&self) -> ClientCerts {
+        ClientCerts::new(self.settings.cert_dir(), self.settings.client_certs.clone())
     }
 
-    // Full URL entered in location bar, or set by app.
-    pub fn goto_url(&mut self, url: SCow) {
-        let fw_history_matches = self.forward_history.last().map(|it| it == &url).unwrap_or(false);
-        if fw_history_matches {
-            self.forward_history.pop();
-        } else {
-            self.forward_history.clear();
-        }
+    /// The host that asked for a client certificate.
+    fn cert_host(&self) -> Option<String> {
+        let url = Url::parse(self.cert_wanted.as_ref()?).ok()?;
+        url.host_str().map(String::from)
+    }
 
-        self.load_url(url);
+    /// Present the certificate `name` to the whole capsule that asked for one, and reload.
+    fn use_cert(&mut self, name: String) {
+        let Some(host) = self.cert_host() else {
+            return;
+        };
+        let prefix = format!("{host}/");
+        self.settings.client_certs.retain(|it| it.prefix.trim() != prefix);
+        self.settings.client_certs.push(ClientCertMapping { prefix, name });
+        self.settings_changed = true;
+        self.reload_with_loader = true;
     }
 
-    /// Like goto_url(), but does NOT clear the forward_history.
-    /// You should prefer goto_url() for most cases.
-    fn load_url(&mut self, url: SCow) {
-        if let Some(loading) = self.loading.take() {
-            loading.abort();
-            // (drop)
+    fn new_cert(&mut self) {
+        let Some(host) = self.cert_host() else {
+            return;
+        };
+        match self.client_certs().generate(&host) {
+            Ok(name) => self.use_cert(name),
+            Err(err) => self.render_err(err),
         }
+    }
 
-        let url: SCow = url.into();
+    fn render_cert_required(&mut self, code: u8, message: &str) {
+        let (title, explanation) = match code {
+            61 => ("Certificate not authorized", "Your certificate isn't allowed to see this page."),
+            62 => ("Certificate not valid", "The capsule didn't accept your certificate."),
+            _ => ("Client certificate required", "This capsule uses a certificate to identify you. It's like an account, without a password."),
+        };
+        let mut text = format!("## {title}\n\n");
+        if !message.trim().is_empty() {
+            text += &format!("> {}\n\n", message.trim());
+        }
+        text += explanation;
+        text += "\n\n";
 
-        self.history.push(url.clone());
-        self.location = url.clone();
+        let certs = self.client_certs();
+        if let Some(dir) = certs.dir() {
+            text += &format!("Certificates are kept in:\n```\n{}\n```\n\n", dir.display());
+        }
 
-        // TODO: Move the builtin loading to its own network/ loader module.
-        for builtin in BuiltinUrl::ALL {
-            if builtin.url == url.as_ref() {
-                self.set_gemtext(builtin.text);
-                return;
-            }
+        text += "### Use an existing certificate\n";
+        let available = certs.available();
+        if available.is_empty() {
+            text += "You don't have any certificates yet.\n";
         }
-        
-        let handle = self.loader.fetch(url);
-        self.loading = Some(handle);       
+        for name in available {
+            let encoded = utf8_percent_encode(&name, NON_ALPHANUMERIC);
+            text += &format!("=> {USE_CERT_URL}?{encoded} {name}\n");
+        }
+
+        let host = self.cert_host().unwrap_or_default();
+        text += &format!("\n### Create a new certificate\n=> {NEW_CERT_URL} Generate a certificate for {host}\n");
+        text += "This uses `openssl`. You can also copy a <name>.crt and <name>.key into the directory above.\n";
+        text += "\nYou can change which certificate is used for which capsule in Settings.\n";
+
+        self.set_gemtext(&text);
     }
 
-    pub fn link_clicked(&mut self, ui: &egui::Ui, url: String) {
-        if let Ok(joined) = url_join(&self.location, &url) {
-            self.goto_url(joined.to_string().into());
+    fn load_image(&mut self, link: String) {
+        let url = url_join(&self.location, &link)
+            .map(String::from)
+            .unwrap_or_else(|_| link.clone());
+        let handle = self.loader.fetch(url.into());
+        self.image_loads.push(ImageLoad { link, handle });
+    }
+
+    /// Start fetching the title of `link`'s target, unless we already know it.
+    fn preview_link(&mut self, link: String) {
+        let Ok(url) = url_join(&self.location, &link) else {
+            return;
+        };
+        if !preview::allowed(&self.location, &url) {
             return;
         }
-                
-        self.goto_url(url.into());
+        let url: SCow = String::from(url).into();
+        if let Some(title) = self.titles.get(&normalize_url(&url)) {
+            if let Some(document) = self.document.as_mut() {
+                document.link_preview(&link, Some(title.clone()));
+            }
+            return;
+        }
+        let handle = self.loader.fetch_preview(url);
+        let settings = self.settings.clone();
+        let handle = rt().spawn(async move {
+            let _abort = network::AbortOnDrop(handle.abort_handle());
+            let loaded = handle.await.ok()?.ok().filter(|it| it.status.ok())?;
+            // Here, rather than on the UI thread:
+            let title = content::content_to_doc(&loaded, &settings).title()?;
+            Some((loaded.url, title))
+        });
+        self.previewer.start(link, handle);
+    }
+
+    fn check_preview_tasks(&mut self, ctx: &egui::Context) {
+        for (link, found) in self.previewer.finished(ctx) {
+            let title = found.map(|(url, title)| {
+                self.titles.insert(normalize_url(&url), title.clone());
+                title
+            });
+            if let Some(document) = self.document.as_mut() {
+                document.link_preview(&link, title);
+            }
+        }
     }
 
     pub fn go_back(&mut self) {
@@ -198,57 +1102,97 @@ impl Tab {
 
         // The top of history is the current URL:
         let current_url = self.history.pop().expect("drop current URL");
-        self.forward_history.push(current_url);
+        push_bounded(&mut self.forward_history, current_url, self.settings.max_history);
 
         // Easier to just pop the old URL and nagivate to it again like it's the first time:
         let url = self.history.pop().expect("previous url");
+        self.restore_scroll = true;
         self.load_url(url);
     }
 
+    /// Go back `steps` pages at once. `go_back_steps(1)` is the same as [`Self::go_back`].
+    pub fn go_back_steps(&mut self, steps: usize) {
+        if steps == 0 || steps >= self.history.len() {
+            eprintln!("Warning: Tried to go back {steps} steps with only {} in history.", self.history.len());
+            return;
+        }
+
+        // Skipped pages go onto the forward history, so that they're one click away again:
+        for _ in 1..steps {
+            let url = self.history.pop().expect("skipped URL");
+            push_bounded(&mut self.forward_history, url, self.settings.max_history);
+        }
+        self.go_back();
+    }
+
+    /// Lists previous pages, newest first, so the user can jump back several at once.
+    fn back_menu_ui(&mut self, ui: &mut egui::Ui) {
+        let mut steps = None;
+        // Skip the current page:
+        for (index, url) in self.history.iter().rev().enumerate().skip(1) {
+            let title = self.titles.get(&normalize_url(url));
+            let button = ui.button(title.map(String::as_str).unwrap_or(url));
+            if button.clicked() {
+                steps = Some(index);
+                ui.close();
+            }
+            if title.is_some() {
+                button.on_hover_text(url.as_ref());
+            }
+        }
+        if let Some(steps) = steps {
+            self.go_back_steps(steps);
+        }
+    }
+
     pub fn go_forward(&mut self) {
         let Some(next_url) = self.forward_history.pop() else {
             eprintln!("Warning: Clicked forward button when no fw history available.");
             return;
         };
 
+        self.restore_scroll = true;
         self.load_url(next_url);
     }
 
     pub fn reload(&mut self) {
-        // Right now there's no caching, so just 'goto' this URL again.
-        // When there's caching, we'll need to clear/invalidate cache first. Or fetch & replace.
         if let Some(url) = self.history.pop() {
+            // Reloading should always get the latest version:
+            self.loader.invalidate(&url);
+            self.restore_scroll = true;
             self.goto_url(url);
         }
     }
 
     fn set_gemtext(&mut self, text: &str) {
-        let parser = gemtext::Options::default();
-        let blocks = match parser.parse(text) {
-            Ok(blocks) => blocks,
-            Err(err) => {
-                let text = format!("{err:#?}");
-                vec![
-                    Block::Heading { level: 1, text: "Gemtext Parse Error".into() },
-                    Block::Text(String::new()),
-                    Block::Text(text),
-                ]
-            },
-        };
-        let mut new_doc = GemtextWidget::default();
-        new_doc.set_blocks(blocks);
-        self.document = Some(Box::new(new_doc));
-        self.doc_id = time_hash();
+        self.set_document(Box::new(content::gemtext_doc(text)));
     }
 
-    fn set_plaintext(&mut self, text: &str) {
-        let blocks: Vec<Block> = text.lines().map(|line| Block::Text(line.into())).collect();
-        let mut new_doc = GemtextWidget::default();
-        new_doc.set_blocks(blocks);
-        self.document = Some(Box::new(new_doc));
+    fn set_document(&mut self, document: Box<dyn DocWidget>) {
+        self.document = Some(document);
         self.doc_id = time_hash();
+        self.scroll_to = self.pending_scroll.take();
+        self.scroll = Some((self.location.clone(), self.scroll_to.unwrap_or_default()));
     }
     
+    /// Show any more of the page that's arrived while it loads.
+    fn check_stream(&mut self) {
+        let Some(update) = self.stream.as_mut().and_then(PageStream::poll) else {
+            return;
+        };
+        match update {
+            Update::Replace { url, blocks } => {
+                self.show_location(&url);
+                self.set_document(content::streaming_gemtext_doc(blocks, &self.settings));
+            },
+            Update::Append(blocks) => {
+                if let Some(document) = self.document.as_mut() {
+                    document.append_gemtext(blocks);
+                }
+            },
+        }
+    }
+
     /// Check if any async tasks completed. Right now, this is just whether a page loaded.
     fn check_tasks(&mut self) {
         let Some(loading) = &self.loading else {
@@ -270,12 +1214,18 @@ impl Tab {
         let result = match result {
             Ok(ok) => ok,
             Err(err) => {
+                self.stream = None;
                 let msg = format!("{err:#?}");
                 self.set_gemtext(&msg);
                 return;
             }
         };
+        self.show_result(result);
+    }
 
+    /// Show a page we've finished loading. (Or an error for why we couldn't.)
+    fn show_result(&mut self, result: network::Result<LoadedResource>) {
+        let stream = self.stream.take();
         let loaded = match result {
             Ok(ok) => ok,
             Err(err) => {
@@ -283,6 +1233,8 @@ impl Tab {
                 return;
             },
         };
+        self.show_location(&loaded.url);
+        self.page_info = Some(PageInfo::new(&loaded));
 
         if !loaded.status.ok() {
             use network::Status::*;
@@ -303,49 +1255,98 @@ impl Tab {
                     self.set_gemtext(&text);
                     return;
                 },
+                GeminiCertRequired { code, message } => {
+                    self.cert_wanted = Some(loaded.url.clone());
+                    self.render_cert_required(code, &message);
+                    return;
+                },
+                HttpAuthRequired { realm, rejected } => {
+                    self.login = Some(LoginPrompt::new(self.location.clone(), realm, rejected));
+                    self.document = None;
+                    self.doc_id = time_hash();
+                    return;
+                },
+                TitanUpload => {
+                    self.upload = Some(UploadPrompt::new(self.location.clone()));
+                    self.document = None;
+                    self.doc_id = time_hash();
+                    return;
+                },
+                GeminiInput { sensitive, prompt } => {
+                    self.input = Some(InputPrompt::new(self.location.clone(), prompt, sensitive));
+                    self.document = None;
+                    self.doc_id = time_hash();
+                    return;
+                },
             }            
         }
 
-        let is_text = match &loaded.content_type {
-            None => {
-                // Not actually sure, but show text anyway?
-                true
-            },
-            Some(content) => {
-                content.type_().as_str() == "text"
+        // We've been showing this page as it loaded. Keep it, and where the user has scrolled to:
+        if let Some(rest) = stream.and_then(|it| it.finish(&loaded))
+            && let Some(document) = self.document.as_mut()
+        {
+            document.append_gemtext(rest);
+            if let Some(title) = document.title() {
+                self.titles.insert(normalize_url(&self.location), title);
             }
-        };
-
-        if !is_text {
-            let content = loaded.content_type
-                .map(|it| format!("{it}"))
-                .unwrap_or_else(|| format!("<unknown>"));
-            let msg = format!("## Unsupported Content-Type\n\n")
-                + &format!("Content-Type: {content}\n")
-                + "is not yet supported.\n\n"
-            ;
+            self.visits.push(Visit::new(self.location.clone()).with_title(document.title()));
+            return;
+        }
 
-            self.set_gemtext(&msg);
+        let document = content::content_to_doc(&loaded, &self.settings);
+        if let network::Body::Bytes(bytes) = &loaded.body {
+            self.download = Some(Download {
+                url: self.location.clone(),
+                content_type: loaded.content_type.as_deref().cloned(),
+                bytes: bytes.clone(),
+            });
+        }
+        if let Some(title) = document.title() {
+            self.titles.insert(normalize_url(&self.location), title);
+        }
+        self.visits.push(Visit::new(self.location.clone()).with_title(document.title()));
+        self.set_document(document);
+    }
+    
+    /// Show `url` as the current page, if the loader followed redirects there from [`Self::requested`],
+    /// so that relative links resolve correctly.
+    fn show_location(&mut self, url: &SCow) {
+        if *url == self.requested {
             return;
         }
+        self.location = url.clone();
+        if let Some(last) = self.history.last_mut() {
+            *last = url.clone();
+        }
+    }
 
-        let body = match loaded.body {
-            network::Body::Bytes(_cow) => "binary data".into(),
-            network::Body::Text(cow) => cow,
-        };
+    /// Hand any finished image loads back to the document that requested them.
+    fn check_image_tasks(&mut self, ctx: &egui::Context) {
+        if !self.image_loads.is_empty() {
+            // Nothing else will wake us up when they finish:
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        if self.image_loads.iter().all(|it| !it.handle.is_finished()) {
+            return;
+        }
+        let (done, pending) = std::mem::take(&mut self.image_loads)
+            .into_iter()
+            .partition::<Vec<_>, _>(|it| it.handle.is_finished());
+        self.image_loads = pending;
 
-        let essence = loaded.content_type.as_ref().map(|it| it.essence_str());
-        if let Some("text/gemini") = essence {
-            self.set_gemtext(&body);
-        } else if let Some("text/html") = essence {
-            self.render_html(body);
-        } else if let Some("text/markdown") = essence {
-            self.render_markdown(body)
-        } else {
-            self.set_plaintext(&body);
+        for ImageLoad { link, handle } in done {
+            // We expect this not to block (long) because the task is finished already:
+            let result = match rt().block_on(handle) {
+                Ok(Ok(loaded)) => image_data(loaded),
+                Ok(Err(err)) => Err(err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            if let Some(document) = self.document.as_mut() {
+                document.image_loaded(&link, result);
+            }
         }
     }
-    
+
     fn is_loading(&self) -> bool {
         let Some(loading) = &self.loading else {
             return false;
@@ -359,12 +1360,22 @@ impl Tab {
             MissingContentType 
             | MimeParseError(_) 
             | UnsupportedUrlScheme(_)
-            | InvalidUrl(_)
             | IoError(_)
-            | UnsupportedContentType(_)
+            | Connection(_)
             | Unknown(_) => {
                 // Just show default error.
             },
+            InvalidUrl(url) => {
+                // Usually a relative link (ex: `/about`) clicked on a page that has no host to resolve it against.
+                let text = format!("## Invalid URL\n\n```\n{url}\n```\n\nIf this was a relative link, it can't be resolved from a builtin page.");
+                self.set_gemtext(&text);
+                return;
+            },
+            e @ OutsideRoot{..} => {
+                let text = format!("## Access denied\n\n{e}");
+                self.set_gemtext(&text);
+                return;
+            },
             e @ ResponseTooBig{..} => {
                 let text = format!("## Response too big\n\n{e:?}");
                 self.set_gemtext(&text);
@@ -377,11 +1388,43 @@ impl Tab {
             },
             Redirect{destination, temporary: _} => {
                 let dest = destination.replace(" ", "%20");
+                let mut text = format!("## Redirect\n\nThe server would like to redirect you to:\n=> {dest}\n");
+                if self.location.starts_with("gemini:") && !dest.starts_with("gemini:") {
+                    text.push_str("\n⚠ This link leaves Geminispace.\n");
+                }
+                self.set_gemtext(&text);
+                return
+            },
+            e @ ClientCert{..} => {
+                let text = format!("## Client certificate error\n\n{e}\n\nYou can change which certificates are used in Settings.");
+                self.set_gemtext(&text);
+                return;
+            },
+            Blocked { url, pattern } => {
+                let text = format!(
+                    "## Blocked\n\nThis page matches a pattern in your blocklist:\n```\n{pattern}\n```\n\nYou can change the blocklist in Settings.\n\n=> {ALLOW_ONCE_URL} Load it anyway, just this once\n"
+                );
+                self.blocked = Some(url);
+                self.set_gemtext(&text);
+                return
+            },
+            Timeout { url, after } => {
+                let url = url.replace(" ", "%20");
                 self.set_gemtext(&format!(
-                    "## Redirect\n\nThe server would like to redirect you to:\n=> {dest}"
+                    "## Timed out\n\nThe server didn't finish responding within {} seconds:\n```\n{url}\n```\n\nIt may be busy or down. You can change how long to wait in Settings.\n\n=> {RELOAD_URL} 🔄 Try again\n",
+                    after.as_secs_f32(),
                 ));
                 return
-            }
+            },
+            TooManyRedirects { url, hops } => {
+                let mut text = String::from("## Too many redirects\n\nThe server redirected too many times. It may be redirecting in a loop.\n\n### Redirected from\n");
+                for hop in hops {
+                    text.push_str(&format!("* {hop}\n"));
+                }
+                text.push_str(&format!("\nThe last redirect was to:\n=> {}\n", url.replace(" ", "%20")));
+                self.set_gemtext(&text);
+                return
+            },
         };
         
         let msg = format!("{err:#?}");
@@ -394,17 +1437,124 @@ impl Tab {
         self.location.replace(" ", "%20")
     }
     
-    fn render_html(&mut self, body: SCow) {
-        let new_doc = markdown::MarkdownWidget::for_html(&body);
-        self.document = Some(Box::new(new_doc));
+
+
+}
+
+/// Longer [`Tab::title`]s are truncated.
+const MAX_TITLE_CHARS: usize = 32;
+
+/// Link shown on the blocked page, to load it anyway.
+const ALLOW_ONCE_URL: &str = "about:allow-once";
+
+/// Links shown when a Gemini capsule asks for a client certificate.
+/// [`USE_CERT_URL`] takes the certificate's name as its query.
+const USE_CERT_URL: &str = "about:use-cert";
+const NEW_CERT_URL: &str = "about:new-cert";
+
+/// Link shown on pages we can't render, to save them to disk instead.
+const SAVE_URL: &str = "about:save";
+
+/// Link shown on pages that timed out, to try loading them again.
+const RELOAD_URL: &str = "about:reload";
+
+/// Toggles [`Tab::outline`].
+const OUTLINE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::O);
+
+/// Scrolls the document back to the top.
+const TOP: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Home);
+
+/// Goes to the directory containing the current page. See [`parent_url`].
+const PARENT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::ALT, Key::ArrowUp);
+
+/// Bigger SVGs aren't shown inline. Unlike raster images, their size says little about how long they take to draw.
+const MAX_SVG_BYTES: usize = 1024 * 1024;
+
+/// Links with this prefix (ex: `browser+https://example.com`) are always opened in the system's web browser.
+const EXTERNAL_PREFIX: &str = "browser+";
+
+/// If `url` should be opened in the system's web browser instead of egemi, the URL to open.
+/// `open_in_app` overrides the user's setting to open web links externally.
+fn external_url<'a>(url: &'a str, settings: &Settings, open_in_app: bool) -> Option<&'a str> {
+    if let Some(external) = url.strip_prefix(EXTERNAL_PREFIX) {
+        return Some(external);
+    }
+    // egemi can't send email:
+    if url.starts_with("mailto:") {
+        return Some(url);
     }
+    let is_web = url.starts_with("http://") || url.starts_with("https://");
+    (is_web && settings.open_web_links_externally && !open_in_app).then_some(url)
+}
+
+/// A page that's loading while the user browses other pages.
+#[derive(Debug)]
+struct BackgroundLoad {
+    /// See [`Tab::load`].
+    url: SCow,
+    history_url: SCow,
+    handle: JoinHandle<network::Result<LoadedResource>>,
+}
+
+#[derive(Debug)]
+struct FinishedLoad {
+    url: SCow,
+    history_url: SCow,
+    result: network::Result<LoadedResource>,
+}
+
+/// What we show in the page info popup.
+#[derive(Debug)]
+struct PageInfo {
+    status: String,
+    content_type: Option<String>,
+    timings: Option<Timings>,
+}
 
-    fn render_markdown(&mut self, body: SCow) {
-        let new_doc = markdown::MarkdownWidget::for_md(&body);
-        self.document = Some(Box::new(new_doc));
+impl PageInfo {
+    fn new(loaded: &LoadedResource) -> Self {
+        Self {
+            status: loaded.status.to_string(),
+            content_type: loaded.content_type.as_ref().map(|it| it.to_string()),
+            timings: loaded.timings,
+        }
     }
 }
 
+fn page_info_ui(ui: &mut Ui, location: &str, info: Option<&PageInfo>) {
+    let Some(info) = info else {
+        ui.label("Nothing loaded yet.");
+        return;
+    };
+    egui::Grid::new("page info").num_columns(2).show(ui, |ui| {
+        ui.strong("URL");
+        ui.monospace(location);
+        ui.end_row();
+
+        ui.strong("Status");
+        ui.label(&info.status);
+        ui.end_row();
+
+        ui.strong("Content-Type");
+        ui.label(info.content_type.as_deref().unwrap_or("unknown"));
+        ui.end_row();
+
+        if let Some(timings) = info.timings {
+            let steps = [("DNS", timings.dns), ("Connect", timings.connect), ("TLS", timings.tls), ("First byte", timings.first_byte)];
+            for (name, time) in steps {
+                if let Some(time) = time {
+                    ui.strong(name);
+                    ui.label(format!("{} ms", time.as_millis()));
+                    ui.end_row();
+                }
+            }
+            ui.strong("Total time");
+            ui.label(format!("{} ms", timings.total.as_millis()));
+            ui.end_row();
+        }
+    });
+}
+
 fn time_hash() -> u128 {
     let dur = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
         Ok(dur) => dur,
@@ -417,16 +1567,196 @@ fn time_hash() -> u128 {
     dur.as_millis()
 }
 
+#[derive(Debug)]
+struct ImageLoad {
+    /// The link, as the document asked for it.
+    link: String,
+    handle: JoinHandle<network::Result<LoadedResource>>,
+}
+
+fn image_data(loaded: LoadedResource) -> Result<ImageData, String> {
+    if !loaded.status.ok() {
+        return Err(format!("{}", loaded.status));
+    }
+    let is_svg = loaded.content_type.as_deref().is_some_and(|it| it.essence_str() == mime::IMAGE_SVG.essence_str());
+    match loaded.body {
+        network::Body::Bytes(bytes) if is_svg => {
+            if bytes.len() > MAX_SVG_BYTES {
+                return Err(format!("SVG is too big to show ({} KiB)", bytes.len() / 1024));
+            }
+            // egui only hands URIs that end in .svg to its SVG loader:
+            let uri = if loaded.url.ends_with(".svg") { loaded.url.into() } else { format!("{}#.svg", loaded.url) };
+            Ok(ImageData { uri, bytes: bytes.into() })
+        },
+        network::Body::Bytes(bytes) => Ok(ImageData {
+            uri: loaded.url.into(),
+            bytes: bytes.into(),
+        }),
+        network::Body::Text(_) => {
+            let content = loaded.content_type
+                .map(|it| format!("{it}"))
+                .unwrap_or_else(|| "<unknown>".into());
+            Err(format!("Not an image: {content}"))
+        }
+    }
+}
+
 pub struct TabResponse {
-    pub toggle_menu: bool
+    pub toggle_menu: bool,
+
+    /// The tab changed its settings. (ex: the user picked a client certificate.) These should be saved.
+    pub settings: Option<Settings>,
+
+    /// The user Ctrl/Cmd-clicked a link.
+    pub open_in_new_tab: Option<SCow>,
+
+    /// Show the settings window.
+    pub open_settings: bool,
+
+    /// Pages visited since the last frame, for the global [`History`](crate::browser::history::History).
+    pub visits: Vec<Visit>,
+
+    /// The absolute URL of the link the mouse is over, for the status bar.
+    pub hovered_link: Option<String>,
 }
 
+/// Removes tracking query parameters from http(s) URLs, if the user has enabled that.
+fn clean_url(url: SCow, settings: &Settings) -> SCow {
+    if !settings.strip_tracking_params {
+        return url;
+    }
+    let Ok(mut parsed) = Url::parse(&url) else {
+        return url;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return url;
+    }
+    let Some(query) = parsed.query() else {
+        return url;
+    };
+    let is_tracking = |segment: &&str| {
+        url::form_urlencoded::parse(segment.as_bytes()).next()
+            .is_some_and(|(key, _)| settings.is_tracking_param(&key))
+    };
+    if !query.split('&').any(|it| is_tracking(&it)) {
+        // Leave the URL exactly as-is:
+        return url;
+    }
+
+    // Keep the rest of the query as it was written, since re-encoding it could change its meaning to the server:
+    let kept = query.split('&').filter(|it| !is_tracking(it)).collect::<Vec<_>>().join("&");
+    parsed.set_query((!kept.is_empty()).then_some(kept.as_str()));
+    String::from(parsed).into()
+}
+
+/// Adds the user's default scheme to an address typed without one. (ex: `example.com/page`)
+fn with_default_scheme(url: SCow, settings: &Settings) -> SCow {
+    let scheme = settings.default_scheme.trim();
+    if scheme.is_empty() {
+        return url;
+    }
+    let has_scheme = match Url::parse(url.trim()) {
+        // `example.com:1965/` and `localhost:1965` parse, but as a scheme and path:
+        Ok(parsed) => {
+            let rest = &url.trim()[parsed.scheme().len() + 1..];
+            !parsed.scheme().contains('.') && !rest.starts_with(|c: char| c.is_ascii_digit())
+        },
+        Err(_) => false,
+    };
+    if has_scheme {
+        return url;
+    }
+    format!("{scheme}://{}", url.trim()).into()
+}
+
+/// The URL to go to for an address the user typed into the location bar.
+/// Paths to local files become file:// URLs. Other addresses get [`Settings::default_scheme`] if they don't have a
+/// scheme, and are [tidied](tidy_url). (ex: `example.com` and `gemini://example.com:1965` both go to `gemini://example.com/`)
+fn entered_url(text: &str, settings: &Settings) -> SCow {
+    let text = text.trim();
+    if is_file_path(text) {
+        let url = super::try_file_url(text.to_string());
+        if Url::parse(&url).is_ok() {
+            return url.into();
+        }
+        // Files that don't exist still get a file:// URL (relative to the current directory), so that we say so:
+        let absolute = std::path::absolute(&url).ok().and_then(|it| Url::from_file_path(it).ok());
+        return absolute.map_or(url, String::from).into();
+    }
+    let url = with_default_scheme(text.to_string().into(), settings);
+    if let Ok(mut parsed) = Url::parse(&url) && tidy_url(&mut parsed) {
+        return String::from(parsed).into();
+    }
+    url
+}
+
+/// Whether `text` is a local path, rather than an address without a scheme. (ex: `./page.gmi`, but not `example.com/page`)
+fn is_file_path(text: &str) -> bool {
+    let windows_drive = text.get(1..3).is_some_and(|it| it == ":\\" || it == ":/")
+        && text.starts_with(|c: char| c.is_ascii_alphabetic());
+    text.starts_with(['/', '.', '\\']) || windows_drive
+}
+
+/// The URL in some selected text, if that's all it is. Addresses without a scheme (ex: `example.com/page`)
+/// get [`Settings::default_scheme`], but other words don't, so that stray selections don't go anywhere.
+fn selection_url(text: &str, settings: &Settings) -> Option<SCow> {
+    let text = text.trim();
+    if text.is_empty() || text.chars().any(char::is_whitespace) {
+        return None;
+    }
+    let known_scheme = |url: &str| Url::parse(url)
+        .is_ok_and(|it| matches!(it.scheme(), "gemini" | "http" | "https" | "file" | "about"));
+    if known_scheme(text) {
+        return Some(text.to_string().into());
+    }
+    let host = text.split('/').next().unwrap_or_default();
+    if !host.contains('.') {
+        return None;
+    }
+    let url = with_default_scheme(text.to_string().into(), settings);
+    known_scheme(&url).then_some(url)
+}
+
+/// The directory listing that contains `url`: a file's directory, or a directory's parent. (ex: `/a/b` and
+/// `/a/b/` are both in `/a/`.) The query and fragment are dropped. None at the root, or for URLs without paths.
+fn parent_url(url: &str) -> Option<String> {
+    let mut parsed = Url::parse(url).ok()?;
+    if parsed.cannot_be_a_base() || parsed.path() == "/" || parsed.path().is_empty() {
+        return None;
+    }
+    parsed.set_query(None);
+    parsed.set_fragment(None);
+    let relative = if parsed.path().ends_with('/') { "../" } else { "./" };
+    parsed.join(relative).ok().map(String::from)
+}
+
+/// Resolve a (possibly relative) link against the current location.
+/// Fails if `location` can't be a base URL (ex: `about:` pages) and `url` isn't absolute.
+///
+/// [`Url`] only knows the defaults of "special" schemes like http, so for gemini we also
+/// drop an explicit default port, and give a bare host the root path.
 fn url_join(location: &str, url: &str) -> Result<Url, ()> {
     let base = Url::parse(location).map_err(|_| ())?;
-    let joined = base.join(url).map_err(|_| ())?;
+    let mut joined = base.join(url).map_err(|_| ())?;
+    tidy_url(&mut joined);
     Ok(joined)
 }
 
+/// Removes a port that's the default for the scheme (which [`Url`] only does for schemes it knows), and gives a bare
+/// host the root path. (ex: `gemini://example.com:1965` → `gemini://example.com/`)
+/// Returns whether `url` changed.
+fn tidy_url(url: &mut Url) -> bool {
+    let mut changed = false;
+    if url.port().is_some() && url.port() == default_port(url.scheme()) {
+        changed |= url.set_port(None).is_ok();
+    }
+    if url.has_host() && url.path().is_empty() {
+        url.set_path("/");
+        changed = true;
+    }
+    changed
+}
+
 struct BuiltinUrl {
     url: &'static str,
     text: &'static str,
@@ -440,15 +1770,32 @@ impl BuiltinUrl {
         url: "about:changelog",
         text: include_str!("../../changelog.gmi")
     };
+    /// Also opens the settings window.
+    const SETTINGS: Self = Self {
+        url: "about:settings",
+        text: "# Settings\n\nSettings are shown in their own window. You can also open it from the File menu.\n",
+    };
 
     const ALL: &'static [BuiltinUrl] = &[
         Self::ABOUT,
         Self::CHANGELOG,
+        Self::SETTINGS,
     ];
 }
 
 
 
+/// `url`, shortened to its host and path.
+fn url_title(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) if parsed.host_str().is_some() => {
+            let path = parsed.path().trim_end_matches('/');
+            format!("{}{path}", parsed.host_str().unwrap_or_default())
+        },
+        _ => url.to_string(),
+    }
+}
+
 /// A place to check whether keyboard shortcuts were pressed.
 /// May be configurable in the future.
 #[derive(Default, Debug)]
@@ -466,4 +1813,54 @@ impl Shortcuts {
             i.consume_key(Modifiers::COMMAND, Key::R)
         })
     }
+
+    /// Alt+Left (Cmd+[ on macOS), or the mouse's back button.
+    fn back(&self, ui: &Ui) -> bool {
+        let shortcut = if ui.ctx().os() == OperatingSystem::Mac {
+            KeyboardShortcut::new(Modifiers::COMMAND, Key::OpenBracket)
+        } else {
+            KeyboardShortcut::new(Modifiers::ALT, Key::ArrowLeft)
+        };
+        ui.input_mut(|i| {
+            i.consume_shortcut(&shortcut) || i.pointer.button_pressed(PointerButton::Extra1)
+        })
+    }
+
+    /// Alt+Right (Cmd+] on macOS), or the mouse's forward button.
+    fn forward(&self, ui: &Ui) -> bool {
+        let shortcut = if ui.ctx().os() == OperatingSystem::Mac {
+            KeyboardShortcut::new(Modifiers::COMMAND, Key::CloseBracket)
+        } else {
+            KeyboardShortcut::new(Modifiers::ALT, Key::ArrowRight)
+        };
+        ui.input_mut(|i| {
+            i.consume_shortcut(&shortcut) || i.pointer.button_pressed(PointerButton::Extra2)
+        })
+    }
+
+    fn parent(&self, ui: &Ui) -> bool {
+        ui.input_mut(|i| i.consume_shortcut(&PARENT))
+    }
+
+    fn find(&self, ui: &Ui) -> bool {
+        ui.input_mut(|i| {
+            i.consume_key(Modifiers::COMMAND, Key::F)
+        })
+    }
+
+    fn outline(&self, ui: &Ui) -> bool {
+        ui.input_mut(|i| i.consume_shortcut(&OUTLINE))
+    }
+
+    /// Only when nothing (like the location bar) has keyboard focus, since text boxes use Home too.
+    fn scroll_to_top(&self, ui: &Ui) -> bool {
+        ui.memory(|m| m.focused().is_none()) && ui.input_mut(|i| i.consume_shortcut(&TOP))
+    }
+
+    /// Just `f`, like Vimium. Only when nothing (like the location bar) has keyboard focus.
+    fn link_hints(&self, ui: &Ui) -> bool {
+        ui.memory(|m| m.focused().is_none()) && ui.input_mut(|i| {
+            i.consume_key(Modifiers::NONE, Key::F)
+        })
+    }
 }
\ No newline at end of file
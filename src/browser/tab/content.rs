@@ -0,0 +1,153 @@
+//! Picks and builds the [`DocWidget`] for loaded content.
+//! This has no UI state, so that content-type dispatch can be tested on its own.
+
+use log::warn;
+
+use crate::{browser::{network::{is_binary, is_json, Body, LoadedResource}, parsers::{feed, html}, settings::Settings, widgets::{json::{self, JsonWidget}, markdown::MarkdownWidget, plaintext::PlaintextWidget, table::TableWidget, DocWidget}}, gemtext::{self, Block}, gemtext_widget::GemtextWidget};
+
+use super::SAVE_URL;
+
+mod content_test;
+
+/// Which kind of [`DocWidget`] renders a text document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocKind {
+    Gemtext,
+    Html,
+    Markdown,
+    Feed,
+    Csv,
+    Tsv,
+    Json,
+    Plaintext,
+}
+
+impl DocKind {
+    /// `essence` is the content type, without parameters. (ex: `text/html`)
+    pub fn for_content_type(essence: Option<&str>) -> Self {
+        match essence {
+            Some("text/gemini") => Self::Gemtext,
+            Some("text/html") => Self::Html,
+            // text/x-markdown predates markdown's official registration, but is still common:
+            Some("text/markdown" | "text/x-markdown") => Self::Markdown,
+            Some("application/atom+xml" | "application/rss+xml") => Self::Feed,
+            Some("text/csv") => Self::Csv,
+            Some("text/tab-separated-values") => Self::Tsv,
+            Some(essence) if essence.parse().is_ok_and(|it| is_json(&it)) => Self::Json,
+            _ => Self::Plaintext,
+        }
+    }
+}
+
+/// Shown instead of a blank page when a server successfully sends nothing.
+const EMPTY_DOC: &str = "## Empty page\n\nThe server sent this page successfully, but it has no content.\n";
+
+/// A document for a successfully loaded resource.
+/// Content we can't show gets a gemtext page saying so.
+pub fn content_to_doc(loaded: &LoadedResource, settings: &Settings) -> Box<dyn DocWidget> {
+    // Loaders decode everything we can show as text:
+    if is_binary(loaded.content_type.as_deref()) {
+        let content = loaded.content_type
+            .as_ref()
+            .map(|it| format!("{it}"))
+            .unwrap_or_else(|| "<unknown>".to_string());
+        let mut msg = format!("## Unsupported Content-Type\n\nContent-Type: {content}\nis not yet supported.\n\n");
+        if let Body::Bytes(bytes) = &loaded.body {
+            msg.push_str(&format!("=> {SAVE_URL} 💾 Save to disk ({} bytes)\n", bytes.len()));
+        }
+        return Box::new(gemtext_doc(&msg));
+    }
+
+    let body = match &loaded.body {
+        Body::Bytes(_cow) => "binary data",
+        Body::Text(cow) => cow.as_ref(),
+    };
+    if body.trim().is_empty() {
+        // Otherwise, it looks like we failed to render something:
+        return Box::new(gemtext_doc(EMPTY_DOC));
+    }
+
+    let essence = loaded.content_type.as_ref().map(|it| it.essence_str());
+    let mut doc: Box<dyn DocWidget> = match DocKind::for_content_type(essence) {
+        DocKind::Gemtext => {
+            let doc = gemtext_doc_with(body, &gemtext_options(settings))
+                .compact_links(settings.compact_link_menus)
+                .collapse_captioned(settings.collapse_captioned_blocks);
+            Box::new(doc)
+        },
+        DocKind::Html => {
+            let body = if settings.article_mode { html::extract_main(body) } else { body.to_string() };
+            let doc = MarkdownWidget::for_html(&body)
+                .center_title(settings.center_markdown_title)
+                .inline_images(settings.inline_images);
+            Box::new(doc)
+        },
+        DocKind::Markdown => {
+            let doc = MarkdownWidget::for_md(body)
+                .center_title(settings.center_markdown_title)
+                .inline_images(settings.inline_images);
+            Box::new(doc)
+        },
+        DocKind::Feed => match feed::parse(body) {
+            // Not compacted, since a feed is all links:
+            Ok(feed) => Box::new(gemtext_doc(&feed.to_gemtext())),
+            Err(err) => {
+                warn!("Error parsing feed: {err}");
+                Box::new(PlaintextWidget::new(body))
+            },
+        },
+        DocKind::Csv => Box::new(TableWidget::csv(body)),
+        DocKind::Tsv => Box::new(TableWidget::tsv(body)),
+        DocKind::Json if body.len() > json::MAX_TREE_BYTES => Box::new(PlaintextWidget::new(body)),
+        DocKind::Json => match JsonWidget::parse(body) {
+            Ok(doc) => Box::new(doc),
+            Err(err) => {
+                warn!("Error parsing JSON: {err}");
+                Box::new(PlaintextWidget::new(body))
+            },
+        },
+        DocKind::Plaintext => Box::new(PlaintextWidget::new(body)),
+    };
+    doc.set_block_limit(settings.max_blocks);
+    doc
+}
+
+/// How to parse gemtext pages.
+pub fn gemtext_options(settings: &Settings) -> gemtext::Options {
+    gemtext::Options::default()
+        .close_unclosed_fences(settings.close_unclosed_fences)
+        .collapse_blank_lines(settings.collapse_blank_lines)
+        .numbered_lists(settings.numbered_lists)
+}
+
+/// A gemtext page that's still loading, configured like [`content_to_doc`] would. See [`GemtextWidget::append_blocks`].
+pub fn streaming_gemtext_doc(blocks: Vec<Block>, settings: &Settings) -> Box<dyn DocWidget> {
+    let mut doc = GemtextWidget::default()
+        .compact_links(settings.compact_link_menus)
+        .collapse_captioned(settings.collapse_captioned_blocks);
+    doc.set_blocks(blocks);
+    doc.set_block_limit(settings.max_blocks);
+    Box::new(doc)
+}
+
+/// Parse `text` as gemtext. Parse errors are shown in the document.
+pub fn gemtext_doc(text: &str) -> GemtextWidget {
+    gemtext_doc_with(text, &gemtext::Options::default())
+}
+
+pub fn gemtext_doc_with(text: &str, parser: &gemtext::Options) -> GemtextWidget {
+    let blocks = match parser.parse(text) {
+        Ok(blocks) => blocks,
+        Err(err) => {
+            let text = format!("{err:#?}");
+            vec![
+                Block::Heading { level: 1, text: "Gemtext Parse Error".into() },
+                Block::Text(String::new()),
+                Block::Text(text),
+            ]
+        },
+    };
+    let mut doc = GemtextWidget::default();
+    doc.set_blocks(blocks);
+    doc
+}
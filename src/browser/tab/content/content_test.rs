@@ -0,0 +1,89 @@
+#![cfg(test)]
+
+use std::{any::Any, sync::Arc};
+
+use crate::{browser::{network::{Body, LoadedResource, Status}, settings::Settings, tab::content::{content_to_doc, DocKind}, widgets::{json::JsonWidget, markdown::MarkdownWidget, plaintext::PlaintextWidget, DocWidget}}, gemtext_widget::GemtextWidget};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn doc_kinds() {
+    let kind = |essence| DocKind::for_content_type(Some(essence));
+    assert_eq!(kind("text/gemini"), DocKind::Gemtext);
+    assert_eq!(kind("text/html"), DocKind::Html);
+    assert_eq!(kind("text/markdown"), DocKind::Markdown);
+    assert_eq!(kind("text/x-markdown"), DocKind::Markdown);
+    assert_eq!(kind("application/rss+xml"), DocKind::Feed);
+    assert_eq!(kind("text/plain"), DocKind::Plaintext);
+    assert_eq!(kind("text/csv"), DocKind::Csv);
+    assert_eq!(kind("text/tab-separated-values"), DocKind::Tsv);
+    assert_eq!(kind("application/json"), DocKind::Json);
+    assert_eq!(kind("application/activity+json"), DocKind::Json);
+    assert_eq!(DocKind::for_content_type(None), DocKind::Plaintext);
+}
+
+fn loaded(content_type: Option<&str>, body: Body) -> LoadedResource {
+    LoadedResource {
+        url: "gemini://example.com/".into(),
+        status: Status::HttpStatus { code: 200 },
+        length: None,
+        content_type: content_type.map(|it| Arc::new(it.parse().unwrap())),
+        cache_control: None,
+        timings: None,
+        body,
+    }
+}
+
+/// The widget for this content.
+fn doc(content_type: Option<&str>, body: Body) -> Box<dyn DocWidget> {
+    content_to_doc(&loaded(content_type, body), &Settings::default())
+}
+
+/// Whether `doc` is a `W`.
+fn is<W: DocWidget>(doc: &dyn DocWidget) -> bool {
+    (doc as &dyn Any).is::<W>()
+}
+
+/// The widget's contents, for checking what it says.
+fn doc_debug(content_type: Option<&str>, body: Body) -> String {
+    format!("{:?}", doc(content_type, body))
+}
+
+fn text(text: &str) -> Body {
+    Body::Text(text.to_string().into())
+}
+
+#[test]
+fn dispatch() {
+    assert!(is::<GemtextWidget>(&*doc(Some("text/gemini"), text("# Hi"))));
+    assert!(is::<GemtextWidget>(&*doc(Some("text/gemini; charset=utf-8"), text("# Hi"))));
+    assert!(is::<MarkdownWidget>(&*doc(Some("text/markdown"), text("# Hi"))));
+    assert!(is::<MarkdownWidget>(&*doc(Some("text/html"), text("<h1>Hi</h1>"))));
+    assert!(is::<PlaintextWidget>(&*doc(Some("text/plain"), text("Hi"))));
+    assert!(is::<PlaintextWidget>(&*doc(None, text("Hi"))));
+
+    // Feeds become gemtext, unless they're broken:
+    let rss = r#"<rss version="2.0"><channel><title>News</title></channel></rss>"#;
+    assert!(is::<GemtextWidget>(&*doc(Some("application/rss+xml"), text(rss))));
+    assert!(is::<PlaintextWidget>(&*doc(Some("application/rss+xml"), text("not xml"))));
+
+    // JSON gets a tree, unless it's broken:
+    assert!(is::<JsonWidget>(&*doc(Some("application/json"), text(r#"{"a": [1, 2]}"#))));
+    assert!(is::<PlaintextWidget>(&*doc(Some("application/json"), text("{not json"))));
+}
+
+#[test]
+fn unsupported_content() {
+    let pdf = doc(Some("application/pdf"), Body::Bytes(b"%PDF".to_vec().into()));
+    assert!(is::<GemtextWidget>(&*pdf));
+    let debug = format!("{pdf:?}");
+    assert!(debug.contains("Unsupported Content-Type"), "{debug}");
+    assert!(debug.contains("application/pdf"), "{debug}");
+}
+
+#[test]
+fn empty_content() {
+    for content_type in [Some("text/gemini"), Some("text/html"), None] {
+        let debug = doc_debug(content_type, text(" \n"));
+        assert!(debug.contains("Empty page"), "{debug}");
+    }
+}
@@ -0,0 +1,51 @@
+//! Saving content we can't show to disk.
+
+use std::path::Path;
+
+use mime::Mime;
+use url::Url;
+
+use crate::browser::network::{BCow, SCow};
+
+mod download_test;
+
+/// The body of a page we couldn't render, kept so that the user can save it.
+#[derive(Debug)]
+pub struct Download {
+    pub url: SCow,
+    pub content_type: Option<Mime>,
+    pub bytes: BCow,
+}
+
+impl Download {
+    /// A file name to suggest in the save dialog.
+    pub fn file_name(&self) -> String {
+        file_name(&self.url, self.content_type.as_ref())
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, &self.bytes)
+    }
+}
+
+/// The last segment of `url`'s path. If it has no extension, one guessed from `content_type`.
+fn file_name(url: &str, content_type: Option<&Mime>) -> String {
+    let name = Url::parse(url).ok()
+        .and_then(|url| {
+            let segment = url.path_segments()?.next_back()?.to_string();
+            Some(percent_encoding::percent_decode_str(&segment).decode_utf8_lossy().into_owned())
+        })
+        .map(|it| it.replace(['/', '\\'], "_"))
+        .filter(|it| !it.is_empty())
+        .unwrap_or_else(|| "download".to_string());
+    if name.contains('.') {
+        return name;
+    }
+    let extension = content_type
+        .and_then(mime_guess::get_mime_extensions)
+        .and_then(|it| it.first());
+    match extension {
+        Some(extension) => format!("{name}.{extension}"),
+        None => name,
+    }
+}
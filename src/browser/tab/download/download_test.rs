@@ -0,0 +1,17 @@
+#![cfg(test)]
+
+use crate::browser::tab::download::file_name;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn file_names() {
+    let pdf: mime::Mime = "application/pdf".parse().unwrap();
+    assert_eq!(file_name("gemini://example.com/docs/paper.pdf", Some(&pdf)), "paper.pdf");
+    assert_eq!(file_name("https://example.com/docs/paper", Some(&pdf)), "paper.pdf");
+    assert_eq!(file_name("https://example.com/my%20paper.pdf?v=2", None), "my paper.pdf");
+    assert_eq!(file_name("https://example.com/my%2Fpaper.pdf", None), "my_paper.pdf");
+
+    // Nothing to go on but the content type:
+    assert_eq!(file_name("https://example.com/", Some(&pdf)), "download.pdf");
+    assert_eq!(file_name("https://example.com/", None), "download");
+}
@@ -0,0 +1,68 @@
+//! Up/Down in the location bar steps through URLs the user typed before, like a shell's history.
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::push_bounded;
+
+mod entered_test;
+
+/// How many typed URLs each tab remembers.
+const MAX_ENTERED: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct EnteredUrls {
+    /// Oldest first.
+    urls: Vec<String>,
+
+    /// Index into [`Self::urls`] of the one shown in the location bar, while stepping through them.
+    #[serde(skip)]
+    position: Option<usize>,
+
+    /// What was in the location bar before the user started stepping, to return to after the newest URL.
+    #[serde(skip)]
+    draft: String,
+}
+
+impl EnteredUrls {
+    /// The user typed `url` into the location bar and pressed Enter.
+    pub fn record(&mut self, url: &str) {
+        self.reset();
+        let url = url.trim();
+        if url.is_empty() {
+            return;
+        }
+        self.urls.retain(|it| it != url);
+        push_bounded(&mut self.urls, url.to_string(), MAX_ENTERED);
+    }
+
+    /// Stop stepping. The next [`Self::older`] starts from the newest URL again.
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    /// The URL entered before the one shown. `current` is what's in the location bar now.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None => {
+                self.draft = current.to_string();
+                self.urls.len().checked_sub(1)?
+            },
+            Some(position) => position.checked_sub(1)?,
+        };
+        self.position = Some(position);
+        Some(&self.urls[position])
+    }
+
+    /// The URL entered after the one shown, or what the user had typed before stepping back.
+    pub fn newer(&mut self) -> Option<String> {
+        let position = self.position? + 1;
+        if position < self.urls.len() {
+            self.position = Some(position);
+            return Some(self.urls[position].clone());
+        }
+        let draft = std::mem::take(&mut self.draft);
+        self.reset();
+        Some(draft)
+    }
+}
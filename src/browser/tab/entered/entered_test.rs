@@ -0,0 +1,28 @@
+#![cfg(test)]
+
+use pretty_assertions::assert_eq;
+
+use crate::browser::tab::entered::EnteredUrls;
+
+#[test]
+fn steps_through_entered_urls() {
+    let mut entered = EnteredUrls::default();
+    assert_eq!(entered.older("typing"), None);
+    assert_eq!(entered.newer(), None);
+
+    entered.record("gemini://one/");
+    entered.record("gemini://two/");
+    entered.record("gemini://one/");
+
+    assert_eq!(entered.older("typing"), Some("gemini://one/"));
+    assert_eq!(entered.older("ignored"), Some("gemini://two/"));
+    assert_eq!(entered.older("ignored"), None);
+    assert_eq!(entered.newer().as_deref(), Some("gemini://one/"));
+    assert_eq!(entered.newer().as_deref(), Some("typing"));
+    assert_eq!(entered.newer(), None);
+
+    // Starts over from the newest:
+    assert_eq!(entered.older("again"), Some("gemini://one/"));
+    entered.reset();
+    assert_eq!(entered.older("again"), Some("gemini://one/"));
+}
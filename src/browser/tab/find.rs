@@ -0,0 +1,117 @@
+//! The find-in-page bar. (Ctrl+F)
+
+use eframe::egui::{self, Key, Modifiers, RichText, TextEdit};
+
+use crate::browser::widgets::DocWidget;
+
+#[derive(Debug, Default)]
+pub struct FindBar {
+    query: String,
+    case_sensitive: bool,
+
+    /// Matches in the document, as of the last search.
+    count: usize,
+    current: usize,
+
+    /// The document we last searched, so that we can search again when it changes.
+    doc_id: Option<u128>,
+
+    /// Focus the text box on the next frame.
+    focus: bool,
+}
+
+impl FindBar {
+    pub fn new() -> Self {
+        Self { focus: true, ..Self::default() }
+    }
+
+    /// Shows the bar. Returns true if the user closed it.
+    pub fn ui(&mut self, ui: &mut egui::Ui, document: Option<&mut Box<dyn DocWidget>>, doc_id: u128) -> bool {
+        let Some(document) = document else {
+            self.count = 0;
+            self.doc_id = None;
+            return self.bar_ui(ui).close;
+        };
+        if self.doc_id != Some(doc_id) {
+            self.doc_id = Some(doc_id);
+            self.search(document.as_mut());
+        }
+
+        let actions = self.bar_ui(ui);
+        if actions.search {
+            self.search(document.as_mut());
+        }
+        if let Some(forward) = actions.step && self.count > 0 {
+            self.current = if forward {
+                (self.current + 1) % self.count
+            } else {
+                (self.current + self.count - 1) % self.count
+            };
+            document.find_select(self.current);
+        }
+        if actions.close {
+            // Clear highlights:
+            document.find("", false);
+        }
+        actions.close
+    }
+
+    fn bar_ui(&mut self, ui: &mut egui::Ui) -> Actions {
+        let mut actions = Actions::default();
+        ui.horizontal(|ui| {
+            let edit = TextEdit::singleline(&mut self.query).hint_text("Find in page");
+            let response = ui.add(edit);
+            if std::mem::take(&mut self.focus) {
+                response.request_focus();
+            }
+            actions.search |= response.changed();
+            if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                actions.step = Some(!ui.input(|i| i.modifiers.contains(Modifiers::SHIFT)));
+                response.request_focus();
+            }
+            if response.has_focus() && ui.input(|i| i.key_pressed(Key::Escape)) {
+                actions.close = true;
+            }
+
+            actions.search |= ui.toggle_value(&mut self.case_sensitive, "Aa")
+                .on_hover_text("Match case")
+                .changed();
+
+            let has_matches = self.count > 0;
+            if ui.add_enabled(has_matches, egui::Button::new("⏶")).on_hover_text("Previous (Shift+Enter)").clicked() {
+                actions.step = Some(false);
+            }
+            if ui.add_enabled(has_matches, egui::Button::new("⏷")).on_hover_text("Next (Enter)").clicked() {
+                actions.step = Some(true);
+            }
+
+            if has_matches {
+                ui.label(format!("{} of {}", self.current + 1, self.count));
+            } else if !self.query.is_empty() {
+                ui.label(RichText::new("No matches").color(ui.visuals().warn_fg_color));
+            }
+
+            if ui.button("✖").on_hover_text("Close (Esc)").clicked() {
+                actions.close = true;
+            }
+        });
+        actions
+    }
+
+    fn search(&mut self, document: &mut dyn DocWidget) {
+        self.count = document.find(&self.query, self.case_sensitive);
+        self.current = 0;
+        if self.count > 0 {
+            document.find_select(0);
+        }
+    }
+}
+
+/// What the user did with the bar this frame.
+#[derive(Default)]
+struct Actions {
+    search: bool,
+    /// Go to the next (true) or previous (false) match.
+    step: Option<bool>,
+    close: bool,
+}
@@ -0,0 +1,114 @@
+//! Follow links from the keyboard, by typing the short label shown over them. (Press `f`.)
+
+use eframe::egui::{self, Color32, Event, FontId, Id, Key, LayerId, Modifiers, Order, Rect, Stroke};
+
+mod hints_test;
+
+/// Letters used for labels. The home row comes first, since it's easiest to type.
+const ALPHABET: &[char] = &['s', 'a', 'd', 'f', 'j', 'k', 'l', 'e', 'w', 'c', 'm', 'p', 'g', 'h'];
+
+#[derive(Debug)]
+pub struct LinkHints {
+    /// Label, where the link is on screen, and its URL.
+    hints: Vec<(String, Rect, String)>,
+
+    /// What the user has typed so far.
+    typed: String,
+}
+
+/// What the user did while hints were shown.
+#[derive(Debug, PartialEq)]
+pub enum HintAction {
+    Follow(String),
+    Cancel,
+}
+
+impl LinkHints {
+    /// Hints for `links`. None if there are no links to follow.
+    pub fn new(links: Vec<(Rect, String)>) -> Option<Self> {
+        if links.is_empty() {
+            return None;
+        }
+        let hints = labels(links.len()).into_iter()
+            .zip(links)
+            .map(|(label, (rect, link))| (label, rect, link))
+            .collect();
+        Some(Self { hints, typed: String::new() })
+    }
+
+    /// Keep the labels over their links as the document moves.
+    /// If the links themselves changed, the user has to start over.
+    pub fn update(&mut self, links: Vec<(Rect, String)>) {
+        let same = links.len() == self.hints.len()
+            && links.iter().zip(&self.hints).all(|((_, link), (_, _, hinted))| link == hinted);
+        if !same {
+            if let Some(hints) = Self::new(links) {
+                *self = hints;
+            }
+            return;
+        }
+        for ((rect, _), (_, hinted, _)) in links.into_iter().zip(&mut self.hints) {
+            *hinted = rect;
+        }
+    }
+
+    /// Handles this frame's typing.
+    pub fn input(&mut self, ctx: &egui::Context) -> Option<HintAction> {
+        if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) {
+            return Some(HintAction::Cancel);
+        }
+        if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Backspace)) {
+            self.typed.pop();
+        }
+        let typed: String = ctx.input(|i| i.events.iter()
+            .filter_map(|event| match event {
+                Event::Text(text) => Some(text.clone()),
+                _ => None,
+            })
+            .collect());
+        typed.chars().find_map(|c| self.type_char(c))
+    }
+
+    /// Returns an action once `c` completes a label, or can't be part of one.
+    pub fn type_char(&mut self, c: char) -> Option<HintAction> {
+        self.typed.push(c.to_ascii_lowercase());
+        let mut matching = self.hints.iter().filter(|(label, ..)| label.starts_with(&self.typed)).peekable();
+        let Some((label, _, link)) = matching.peek() else {
+            return Some(HintAction::Cancel);
+        };
+        (*label == self.typed).then(|| HintAction::Follow(link.clone()))
+    }
+
+    /// Paints the labels of links that still match what's been typed.
+    pub fn paint(&self, ctx: &egui::Context) {
+        let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("link hints")));
+        let font = FontId::monospace(12.0);
+        for (label, rect, _) in &self.hints {
+            let Some(rest) = label.strip_prefix(&self.typed) else {
+                continue;
+            };
+            let galley = painter.layout_no_wrap(rest.to_uppercase(), font.clone(), Color32::BLACK);
+            let background = Rect::from_min_size(rect.min, galley.size()).expand(2.0);
+            painter.rect(background, 2.0, Color32::from_rgb(255, 221, 85), Stroke::new(1.0, Color32::from_rgb(160, 130, 20)), egui::StrokeKind::Outside);
+            painter.galley(background.shrink(2.0).min, galley, Color32::BLACK);
+        }
+    }
+}
+
+/// `count` distinct labels, all the same length, so that none is a prefix of another.
+pub fn labels(count: usize) -> Vec<String> {
+    let mut length = 1;
+    while ALPHABET.len().pow(length) < count {
+        length += 1;
+    }
+    (0..count)
+        .map(|mut index| {
+            let mut label = Vec::new();
+            for _ in 0..length {
+                label.push(ALPHABET[index % ALPHABET.len()]);
+                index /= ALPHABET.len();
+            }
+            label.into_iter().rev().collect()
+        })
+        .collect()
+}
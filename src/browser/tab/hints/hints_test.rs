@@ -0,0 +1,35 @@
+#![cfg(test)]
+
+use eframe::egui::Rect;
+use pretty_assertions::assert_eq;
+
+use crate::browser::tab::hints::{labels, HintAction, LinkHints};
+
+#[test]
+fn hint_labels() {
+    assert_eq!(labels(3), vec!["s", "a", "d"]);
+
+    let many = labels(20);
+    assert_eq!(many[..3], ["ss", "sa", "sd"]);
+    assert!(many.iter().all(|it| it.len() == 2));
+    let mut unique = many.clone();
+    unique.dedup();
+    assert_eq!(unique.len(), many.len());
+}
+
+#[test]
+fn typing_hints() {
+    let links = |count: usize| (0..count).map(|it| (Rect::NOTHING, format!("/{it}"))).collect::<Vec<_>>();
+    assert!(LinkHints::new(Vec::new()).is_none());
+
+    let mut hints = LinkHints::new(links(3)).unwrap();
+    assert_eq!(hints.type_char('A'), Some(HintAction::Follow("/1".into())));
+
+    let mut hints = LinkHints::new(links(20)).unwrap();
+    assert_eq!(hints.type_char('s'), None);
+    assert_eq!(hints.type_char('d'), Some(HintAction::Follow("/2".into())));
+
+    // Not a label:
+    let mut hints = LinkHints::new(links(3)).unwrap();
+    assert_eq!(hints.type_char('z'), Some(HintAction::Cancel));
+}
@@ -0,0 +1,281 @@
+//! Gemini input prompts. (Status 10 and 11)
+//! See: https://geminiprotocol.net/docs/protocol-specification.gmi#input-expected
+//!
+//! Also, logins for HTTP basic auth, and Titan uploads.
+
+use std::{fmt::Debug, sync::LazyLock};
+
+use eframe::egui::{self, Button, Grid, RichText, TextEdit};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use regex::Regex;
+use url::Url;
+
+use crate::{browser::network::{http::auth::Credentials, titan::{self, Upload}, SCow}, gemtext_widget::Style, widgets::textbox::TextBox};
+
+/// Characters to percent-encode in a query. Everything but RFC 3986 "unreserved" characters.
+/// Notably, spaces become `%20`, not `+`.
+const QUERY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Gemini URLs (including the query) can be at most this many bytes.
+/// See: https://geminiprotocol.net/docs/protocol-specification.gmi#requests
+const MAX_URL_BYTES: usize = 1024;
+
+pub struct InputPrompt {
+    /// The URL that asked for input.
+    pub url: SCow,
+    pub prompt: String,
+
+    /// Status 11. Mask the input, and don't save it anywhere.
+    pub sensitive: bool,
+
+    value: String,
+
+    /// From the prompt's [`length_hint`], or else the most that could fit in a URL.
+    max_chars: usize,
+
+    /// Focus the text box when it's first shown.
+    focused: bool,
+}
+
+// Manual impl, so that we never log sensitive input.
+impl Debug for InputPrompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InputPrompt")
+            .field("url", &self.url)
+            .field("prompt", &self.prompt)
+            .field("sensitive", &self.sensitive)
+            .finish_non_exhaustive()
+    }
+}
+
+impl InputPrompt {
+    pub fn new(url: SCow, prompt: String, sensitive: bool) -> Self {
+        let max_chars = length_hint(&prompt).unwrap_or(MAX_URL_BYTES);
+        Self { url, prompt, sensitive, value: String::new(), max_chars, focused: false }
+    }
+
+    /// Returns the URL to load, once the user submits their input.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<SCow> {
+        let prompt = if self.prompt.trim().is_empty() { "Input requested" } else { self.prompt.trim() };
+        ui.label(RichText::new(prompt).text_style(Style::h2()).strong());
+        ui.add_space(ui.spacing().item_spacing.y.max(4.0));
+
+        let url = query_url(&self.url, &self.value);
+        let url_bytes = url.as_ref().map(String::len).unwrap_or_default();
+        let too_long = url_bytes > MAX_URL_BYTES;
+
+        let mut submit = false;
+        ui.horizontal(|ui| {
+            let mut textbox = TextBox::new(&mut self.value)
+                .password(self.sensitive)
+                .char_limit(self.max_chars);
+            ui.add(&mut textbox);
+            if !self.focused {
+                textbox.request_focus();
+                self.focused = true;
+            }
+            submit |= textbox.enter_pressed(ui);
+            submit |= ui.add_enabled(!too_long, Button::new("Submit")).clicked();
+        });
+
+        // Characters, for the user. But non-ASCII characters take several bytes once percent-encoded:
+        let chars = self.value.chars().count();
+        ui.label(RichText::new(format!("{chars}/{} characters, URL is {url_bytes}/{MAX_URL_BYTES} bytes", self.max_chars)).weak());
+        if too_long {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("Your input is too long to send. Gemini URLs can be at most {MAX_URL_BYTES} bytes."),
+            );
+        }
+        if self.sensitive {
+            ui.label(RichText::new("🔒 Sensitive input. It won't be shown or saved in your history.").weak());
+        }
+
+        if !submit || too_long {
+            return None;
+        }
+        match url {
+            Ok(url) => Some(url.into()),
+            Err(err) => {
+                log::warn!("Couldn't build input URL from {}: {err}", self.url);
+                None
+            }
+        }
+    }
+}
+
+/// Shown when an HTTP server asks for basic auth.
+pub struct LoginPrompt {
+    /// The URL that asked for a login.
+    pub url: SCow,
+
+    /// The server's name for what's protected. May be empty.
+    realm: String,
+
+    /// The server didn't accept the credentials we last sent.
+    rejected: bool,
+
+    user: String,
+    password: String,
+
+    /// Focus the user name when it's first shown.
+    focused: bool,
+}
+
+// Manual impl, so that we never log passwords.
+impl Debug for LoginPrompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoginPrompt")
+            .field("url", &self.url)
+            .field("realm", &self.realm)
+            .field("rejected", &self.rejected)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LoginPrompt {
+    pub fn new(url: SCow, realm: String, rejected: bool) -> Self {
+        Self { url, realm, rejected, user: String::new(), password: String::new(), focused: false }
+    }
+
+    /// Returns the credentials to retry with, once the user submits them.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<Credentials> {
+        let url = Url::parse(&self.url).ok();
+        let host = url.as_ref().and_then(|it| it.host_str()).unwrap_or("this site");
+        ui.label(RichText::new(format!("Log in to {host}")).text_style(Style::h2()).strong());
+        if !self.realm.trim().is_empty() {
+            ui.label(RichText::new(format!("“{}”", self.realm.trim())).weak());
+        }
+        ui.add_space(ui.spacing().item_spacing.y.max(4.0));
+        if self.rejected {
+            ui.colored_label(ui.visuals().error_fg_color, "The server didn't accept that user name and password.");
+        }
+
+        let mut submit = false;
+        Grid::new("login").num_columns(2).show(ui, |ui| {
+            ui.label("User name:");
+            let mut user = TextBox::new(&mut self.user);
+            ui.add(&mut user);
+            if !self.focused {
+                user.request_focus();
+                self.focused = true;
+            }
+            submit |= user.enter_pressed(ui);
+            ui.end_row();
+
+            ui.label("Password:");
+            let mut password = TextBox::new(&mut self.password).password(true);
+            ui.add(&mut password);
+            submit |= password.enter_pressed(ui);
+            ui.end_row();
+        });
+        submit |= ui.button("Log in").clicked();
+
+        if url.is_some_and(|it| it.scheme() == "http") {
+            ui.colored_label(ui.visuals().warn_fg_color, "⚠ This connection isn't encrypted. Your password will be sent as plain text.");
+        }
+        ui.label(RichText::new("🔒 Your login is only kept until you quit.").weak());
+
+        submit.then(|| Credentials { user: self.user.clone(), password: self.password.clone() })
+    }
+}
+
+/// Shown for titan:// URLs, to write what to upload to them.
+pub struct UploadPrompt {
+    /// The titan:// URL to upload to.
+    pub url: SCow,
+
+    text: String,
+    mime: String,
+    token: String,
+
+    /// Focus the text when it's first shown.
+    focused: bool,
+}
+
+// Manual impl, so that we never log tokens.
+impl Debug for UploadPrompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadPrompt")
+            .field("url", &self.url)
+            .field("mime", &self.mime)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UploadPrompt {
+    pub fn new(url: SCow) -> Self {
+        Self { url, text: String::new(), mime: "text/gemini".into(), token: String::new(), focused: false }
+    }
+
+    /// Returns what to upload, once the user submits it.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<Upload> {
+        let page = Url::parse(&self.url).map(|it| titan::gemini_url(&it).to_string()).unwrap_or_else(|_| self.url.to_string());
+        ui.label(RichText::new("Upload").text_style(Style::h2()).strong());
+        ui.label(RichText::new(format!("to {page}")).weak());
+        ui.add_space(ui.spacing().item_spacing.y.max(4.0));
+
+        Grid::new("upload").num_columns(2).show(ui, |ui| {
+            ui.label("Type:");
+            ui.add(&mut TextBox::new(&mut self.mime));
+            ui.end_row();
+
+            ui.label("Token:");
+            ui.add(&mut TextBox::new(&mut self.token).password(true))
+                .on_hover_text("Some servers require a token (or password) to accept uploads. Leave it empty if not.");
+            ui.end_row();
+        });
+
+        let edit = ui.add(
+            TextEdit::multiline(&mut self.text)
+                .code_editor()
+                .desired_rows(16)
+                .desired_width(f32::INFINITY)
+        );
+        if !self.focused {
+            edit.request_focus();
+            self.focused = true;
+        }
+
+        let mime = self.mime.trim();
+        let valid = mime.parse::<mime::Mime>().is_ok();
+        let mut submit = false;
+        ui.horizontal(|ui| {
+            submit = ui.add_enabled(valid, Button::new("⬆ Upload")).clicked();
+            ui.label(RichText::new(format!("{} bytes", self.text.len())).weak());
+        });
+        if !valid {
+            ui.colored_label(ui.visuals().error_fg_color, format!("“{mime}” isn't a valid content type."));
+        }
+        if self.text.is_empty() {
+            ui.colored_label(ui.visuals().warn_fg_color, "⚠ Many servers delete the page when sent an empty upload.");
+        }
+
+        submit.then(|| Upload {
+            body: self.text.clone().into_bytes(),
+            mime: mime.to_string(),
+            token: Some(self.token.trim()).filter(|it| !it.is_empty()).map(String::from),
+        })
+    }
+}
+
+/// Some servers say how long input can be in their prompt. (ex: "Message (max 140 characters)")
+pub fn length_hint(prompt: &str) -> Option<usize> {
+    static HINT: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?i)\b(?:max(?:imum)?|up to|at most)\s*(?:of\s+)?(\d+)\s*(?:chars?|characters)\b").unwrap()
+    });
+    let max = HINT.captures(prompt)?[1].parse().ok()?;
+    (max > 0).then_some(max)
+}
+
+/// Replace the query of `url` with the (percent-encoded) `input`.
+pub fn query_url(url: &str, input: &str) -> Result<String, url::ParseError> {
+    let mut url = Url::parse(url)?;
+    let query = utf8_percent_encode(input, QUERY).to_string();
+    url.set_query(Some(&query));
+    Ok(url.into())
+}
@@ -0,0 +1,115 @@
+//! Fetches the titles of links the user hovers over, so that they know where a link goes before clicking it.
+
+use std::{collections::HashSet, time::{Duration, Instant}};
+
+use reqwest::Url;
+use tokio::task::JoinHandle;
+
+use crate::browser::network::{is_binary, rt, SCow};
+
+mod preview_test;
+
+/// The URL a preview loaded, and its title.
+pub type Found = (SCow, String);
+
+#[derive(Debug, Default)]
+pub struct Previewer {
+    /// The link being hovered, and since when.
+    hovered: Option<(String, Instant)>,
+
+    /// Links we've already previewed (or tried to) on this page.
+    started: HashSet<String>,
+
+    /// Links we're loading previews for.
+    loading: Vec<(String, JoinHandle<Option<Found>>)>,
+}
+
+impl Previewer {
+    /// Call every frame with the hovered link, if any.
+    /// Returns the link to preview, the first time it's been hovered for `delay`.
+    pub fn hover(&mut self, link: Option<String>, delay: Duration, ctx: &eframe::egui::Context) -> Option<String> {
+        let Some(link) = link else {
+            self.hovered = None;
+            return None;
+        };
+        if self.started.contains(&link) {
+            return None;
+        }
+        let since = match &self.hovered {
+            Some((hovered, since)) if *hovered == link => *since,
+            _ => {
+                self.hovered = Some((link.clone(), Instant::now()));
+                Instant::now()
+            },
+        };
+        let elapsed = since.elapsed();
+        if elapsed < delay {
+            ctx.request_repaint_after(delay - elapsed);
+            return None;
+        }
+        self.hovered = None;
+        self.started.insert(link.clone());
+        Some(link)
+    }
+
+    pub fn start(&mut self, link: String, handle: JoinHandle<Option<Found>>) {
+        self.loading.push((link, handle));
+    }
+
+    /// Previews that finished loading since the last call, with the URL and title they found, if any.
+    pub fn finished(&mut self, ctx: &eframe::egui::Context) -> Vec<(String, Option<Found>)> {
+        if self.loading.is_empty() {
+            return Vec::new();
+        }
+        // Nothing else will wake us up when they finish:
+        ctx.request_repaint_after(Duration::from_millis(100));
+
+        let (done, pending) = std::mem::take(&mut self.loading)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(_, handle)| handle.is_finished());
+        self.loading = pending;
+        done.into_iter()
+            .filter_map(|(link, handle)| {
+                // We expect this not to block (long) because the task is finished already:
+                rt().block_on(handle).ok().map(|result| (link, result))
+            })
+            .collect()
+    }
+
+    /// Forget the previous page's links, and stop loading their previews.
+    pub fn clear(&mut self) {
+        for (_, handle) in self.loading.drain(..) {
+            handle.abort();
+        }
+        self.started.clear();
+        self.hovered = None;
+    }
+}
+
+/// For privacy, we only preview links that don't leave the current page's protocol.
+/// (ex: Hovering an http link on a gemini page shouldn't tell a web server you're there.)
+/// Links with a query may do something (ex: vote, or delete), and links to files (ex: images) don't have titles,
+/// so neither are previewed.
+pub fn allowed(location: &str, target: &Url) -> bool {
+    let Ok(location) = Url::parse(location) else {
+        return false;
+    };
+    matches!(target.scheme(), "gemini" | "http" | "https")
+        && target.scheme() == location.scheme()
+        && target.query().is_none()
+        && is_page(target)
+}
+
+/// Server-side scripts that are usually pages, though their extensions don't say so.
+const SCRIPT_EXTENSIONS: &[&str] = &["php", "asp", "aspx", "jsp", "cgi"];
+
+/// Whether `url` could be a page with a title, going by its extension. Unknown extensions might be.
+fn is_page(url: &Url) -> bool {
+    let name = url.path_segments().and_then(|mut it| it.next_back()).unwrap_or_default();
+    let Some((_, extension)) = name.rsplit_once('.') else {
+        return true;
+    };
+    let extension = extension.to_ascii_lowercase();
+    SCRIPT_EXTENSIONS.contains(&extension.as_str())
+        || mime_guess::from_ext(&extension).first().is_none_or(|it| !is_binary(Some(&it)))
+}
@@ -0,0 +1,54 @@
+#![cfg(test)]
+
+use std::time::Duration;
+
+use reqwest::Url;
+
+use crate::browser::tab::preview::{allowed, Previewer};
+
+#[test]
+fn same_scheme_only() {
+    let url = |it: &str| Url::parse(it).unwrap();
+    assert!(allowed("gemini://example.com/", &url("gemini://other.example/page.gmi")));
+    assert!(allowed("https://example.com/", &url("https://other.example/")));
+    assert!(!allowed("gemini://example.com/", &url("https://example.com/")));
+    assert!(!allowed("http://example.com/", &url("https://example.com/")));
+    assert!(!allowed("about:egemi", &url("about:changelog")));
+    assert!(!allowed("file:///tmp/", &url("file:///tmp/a.gmi")));
+}
+
+#[test]
+fn pages_only() {
+    let allowed = |it: &str| allowed("https://example.com/", &Url::parse(it).unwrap());
+    assert!(allowed("https://example.com/posts/hello"));
+    assert!(allowed("https://example.com/index.html"));
+    assert!(allowed("https://example.com/feed.xml"));
+    assert!(allowed("https://example.com/wiki.php"));
+    assert!(allowed("https://example.com/notes.gmi"));
+
+    // Files without titles:
+    assert!(!allowed("https://example.com/photo.JPG"));
+    assert!(!allowed("https://example.com/release.tar.gz"));
+    assert!(!allowed("https://example.com/talk.mp4"));
+
+    // Queries may do something:
+    assert!(!allowed("https://example.com/vote?post=3"));
+    assert!(!allowed("https://example.com/search?q=egemi"));
+}
+
+#[test]
+fn previews_each_link_once() {
+    let ctx = eframe::egui::Context::default();
+    let mut previewer = Previewer::default();
+    let link = || Some("page.gmi".to_string());
+
+    // Not until it's been hovered long enough:
+    assert_eq!(previewer.hover(link(), Duration::from_secs(60), &ctx), None);
+    assert_eq!(previewer.hover(link(), Duration::ZERO, &ctx), link());
+    assert_eq!(previewer.hover(link(), Duration::ZERO, &ctx), None);
+    assert_eq!(previewer.hover(None, Duration::ZERO, &ctx), None);
+
+    // A new page can preview it again:
+    previewer.clear();
+    assert_eq!(previewer.hover(link(), Duration::ZERO, &ctx), link());
+}
@@ -0,0 +1,98 @@
+//! Showing gemtext pages while they're still loading.
+
+use std::sync::mpsc::{self, Receiver};
+
+use crate::{browser::network::{Chunk, Chunks, LoadedResource, SCow}, gemtext::{self, Block, StreamParser}};
+
+use super::content::DocKind;
+
+/// Receives a page's [`Chunk`]s as they arrive, and parses them if they're gemtext.
+#[derive(Debug)]
+pub struct PageStream {
+    chunks: Receiver<Chunk>,
+
+    /// Given to the parser when a gemtext response starts.
+    options: gemtext::Options,
+
+    /// The URL of the response we're parsing, and its parser. None if it isn't gemtext.
+    parser: Option<(SCow, StreamParser)>,
+
+    /// The response (re)started, and we haven't shown any of it yet.
+    fresh: bool,
+}
+
+/// What to do with the document after [`PageStream::poll`].
+#[derive(Debug, PartialEq)]
+pub enum Update {
+    /// Show a new document, for the (gemtext) response from `url`.
+    Replace { url: SCow, blocks: Vec<Block> },
+
+    /// Add more blocks to the end of the document we're already showing.
+    Append(Vec<Block>),
+}
+
+impl PageStream {
+    /// A stream that parses with `options`, and the sender for the loader to send chunks to.
+    pub fn new(options: gemtext::Options) -> (Chunks, Self) {
+        let (sender, chunks) = mpsc::channel();
+        (sender, Self { chunks, options, parser: None, fresh: true })
+    }
+
+    /// Parse any chunks that have arrived.
+    pub fn poll(&mut self) -> Option<Update> {
+        let mut update: Option<Update> = None;
+        while let Ok(chunk) = self.chunks.try_recv() {
+            match chunk {
+                Chunk::Start { url, content_type } => {
+                    // (A retry after a dropped connection starts over, so anything we already showed is replaced.)
+                    let essence = content_type.as_ref().map(|it| it.essence_str());
+                    self.parser = (DocKind::for_content_type(essence) == DocKind::Gemtext)
+                        .then(|| (url, StreamParser::new(self.options.clone())));
+                    self.fresh = true;
+                    update = None;
+                },
+                Chunk::Body(bytes) => {
+                    let Some((url, parser)) = &mut self.parser else {
+                        continue;
+                    };
+                    let Ok(blocks) = parser.push(&bytes) else {
+                        // Showing the whole response will show the error, too.
+                        self.parser = None;
+                        update = None;
+                        continue;
+                    };
+                    if blocks.is_empty() {
+                        continue;
+                    }
+                    if std::mem::take(&mut self.fresh) {
+                        update = Some(Update::Replace { url: url.clone(), blocks });
+                        continue;
+                    }
+                    match &mut update {
+                        Some(Update::Replace { blocks: shown, .. } | Update::Append(shown)) => shown.extend(blocks),
+                        None => update = Some(Update::Append(blocks)),
+                    }
+                },
+            }
+        }
+        update
+    }
+
+    /// The rest of the page, if we've been showing it and `loaded` is the response we showed.
+    /// None if `loaded` should be shown as usual, instead.
+    pub fn finish(mut self, loaded: &LoadedResource) -> Option<Vec<Block>> {
+        // The loader sent these before it finished:
+        let mut blocks = match self.poll() {
+            Some(Update::Replace { .. }) => return None,
+            Some(Update::Append(blocks)) => blocks,
+            None => Vec::new(),
+        };
+        let (url, parser) = self.parser?;
+        let essence = loaded.content_type.as_ref().map(|it| it.essence_str());
+        if self.fresh || url != loaded.url || DocKind::for_content_type(essence) != DocKind::Gemtext {
+            return None;
+        }
+        blocks.extend(parser.finish().ok()?);
+        Some(blocks)
+    }
+}
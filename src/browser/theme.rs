@@ -0,0 +1,107 @@
+//! Colors, font sizes, and animations for the whole app, on top of egui's light and dark themes.
+
+use eframe::egui::{self, style::ScrollAnimation, Color32, Stroke, Theme, Visuals};
+
+use crate::{browser::settings::Palette, gemtext_widget::Style};
+
+mod theme_test;
+
+/// Set both the light and dark visuals for `palette`, so that switching themes keeps it.
+pub fn apply(ctx: &egui::Context, palette: Palette, font_size: f32, reduce_motion: bool) {
+    for theme in [Theme::Light, Theme::Dark] {
+        ctx.set_visuals_of(theme, visuals(palette, theme));
+    }
+    Style::set_body_size(ctx, font_size.clamp(4.0, 64.0));
+    set_reduce_motion(ctx, reduce_motion);
+}
+
+/// Without animations, panels, menus, and collapsing sections open instantly, and scrolling jumps straight to where it's going.
+pub fn set_reduce_motion(ctx: &egui::Context, reduce: bool) {
+    let defaults = egui::Style::default();
+    ctx.all_styles_mut(|style| {
+        if reduce {
+            style.animation_time = 0.0;
+            style.scroll_animation = ScrollAnimation::none();
+        } else {
+            style.animation_time = defaults.animation_time;
+            style.scroll_animation = defaults.scroll_animation;
+        }
+    });
+}
+
+pub fn visuals(palette: Palette, theme: Theme) -> Visuals {
+    let mut visuals = theme.default_visuals();
+    match palette {
+        Palette::Default => {},
+        Palette::Sepia => sepia(&mut visuals, theme),
+        Palette::HighContrast => high_contrast(&mut visuals, theme),
+    }
+    visuals
+}
+
+/// Browns and creams, with softer contrast than the defaults.
+fn sepia(visuals: &mut Visuals, theme: Theme) {
+    let (text, panel, document, faint, widget, link, selection) = match theme {
+        Theme::Light => (
+            Color32::from_rgb(91, 70, 54),
+            Color32::from_rgb(236, 226, 204),
+            Color32::from_rgb(248, 241, 225),
+            Color32::from_rgb(228, 216, 190),
+            Color32::from_rgb(222, 208, 178),
+            Color32::from_rgb(150, 75, 20),
+            Color32::from_rgb(214, 190, 140),
+        ),
+        Theme::Dark => (
+            Color32::from_rgb(222, 205, 180),
+            Color32::from_rgb(43, 36, 29),
+            Color32::from_rgb(33, 27, 22),
+            Color32::from_rgb(52, 44, 36),
+            Color32::from_rgb(68, 57, 46),
+            Color32::from_rgb(225, 165, 95),
+            Color32::from_rgb(110, 80, 45),
+        ),
+    };
+    visuals.override_text_color = Some(text);
+    visuals.panel_fill = panel;
+    visuals.window_fill = panel;
+    visuals.extreme_bg_color = document;
+    visuals.faint_bg_color = faint;
+    visuals.code_bg_color = faint;
+    visuals.hyperlink_color = link;
+    visuals.selection.bg_fill = selection;
+    visuals.selection.stroke = Stroke::new(1.0, text);
+    visuals.widgets.noninteractive.bg_fill = panel;
+    visuals.widgets.noninteractive.weak_bg_fill = panel;
+    visuals.widgets.inactive.bg_fill = widget;
+    visuals.widgets.inactive.weak_bg_fill = widget;
+    visuals.widgets.hovered.bg_fill = selection;
+    visuals.widgets.hovered.weak_bg_fill = selection;
+}
+
+/// Pure black and white, with bright links and outlined widgets.
+fn high_contrast(visuals: &mut Visuals, theme: Theme) {
+    let (text, background, link) = match theme {
+        Theme::Light => (Color32::BLACK, Color32::WHITE, Color32::from_rgb(0, 0, 200)),
+        Theme::Dark => (Color32::WHITE, Color32::BLACK, Color32::from_rgb(255, 230, 0)),
+    };
+    visuals.override_text_color = Some(text);
+    visuals.panel_fill = background;
+    visuals.window_fill = background;
+    visuals.extreme_bg_color = background;
+    visuals.faint_bg_color = background;
+    visuals.code_bg_color = background;
+    visuals.hyperlink_color = link;
+    visuals.window_stroke = Stroke::new(1.0, text);
+    visuals.selection.bg_fill = link;
+    visuals.selection.stroke = Stroke::new(1.0, background);
+    for widget in [&mut visuals.widgets.noninteractive, &mut visuals.widgets.inactive] {
+        widget.bg_fill = background;
+        widget.weak_bg_fill = background;
+        widget.bg_stroke = Stroke::new(1.0, text);
+        widget.fg_stroke = Stroke::new(1.0, text);
+    }
+    for widget in [&mut visuals.widgets.hovered, &mut visuals.widgets.active, &mut visuals.widgets.open] {
+        widget.bg_stroke = Stroke::new(2.0, link);
+        widget.fg_stroke = Stroke::new(1.5, text);
+    }
+}
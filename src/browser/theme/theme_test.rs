@@ -0,0 +1,41 @@
+#![cfg(test)]
+
+use eframe::egui::{self, style::ScrollAnimation, TextStyle, Theme};
+
+use crate::{browser::{settings::Palette, theme::{apply, set_reduce_motion, visuals}}, gemtext_widget::Style};
+
+#[test]
+fn font_size_resizes_headings() {
+    let ctx = egui::Context::default();
+    let size = |style: TextStyle| ctx.style().text_styles[&style].size;
+
+    apply(&ctx, Palette::Default, 20.0, false);
+    assert_eq!(size(TextStyle::Body), 20.0);
+    assert_eq!(size(Style::h1()), 40.0);
+    assert_eq!(size(Style::mono()), 16.0);
+
+    // Derived sizes are recomputed, not just set once:
+    apply(&ctx, Palette::Default, 10.0, false);
+    assert_eq!(size(TextStyle::Body), 10.0);
+    assert_eq!(size(Style::h1()), 20.0);
+}
+
+#[test]
+fn palettes_keep_their_theme() {
+    for palette in Palette::ALL {
+        assert!(visuals(*palette, Theme::Dark).dark_mode, "{palette:?}");
+        assert!(!visuals(*palette, Theme::Light).dark_mode, "{palette:?}");
+    }
+    assert_eq!(visuals(Palette::Default, Theme::Dark), Theme::Dark.default_visuals());
+}
+
+#[test]
+fn reduce_motion() {
+    let ctx = egui::Context::default();
+    set_reduce_motion(&ctx, true);
+    assert_eq!(ctx.style().animation_time, 0.0);
+    assert_eq!(ctx.style().scroll_animation, ScrollAnimation::none());
+
+    set_reduce_motion(&ctx, false);
+    assert_eq!(ctx.style().animation_time, egui::Style::default().animation_time);
+}
@@ -0,0 +1,149 @@
+//! Actions for code blocks, shared by the gemtext and markdown widgets.
+
+use std::{fs::{self, OpenOptions}, io::{self, Write as _}, path::{Path, PathBuf}};
+
+use eframe::egui::{Id, OpenUrl, RichText, ScrollArea, TextWrapMode, Ui};
+
+use crate::browser::APP_ID;
+
+mod code_test;
+
+/// Small "copy", "wrap", and "save" buttons to show above a code block.
+/// `meta` is the text after the opening fence, which may name the language or a file name.
+/// Saving is only offered when it does, since untagged blocks are usually output or ASCII art.
+/// Returns whether the user wants the block soft-wrapped. See [`body`].
+pub fn toolbar(ui: &mut Ui, meta: &str, text: &str) -> bool {
+    let id = Id::new(("code snippet", text));
+    let saved: Option<Result<PathBuf, String>> = ui.data(|it| it.get_temp(id));
+    let wrap_id = id.with("wrap");
+    let mut wrap = ui.data(|it| it.get_temp(wrap_id)).unwrap_or(false);
+
+    ui.horizontal(|ui| {
+        if ui.small_button("📋 Copy").on_hover_text("Copy this code").clicked() {
+            ui.ctx().copy_text(text.to_string());
+        }
+        let toggle = ui.toggle_value(&mut wrap, RichText::new("↩ Wrap").small())
+            .on_hover_text("Wrap long lines, instead of scrolling sideways");
+        if toggle.changed() {
+            ui.data_mut(|it| it.insert_temp(wrap_id, wrap));
+        }
+        if meta.trim().is_empty() {
+            return;
+        }
+        let save = ui.small_button("💾 Save")
+            .on_hover_text(format!("Save as {} in egemi's snippets directory", file_name(meta)));
+        if save.clicked() {
+            let result = save_snippet(meta, text).map_err(|err| err.to_string());
+            ui.data_mut(|it| it.insert_temp(id, result));
+        }
+        match saved {
+            Some(Ok(path)) => {
+                let shown = ui.link(RichText::new(path.display().to_string()).small())
+                    .on_hover_text("Show in file manager");
+                if shown.clicked() && let Some(dir) = path.parent() {
+                    ui.ctx().open_url(OpenUrl::new_tab(format!("file://{}", dir.display())));
+                }
+            },
+            Some(Err(err)) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Couldn't save: {err}"));
+            },
+            None => {},
+        }
+    });
+    wrap
+}
+
+/// Shows a code block's lines with `add_lines`. Long lines scroll sideways, so that alignment (ex: of ASCII art)
+/// is kept, unless `wrap`. `text` is the whole block, to tell blocks' scroll positions apart.
+pub fn body(ui: &mut Ui, text: &str, wrap: bool, add_lines: impl FnOnce(&mut Ui)) {
+    if wrap {
+        ui.scope(|ui| {
+            ui.style_mut().wrap_mode = Some(TextWrapMode::Wrap);
+            add_lines(ui);
+        });
+        return;
+    }
+    ScrollArea::horizontal()
+        .id_salt(("code block", text))
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+            add_lines(ui);
+        });
+}
+
+/// Write `text` to a new file in egemi's snippets directory, which is in its per-user data directory.
+pub fn save_snippet(meta: &str, text: &str) -> io::Result<PathBuf> {
+    let dir = eframe::storage_dir(APP_ID)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory for this user"))?
+        .join("snippets");
+    save_snippet_in(&dir, meta, text)
+}
+
+/// Write `text` to a new file in `dir`. Never overwrites an existing file.
+pub fn save_snippet_in(dir: &Path, meta: &str, text: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let mut text = text.to_string();
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+
+    let name = file_name(meta);
+    let (stem, ext) = name.rsplit_once('.').unwrap_or((&name, "txt"));
+    let mut path = dir.join(&name);
+    let mut count = 1;
+    loop {
+        // (Checking first, then writing, would follow a symlink someone put there in between.)
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(text.as_bytes())?;
+                return Ok(path);
+            },
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                count += 1;
+                path = dir.join(format!("{stem}-{count}.{ext}"));
+            },
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// A file name for a snippet, based on its fence metadata.
+/// The metadata may be a file name (ex: `install.sh`) or a language (ex: `python`).
+pub fn file_name(meta: &str) -> String {
+    let word = meta.split(|c: char| c.is_whitespace() || c == ',').find(|it| !it.is_empty()).unwrap_or_default();
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_');
+    if word.contains('.') && !word.starts_with('.') && word.chars().all(is_safe) {
+        return word.to_string();
+    }
+    format!("snippet.{}", extension(word))
+}
+
+fn extension(language: &str) -> &'static str {
+    match language.to_ascii_lowercase().as_str() {
+        "sh" | "shell" | "bash" | "zsh" | "console" => "sh",
+        "fish" => "fish",
+        "ps1" | "powershell" => "ps1",
+        "py" | "python" => "py",
+        "rs" | "rust" => "rs",
+        "js" | "javascript" => "js",
+        "ts" | "typescript" => "ts",
+        "rb" | "ruby" => "rb",
+        "go" => "go",
+        "c" => "c",
+        "cpp" | "c++" => "cpp",
+        "java" => "java",
+        "lua" => "lua",
+        "pl" | "perl" => "pl",
+        "html" => "html",
+        "css" => "css",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "md" | "markdown" => "md",
+        "gmi" | "gemini" | "gemtext" => "gmi",
+        "sql" => "sql",
+        _ => "txt",
+    }
+}
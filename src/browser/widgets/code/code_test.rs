@@ -0,0 +1,32 @@
+#![cfg(test)]
+
+use crate::browser::widgets::code::{file_name, save_snippet_in};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn file_names() {
+    assert_eq!(file_name("install.sh"), "install.sh");
+    assert_eq!(file_name("bash"), "snippet.sh");
+    assert_eq!(file_name("Python 3"), "snippet.py");
+    assert_eq!(file_name("rust,ignore"), "snippet.rs");
+    assert_eq!(file_name("whatever"), "snippet.txt");
+    assert_eq!(file_name(""), "snippet.txt");
+    // Don't let capsules write outside of the snippets directory:
+    assert_eq!(file_name("../../.bashrc"), "snippet.txt");
+    assert_eq!(file_name(".bashrc"), "snippet.txt");
+}
+
+#[test]
+fn snippets_dont_overwrite() {
+    let dir = std::env::temp_dir().join(format!("egemi-snippets-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let first = save_snippet_in(&dir, "bash", "echo one").unwrap();
+    let second = save_snippet_in(&dir, "bash", "echo two\n").unwrap();
+    assert_eq!(first, dir.join("snippet.sh"));
+    assert_eq!(second, dir.join("snippet-2.sh"));
+    assert_eq!(std::fs::read_to_string(&first).unwrap(), "echo one\n");
+    assert_eq!(std::fs::read_to_string(&second).unwrap(), "echo two\n");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
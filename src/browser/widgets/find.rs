@@ -0,0 +1,159 @@
+//! Highlights text matching a search within a document. Shared by the gemtext and markdown widgets.
+//! See [`DocWidget::find`](super::DocWidget::find).
+
+use std::ops::Range;
+
+use eframe::egui::{text::{LayoutJob, LayoutSection}, Align, Color32, FontSelection, Response, RichText, Ui, WidgetText};
+
+mod find_test;
+
+#[derive(Debug, Default)]
+pub struct Find {
+    query: String,
+    case_sensitive: bool,
+
+    /// The match the user is looking at.
+    current: usize,
+
+    /// Scroll to [`Self::current`] the next time it's shown.
+    scroll_to_current: bool,
+
+    /// The index of the next match to be shown, this frame.
+    next: usize,
+
+    /// The last text passed to [`Self::highlight`] contains the current match.
+    highlighted_current: bool,
+}
+
+impl Find {
+    pub fn new(query: &str, case_sensitive: bool) -> Self {
+        Self {
+            query: query.to_string(),
+            case_sensitive,
+            scroll_to_current: true,
+            ..Self::default()
+        }
+    }
+
+    /// Whether there's a query to highlight.
+    pub fn is_searching(&self) -> bool {
+        !self.query.is_empty()
+    }
+
+    /// How many matches are in `text`.
+    pub fn count(&self, text: &str) -> usize {
+        match_ranges(text, &self.query, self.case_sensitive).len()
+    }
+
+    /// Make the `index`th match current, and scroll to it.
+    pub fn select(&mut self, index: usize) {
+        self.current = index;
+        self.scroll_to_current = true;
+    }
+
+    /// Call before showing the document each frame, since matches are numbered in the order they're shown.
+    pub fn begin(&mut self) {
+        self.next = 0;
+    }
+
+    /// `text`, with any matches highlighted.
+    /// Call [`Self::shown`] with the response of the widget you show it in.
+    pub fn highlight(&mut self, ui: &Ui, text: impl Into<RichText>) -> WidgetText {
+        let text = text.into();
+        let ranges = match_ranges(text.text(), &self.query, self.case_sensitive);
+        self.highlighted_current = false;
+        if ranges.is_empty() {
+            return text.into();
+        }
+
+        let mut job = LayoutJob::default();
+        text.append_to(&mut job, ui.style(), FontSelection::Default, Align::Center);
+        let Some(section) = job.sections.first().cloned() else {
+            return job.into();
+        };
+        let (found, current) = highlight_colors(ui);
+        let mut sections = Vec::new();
+        let mut pos = 0;
+        for range in ranges {
+            if pos < range.start {
+                sections.push(LayoutSection { byte_range: pos..range.start, ..section.clone() });
+            }
+            let mut format = section.format.clone();
+            format.background = if self.next == self.current { current } else { found };
+            self.highlighted_current |= self.next == self.current;
+            self.next += 1;
+            pos = range.end;
+            sections.push(LayoutSection { byte_range: range, leading_space: 0.0, format });
+        }
+        if pos < job.text.len() {
+            sections.push(LayoutSection { byte_range: pos..job.text.len(), leading_space: 0.0, ..section });
+        }
+        job.sections = sections;
+        job.into()
+    }
+
+    /// Scroll to the text last passed to [`Self::highlight`] if it has the current match, and we haven't yet.
+    pub fn shown(&mut self, response: &Response) {
+        if self.highlighted_current && self.scroll_to_current {
+            response.scroll_to_me(Some(Align::Center));
+            self.scroll_to_current = false;
+        }
+        self.highlighted_current = false;
+    }
+
+    /// A label, with matches highlighted.
+    pub fn label(&mut self, ui: &mut Ui, text: impl Into<RichText>) -> Response {
+        let text = self.highlight(ui, text);
+        let response = ui.label(text);
+        self.shown(&response);
+        response
+    }
+}
+
+fn highlight_colors(ui: &Ui) -> (Color32, Color32) {
+    let selection = ui.visuals().selection.bg_fill;
+    (selection.gamma_multiply(0.5), ui.visuals().warn_fg_color.gamma_multiply(0.6))
+}
+
+/// The byte ranges of non-overlapping matches of `query` in `text`.
+pub fn match_ranges(text: &str, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    if case_sensitive {
+        return text.match_indices(query).map(|(start, it)| start..start + it.len()).collect();
+    }
+
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let mut out = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        match match_len(&text[start..], &query) {
+            Some(len) => {
+                out.push(start..start + len);
+                start += len;
+            },
+            None => {
+                start += text[start..].chars().next().map_or(1, char::len_utf8);
+            },
+        }
+    }
+    out
+}
+
+/// If `text` starts with (lowercased) `query`, how many bytes of `text` it matched.
+fn match_len(text: &str, query: &[char]) -> Option<usize> {
+    let mut matched = 0;
+    for (index, c) in text.char_indices() {
+        for lower in c.to_lowercase() {
+            if query.get(matched) != Some(&lower) {
+                return None;
+            }
+            matched += 1;
+        }
+        if matched == query.len() {
+            return Some(index + c.len_utf8());
+        }
+    }
+    None
+}
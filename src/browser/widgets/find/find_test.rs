@@ -0,0 +1,16 @@
+#![cfg(test)]
+
+use crate::browser::widgets::find::match_ranges;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn matches() {
+    assert_eq!(match_ranges("Gemini gemini GEMINI", "gemini", true), vec![7..13]);
+    assert_eq!(match_ranges("Gemini gemini GEMINI", "gemini", false), vec![0..6, 7..13, 14..20]);
+    assert_eq!(match_ranges("aaaa", "aa", false), vec![0..2, 2..4]);
+    assert!(match_ranges("anything", "", false).is_empty());
+
+    // Byte ranges, even when lowercasing changes lengths:
+    assert_eq!(match_ranges("Ünïcode ÜNÏ", "ünï", false), vec![0..5, 10..15]);
+    assert_eq!(match_ranges("İx", "x", false), vec![2..3]);
+}
@@ -0,0 +1,22 @@
+//! Remembers where links were shown, for following them from the keyboard. See [`DocumentResponse::links`](super::DocumentResponse::links).
+
+use eframe::egui::{Rect, Response};
+
+#[derive(Debug, Default)]
+pub struct ShownLinks {
+    links: Vec<(Rect, String)>,
+}
+
+impl ShownLinks {
+    /// Record `link` if any of it is on screen.
+    pub fn add(&mut self, response: &Response, link: &str) {
+        if response.interact_rect.is_positive() {
+            self.links.push((response.interact_rect, link.to_string()));
+        }
+    }
+
+    /// The links shown since the last call, in the order they were rendered.
+    pub fn take(&mut self) -> Vec<(Rect, String)> {
+        std::mem::take(&mut self.links)
+    }
+}
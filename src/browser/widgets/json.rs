@@ -0,0 +1,165 @@
+//! Renders JSON as a collapsible tree, or as pretty-printed text.
+
+use eframe::egui::{collapsing_header::CollapsingState, Color32, Id, Label, RichText, Ui, Vec2};
+use serde_json::Value;
+
+use crate::{browser::widgets::{plaintext::PlaintextWidget, DocWidget, DocumentResponse}, gemtext_widget::Style};
+
+mod json_test;
+
+/// Bigger documents are shown as plain text, since building and laying out the tree gets slow.
+pub const MAX_TREE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Objects and arrays nested deeper than this start out collapsed.
+const OPEN_DEPTH: usize = 2;
+
+/// Only this many children of an object or array are shown, so that huge arrays stay responsive.
+const MAX_CHILDREN: usize = 1000;
+
+#[derive(Debug)]
+pub struct JsonWidget {
+    value: Value,
+
+    /// The pretty-printed JSON, for when the tree is turned off.
+    text: PlaintextWidget,
+    show_text: bool,
+
+    /// Set by "Expand all"/"Collapse all", and overrides [`OPEN_DEPTH`].
+    open_all: Option<bool>,
+
+    /// Bumped by each "Expand all"/"Collapse all", so that the tree forgets what was toggled before.
+    generation: u32,
+}
+
+impl JsonWidget {
+    pub fn parse(text: &str) -> serde_json::Result<Self> {
+        let value: Value = serde_json::from_str(text)?;
+        let pretty = serde_json::to_string_pretty(&value)?;
+        Ok(Self {
+            value,
+            text: PlaintextWidget::new(&pretty),
+            show_text: false,
+            open_all: None,
+            generation: 0,
+        })
+    }
+
+    fn toolbar(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing = Vec2::new(8.0, 0.0);
+            ui.checkbox(&mut self.show_text, "Show as text");
+            ui.add_enabled_ui(!self.show_text, |ui| {
+                for (open, label) in [(true, "⊞ Expand all"), (false, "⊟ Collapse all")] {
+                    if ui.small_button(label).clicked() {
+                        self.open_all = Some(open);
+                        self.generation += 1;
+                    }
+                }
+            });
+        });
+        ui.separator();
+    }
+
+    fn value_ui(&self, ui: &mut Ui, id: Id, key: Option<&str>, value: &Value, depth: usize) {
+        let count = match value {
+            Value::Object(map) => map.len(),
+            Value::Array(items) => items.len(),
+            _ => {
+                ui.horizontal_wrapped(|ui| {
+                    key_label(ui, key);
+                    let text = RichText::new(scalar_text(value)).text_style(Style::mono()).color(value_color(ui, value));
+                    ui.add(Label::new(text).wrap());
+                });
+                return;
+            },
+        };
+
+        let open = self.open_all.unwrap_or(depth < OPEN_DEPTH);
+        CollapsingState::load_with_default_open(ui.ctx(), id, open)
+            .show_header(ui, |ui| {
+                key_label(ui, key);
+                ui.add(Label::new(RichText::new(summary(value)).text_style(Style::mono()).weak()).selectable(false));
+            })
+            .body(|ui| {
+                let children: Box<dyn Iterator<Item = (String, &Value)>> = match value {
+                    Value::Object(map) => Box::new(map.iter().map(|(key, value)| (key_text(key), value))),
+                    Value::Array(items) => Box::new(items.iter().enumerate().map(|(index, value)| (index.to_string(), value))),
+                    _ => Box::new(std::iter::empty()),
+                };
+                for (child_key, child) in children.take(MAX_CHILDREN) {
+                    self.value_ui(ui, id.with(&child_key), Some(&child_key), child, depth + 1);
+                }
+                if count > MAX_CHILDREN {
+                    ui.weak(format!("… and {} more", count - MAX_CHILDREN));
+                }
+            });
+    }
+}
+
+impl DocWidget for JsonWidget {
+    fn ui(&mut self, ui: &mut Ui) -> DocumentResponse {
+        self.toolbar(ui);
+        if self.show_text {
+            self.text.ui(ui);
+        } else {
+            let id = Id::new(("json", self.generation));
+            self.value_ui(ui, id, None, &self.value, 0);
+        }
+
+        DocumentResponse {
+            link_clicked: None,
+            load_image: None,
+            block_clicked: None,
+            link_hovered: None,
+            links: Vec::new(),
+            link_action: None,
+        }
+    }
+
+    fn to_html(&self) -> Option<String> {
+        self.text.to_html()
+    }
+
+    #[cfg(feature = "tts")]
+    fn readable_text(&self) -> Vec<String> {
+        self.text.readable_text()
+    }
+}
+
+/// What a collapsed object or array shows in place of its children. (ex: `{ 2 keys }`)
+pub fn summary(value: &Value) -> String {
+    let plural = |count: usize, noun: &str| if count == 1 { format!("1 {noun}") } else { format!("{count} {noun}s") };
+    match value {
+        Value::Object(map) => format!("{{ {} }}", plural(map.len(), "key")),
+        Value::Array(items) => format!("[ {} ]", plural(items.len(), "item")),
+        _ => scalar_text(value),
+    }
+}
+
+/// Strings are quoted and escaped, as they'd appear in JSON.
+fn scalar_text(value: &Value) -> String {
+    value.to_string()
+}
+
+fn key_text(key: &str) -> String {
+    Value::from(key).to_string()
+}
+
+fn key_label(ui: &mut Ui, key: Option<&str>) {
+    if let Some(key) = key {
+        ui.add(Label::new(RichText::new(format!("{key}:")).text_style(Style::mono()).strong()).selectable(false));
+    }
+}
+
+fn value_color(ui: &Ui, value: &Value) -> Color32 {
+    let dark = ui.visuals().dark_mode;
+    match value {
+        Value::String(_) if dark => Color32::from_rgb(152, 195, 121),
+        Value::String(_) => Color32::from_rgb(60, 125, 30),
+        Value::Number(_) if dark => Color32::from_rgb(209, 154, 102),
+        Value::Number(_) => Color32::from_rgb(165, 85, 0),
+        Value::Bool(_) | Value::Null if dark => Color32::from_rgb(97, 175, 239),
+        Value::Bool(_) | Value::Null => Color32::from_rgb(0, 85, 180),
+        Value::Object(_) | Value::Array(_) => ui.visuals().text_color(),
+    }
+}
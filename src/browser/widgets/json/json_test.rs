@@ -0,0 +1,14 @@
+#![cfg(test)]
+
+use crate::browser::widgets::json::summary;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+#[test]
+fn summaries() {
+    assert_eq!(summary(&json!({"a": 1, "b": [2]})), "{ 2 keys }");
+    assert_eq!(summary(&json!({"a": 1})), "{ 1 key }");
+    assert_eq!(summary(&json!([])), "[ 0 items ]");
+    assert_eq!(summary(&json!("quote\"d")), r#""quote\"d""#);
+    assert_eq!(summary(&json!(null)), "null");
+}
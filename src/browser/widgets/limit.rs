@@ -0,0 +1,50 @@
+//! Renders only the start of very long documents, with a button to show more.
+//! Laying out tens of thousands of blocks every frame makes scrolling sluggish.
+
+use eframe::egui::{RichText, Ui};
+
+mod limit_test;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockLimit {
+    /// How many blocks to show. None shows them all.
+    shown: Option<usize>,
+
+    /// How many more blocks "Show more" shows.
+    step: usize,
+}
+
+impl BlockLimit {
+    /// Show `step` blocks at a time. 0 shows everything.
+    pub fn new(step: usize) -> Self {
+        Self { shown: (step > 0).then_some(step), step }
+    }
+
+    /// How many of `total` blocks to render.
+    pub fn shown(&self, total: usize) -> usize {
+        self.shown.map_or(total, |it| it.min(total))
+    }
+
+    pub fn show_all(&mut self) {
+        self.shown = None;
+    }
+
+    /// Offers to show more, if some of the `total` blocks are hidden.
+    pub fn show_more_ui(&mut self, ui: &mut Ui, total: usize) {
+        let shown = self.shown(total);
+        if shown >= total {
+            return;
+        }
+        ui.separator();
+        ui.horizontal_wrapped(|ui| {
+            ui.label(RichText::new(format!("Showing {shown} of {total} blocks. ")).weak());
+            if ui.button(format!("Show {} more", self.step.min(total - shown))).clicked() {
+                self.shown = Some(shown + self.step);
+            }
+            ui.label(" ");
+            if ui.button("Show all").on_hover_text("May be slow for very long pages").clicked() {
+                self.show_all();
+            }
+        });
+    }
+}
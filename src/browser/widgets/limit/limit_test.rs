@@ -0,0 +1,17 @@
+#![cfg(test)]
+
+use crate::browser::widgets::limit::BlockLimit;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn block_limits() {
+    let mut limit = BlockLimit::new(100);
+    assert_eq!(limit.shown(40), 40);
+    assert_eq!(limit.shown(1000), 100);
+    limit.show_all();
+    assert_eq!(limit.shown(1000), 1000);
+
+    // 0 is no limit:
+    assert_eq!(BlockLimit::new(0).shown(1000), 1000);
+    assert_eq!(BlockLimit::default().shown(1000), 1000);
+}
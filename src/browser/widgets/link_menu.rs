@@ -0,0 +1,47 @@
+//! The menu shown when right-clicking a link. See [`DocumentResponse::link_action`](super::DocumentResponse::link_action).
+
+use eframe::egui::Response;
+
+/// Something to do with a link, picked from its context menu.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkAction {
+    CopyAddress,
+    OpenInBrowser,
+    OpenInNewTab,
+}
+
+impl LinkAction {
+    pub const ALL: [Self; 3] = [Self::CopyAddress, Self::OpenInBrowser, Self::OpenInNewTab];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::CopyAddress => "Copy link address",
+            Self::OpenInBrowser => "Open in web browser",
+            Self::OpenInNewTab => "Open in new tab",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LinkContextMenu {
+    chosen: Option<(LinkAction, String)>,
+}
+
+impl LinkContextMenu {
+    /// Show a context menu for `link` when `response` is right-clicked.
+    pub fn ui(&mut self, response: &Response, link: &str) {
+        response.context_menu(|ui| {
+            for action in LinkAction::ALL {
+                if ui.button(action.label()).clicked() {
+                    self.chosen = Some((action, link.to_string()));
+                    ui.close();
+                }
+            }
+        });
+    }
+
+    /// The action chosen since the last call, and its (possibly relative) link.
+    pub fn take(&mut self) -> Option<(LinkAction, String)> {
+        self.chosen.take()
+    }
+}
@@ -1,10 +1,10 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use eframe::{egui::{self, Align, Color32, Frame, Layout, Link, RichText, TextStyle, Ui, UiBuilder, Vec2}, epaint::MarginF32};
+use eframe::{egui::{self, load::Bytes, Align, CollapsingHeader, Color32, Frame, Layout, Link, Rect, RichText, Sense, TextStyle, Ui, UiBuilder, Vec2}, epaint::MarginF32};
 use log::debug;
 use pulldown_cmark::{Tag, TagEnd};
 
-use crate::{browser::{network::SCow, parsers::html::to_md, widgets::{markdown::tree::{Block, Image, Inline}, DocWidget}}, gemtext_widget::Style};
+use crate::{browser::{network::SCow, parsers::html::{to_md, Diagnostics}, widgets::{code, find::Find, hints::ShownLinks, limit::BlockLimit, link_menu::LinkContextMenu, markdown::tree::{Block, Image, Inline}, preview::LinkPreviews, image_error, DocWidget, Heading, ImageData}}, gemtext_widget::Style, util::strip_bom};
 
 use super::DocumentResponse;
 mod tree;
@@ -17,16 +17,73 @@ pub struct MarkdownWidget {
     justify: bool,
 
     parsed_blocks: Arc<Vec<tree::Block>>,
+    /// Rendered after [`Self::parsed_blocks`], numbered in order.
+    footnotes: Arc<Vec<tree::Footnote>>,
     link_clicked: Option<String>,
 
-    text_bold: bool,
-    text_italics: bool,
+    /// A footnote (or reference to one) that the user clicked to jump to.
+    scroll_to: Option<FootnoteTarget>,
+
+    /// How many lists we're currently rendering inside of.
+    list_depth: usize,
+
+    /// Styles of the [`Inline::Styled`]s we're currently rendering inside of. Innermost last.
+    styles: Vec<tree::Style>,
+
+    /// Problems converting the source HTML, if any. Shown as a banner until dismissed.
+    diagnostics: Option<Diagnostics>,
+
+    /// Center the title (see [`title_index`]) like [`GemtextWidget`](crate::gemtext_widget::GemtextWidget) does.
+    center_title: bool,
+
+    /// Show images in the document, instead of links to them.
+    inline_images: bool,
+    /// Inline images we've requested, keyed by `src`.
+    images: HashMap<String, InlineImage>,
+    load_image: Option<String>,
+
+    /// Highlights matches for [`DocWidget::find`].
+    find: Find,
+
+    previews: LinkPreviews,
+    shown_links: ShownLinks,
+    context_menu: LinkContextMenu,
+
+    limit: BlockLimit,
+
+    /// The index of a heading in [`Self::parsed_blocks`] to scroll to. See [`DocWidget::scroll_to_heading`].
+    scroll_to_block: Option<usize>,
+
+    /// From the document's frontmatter, if it had one.
+    title: Option<String>,
+
+    /// The markdown we parsed, for [`DocWidget::to_html`]. (For HTML pages, what we converted them to.)
+    source: String,
+}
+
+/// Either end of a footnote link. Both hold the footnote's index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FootnoteTarget {
+    /// The first reference to the footnote.
+    Reference(usize),
+    Definition(usize),
+}
+
+#[derive(Debug)]
+enum InlineImage {
+    Loading,
+    Loaded(ImageData),
+    Failed(String),
 }
 
 impl MarkdownWidget {
     pub fn for_html(html: &str) -> Self {
-        let md = to_md(html);
-        Self::for_md(&md)
+        let (md, diagnostics) = to_md(html);
+        let mut widget = Self::for_md(&md);
+        if !diagnostics.is_empty() {
+            widget.diagnostics = Some(diagnostics);
+        }
+        widget
     }
 
     pub fn for_md(md: &str) -> Self {
@@ -35,29 +92,152 @@ impl MarkdownWidget {
         Self {
             justify: false,
             parsed_blocks: Arc::new(parsed.blocks),
+            footnotes: Arc::new(parsed.footnotes),
             link_clicked: None,
-            text_bold: false,
-            text_italics: false,
+            scroll_to: None,
+            list_depth: 0,
+            styles: Vec::new(),
+            diagnostics: None,
+            center_title: false,
+            inline_images: false,
+            images: HashMap::new(),
+            load_image: None,
+            find: Find::default(),
+            previews: LinkPreviews::default(),
+            shown_links: ShownLinks::default(),
+            context_menu: LinkContextMenu::default(),
+            limit: BlockLimit::default(),
+            scroll_to_block: None,
+            title: parsed.title,
+            source: md.to_string(),
         }
     }
+
+    pub fn center_title(mut self, center: bool) -> Self {
+        self.center_title = center;
+        self
+    }
+
+    pub fn inline_images(mut self, inline: bool) -> Self {
+        self.inline_images = inline;
+        self
+    }
+}
+
+/// The top-level block that's the document's title: The first H1, as long as no other heading comes before it.
+fn title_index(blocks: &[Block]) -> Option<usize> {
+    let (index, level) = blocks.iter().enumerate().find_map(|(index, block)| match block {
+        Block::Heading { level, .. } => Some((index, *level)),
+        _ => None,
+    })?;
+    (level == 1).then_some(index)
 }
 
 impl MarkdownWidget {
     fn render(&mut self, ui: &mut Ui) {
+        self.find.begin();
+        self.diagnostics_banner(ui);
         let blocks = Arc::clone(&self.parsed_blocks);
-        self.render_blocks(ui, &blocks);
+        let title = if self.center_title { title_index(&blocks) } else { None };
+        let shown = self.limit.shown(blocks.len());
+        self.render_blocks(ui, &blocks[..shown], title);
+        if shown < blocks.len() {
+            self.limit.show_more_ui(ui, blocks.len());
+        } else {
+            self.render_footnotes(ui);
+        }
         ui.label("");
 
         // return click events
     }
 
-    fn render_blocks(&mut self, ui: &mut Ui, blocks: &[Block]) {
+    /// Lets the user know why a converted HTML page might look incomplete.
+    fn diagnostics_banner(&mut self, ui: &mut Ui) {
+        let Some(diagnostics) = &self.diagnostics else {
+            return;
+        };
+        let mut dismissed = false;
+        Frame::new()
+            .fill(ui.visuals().faint_bg_color)
+            .inner_margin(4.0)
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.colored_label(ui.visuals().warn_fg_color, "⚠ ");
+                    ui.label(RichText::new("Some HTML couldn't be converted. ").weak());
+                    ui.label(RichText::new(diagnostics.summary()).weak());
+                    ui.label(" ");
+                    dismissed = ui.small_button("✖").on_hover_text("Dismiss").clicked();
+                });
+                ui.collapsing("Details", |ui| {
+                    let all = diagnostics.skipped.iter().map(|it| ("skipped", it))
+                        .chain(diagnostics.unhandled.iter().map(|it| ("unsupported", it)));
+                    for (kind, location) in all {
+                        ui.monospace(format!("<{}> ({kind}) in {}", location.tag, location.path));
+                    }
+                });
+            });
+        if dismissed {
+            self.diagnostics = None;
+        }
+        ui.label("");
+    }
+
+    fn render_footnotes(&mut self, ui: &mut Ui) {
+        let footnotes = Arc::clone(&self.footnotes);
+        if footnotes.is_empty() {
+            return;
+        }
+        ui.label("");
+        ui.separator();
+        for (index, footnote) in footnotes.iter().enumerate() {
+            let response = ui.horizontal_top(|ui| {
+                let back = ui.link(format!("{}. ", index + 1)).on_hover_text("Back to reference");
+                if back.clicked() {
+                    self.jump_to(ui, FootnoteTarget::Reference(index));
+                }
+                ui.vertical(|ui| {
+                    self.render_blocks(ui, &footnote.blocks, None);
+                });
+            }).response;
+            self.scroll_here(&response, FootnoteTarget::Definition(index));
+        }
+    }
+
+    fn jump_to(&mut self, ui: &Ui, target: FootnoteTarget) {
+        self.scroll_to = Some(target);
+        ui.ctx().request_repaint();
+    }
+
+    /// Scrolls to `response` if it's what the user asked to jump to.
+    fn scroll_here(&mut self, response: &egui::Response, target: FootnoteTarget) {
+        if self.scroll_to == Some(target) {
+            self.scroll_to = None;
+            response.scroll_to_me(Some(Align::TOP));
+        }
+    }
+
+    /// `title` is the index of a heading in `blocks` to render as a title.
+    fn render_blocks(&mut self, ui: &mut Ui, blocks: &[Block], title: Option<usize>) {
         let last_block_num = blocks.len();
         let mut block_num = 0;
         for block in blocks {
             block_num += 1;
             let last_block = block_num == last_block_num;
-            self.render_block(ui, block);
+            let top = ui.cursor().top();
+            match block {
+                Block::Heading { text, .. } if title == Some(block_num - 1) => {
+                    let rt = RichText::new(text).text_style(Style::title()).strong();
+                    ui.vertical_centered(|ui| {
+                        self.find.label(ui, rt);
+                    });
+                },
+                _ => self.render_block(ui, block),
+            }
+            if let Some(target) = self.scroll_to_block && std::ptr::eq(block, &self.parsed_blocks[target]) {
+                self.scroll_to_block = None;
+                let rect = Rect::from_x_y_ranges(ui.max_rect().x_range(), top..=ui.cursor().top());
+                ui.scroll_to_rect(rect, Some(Align::TOP));
+            }
             
             let is_pseudo = matches!(block, Block::PseudoP { .. });
             if !last_block && !is_pseudo { self.line_spacing(ui); }
@@ -69,11 +249,14 @@ impl MarkdownWidget {
             Block::Heading { level, text } => {
                 let style = Style::heading(*level);
                 let rt = RichText::new(text).text_style(style).strong();
-                ui.label(rt);
+                self.find.label(ui, rt);
             },
-            Block::CodeBlock { text, .. } => {
-                let rt = RichText::new(text).text_style(Style::mono());
-                ui.label(rt);
+            Block::CodeBlock { fenced, text } => {
+                let wrap = code::toolbar(ui, fenced.as_deref().unwrap_or_default(), text);
+                code::body(ui, text, wrap, |ui| {
+                    let rt = RichText::new(text).text_style(Style::mono());
+                    self.find.label(ui, rt);
+                });
             },
             Block::BlockQuote { blocks } => {
                 self.render_bq(ui, blocks);
@@ -84,7 +267,7 @@ impl MarkdownWidget {
                 });
             },
             Block::List { start_num, blocks } => {
-                self.render_list(ui, start_num.clone(), blocks);
+                self.render_nested_list(ui, *start_num, blocks);
             },
             Block::ListItem { .. } => {
                 // ListItems should always appear directly in a List, right?
@@ -92,19 +275,53 @@ impl MarkdownWidget {
             },
             Block::Hr => {
                 ui.separator();
-            }
+            },
+            Block::Math(math) => {
+                ui.vertical_centered(|ui| {
+                    ui.label(math_text(math));
+                });
+            },
+            Block::Collapsible { summary, open, blocks } => {
+                self.render_collapsible(ui, summary, *open, blocks);
+            },
         }
     }
+
+    /// Collapsed sections are opened while searching, if they contain a match.
+    fn render_collapsible(&mut self, ui: &mut Ui, summary: &str, open: bool, blocks: &[Block]) {
+        let show_match = self.find.is_searching() && find_in_blocks(&self.find, blocks) > 0;
+        CollapsingHeader::new(RichText::new(summary).strong())
+            // Summaries needn't be unique, but each block has its own place in memory:
+            .id_salt(blocks.as_ptr())
+            .default_open(open)
+            .open(show_match.then_some(true))
+            .show(ui, |ui| {
+                self.render_blocks(ui, blocks, None);
+            });
+    }
     
+    /// Renders a list, indented one level further than the list it's inside of (if any).
+    fn render_nested_list(&mut self, ui: &mut Ui, start_num: Option<u64>, blocks: &[Block]) {
+        self.list_depth += 1;
+        let depth = self.list_depth;
+        if depth == 1 {
+            self.render_list(ui, start_num, blocks);
+        } else {
+            // Unique per level, so that sibling lists at different depths don't share egui state:
+            ui.indent(("list", depth), |ui| {
+                self.render_list(ui, start_num, blocks);
+            });
+        }
+        self.list_depth -= 1;
+    }
+
     fn render_list(&mut self, ui: &mut Ui, start_num: Option<u64>, blocks: &[Block]) {
+        // Each list keeps its own counter, so nested lists don't affect their parent's numbering:
         let mut start_num = start_num;
         for block in blocks {
             match block {
                 Block::List { start_num, blocks } => {
-                    // TODO: Adjust indentation.
-                    ui.indent("list", |ui| {
-                        self.render_list(ui, start_num.clone(), blocks);
-                    });
+                    self.render_nested_list(ui, *start_num, blocks);
                 },
                 Block::ListItem { blocks } => {
                     let bullet = if let Some(num) = &mut start_num {
@@ -112,12 +329,12 @@ impl MarkdownWidget {
                         *num += 1;
                         out
                     } else {
-                        " • ".to_string()
+                        bullet(self.list_depth).to_string()
                     };
                     ui.horizontal_top(|ui| {
                         ui.label(bullet);
                         ui.vertical(|ui| {
-                            self.render_blocks(ui, blocks);
+                            self.render_blocks(ui, blocks, None);
                         })
                     });
                 },
@@ -138,77 +355,56 @@ impl MarkdownWidget {
         for part in parts {
             match part {
                 Inline::Text(text) => { 
-                    let mut text = RichText::new(text);
-                    if self.text_italics {
-                        text = text.italics();
-                    }
-                    if self.text_bold {
-                        text = text.strong();
-                    }
-        
-                    ui.label(text); 
+                    let text = self.styled(RichText::new(text));
+                    self.find.label(ui, text);
                 },
                 Inline::Code(text) => {
-                    ui.monospace(text);
-                }
+                    let text = self.styled(RichText::new(text).monospace());
+                    self.find.label(ui, text);
+                },
+                Inline::Math(math) => {
+                    ui.label(math_text(math));
+                },
+                Inline::FootnoteRef(label) => {
+                    self.footnote_ref(ui, label);
+                },
                 Inline::Link(tree::Link{ text, href }) => {
-                    let link = egui::Link::new(text);
+                    let link = egui::Link::new(self.find.highlight(ui, text));
                     let response = ui.add(link);
+                    self.find.shown(&response);
                     if response.clicked() {
                         self.link_clicked = Some(href.clone());
                     }
-                    response.on_hover_ui(|ui| {
-                        ui.monospace(href);
-                    });
+                    self.shown_links.add(&response, href);
+                    self.context_menu.ui(&response, href);
+                    self.previews.hover_ui(response, href);
                 },
                 Inline::Styled { style, parts } => {
-                    use tree::Style::*;
-                    match style {
-                        Bold => {
-                            self.text_bold = true;
-                            self.render_inline(ui, &parts);
-                            self.text_bold = false;
-                        },
-                        Italics => {
-                            self.text_italics = true;
-                            self.render_inline(ui, &parts);
-                            self.text_italics = false;
-                        },
-                    };
+                    self.styles.push(*style);
+                    self.render_inline(ui, parts);
+                    self.styles.pop();
                 },
-                Inline::Image(Image { src, title, alt }) => {
-                    // We render this like a link, but surrounded w/ Markdown image syntax.
-                    // In the future we can add options for different ways to display/distinguish image links.
-                    let response = ui.link(format!("![{alt}]"));
-                    if response.clicked() {
-                        self.link_clicked = Some(src.clone())
+                Inline::Image(image) => {
+                    if self.inline_image(ui, image, &image.src) {
+                        continue;
                     }
-                    response.on_hover_ui(|ui| {
-                        ui.monospace(src);
-                        if !title.is_empty() {
-                            ui.label(title);
-                        }
-                    });
+                    // We render this like a link, but surrounded w/ Markdown image syntax.
+                    self.image_link(ui, image);
                 },
                 Inline::LinkedImage { link, image } => {
-                    let Image{alt, src, title} = image;
-                    // Same as above, but we append an [href] link too:
-                    let response = ui.link(format!("![{alt}]"));
-                    if response.clicked() {
-                        self.link_clicked = Some(src.clone());
+                    if self.inline_image(ui, image, &link.href) {
+                        continue;
                     }
-                    response.on_hover_ui(|ui| {
-                        ui.monospace(src);
-                        if !title.is_empty() {
-                            ui.label(title);
-                        }
-                    });
+                    // Same as above, but we append an [href] link too:
+                    self.image_link(ui, image);
 
                     if link.href != image.src {
                         let r2 = ui.link("[href]");
                         if r2.clicked() {
                             self.link_clicked = Some(link.href.clone());
                         }
+                        self.shown_links.add(&r2, &link.href);
+                        self.context_menu.ui(&r2, &link.href);
                         r2.on_hover_ui(|ui| {
                             ui.monospace(&link.href);
                         });
@@ -218,6 +414,105 @@ impl MarkdownWidget {
         }
     }
 
+    /// Applies the styles of all the [`Inline::Styled`]s we're inside of, so that they combine.
+    fn styled(&self, mut text: RichText) -> RichText {
+        for style in &self.styles {
+            text = match style {
+                tree::Style::Bold => text.strong(),
+                tree::Style::Italics => text.italics(),
+                tree::Style::Strikethrough => text.strikethrough(),
+            };
+        }
+        text
+    }
+
+    /// A superscript number that jumps to the footnote.
+    fn footnote_ref(&mut self, ui: &mut Ui, label: &str) {
+        let Some(index) = self.footnotes.iter().position(|it| it.label == label) else {
+            // No such footnote. Show what the author wrote:
+            self.find.label(ui, RichText::new(format!("[^{label}]")));
+            return;
+        };
+        let response = ui.link(RichText::new(format!("[{}]", index + 1)).small_raised());
+        if response.clicked() {
+            self.jump_to(ui, FootnoteTarget::Definition(index));
+        }
+        self.scroll_here(&response, FootnoteTarget::Reference(index));
+    }
+
+    /// An `![alt]` link to the image.
+    fn image_link(&mut self, ui: &mut Ui, image: &Image) {
+        let Image { src, title, alt } = image;
+        let response = ui.link(format!("![{alt}]"));
+        if response.clicked() {
+            self.link_clicked = Some(src.clone())
+        }
+        self.shown_links.add(&response, src);
+        self.context_menu.ui(&response, src);
+        response.on_hover_ui(|ui| {
+            ui.monospace(src);
+            if !title.is_empty() {
+                ui.label(title);
+            }
+            if let Some(InlineImage::Failed(err)) = self.images.get(src) {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Couldn't load image: {err}"));
+            }
+        });
+    }
+
+    /// Show `image` itself, if we're showing images inline. Clicking it opens `href`.
+    /// Returns false if the caller should show a link instead.
+    fn inline_image(&mut self, ui: &mut Ui, image: &Image, href: &str) -> bool {
+        if !self.inline_images {
+            return false;
+        }
+        let data = match self.images.get(&image.src) {
+            Some(InlineImage::Loaded(data)) => data,
+            Some(InlineImage::Failed(_)) => return false,
+            Some(InlineImage::Loading) => {
+                ui.label(RichText::new(format!("🖼 {}", image.alt)).weak());
+                return true;
+            },
+            None => {
+                // We can only request one image per frame:
+                if self.load_image.is_none() {
+                    self.load_image = Some(image.src.clone());
+                    self.images.insert(image.src.clone(), InlineImage::Loading);
+                    ui.ctx().request_repaint();
+                }
+                ui.label(RichText::new(format!("🖼 {}", image.alt)).weak());
+                return true;
+            },
+        };
+
+        // Use the whole width of the paragraph, not just what's left on this row:
+        let widget = egui::Image::from_bytes(data.uri.clone(), Bytes::Shared(data.bytes.clone()))
+            .fit_to_original_size(1.0)
+            .max_width(ui.max_rect().width())
+            .sense(Sense::click());
+        if let Some(err) = image_error(ui, &widget) {
+            // Show the alt text link instead:
+            self.images.insert(image.src.clone(), InlineImage::Failed(err));
+            return false;
+        }
+        let response = ui.add(widget);
+        if response.clicked() {
+            self.link_clicked = Some(href.to_string());
+        }
+        self.shown_links.add(&response, href);
+        self.context_menu.ui(&response, href);
+        response.on_hover_ui(|ui| {
+            if !image.alt.is_empty() {
+                ui.label(&image.alt);
+            }
+            if !image.title.is_empty() {
+                ui.label(&image.title);
+            }
+            ui.monospace(href);
+        });
+        true
+    }
+
     fn render_bq(&mut self, ui: &mut Ui, blocks: &[Block]) {
         let builder = UiBuilder::new();
         let row_height = ui.text_style_height(&TextStyle::Body);
@@ -226,7 +521,7 @@ impl MarkdownWidget {
             let frame = Frame::new()
                 .outer_margin(left_margin);
             frame.show(ui, |ui| {
-                self.render_blocks(ui, blocks);
+                self.render_blocks(ui, blocks, None);
             });
 
         });
@@ -238,6 +533,17 @@ impl MarkdownWidget {
     }
 }
 
+/// Unordered list bullets vary with depth, so that nesting is easy to follow.
+fn bullet(depth: usize) -> &'static str {
+    const BULLETS: &[&str] = &[" • ", " ◦ ", " ▪ "];
+    BULLETS[depth.saturating_sub(1) % BULLETS.len()]
+}
+
+/// We don't render LaTeX, but at least make it distinct from the surrounding text.
+fn math_text(math: &str) -> RichText {
+    RichText::new(math.trim()).text_style(Style::mono()).italics()
+}
+
 impl DocWidget for MarkdownWidget {
     fn ui(&mut self, ui: &mut Ui) -> DocumentResponse {
         // Unlike Gemtext, markdown can have inline styling and links,
@@ -256,7 +562,151 @@ impl DocWidget for MarkdownWidget {
         });
         DocumentResponse {
             link_clicked: self.link_clicked.take(),
+            load_image: self.load_image.take(),
+            block_clicked: None,
+            link_hovered: self.previews.take_hovered(),
+            links: self.shown_links.take(),
+            link_action: self.context_menu.take(),
+        }
+    }
+
+    fn link_preview(&mut self, link: &str, title: Option<String>) {
+        self.previews.set_title(link, title);
+    }
+
+    /// Only top-level headings. Those in lists, quotes, etc. usually aren't section headings.
+    fn outline(&self) -> Vec<Heading> {
+        self.parsed_blocks.iter()
+            .filter_map(|block| match block {
+                Block::Heading { level, text } => Some(Heading { level: *level, text: text.trim().to_string() }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn scroll_to_heading(&mut self, index: usize) {
+        let block = self.parsed_blocks.iter()
+            .enumerate()
+            .filter(|(_, block)| matches!(block, Block::Heading { .. }))
+            .nth(index)
+            .map(|(block, _)| block);
+        if let Some(block) = block && block >= self.limit.shown(self.parsed_blocks.len()) {
+            self.limit.show_all();
+        }
+        self.scroll_to_block = block;
+    }
+
+    /// The frontmatter's title, or else the first heading.
+    fn title(&self) -> Option<String> {
+        if let Some(title) = &self.title {
+            return Some(title.clone());
+        }
+        self.parsed_blocks.iter().find_map(|block| match block {
+            Block::Heading { text, .. } => Some(text.trim().to_string()),
+            _ => None,
+        })
+    }
+
+    fn to_html(&self) -> Option<String> {
+        let parser = pulldown_cmark::Parser::new_ext(strip_bom(&self.source), tree::Parser::options());
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, parser);
+        Some(html)
+    }
+
+    fn image_loaded(&mut self, link: &str, image: Result<ImageData, String>) {
+        let image = match image {
+            Ok(image) => InlineImage::Loaded(image),
+            Err(err) => InlineImage::Failed(err),
+        };
+        self.images.insert(link.to_string(), image);
+    }
+
+    fn set_inline_images(&mut self, inline: bool) {
+        self.inline_images = inline;
+    }
+
+    fn set_block_limit(&mut self, step: usize) {
+        self.limit = BlockLimit::new(step);
+    }
+
+    fn find(&mut self, query: &str, case_sensitive: bool) -> usize {
+        self.find = Find::new(query, case_sensitive);
+        if !query.is_empty() {
+            self.limit.show_all();
+        }
+        find_in_blocks(&self.find, &self.parsed_blocks)
+            + self.footnotes.iter().map(|it| find_in_blocks(&self.find, &it.blocks)).sum::<usize>()
+    }
+
+    fn find_select(&mut self, index: usize) {
+        self.find.select(index);
+    }
+
+    #[cfg(feature = "tts")]
+    fn readable_text(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        readable_blocks(&self.parsed_blocks, &mut out);
+        out.retain(|it| !it.trim().is_empty());
+        out
+    }
+}
+
+/// Counts matches in the same text (and order) that [`MarkdownWidget::render_blocks`] highlights them.
+fn find_in_blocks(find: &Find, blocks: &[Block]) -> usize {
+    blocks.iter()
+        .map(|block| match block {
+            Block::Heading { text, .. } | Block::CodeBlock { text, .. } => find.count(text),
+            Block::P { parts } | Block::PseudoP { parts } => find_in_inlines(find, parts),
+            Block::BlockQuote { blocks } | Block::List { blocks, .. } | Block::ListItem { blocks } | Block::Collapsible { blocks, .. } => {
+                find_in_blocks(find, blocks)
+            },
+            Block::Hr | Block::Math(_) => 0,
+        })
+        .sum()
+}
+
+fn find_in_inlines(find: &Find, inlines: &[Inline]) -> usize {
+    inlines.iter()
+        .map(|inline| match inline {
+            Inline::Text(text) | Inline::Code(text) => find.count(text),
+            Inline::Link(link) => find.count(&link.text),
+            Inline::Styled { parts, .. } => find_in_inlines(find, parts),
+            Inline::Image(_) | Inline::LinkedImage { .. } | Inline::Math(_) | Inline::FootnoteRef(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(feature = "tts")]
+fn readable_blocks(blocks: &[Block], out: &mut Vec<String>) {
+    for block in blocks {
+        match block {
+            Block::Heading { text, .. } => out.push(text.clone()),
+            Block::P { parts } | Block::PseudoP { parts } => out.push(readable_inlines(parts)),
+            Block::BlockQuote { blocks } | Block::List { blocks, .. } | Block::ListItem { blocks } => {
+                readable_blocks(blocks, out)
+            },
+            Block::Collapsible { summary, blocks, .. } => {
+                out.push(summary.clone());
+                readable_blocks(blocks, out)
+            },
+            Block::CodeBlock { .. } | Block::Hr | Block::Math(_) => {},
+        }
+    }
+}
+
+#[cfg(feature = "tts")]
+fn readable_inlines(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) | Inline::Code(text) => out.push_str(text),
+            Inline::Link(link) => out.push_str(&link.text),
+            Inline::Image(image) | Inline::LinkedImage { image, .. } => out.push_str(&image.alt),
+            Inline::Styled { parts, .. } => out.push_str(&readable_inlines(parts)),
+            Inline::Math(_) | Inline::FootnoteRef(_) => {},
         }
     }
+    out
 }
 
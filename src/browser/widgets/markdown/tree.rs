@@ -1,7 +1,7 @@
 use log::debug;
-use pulldown_cmark::{CodeBlockKind, Parser as CmParser, Tag, TagEnd, TextMergeStream};
+use pulldown_cmark::{CodeBlockKind, Options, Parser as CmParser, Tag, TagEnd, TextMergeStream};
 
-use crate::browser::parsers::html::to_md;
+use crate::{browser::parsers::html::to_md, util::{collapse_blank_runs, strip_bom}};
 
 /// pulldown-commonmark gives a parser as an iterator, but no way to serialize the parsed document.
 /// Which means we would have to re-parse it with every render to screen. Booo.
@@ -9,31 +9,70 @@ use crate::browser::parsers::html::to_md;
 /// 
 #[derive(Debug)]
 pub struct Parsed {
-    // TODO: title: Option<String>
+    /// From the YAML frontmatter's `title:`, if any. Also added to the start of [`Self::blocks`] as an H1.
+    pub title: Option<String>,
 
-    pub blocks: Vec<Block>
+    pub blocks: Vec<Block>,
+
+    /// Footnote definitions, in the order they appear. Rendered after [`Self::blocks`].
+    pub footnotes: Vec<Footnote>,
+}
+
+/// A footnote definition, i.e.: `[^label]: Some text.`
+#[derive(Debug)]
+pub struct Footnote {
+    pub label: String,
+    pub blocks: Vec<Block>,
 }
 
 pub struct Parser<'a> {
-    inner: TextMergeStream<'a, CmParser<'a>>
+    inner: TextMergeStream<'a, CmParser<'a>>,
+
+    /// Footnote definitions can appear anywhere, so we collect them as we go.
+    footnotes: Vec<Footnote>,
+
+    /// The summaries of the `<details>` we're inside of, innermost last. See [`Self::parse_details`].
+    details: Vec<Option<String>>,
+
+    /// See [`Parsed::title`].
+    title: Option<String>,
 }
 
 impl <'a> Parser<'a> {
     pub fn from_html(html: &str) -> Parsed {
-        let md = to_md(html);
+        let (md, _) = to_md(html);
         Parser::from_md(&md)
     }
 
+    /// The markdown extensions we support.
+    pub fn options() -> Options {
+        Options::ENABLE_MATH | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_FOOTNOTES
+            | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+    }
+
     pub fn from_md(md: &str) -> Parsed {
         let mut parser = Parser {
-            inner: TextMergeStream::new(CmParser::new(&md))
+            inner: TextMergeStream::new(CmParser::new_ext(strip_bom(md), Self::options())),
+            footnotes: Vec::new(),
+            details: Vec::new(),
+            title: None,
         };
         parser.parse_all()
     }
 
     fn parse_all(&mut self) -> Parsed {
+        let mut blocks = self.parse_blocks_until(|_| false);
+        let title = self.title.take();
+        if let Some(title) = &title {
+            let repeated = matches!(blocks.first(), Some(Block::Heading { level: 1, text }) if text.trim() == title);
+            if !repeated {
+                blocks.insert(0, Block::Heading { level: 1, text: title.clone() });
+            }
+        }
         Parsed {
-            blocks: self.parse_blocks_until(|_| false)
+            title,
+            blocks,
+            footnotes: std::mem::take(&mut self.footnotes),
         }
     }
 
@@ -46,7 +85,10 @@ impl <'a> Parser<'a> {
         use pulldown_cmark::Event::*;
         while let Some(event) = self.inner.next() {
             match event {
-                End(tag) if matches(tag) => { return blocks; },
+                End(tag) if matches(tag) => {
+                    collapse_spaces(&mut blocks);
+                    return blocks;
+                },
                 Start(tag) => {
                     match tag {
                         Tag::Paragraph => {
@@ -61,8 +103,31 @@ impl <'a> Parser<'a> {
                         Tag::CodeBlock(kind) => {
                             blocks.push(self.parse_code(kind.into_static()))
                         },
-                        tag @ Tag::HtmlBlock => {
-                            blocks.push(format!("TODO: Start Tag {tag:?}").into());
+                        Tag::HtmlBlock => {
+                            let html = self.parse_html_block();
+                            if let Some(open) = details_start(&html) {
+                                let Some(end) = details_end(&html) else {
+                                    blocks.push(self.parse_details(&html, open));
+                                    continue;
+                                };
+                                blocks.push(self.details_in_block(&html[..end], open));
+                                // The block may also end the <details> we're inside of:
+                                if html[end..].matches("</details>").count() > 1 && !self.details.is_empty() {
+                                    return blocks;
+                                }
+                            } else if html.trim() == "</details>" {
+                                if !self.details.is_empty() {
+                                    return blocks;
+                                }
+                                // Otherwise, a stray end tag. Ignore it.
+                            } else if html.trim_start().starts_with("<summary") {
+                                // A summary that didn't share a block with its <details>:
+                                if let Some(summary @ None) = self.details.last_mut() {
+                                    *summary = summary_text(&html);
+                                }
+                            } else {
+                                blocks.push(format!("(Unimplemented HTML block: {html:?})").into());
+                            }
                         },
                         Tag::List(start_info) => {
                             blocks.push(self.parse_list(start_info));
@@ -70,11 +135,13 @@ impl <'a> Parser<'a> {
                         Tag::Item => {
                             blocks.push(self.parse_list_item());
                         },
+                        Tag::FootnoteDefinition(label) => {
+                            self.parse_footnote(label.into());
+                        },
 
                         tag @ Tag::DefinitionList
                         | tag @ Tag::DefinitionListTitle
                         | tag @ Tag::DefinitionListDefinition
-                        | tag @ Tag::FootnoteDefinition(_)
                         | tag @ Tag::Table(_)
                         | tag @ Tag::TableHead
                         | tag @ Tag::TableRow
@@ -97,6 +164,13 @@ impl <'a> Parser<'a> {
                             };
                             blocks.push_inline(inline);
                         },
+                        Tag::Strikethrough => {
+                            let inline = Inline::Styled {
+                                style:  Style::Strikethrough,
+                                parts: self.parse_inline(&|end| end == TagEnd::Strikethrough)
+                            };
+                            blocks.push_inline(inline);
+                        },
                         Tag::Link { link_type, dest_url, title, id } => {
                             for inline in self.parse_link(link_type, dest_url, title, id) {
                                 blocks.push_inline(inline);
@@ -107,10 +181,13 @@ impl <'a> Parser<'a> {
                         },
 
 
-                        tag @ Tag::Strikethrough
-                        | tag @ Tag::Superscript
-                        | tag @ Tag::Subscript
-                        | tag @ Tag::MetadataBlock(_) => {
+                        Tag::MetadataBlock(_) => {
+                            let metadata = self.parse_metadata();
+                            self.title = self.title.take().or_else(|| frontmatter_title(&metadata));
+                        },
+
+                        tag @ Tag::Superscript
+                        | tag @ Tag::Subscript => {
                             eprintln!("TODO: {tag:?}");
                         },
                     }
@@ -122,12 +199,11 @@ impl <'a> Parser<'a> {
                     blocks.push(Block::Hr);
                 },
 
+                // Extra spaces are collapsed by `collapse_spaces`:
                 SoftBreak => {
-                    // TODO: Check whether we need this space. (Collapse spaces)
                     blocks.push_inline(Inline::Text(" ".into()))
                 },
                 HardBreak => {
-                    // TODO: Check whether we need this space. (Collapse spaces)
                     blocks.push_inline(Inline::Text("\n".into()))
                 },
 
@@ -135,26 +211,40 @@ impl <'a> Parser<'a> {
                     blocks.push_inline(Inline::Code(mono.into()));
                 },
 
+                InlineMath(math) => {
+                    blocks.push_inline(Inline::Math(math.into()));
+                },
+                FootnoteReference(label) => {
+                    blocks.push_inline(Inline::FootnoteRef(label.into()));
+                },
+                DisplayMath(math) => {
+                    blocks.push(Block::Math(math.into()));
+                },
+
                 item @ End(_)
                 | item @ Code(_)
-                | item @ InlineMath(_)
-                | item @ DisplayMath(_)
                 | item @ Html(_)
                 | item @ InlineHtml(_)
-                | item @ FootnoteReference(_)
                 | item @ TaskListMarker(_) => {
                     let msg = format!("(Unimplemented top-level item: {item:?})");
                     blocks.push_inline(msg.into());
                 },
             }
         }
-        
+
+        collapse_spaces(&mut blocks);
         blocks
     }
     
     fn parse_p(&mut self) -> Block {
-        let parts: Vec<Inline> = self.parse_inline(&|tag| tag == TagEnd::Paragraph);
-        Block::P{ parts }
+        let mut blocks = self.parse_blocks_until(|tag| tag == TagEnd::Paragraph);
+
+        // A paragraph that is just `$$ display math $$` is better rendered as its own block:
+        if let [Block::Math(_)] = blocks.as_slice() {
+            return blocks.pop().expect("exactly one block");
+        }
+
+        Block::P{ parts: to_inlines(blocks) }
     }
 
     fn parse_list_item(&mut self) -> Block {
@@ -169,21 +259,73 @@ impl <'a> Parser<'a> {
     fn parse_inline(&mut self, end_condition: &dyn Fn(TagEnd) -> bool) -> Vec<Inline> {
         // Re-use the block-level parsing:
         let blocks = self.parse_blocks_until(end_condition);
+        to_inlines(blocks)
+    }
 
-        // But we expect to only get inline items in this context, so check & extract those:
-        let mut inlines: Vec<Inline> = vec![];
-        for block in blocks {
-            match block {
-                Block::PseudoP { parts } => {
-                    inlines.extend(parts);
-                },
-                block => {
-                    inlines.push(format!("(Unexpected Inline Block: {block:?})").into());
+    fn parse_footnote(&mut self, label: String) {
+        let blocks = self.parse_blocks_until(|end| end == TagEnd::FootnoteDefinition);
+        self.footnotes.push(Footnote { label, blocks });
+    }
+
+    /// The raw HTML of an HTML block.
+    /// The text of a metadata block. (ex: YAML frontmatter.)
+    fn parse_metadata(&mut self) -> String {
+        let mut text = String::new();
+        use pulldown_cmark::Event::*;
+        for event in self.inner.by_ref() {
+            match event {
+                End(TagEnd::MetadataBlock(_)) => break,
+                Text(it) => text.push_str(&it),
+                event => {
+                    debug!("Skipping unexpected metadata event: {event:?}");
+                }
+            }
+        }
+        text
+    }
+
+    fn parse_html_block(&mut self) -> String {
+        let mut html = String::new();
+        use pulldown_cmark::Event::*;
+        for event in self.inner.by_ref() {
+            match event {
+                End(TagEnd::HtmlBlock) => break,
+                Html(text) | Text(text) => html.push_str(&text),
+                event => {
+                    debug!("Skipping unexpected HTML block event: {event:?}");
                 }
             }
         }
+        html
+    }
 
-        inlines
+    /// Parses blocks up to the `</details>` that ends the `<details>` in `html`.
+    /// Its `<summary>` is usually in the same HTML block. (ex: `<details><summary>Why?</summary>`)
+    fn parse_details(&mut self, html: &str, open: bool) -> Block {
+        self.details.push(summary_text(html));
+        let blocks = self.parse_blocks_until(|_| false);
+        let summary = self.details.pop().flatten();
+        Block::Collapsible {
+            summary: summary.unwrap_or_else(|| "Details".into()),
+            open,
+            blocks,
+        }
+    }
+
+    /// A whole `<details>` element in one HTML block, ex: `<details><summary>Why?</summary>Because.</details>`.
+    /// `html` is everything before the `</details>`.
+    fn details_in_block(&mut self, html: &str, open: bool) -> Block {
+        let body_start = match html.find("</summary>") {
+            Some(end) => end + "</summary>".len(),
+            None => html.find('>').map_or(html.len(), |it| it + 1),
+        };
+        let mut body = Parser::from_html(&html[body_start..]);
+        self.footnotes.append(&mut body.footnotes);
+        Block::Collapsible {
+            summary: summary_text(html).unwrap_or_else(|| "Details".into()),
+            open,
+            blocks: body.blocks,
+        }
     }
 
     fn parse_list(&mut self, start_num: Option<u64>) -> Block {
@@ -260,13 +402,21 @@ impl <'a> Parser<'a> {
     
     fn parse_link(
         &mut self, 
-        _link_type: pulldown_cmark::LinkType,
+        link_type: pulldown_cmark::LinkType,
         dest_url: pulldown_cmark::CowStr<'_>,
         _title: pulldown_cmark::CowStr<'_>,
         _id: pulldown_cmark::CowStr<'_>
     ) -> Vec<Inline> {
         let mut out: Vec<Inline> = vec![];
 
+        // pulldown-cmark already resolved reference links (`[text][id]`, `[id][]`, `[id]`) to their
+        // destination, and leaves broken references as text. But email autolinks (`<me@example.com>`)
+        // are just the address:
+        let dest_url = match link_type {
+            pulldown_cmark::LinkType::Email => format!("mailto:{dest_url}").into(),
+            _ => dest_url,
+        };
+
         let parts = self.parse_inline(&|end| end == TagEnd::Link);
 
         // An HTML link can span multiple other elements, ex:
@@ -281,6 +431,8 @@ impl <'a> Parser<'a> {
                 }),
 
                 inner @ Inline::Code(_)
+                | inner @ Inline::Math(_)
+                | inner @ Inline::FootnoteRef(_)
                 | inner @ Inline::Styled { .. } 
                 => {
                     // TODO: I don't believe egui supports styled links.
@@ -338,6 +490,110 @@ impl <'a> Parser<'a> {
     }
 }
 
+/// If `html` starts a `<details>` element, whether it starts out open.
+fn details_start(html: &str) -> Option<bool> {
+    let rest = html.trim_start().strip_prefix("<details")?;
+    let end = rest.find('>')?;
+    let attributes = &rest[..end];
+    if !(attributes.is_empty() || attributes.starts_with(char::is_whitespace)) {
+        // Some other tag, like <detailsfoo>.
+        return None;
+    }
+    Some(attributes.split_whitespace().any(|it| it == "open" || it.starts_with("open=")))
+}
+
+/// Where the `</details>` that ends the `<details>` at the start of `html` is, if it's in `html`.
+fn details_end(html: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut rest = html;
+    while let Some(start) = rest.find("<details").into_iter().chain(rest.find("</details>")).min() {
+        let offset = html.len() - rest.len() + start;
+        if rest[start..].starts_with("</details>") {
+            depth -= 1;
+            if depth == 0 {
+                return Some(offset);
+            }
+        } else {
+            depth += 1;
+        }
+        rest = &rest[start + 1..];
+    }
+    None
+}
+
+/// The text of the `<summary>` in `html`, without any tags inside it.
+fn summary_text(html: &str) -> Option<String> {
+    let start = html.find("<summary")?;
+    let rest = &html[start..];
+    let rest = &rest[rest.find('>')? + 1..];
+    let inner = rest.find("</summary>").map_or(rest, |end| &rest[..end]);
+
+    let mut text = String::new();
+    let mut in_tag = false;
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            // html2md escapes markdown characters in text, and styles it with markdown:
+            '\\' if !in_tag => text.extend(chars.next()),
+            '*' | '`' => {},
+            c if !in_tag => text.push(c),
+            _ => {},
+        }
+    }
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// The `title:` from YAML frontmatter. We don't need a whole YAML parser for one top-level string.
+/// Other keys are ignored.
+fn frontmatter_title(yaml: &str) -> Option<String> {
+    let value = yaml.lines().find_map(|line| line.strip_prefix("title:"))?.trim();
+    let unquoted = ['"', '\''].iter().find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote));
+    let title = unquoted.unwrap_or(value).trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// Line breaks become separate [`Inline::Text`]s, which can pile up with other whitespace. (ex: a soft break
+/// after a hard one.) Collapse whitespace-only runs of them, like HTML does, but keep each hard break's newline.
+fn collapse_spaces(blocks: &mut [Block]) {
+    let is_blank = |inline: &Inline| matches!(inline, Inline::Text(text) if text.trim().is_empty());
+    for block in blocks {
+        let Block::PseudoP { parts } = block else {
+            continue;
+        };
+        collapse_blank_runs(parts, is_blank, |kept, next| {
+            let (Inline::Text(kept), Inline::Text(next)) = (kept, next) else {
+                return;
+            };
+            let newlines = kept.matches('\n').count() + next.matches('\n').count();
+            *kept = if newlines == 0 { " ".into() } else { "\n".repeat(newlines) };
+        });
+    }
+}
+
+/// When parsing inline content, we re-use block-level parsing, but only expect to get inline items.
+/// So check & extract those.
+fn to_inlines(blocks: Vec<Block>) -> Vec<Inline> {
+    let mut inlines: Vec<Inline> = vec![];
+    for block in blocks {
+        match block {
+            Block::PseudoP { parts } => {
+                inlines.extend(parts);
+            },
+            // Display math mixed in with other inline content. Best we can do is show it inline.
+            Block::Math(math) => {
+                inlines.push(Inline::Math(math));
+            },
+            block => {
+                inlines.push(format!("(Unexpected Inline Block: {block:?})").into());
+            }
+        }
+    }
+    inlines
+}
+
 /// A parsed, top-level block of markdown.
 #[derive(Debug)]
 pub enum Block {
@@ -377,6 +633,17 @@ pub enum Block {
         blocks: Vec<Block> 
     },
     Hr,
+
+    /// Display math, i.e.: `$$ ... $$`. We don't render LaTeX, we just show its source.
+    Math(String),
+
+    /// An HTML `<details>` element: blocks hidden under a clickable summary.
+    Collapsible {
+        summary: String,
+        /// Whether it starts out expanded. (`<details open>`)
+        open: bool,
+        blocks: Vec<Block>,
+    },
 }
 
 /// Mostly used for debugging unexpected Markdown formats.
@@ -392,6 +659,10 @@ impl From<String> for Block {
 pub enum Inline {
     Text(String),
     Code(String),
+    /// Inline math, i.e.: `$ ... $`. Shown as source, like Code.
+    Math(String),
+    /// A reference to a [`Footnote`], by its label. i.e.: `[^label]`
+    FootnoteRef(String),
     Link(Link),
     
     /// Just a normal Markdown(/HTML) image. We make these links so you can browse to the image itself to view it.
@@ -412,6 +683,8 @@ impl Inline {
         match self {
             Inline::Text(text) => text.into(),
             Inline::Code(text) => text.into(),
+            Inline::Math(text) => text.into(),
+            Inline::FootnoteRef(label) => format!("[^{label}]"),
             Inline::Link(Link{ text, href: _ }) => text.into(),
             Inline::Image(Image{ src, alt: _, title: _ }) => src.into(),
             Inline::LinkedImage { image, link: _ } => image.src.clone(),
@@ -441,10 +714,11 @@ pub struct Image {
     pub title: String, 
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Style {
     Bold,
     Italics,
+    Strikethrough,
 }
 
 // Mostly for debug errors.
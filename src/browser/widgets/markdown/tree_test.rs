@@ -47,4 +47,236 @@ fn event_debug(md: &str) -> Vec<String> {
     }
 
     out
-}
\ No newline at end of file
+}
+#[test]
+fn math() {
+    use crate::browser::widgets::markdown::tree::{Block, Inline, Parser};
+
+    let md = indoc!{r#"
+        Inline $e = mc^2$ math.
+
+        $$
+        \sum_{i=0}^n i
+        $$
+    "#};
+
+    let parsed = Parser::from_md(md);
+    let [Block::P { parts }, Block::Math(display)] = parsed.blocks.as_slice() else {
+        panic!("Unexpected blocks: {:#?}", parsed.blocks);
+    };
+    assert!(matches!(&parts[1], Inline::Math(math) if math == "e = mc^2"), "{parts:#?}");
+    assert_eq!(display.trim(), r"\sum_{i=0}^n i");
+}
+
+/// All the links in `md`, as (text, href).
+fn links(md: &str) -> Vec<(String, String)> {
+    use crate::browser::widgets::markdown::tree::{Block, Inline, Parser};
+
+    Parser::from_md(md).blocks.iter()
+        .flat_map(|block| match block {
+            Block::P { parts } => parts.as_slice(),
+            _ => &[],
+        })
+        .filter_map(|part| match part {
+            Inline::Link(link) => Some((link.text.clone(), link.href.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn link_types() {
+    let md = indoc!{"
+        [inline](https://example.com/inline)
+        [full][ref]
+        [collapsed][]
+        [shortcut]
+        <https://example.com/auto>
+        <someone@example.com>
+
+        [ref]: https://example.com/full
+        [collapsed]: https://example.com/collapsed \"A title\"
+        [shortcut]: <https://example.com/short cut>
+    "};
+
+    let link = |text: &str, href: &str| (text.to_string(), href.to_string());
+    assert_eq!(links(md), vec![
+        link("inline", "https://example.com/inline"),
+        link("full", "https://example.com/full"),
+        link("collapsed", "https://example.com/collapsed"),
+        link("shortcut", "https://example.com/short cut"),
+        link("https://example.com/auto", "https://example.com/auto"),
+        link("someone@example.com", "mailto:someone@example.com"),
+    ]);
+}
+
+#[test]
+fn broken_reference_is_text() {
+    assert_eq!(links("[missing][nope] and [also missing]"), vec![]);
+}
+
+#[test]
+fn leading_bom_is_ignored() {
+    use crate::browser::widgets::markdown::tree::{Block, Parser};
+
+    let md = "# Title\n\nText.\n";
+    let parsed = Parser::from_md(&format!("\u{FEFF}{md}"));
+    assert!(matches!(&parsed.blocks[0], Block::Heading { level: 1, text } if text == "Title"), "{:#?}", parsed.blocks);
+    assert_eq!(format!("{:?}", parsed.blocks), format!("{:?}", Parser::from_md(md).blocks));
+}
+
+#[test]
+fn collapses_breaks() {
+    use crate::browser::widgets::markdown::tree::{Block, Inline, Parser};
+
+    let parsed = Parser::from_md("One  \n\\\nTwo\nThree\n");
+    let Block::P { parts } = &parsed.blocks[0] else {
+        panic!("{:#?}", parsed.blocks);
+    };
+    let texts: Vec<&str> = parts.iter().map(|part| match part {
+        Inline::Text(text) => text.as_str(),
+        other => panic!("{other:?}"),
+    }).collect();
+    assert_eq!(texts, vec!["One", "\n\n", "Two", " ", "Three"]);
+}
+
+#[test]
+fn nested_styles() {
+    use crate::browser::widgets::markdown::tree::{Block, Inline, Parser, Style};
+
+    let parsed = Parser::from_md("**_both_** ~~gone~~");
+    let [Block::P { parts }] = parsed.blocks.as_slice() else {
+        panic!("Unexpected blocks: {:#?}", parsed.blocks);
+    };
+    let Inline::Styled { style: Style::Bold, parts: bold } = &parts[0] else {
+        panic!("Expected bold: {parts:#?}");
+    };
+    assert!(matches!(bold.as_slice(), [Inline::Styled { style: Style::Italics, .. }]), "{bold:#?}");
+    assert!(matches!(parts.last(), Some(Inline::Styled { style: Style::Strikethrough, .. })), "{parts:#?}");
+}
+
+#[test]
+fn footnotes() {
+    use crate::browser::widgets::markdown::tree::{Block, Inline, Parser};
+
+    let md = indoc! {"
+        Some claim.[^source] Another.[^2]
+
+        [^source]: A *reliable* source.
+        [^2]: Second note.
+    "};
+    let parsed = Parser::from_md(md);
+    let [Block::P { parts }] = parsed.blocks.as_slice() else {
+        panic!("Unexpected blocks: {:#?}", parsed.blocks);
+    };
+    assert!(matches!(&parts[1], Inline::FootnoteRef(label) if label == "source"), "{parts:#?}");
+
+    let labels: Vec<_> = parsed.footnotes.iter().map(|it| it.label.as_str()).collect();
+    assert_eq!(labels, vec!["source", "2"]);
+    assert!(matches!(parsed.footnotes[0].blocks.as_slice(), [Block::P { .. }]), "{:#?}", parsed.footnotes);
+}
+
+#[test]
+fn nested_lists() {
+    use crate::browser::widgets::markdown::tree::{Block, Parser};
+
+    let md = indoc! {"
+        1. One
+           - Two
+             1. Three
+             2. Four
+        2. Five
+    "};
+    let parsed = Parser::from_md(md);
+
+    // Returns the `Block::List` in a list item, after its text:
+    fn sublist(item: &Block) -> &Block {
+        let Block::ListItem { blocks } = item else {
+            panic!("Expected a list item: {item:#?}");
+        };
+        let [Block::PseudoP { .. }, list @ Block::List { .. }] = blocks.as_slice() else {
+            panic!("Expected text and a list: {blocks:#?}");
+        };
+        list
+    }
+
+    let [Block::List { start_num: Some(1), blocks: level1 }] = parsed.blocks.as_slice() else {
+        panic!("Unexpected blocks: {:#?}", parsed.blocks);
+    };
+    assert_eq!(level1.len(), 2);
+    assert!(matches!(&level1[1], Block::ListItem { blocks } if matches!(blocks.as_slice(), [Block::PseudoP { .. }])));
+
+    let Block::List { start_num: None, blocks: level2 } = sublist(&level1[0]) else {
+        panic!("Expected an unordered list: {:#?}", level1[0]);
+    };
+    assert_eq!(level2.len(), 1);
+
+    let Block::List { start_num: Some(1), blocks: level3 } = sublist(&level2[0]) else {
+        panic!("Expected an ordered list: {:#?}", level2[0]);
+    };
+    assert_eq!(level3.len(), 2);
+    assert!(level3.iter().all(|it| matches!(it, Block::ListItem { .. })), "{level3:#?}");
+}
+
+#[test]
+fn details() {
+    use crate::browser::widgets::markdown::tree::{Block, Parser};
+
+    // Summarizes a collapsible, and what's in it:
+    fn outline(block: &Block) -> String {
+        match block {
+            Block::Collapsible { summary, open, blocks } => {
+                let inner: Vec<String> = blocks.iter().map(outline).collect();
+                format!("{summary} (open: {open}) [{}]", inner.join(", "))
+            },
+            Block::P { .. } | Block::PseudoP { .. } => "p".into(),
+            other => format!("{other:?}"),
+        }
+    }
+
+    let md = indoc! {"
+        <details>
+        <summary>Why?</summary>
+
+        Because.
+
+        <details open><summary>Really?</summary>Yes.</details>
+        </details>
+
+        After.
+    "};
+    let parsed = Parser::from_md(md);
+    let outlines: Vec<String> = parsed.blocks.iter().map(outline).collect();
+    assert_eq!(outlines, vec!["Why? (open: false) [p, Really? (open: true) [p]]", "p"]);
+
+    let html = "<p>Before</p><details><summary>Is it <b>bold</b>?</summary><p>Answer</p></details><p>After</p>";
+    let parsed = Parser::from_html(html);
+    let outlines: Vec<String> = parsed.blocks.iter().map(outline).collect();
+    assert_eq!(outlines, vec!["p", "Is it bold? (open: false) [p]", "p"]);
+}
+
+#[test]
+fn frontmatter() {
+    use crate::browser::widgets::markdown::tree::{Block, Parser};
+
+    let md = indoc::indoc! {r#"
+        ---
+        title: "Notes: Part 1"
+        tags: [rust, gemini]
+        author:
+          name: Someone
+        ---
+        Text.
+    "#};
+    let parsed = Parser::from_md(md);
+    assert_eq!(parsed.title.as_deref(), Some("Notes: Part 1"));
+    assert!(matches!(&parsed.blocks[..], [Block::Heading { level: 1, text }, Block::P { .. }] if text == "Notes: Part 1"), "{:#?}", parsed.blocks);
+
+    // Not repeated if the document starts with it anyway:
+    let parsed = Parser::from_md("---\ntitle: Notes\n---\n# Notes\n");
+    assert_eq!(parsed.blocks.len(), 1);
+
+    let parsed = Parser::from_md("---\ndraft: true\n---\nText.\n");
+    assert_eq!(parsed.title, None);
+    assert!(matches!(&parsed.blocks[..], [Block::P { .. }]), "{:#?}", parsed.blocks);
+}
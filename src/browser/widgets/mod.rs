@@ -1,22 +1,122 @@
+pub mod code;
+pub mod find;
+pub mod hints;
+pub mod json;
+pub mod limit;
+pub mod link_menu;
 pub mod markdown;
+pub mod plaintext;
+pub mod preview;
+pub mod table;
 
-use std::fmt::Debug;
+use std::{any::Any, fmt::Debug, sync::Arc};
 
-use eframe::egui::Ui;
+use eframe::egui::{Image, Rect, Ui};
+
+use crate::gemtext::Block;
+
+use link_menu::LinkAction;
 
 
 /// Returned by a document renderer
 pub struct DocumentResponse {
-    pub link_clicked: Option<String>
+    pub link_clicked: Option<String>,
+
+    /// The document would like the image at this (possibly relative) URL.
+    /// The result is passed back via [`DocWidget::image_loaded`].
+    pub load_image: Option<String>,
+
+    /// The index of a block the user clicked on, for widgets that report that.
+    /// See [`GemtextWidget::report_block_clicks`](crate::gemtext_widget::GemtextWidget::report_block_clicks).
+    pub block_clicked: Option<usize>,
+
+    /// The (possibly relative) URL of a link the mouse is over.
+    /// Its target's title may be passed back via [`DocWidget::link_preview`].
+    pub link_hovered: Option<String>,
+
+    /// The (possibly relative) URLs of links that are on screen, and where. Used for keyboard link hints.
+    pub links: Vec<(Rect, String)>,
+
+    /// Something the user picked from a link's context menu, and the (possibly relative) link.
+    pub link_action: Option<(LinkAction, String)>,
 }
 
 /// Responsible for rendering a document within a tab.
-pub trait DocWidget: Debug {
+/// (`Any`, so that tests can check which kind of widget they got.)
+pub trait DocWidget: Debug + Any {
     fn ui(&mut self, ui: &mut Ui) -> DocumentResponse;
 
+    /// An image requested via [`DocumentResponse::load_image`] has loaded (or failed to).
+    /// `link` is the URL exactly as the document requested it.
+    fn image_loaded(&mut self, _link: &str, _image: Result<ImageData, String>) {}
+
+    /// The title of a link's target, for [`DocumentResponse::link_hovered`]. None if it has no title.
+    /// `link` is the URL exactly as the document reported it.
+    fn link_preview(&mut self, _link: &str, _title: Option<String>) {}
+
+    /// Whether this document can be shown as an image gallery. (It's mostly image links.)
+    fn gallery_available(&self) -> bool { false }
+
+    /// Switch to/from gallery mode, if it's available.
+    fn set_gallery(&mut self, _gallery: bool) {}
+
+    /// Only render the first `step` blocks (or lines) at first, with a button to show more. 0 renders them all.
+    fn set_block_limit(&mut self, _step: usize) {}
+
+    /// More blocks for a gemtext document that's still loading. Other documents ignore them.
+    fn append_gemtext(&mut self, _blocks: Vec<Block>) {}
+
+    /// Show images within the document, for documents that have them.
+    fn set_inline_images(&mut self, _inline: bool) {}
+
+    /// The document's headings, in order, for the outline sidebar.
+    fn outline(&self) -> Vec<Heading> { Vec::new() }
+
+    /// Scroll to the `index`th heading from [`Self::outline`].
+    fn scroll_to_heading(&mut self, _index: usize) {}
+
+    /// The document's title, if it has one. (ex: its first heading.)
+    fn title(&self) -> Option<String> { None }
+
+    /// The document as an HTML fragment, for exporting. None if it can't be exported.
+    fn to_html(&self) -> Option<String> { None }
+
+    /// Highlight text matching `query`, and return how many matches there are. An empty query clears them.
+    fn find(&mut self, _query: &str, _case_sensitive: bool) -> usize { 0 }
+
+    /// Make the `index`th match from [`Self::find`] the current one, and scroll to it.
+    fn find_select(&mut self, _index: usize) {}
+
+    /// The document's text, in reading order, for text-to-speech. Usually one string per block.
+    /// Code and bare URLs are skipped, since they don't read well aloud.
+    #[cfg(feature = "tts")]
+    fn readable_text(&self) -> Vec<String> { Vec::new() }
+
     // TODO: update theme.
 }
 
+/// An entry in a document's [outline](DocWidget::outline).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    /// 1 for top-level headings.
+    pub level: u8,
+    pub text: String,
+}
+
+/// Raw bytes for an image, ready to hand to egui's image loaders.
+#[derive(Clone, Debug)]
+pub struct ImageData {
+    /// Unique URI egui will cache the decoded image under.
+    pub uri: String,
+    pub bytes: Arc<[u8]>,
+}
+
+/// Why egui couldn't decode `image`, if it couldn't. (ex: a malformed SVG)
+/// Otherwise, egui just draws an error icon where it would've been.
+pub fn image_error(ui: &Ui, image: &Image) -> Option<String> {
+    image.load_for_size(ui.ctx(), ui.available_size()).err().map(|err| err.to_string())
+}
+
 // TODO: Necessary?
 // impl <'a, T> DocWidget for &'a mut Box<T> where &'a mut T: DocWidget {
 //     fn ui(self, ui: &mut Ui) -> DocumentResponse {
@@ -0,0 +1,111 @@
+//! Renders plain text (logs, READMEs, etc.) in monospace, so that alignment is preserved.
+
+use eframe::egui::{Label, RichText, ScrollArea, Ui, Vec2};
+
+use crate::{browser::widgets::{limit::BlockLimit, DocWidget, DocumentResponse}, gemtext_widget::Style, util::escape_html};
+
+mod plaintext_test;
+
+#[derive(Debug)]
+pub struct PlaintextWidget {
+    lines: Vec<String>,
+
+    /// Soft-wrap long lines. Otherwise, scroll horizontally.
+    wrap: bool,
+    line_numbers: bool,
+
+    limit: BlockLimit,
+}
+
+impl PlaintextWidget {
+    pub fn new(text: &str) -> Self {
+        Self {
+            lines: text.lines().map(String::from).collect(),
+            wrap: true,
+            line_numbers: false,
+            limit: BlockLimit::default(),
+        }
+    }
+
+    fn toolbar(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing = Vec2::new(8.0, 0.0);
+            ui.checkbox(&mut self.wrap, "Wrap lines");
+            ui.checkbox(&mut self.line_numbers, "Line numbers");
+        });
+        ui.separator();
+    }
+
+    /// The (line number, text) of each line we're showing.
+    fn rows(&self) -> impl Iterator<Item = (Option<String>, String)> + '_ {
+        let width = self.lines.len().to_string().len();
+        let shown = self.limit.shown(self.lines.len());
+        self.lines[..shown].iter().enumerate().map(move |(index, line)| {
+            let num = self.line_numbers.then(|| format!("{:>width$} ", index + 1));
+            (num, keep_indent(line))
+        })
+    }
+
+    fn render_lines(&mut self, ui: &mut Ui) {
+        for (num, line) in self.rows() {
+            ui.horizontal_top(|ui| {
+                if let Some(num) = num {
+                    ui.add(Label::new(RichText::new(num).text_style(Style::mono()).weak()).selectable(false));
+                }
+                let text = RichText::new(line).text_style(Style::mono());
+                let label = if self.wrap { Label::new(text).wrap() } else { Label::new(text).extend() };
+                ui.add(label);
+            });
+        }
+        self.limit.show_more_ui(ui, self.lines.len());
+    }
+}
+
+/// egui drops leading spaces from labels, which would throw off the alignment of indented lines.
+/// See: https://github.com/emilk/egui/issues/1272
+fn keep_indent(line: &str) -> String {
+    let text = line.trim_start_matches(' ');
+    let indent = line.len() - text.len();
+    format!("{}{text}", "\u{a0}".repeat(indent))
+}
+
+impl DocWidget for PlaintextWidget {
+    fn ui(&mut self, ui: &mut Ui) -> DocumentResponse {
+        ui.spacing_mut().item_spacing = Vec2::ZERO;
+
+        self.toolbar(ui);
+        if self.wrap {
+            self.render_lines(ui);
+        } else {
+            ScrollArea::horizontal().show(ui, |ui| {
+                self.render_lines(ui);
+            });
+        }
+
+        DocumentResponse {
+            link_clicked: None,
+            load_image: None,
+            block_clicked: None,
+            link_hovered: None,
+            links: Vec::new(),
+            link_action: None,
+        }
+    }
+
+    fn set_block_limit(&mut self, step: usize) {
+        self.limit = BlockLimit::new(step);
+    }
+
+    fn to_html(&self) -> Option<String> {
+        Some(format!("<pre>{}</pre>\n", escape_html(&self.lines.join("\n"))))
+    }
+
+    /// Reads each paragraph (separated by blank lines) as a single text.
+    #[cfg(feature = "tts")]
+    fn readable_text(&self) -> Vec<String> {
+        self.lines.split(|it| it.trim().is_empty())
+            .filter(|it| !it.is_empty())
+            .map(|it| it.join(" "))
+            .collect()
+    }
+}
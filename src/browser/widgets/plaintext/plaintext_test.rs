@@ -0,0 +1,68 @@
+#![cfg(test)]
+
+use eframe::egui::{self, CentralPanel, Pos2, RawInput, Rect, Vec2};
+
+use crate::{browser::{fonts::{definitions, NOTO_SANS, NOTO_SANS_MONO}, widgets::{plaintext::PlaintextWidget, DocWidget as _}}, gemtext_widget::Style};
+use pretty_assertions::assert_eq;
+
+fn rows(widget: &PlaintextWidget) -> Vec<(Option<String>, String)> {
+    widget.rows().collect()
+}
+
+#[test]
+fn line_numbers() {
+    let text = (1..=10).map(|it| format!("line {it}")).collect::<Vec<_>>().join("\n");
+    let mut widget = PlaintextWidget::new(&text);
+    assert_eq!(rows(&widget)[0], (None, "line 1".into()));
+
+    widget.line_numbers = true;
+    let rows = rows(&widget);
+    assert_eq!(rows.len(), 10);
+    // Right-aligned, so that the text lines up:
+    assert_eq!(rows[0], (Some(" 1 ".into()), "line 1".into()));
+    assert_eq!(rows[9], (Some("10 ".into()), "line 10".into()));
+}
+
+#[test]
+fn keeps_indentation() {
+    let widget = PlaintextWidget::new("  indented\n\tnot spaces\nlast  ");
+    assert_eq!(rows(&widget), vec![
+        (None, "\u{a0}\u{a0}indented".into()),
+        (None, "\tnot spaces".into()),
+        (None, "last  ".into()),
+    ]);
+}
+
+/// The size of `widget` when rendered in a narrow window.
+fn rendered_size(widget: &mut PlaintextWidget) -> Vec2 {
+    let ctx = egui::Context::default();
+    ctx.set_fonts(definitions(NOTO_SANS, NOTO_SANS_MONO));
+    Style::config(&ctx);
+    let input = || RawInput {
+        screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(200.0, 2000.0))),
+        ..Default::default()
+    };
+    let mut size = Vec2::ZERO;
+    // The first pass only sets up fonts, etc.:
+    for _ in 0..2 {
+        let _ = ctx.run(input(), |ctx| {
+            CentralPanel::default().show(ctx, |ui| {
+                size = ui.scope(|ui| { widget.ui(ui); }).response.rect.size();
+            });
+        });
+    }
+    size
+}
+
+#[test]
+fn wrapping() {
+    let text = "word ".repeat(100);
+    let mut widget = PlaintextWidget::new(&text);
+    let wrapped = rendered_size(&mut widget);
+
+    widget.wrap = false;
+    let unwrapped = rendered_size(&mut widget);
+
+    assert!(wrapped.y > unwrapped.y, "{wrapped:?} vs. {unwrapped:?}");
+    assert!(wrapped.x <= 200.0, "{wrapped:?}");
+}
@@ -0,0 +1,38 @@
+//! Shows link targets' titles when hovering links. See [`DocWidget::link_preview`](super::DocWidget::link_preview).
+
+use std::collections::HashMap;
+
+use eframe::egui::Response;
+
+#[derive(Debug, Default)]
+pub struct LinkPreviews {
+    /// Keyed by link URL, exactly as it appears in the document. None if the target has no title.
+    titles: HashMap<String, Option<String>>,
+
+    /// The link hovered this frame.
+    hovered: Option<String>,
+}
+
+impl LinkPreviews {
+    /// Show `link`'s URL, and its target's title once we know it, while `response` is hovered.
+    pub fn hover_ui(&mut self, response: Response, link: &str) {
+        if response.hovered() {
+            self.hovered = Some(link.to_string());
+        }
+        response.on_hover_ui(|ui| {
+            if let Some(Some(title)) = self.titles.get(link) {
+                ui.strong(title);
+            }
+            ui.monospace(link);
+        });
+    }
+
+    pub fn set_title(&mut self, link: &str, title: Option<String>) {
+        self.titles.insert(link.to_string(), title);
+    }
+
+    /// The link hovered since the last call, if any. For [`DocumentResponse::link_hovered`](super::DocumentResponse::link_hovered).
+    pub fn take_hovered(&mut self) -> Option<String> {
+        self.hovered.take()
+    }
+}
@@ -0,0 +1,119 @@
+//! Renders CSV and TSV data as a table.
+
+use eframe::egui::{Label, RichText, ScrollArea, TextStyle, Ui};
+use egui_extras::{Column, TableBuilder};
+
+use crate::browser::widgets::{DocWidget, DocumentResponse};
+
+mod table_test;
+
+/// Columns start out no wider than this, so that one long cell doesn't push the rest off screen.
+/// They can be resized wider.
+const MAX_INITIAL_WIDTH: f32 = 300.0;
+
+#[derive(Debug)]
+pub struct TableWidget {
+    /// The first row is shown as the header.
+    rows: Vec<Vec<String>>,
+    columns: usize,
+}
+
+impl TableWidget {
+    pub fn csv(text: &str) -> Self {
+        Self::new(parse(text, ','))
+    }
+
+    pub fn tsv(text: &str) -> Self {
+        Self::new(parse(text, '\t'))
+    }
+
+    fn new(rows: Vec<Vec<String>>) -> Self {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+        Self { rows, columns }
+    }
+}
+
+impl DocWidget for TableWidget {
+    fn ui(&mut self, ui: &mut Ui) -> DocumentResponse {
+        let Some((header, data)) = self.rows.split_first() else {
+            ui.weak("(empty)");
+            return DocumentResponse { link_clicked: None, load_image: None, block_clicked: None, link_hovered: None, links: Vec::new(), link_action: None };
+        };
+        let row_height = ui.text_style_height(&TextStyle::Body) + ui.spacing().item_spacing.y;
+        let cell = |ui: &mut Ui, text: RichText| {
+            // Truncated cells show their full text on hover:
+            ui.add(Label::new(text).truncate());
+        };
+
+        ScrollArea::horizontal().show(ui, |ui| {
+            let column = Column::auto().at_most(MAX_INITIAL_WIDTH).resizable(true).clip(true);
+            TableBuilder::new(ui)
+                .striped(true)
+                // The tab already scrolls vertically. Rows outside of its view still aren't rendered.
+                .vscroll(false)
+                .columns(column, self.columns)
+                .header(row_height, |mut row| {
+                    for index in 0..self.columns {
+                        let text = header.get(index).map(String::as_str).unwrap_or_default();
+                        row.col(|ui| cell(ui, RichText::new(text).strong()));
+                    }
+                })
+                .body(|body| {
+                    body.rows(row_height, data.len(), |mut row| {
+                        let values = &data[row.index()];
+                        for index in 0..self.columns {
+                            let text = values.get(index).map(String::as_str).unwrap_or_default();
+                            row.col(|ui| cell(ui, RichText::new(text)));
+                        }
+                    });
+                });
+        });
+
+        DocumentResponse {
+            link_clicked: None,
+            load_image: None,
+            block_clicked: None,
+            link_hovered: None,
+            links: Vec::new(),
+            link_action: None,
+        }
+    }
+}
+
+/// Parses delimiter-separated values. Fields may be quoted with `"`, in which case they may contain
+/// the delimiter, newlines, and `""` for a literal quote.
+pub fn parse(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                },
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            },
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
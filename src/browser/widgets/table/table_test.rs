@@ -0,0 +1,24 @@
+#![cfg(test)]
+
+use crate::browser::widgets::table::parse;
+use pretty_assertions::assert_eq;
+
+fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+    rows.iter().map(|row| row.iter().map(|it| it.to_string()).collect()).collect()
+}
+
+#[test]
+fn csv() {
+    let text = "name,notes\r\nAda,\"Wrote \"\"the\"\" first program\"\nBob,\"Multi\nline, with comma\"\n,\n";
+    assert_eq!(parse(text, ','), rows(&[
+        &["name", "notes"],
+        &["Ada", "Wrote \"the\" first program"],
+        &["Bob", "Multi\nline, with comma"],
+        &["", ""],
+    ]));
+}
+
+#[test]
+fn tsv_without_trailing_newline() {
+    assert_eq!(parse("a\tb\n1\t2", '\t'), rows(&[&["a", "b"], &["1", "2"]]));
+}
@@ -1,18 +1,35 @@
-//! A hacky little interactive gemtext editor.
-//! Mostly used to debug gemtext parsing/rendering.
+//! A little interactive gemtext editor.
+//! Originally used to debug gemtext parsing/rendering, but it can open and save `.gmi` files, too.
 
-use eframe::{egui::{self, Context, ScrollArea, TextEdit, TextStyle}, Frame, NativeOptions};
+use std::{path::PathBuf, time::{Duration, Instant}};
+
+use eframe::{egui::{self, text::{CCursor, CCursorRange}, Align, Button, Context, Key, KeyboardShortcut, Modifiers, RichText, ScrollArea, TextEdit, TextStyle, ViewportCommand}, Frame, NativeOptions};
+use serde::{Deserialize, Serialize};
 
 use crate::{browser::{fonts::load_fonts, widgets::DocWidget as _}, gemtext::{self, Block}, gemtext_widget::{self, GemtextWidget}};
+use drafts::{Draft, Drafts};
+
+mod drafts;
+
+/// How often to write a draft while there are unsaved edits.
+const DRAFT_INTERVAL: Duration = Duration::from_secs(10);
+
+const OPEN: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::O);
+const SAVE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::S);
+const SAVE_AS: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::S);
+
+/// Names the editor's window and data directory. Separate from the browser's, so that running both at once
+/// doesn't mix up their window positions and storage.
+const APP_ID: &str = "egemi-editor";
 
 pub fn main() -> eframe::Result {
     let opts = NativeOptions {
-
+        persist_window: true,
         ..Default::default()
     };
 
     eframe::run_native(
-        "egemi",
+        APP_ID,
         opts,
         Box::new(|c| {
             let app = App::new(c);
@@ -25,12 +42,79 @@ pub fn main() -> eframe::Result {
 struct App {
     text: String,
     gemtext: GemtextWidget,
+
+    /// The source lines of each rendered block.
+    block_spans: Vec<gemtext::Span>,
+
+    /// Move the caret to this line on the next frame.
+    jump_to_line: Option<usize>,
+
+    /// Scroll offsets of the (left, right) panes, as of the last frame.
+    scroll: (f32, f32),
+
+    /// Scroll offsets restored from storage, to apply on the first frame.
+    restore_scroll: Option<(f32, f32)>,
+
+    /// None if there's nowhere to keep drafts.
+    drafts: Option<Drafts>,
+    /// The text changed since we last wrote a draft.
+    draft_dirty: bool,
+    last_draft: Instant,
+
+    /// A draft newer than our last save, which the user may want to recover.
+    recoverable: Option<Draft>,
+
+    /// The file we're editing. None until the user opens or saves one.
+    path: Option<PathBuf>,
+    /// The text changed since it was last opened or saved to [`Self::path`].
+    unsaved: bool,
+
+    /// What we last set the window title to.
+    title: String,
+
+    /// Why the last open or save failed.
+    file_error: Option<String>,
+}
+
+/// What we remember between runs, so that an interrupted editing session isn't lost.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+struct Saved {
+    text: String,
+    scroll: (f32, f32),
+
+    /// Seconds since the Unix epoch.
+    saved_at: u64,
+
+    path: Option<PathBuf>,
+    unsaved: bool,
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        self.shortcuts(ctx);
+        self.update_title(ctx);
         self.menu(ctx);
+        self.error_bar(ctx);
+        self.recovery_bar(ctx);
         egui::CentralPanel::default().show(ctx, |ui| self.body(ui));
+        self.autosave(ctx);
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let saved = Saved {
+            text: self.text.clone(),
+            scroll: self.scroll,
+            saved_at: drafts::now(),
+            path: self.path.clone(),
+            unsaved: self.unsaved,
+        };
+        eframe::set_value(storage, eframe::APP_KEY, &saved);
+    }
+
+    /// More often than the default, since losing edits is worse than losing window positions.
+    fn auto_save_interval(&self) -> Duration {
+        Duration::from_secs(5)
     }
 }
 
@@ -39,10 +123,34 @@ impl App {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         load_fonts(cc);
         gemtext_widget::Style::config(&cc.egui_ctx);
-        Self {
-            text: String::from("Edit me! 😅 ✅\nこれは日本語ですよ！\nXièxiè (谢谢)"),
-            gemtext: GemtextWidget::default(),
-        }
+        let saved: Option<Saved> = cc.storage.and_then(|it| eframe::get_value(it, eframe::APP_KEY));
+        let saved_at = saved.as_ref().map(|it| it.saved_at).unwrap_or_default();
+        let (text, restore_scroll, path, unsaved) = match saved {
+            Some(saved) => (saved.text, Some(saved.scroll), saved.path, saved.unsaved),
+            None => (String::from("Edit me! 😅 ✅\nこれは日本語ですよ！\nXièxiè (谢谢)"), None, None, false),
+        };
+        let drafts = eframe::storage_dir(APP_ID).map(|it| Drafts::new(it.join("drafts")));
+        let recoverable = drafts.as_ref()
+            .and_then(Drafts::latest)
+            .filter(|draft| draft.time > saved_at && draft.read().is_ok_and(|it| it != text));
+        let mut app = Self {
+            text,
+            gemtext: GemtextWidget::default().report_block_clicks(true),
+            block_spans: Vec::new(),
+            jump_to_line: None,
+            scroll: restore_scroll.unwrap_or_default(),
+            restore_scroll,
+            drafts,
+            draft_dirty: false,
+            last_draft: Instant::now(),
+            recoverable,
+            path,
+            unsaved,
+            title: String::new(),
+            file_error: None,
+        };
+        app.rerender();
+        app
     }
 
     fn menu(&mut self, ctx: &Context) {
@@ -50,9 +158,25 @@ impl App {
             egui::MenuBar::new().ui(ui, |ui| {
                 ui.menu_button("egemi", |ui| {
                     egui::widgets::global_theme_preference_buttons(ui);
-                    let clicker = ui.button("TODO: Something here");
-                    if clicker.clicked() {
-                        println!("Clicked");
+                });
+                ui.menu_button("File", |ui| {
+                    let ctx = ui.ctx().clone();
+                    let button = |text: &str, shortcut: &KeyboardShortcut| {
+                        Button::new(text).shortcut_text(ctx.format_shortcut(shortcut))
+                    };
+                    if ui.add(button("Open…", &OPEN)).clicked() {
+                        self.open();
+                    }
+                    if ui.add(button("Save", &SAVE)).clicked() {
+                        self.save_file();
+                    }
+                    if ui.add(button("Save As…", &SAVE_AS)).clicked() {
+                        self.save_as();
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    if ui.button("Tidy Gemtext").on_hover_text("Rewrite the text the way egemi parses it").clicked() {
+                        self.tidy();
                     }
                 });
                 egui::warn_if_debug_build(ui);
@@ -60,6 +184,165 @@ impl App {
         });
     }
     
+    fn shortcuts(&mut self, ctx: &Context) {
+        // Check Save As first, since Save's shortcut would also match it:
+        if ctx.input_mut(|i| i.consume_shortcut(&SAVE_AS)) {
+            self.save_as();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&SAVE)) {
+            self.save_file();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&OPEN)) {
+            self.open();
+        }
+    }
+
+    /// Shows the file's name, and whether it has unsaved changes.
+    fn update_title(&mut self, ctx: &Context) {
+        let name = self.path.as_ref()
+            .and_then(|it| it.file_name())
+            .map_or_else(|| "Untitled".into(), |it| it.to_string_lossy());
+        let dirty = if self.unsaved { "● " } else { "" };
+        let title = format!("{dirty}{name} — egemi editor");
+        if title != self.title {
+            ctx.send_viewport_cmd(ViewportCommand::Title(title.clone()));
+            self.title = title;
+        }
+    }
+
+    fn file_dialog(&self) -> rfd::FileDialog {
+        let dialog = rfd::FileDialog::new().add_filter("Gemtext", &["gmi", "gemini"]);
+        match self.path.as_ref().and_then(|it| it.parent()) {
+            Some(dir) => dialog.set_directory(dir),
+            None => dialog,
+        }
+    }
+
+    fn open(&mut self) {
+        if self.unsaved && !confirm_discard() {
+            return;
+        }
+        let Some(path) = self.file_dialog().pick_file() else {
+            // Cancelled.
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                self.text = text;
+                self.path = Some(path);
+                self.unsaved = false;
+                self.file_error = None;
+                self.rerender();
+            },
+            Err(err) => self.file_error = Some(format!("Couldn't open {}: {err}", path.display())),
+        }
+    }
+
+    /// Save to the current file, or ask where to save if there isn't one.
+    fn save_file(&mut self) {
+        match self.path.clone() {
+            Some(path) => self.write(path),
+            None => self.save_as(),
+        }
+    }
+
+    fn save_as(&mut self) {
+        let name = self.path.as_ref()
+            .and_then(|it| it.file_name())
+            .map_or_else(|| "untitled.gmi".into(), |it| it.to_string_lossy().into_owned());
+        if let Some(path) = self.file_dialog().set_file_name(name).save_file() {
+            self.write(path);
+        }
+    }
+
+    fn write(&mut self, path: PathBuf) {
+        match std::fs::write(&path, &self.text) {
+            Ok(()) => {
+                self.path = Some(path);
+                self.unsaved = false;
+                self.file_error = None;
+            },
+            Err(err) => self.file_error = Some(format!("Couldn't save {}: {err}", path.display())),
+        }
+    }
+
+    /// Replaces the text with the gemtext for its parsed blocks. (ex: `#Title` becomes `# Title`.)
+    fn tidy(&mut self) {
+        let Ok(blocks) = gemtext::Options::default().parse(&self.text) else {
+            return;
+        };
+        let tidy = gemtext::serialize(&blocks);
+        if tidy != self.text {
+            self.text = tidy;
+            self.draft_dirty = true;
+            self.unsaved = true;
+            self.rerender();
+        }
+    }
+
+    fn error_bar(&mut self, ctx: &Context) {
+        let Some(error) = &self.file_error else {
+            return;
+        };
+        let mut dismiss = false;
+        egui::TopBottomPanel::top("file error").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(error).color(ui.visuals().error_fg_color));
+                dismiss = ui.button("Dismiss").clicked();
+            });
+        });
+        if dismiss {
+            self.file_error = None;
+        }
+    }
+
+    /// Offers to restore a draft that's newer than what we restored from storage.
+    fn recovery_bar(&mut self, ctx: &Context) {
+        let Some(draft) = self.recoverable.take() else {
+            return;
+        };
+        let mut done = false;
+        egui::TopBottomPanel::top("recover draft").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("Found a newer draft: {}", draft.path.display()));
+                if ui.button("Recover").clicked() {
+                    match draft.read() {
+                        Ok(text) => {
+                            self.text = text;
+                            self.draft_dirty = true;
+                            self.unsaved = true;
+                            self.rerender();
+                        },
+                        Err(err) => log::warn!("Couldn't read draft {}: {err}", draft.path.display()),
+                    }
+                    done = true;
+                }
+                done |= ui.button("Discard").clicked();
+            });
+        });
+        if !done {
+            self.recoverable = Some(draft);
+        }
+    }
+
+    /// Writes a draft every [`DRAFT_INTERVAL`] while there are new edits.
+    fn autosave(&mut self, ctx: &Context) {
+        if !self.draft_dirty {
+            return;
+        }
+        let Some(drafts) = &self.drafts else {
+            return;
+        };
+        let elapsed = self.last_draft.elapsed();
+        if elapsed < DRAFT_INTERVAL {
+            ctx.request_repaint_after(DRAFT_INTERVAL - elapsed);
+            return;
+        }
+        if let Err(err) = drafts.write(&self.text, drafts::now()) {
+            log::warn!("Couldn't write draft: {err}");
+        }
+        self.draft_dirty = false;
+        self.last_draft = Instant::now();
+    }
+
     fn body(&mut self, ui: &mut egui::Ui) {
         ui.columns(2, |ui| {
             self.left_pane_ui(&mut ui[0]);
@@ -69,28 +352,58 @@ impl App {
     }
     
     fn left_pane_ui(&mut self, ui: &mut egui::Ui) {
-        ScrollArea::vertical().id_salt("left").show(ui, |ui| {
-            let edit = TextEdit::multiline(&mut self.text).font(TextStyle::Monospace);
-            let response = ui.add_sized(ui.available_size(), edit);
-            if response.changed() {
+        let mut scroll = ScrollArea::vertical().id_salt("left");
+        if let Some((offset, _)) = self.restore_scroll {
+            scroll = scroll.vertical_scroll_offset(offset);
+        }
+        let output = scroll.show(ui, |ui| {
+            let edit = TextEdit::multiline(&mut self.text)
+                .font(TextStyle::Monospace)
+                .min_size(ui.available_size());
+            let output = edit.show(ui);
+            if output.response.changed() {
+                self.draft_dirty = true;
+                self.unsaved = true;
                 self.rerender();
             }
+
+            if let Some(line) = self.jump_to_line.take() {
+                let ccursor = CCursor::new(line_start(&self.text, line));
+                let mut state = output.state;
+                state.cursor.set_char_range(Some(CCursorRange::one(ccursor)));
+                state.store(ui.ctx(), output.response.id);
+                output.response.request_focus();
+
+                let caret = output.galley.pos_from_cursor(ccursor).translate(output.galley_pos.to_vec2());
+                ui.scroll_to_rect(caret, Some(Align::Center));
+            }
         });
+        self.scroll.0 = output.state.offset.y;
     }
 
     fn right_pane_ui(&mut self, ui: &mut egui::Ui) {
         // Render gemtext:
-        ScrollArea::vertical().id_salt("right").show(ui, |ui| {
-            self.gemtext.ui(ui);
+        let mut scroll = ScrollArea::vertical().id_salt("right");
+        if let Some((_, offset)) = self.restore_scroll.take() {
+            scroll = scroll.vertical_scroll_offset(offset);
+        }
+        let output = scroll.show(ui, |ui| {
+            let response = self.gemtext.ui(ui);
+            if let Some(index) = response.block_clicked {
+                self.jump_to_line = self.block_spans.get(index).map(|it| it.start);
+            }
         });
+        self.scroll.1 = output.state.offset.y;
 
     }
 
     fn rerender(&mut self) {
-        let result = gemtext::Options::default().parse(&self.text);
-        if let Ok(blocks) = result {
+        let result = gemtext::Options::default().parse_with_spans(&self.text);
+        if let Ok((blocks, spans)) = result {
             self.gemtext.set_blocks(blocks);
+            self.block_spans = spans;
         } else {
+            self.block_spans.clear();
             self.gemtext.set_blocks(vec![
                 Block::Text(format!("Error parsing"))
             ]);
@@ -98,3 +411,20 @@ impl App {
     }
 }
 
+/// Asks before throwing away unsaved edits.
+fn confirm_discard() -> bool {
+    let answer = rfd::MessageDialog::new()
+        .set_title("Unsaved changes")
+        .set_description("Discard your unsaved changes?")
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show();
+    answer == rfd::MessageDialogResult::Yes
+}
+
+/// The char index (not byte index!) where `line` starts in `text`.
+fn line_start(text: &str, line: usize) -> usize {
+    text.split_inclusive('\n')
+        .take(line)
+        .map(|it| it.chars().count())
+        .sum()
+}
@@ -0,0 +1,75 @@
+//! Periodic copies of the editor's buffer, so that a crash doesn't lose work.
+
+use std::{fs, io, path::PathBuf, time::SystemTime};
+
+mod drafts_test;
+
+/// How many drafts to keep. Older ones are deleted.
+const MAX_DRAFTS: usize = 5;
+
+#[derive(Debug)]
+pub struct Drafts {
+    dir: PathBuf,
+}
+
+/// A draft saved by [`Drafts::write`].
+#[derive(Debug, PartialEq)]
+pub struct Draft {
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    pub path: PathBuf,
+}
+
+impl Drafts {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Write `text` as a new draft, then delete the oldest drafts.
+    pub fn write(&self, text: &str, time: u64) -> io::Result<Draft> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("draft-{time}.gmi"));
+        fs::write(&path, text)?;
+        for old in self.list()?.into_iter().rev().skip(MAX_DRAFTS) {
+            fs::remove_file(old.path)?;
+        }
+        Ok(Draft { time, path })
+    }
+
+    /// The most recent draft, if there is one.
+    pub fn latest(&self) -> Option<Draft> {
+        self.list().ok()?.pop()
+    }
+
+    /// Drafts, oldest first.
+    fn list(&self) -> io::Result<Vec<Draft>> {
+        let mut drafts = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let time = path.file_name()
+                .and_then(|it| it.to_str())
+                .and_then(|it| it.strip_prefix("draft-"))
+                .and_then(|it| it.strip_suffix(".gmi"))
+                .and_then(|it| it.parse().ok());
+            if let Some(time) = time {
+                drafts.push(Draft { time, path });
+            }
+        }
+        drafts.sort_by_key(|it| it.time);
+        Ok(drafts)
+    }
+}
+
+impl Draft {
+    pub fn read(&self) -> io::Result<String> {
+        fs::read_to_string(&self.path)
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|it| it.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,21 @@
+#![cfg(test)]
+
+use crate::editor::drafts::Drafts;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn keeps_latest_drafts() {
+    let dir = std::env::temp_dir().join(format!("egemi-drafts-{}", std::process::id()));
+    let drafts = Drafts::new(dir.clone());
+    assert_eq!(drafts.latest(), None);
+
+    for time in 1..=7 {
+        drafts.write(&format!("Draft {time}"), time).unwrap();
+    }
+    let latest = drafts.latest().unwrap();
+    assert_eq!(latest.time, 7);
+    assert_eq!(latest.read().unwrap(), "Draft 7");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 5);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
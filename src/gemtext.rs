@@ -1,13 +1,17 @@
 //! Utilities for parsing gemtext.
 
-use std::sync::LazyLock;
+use std::{ops::Range, sync::LazyLock};
 
 use regex::Regex;
 
+use crate::util::{collapse_blank_runs, escape_html, strip_bom};
+
+mod gemtext_test;
+
 /// A parsed chunk of Gemtext.
 /// Usually, each block is a single line.
 /// However, code fences and blockquotes are grouped together.
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Block {
     /// A heading, preceeded by #
     Heading{
@@ -24,9 +28,17 @@ pub enum Block {
         text: String
     },
 
+    /// A numbered list item. (ex: `1. text`)
+    /// Not part of Gemtext, so only parsed with [`Options::numbered_lists`].
+    OrderedListItem {
+        num: u32,
+        text: String,
+    },
+
     /// One or more quoted lines.
-    /// As documented, Gemtext format doesn't allow anything other than plaintext quoted lines, but the parser 
-    /// may in the future support quoting gemtext format.
+    /// As documented, Gemtext format doesn't allow anything other than plaintext quoted lines, so the strict
+    /// parser only produces [`Block::Text`]s here. Otherwise, the quoted lines are parsed as gemtext, so that
+    /// quoted links, headings, and quotes within quotes work.
     BlockQuote {
         lines: Vec<Block>
     },
@@ -46,34 +58,202 @@ pub enum Block {
     },
 }
 
+impl Block {
+    /// Whether this is a code fence that (probably) contains ASCII art, which needs a strictly monospace font.
+    /// Gemtext authors should label art with alt text, but many don't, so we also look for lines made up of
+    /// mostly symbols rather than words.
+    pub fn is_art(&self) -> bool {
+        let Block::CodeFence { meta, lines } = self else {
+            return false;
+        };
+        let meta = meta.to_lowercase();
+        if meta.split(|c: char| !c.is_alphanumeric()).any(|word| word == "art" || word == "ascii") {
+            return true;
+        }
+
+        let chars = lines.iter().flat_map(|it| it.chars()).filter(|it| !it.is_whitespace());
+        let (symbols, total) = chars.fold((0, 0), |(symbols, total), c| {
+            (symbols + usize::from(!c.is_alphanumeric()), total + 1)
+        });
+        // Code has plenty of punctuation, too, but is mostly words:
+        lines.len() >= 3 && symbols * 10 >= total * 7
+    }
+
+    /// The gemtext for this block, without a trailing newline.
+    pub fn to_gemtext(&self) -> String {
+        match self {
+            Block::Heading { level, text } => format!("{} {text}", "#".repeat(usize::from(*level))),
+            Block::Text(text) => text.clone(),
+            Block::ListItem { text } => format!("* {text}"),
+            Block::OrderedListItem { num, text } => format!("{num}. {text}"),
+            // A lone `>` quotes nothing:
+            Block::BlockQuote { lines } if lines.is_empty() => BLOCK_QUOTE.into(),
+            Block::BlockQuote { lines } => {
+                let text = serialize(lines);
+                text.strip_suffix('\n').unwrap_or(&text)
+                    .split('\n')
+                    .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            },
+            Block::CodeFence { meta, lines } => {
+                let mut text = format!("{CODE_GUARD}{meta}\n");
+                for line in lines {
+                    // Undo the parser's workaround for leading spaces:
+                    if line.starts_with('\u{a0}') {
+                        text.push_str(&line.replacen('\u{a0}', " ", 5));
+                    } else {
+                        text.push_str(line);
+                    }
+                    text.push('\n');
+                }
+                text.push_str(CODE_GUARD);
+                text
+            },
+            Block::Link { url, text } if text.is_empty() => format!("=> {url}"),
+            Block::Link { url, text } => format!("=> {url} {text}"),
+        }
+    }
+}
+
+/// Turns parsed `blocks` back into gemtext. Parsing the result gives the same blocks.
+pub fn serialize(blocks: &[Block]) -> String {
+    let mut text = String::new();
+    for block in blocks {
+        text.push_str(&block.to_gemtext());
+        text.push('\n');
+    }
+    text
+}
+
+/// Renders `blocks` as HTML, for exporting. Consecutive list items are grouped into lists.
+/// The result is a fragment, to go inside a `<body>`.
+pub fn to_html(blocks: &[Block]) -> String {
+    let mut html = String::new();
+    let mut index = 0;
+    while let Some(block) = blocks.get(index) {
+        index += 1;
+        match block {
+            Block::Heading { level, text } => {
+                let level = level.clamp(&1, &6);
+                html.push_str(&format!("<h{level}>{}</h{level}>\n", escape_html(text)));
+            },
+            Block::Text(text) if text.trim().is_empty() => {},
+            Block::Text(text) => html.push_str(&format!("<p>{}</p>\n", escape_html(text))),
+            Block::ListItem { text } => {
+                html.push_str("<ul>\n");
+                html.push_str(&format!("<li>{}</li>\n", escape_html(text)));
+                while let Some(Block::ListItem { text }) = blocks.get(index) {
+                    html.push_str(&format!("<li>{}</li>\n", escape_html(text)));
+                    index += 1;
+                }
+                html.push_str("</ul>\n");
+            },
+            Block::OrderedListItem { num, text } => {
+                html.push_str(&format!("<ol start=\"{num}\">\n"));
+                html.push_str(&format!("<li>{}</li>\n", escape_html(text)));
+                while let Some(Block::OrderedListItem { text, .. }) = blocks.get(index) {
+                    html.push_str(&format!("<li>{}</li>\n", escape_html(text)));
+                    index += 1;
+                }
+                html.push_str("</ol>\n");
+            },
+            Block::BlockQuote { lines } => {
+                html.push_str(&format!("<blockquote>\n{}</blockquote>\n", to_html(lines)));
+            },
+            Block::CodeFence { meta, lines } => {
+                let code = lines.iter().map(|it| escape_html(&it.replace('\u{a0}', " "))).collect::<Vec<_>>().join("\n");
+                if meta.trim().is_empty() {
+                    html.push_str(&format!("<pre>{code}</pre>\n"));
+                } else {
+                    // Alt text describes the block, like it does for an image:
+                    html.push_str(&format!("<pre aria-label=\"{}\">{code}</pre>\n", escape_html(meta.trim())));
+                }
+            },
+            Block::Link { url, text } => {
+                let visible = if text.is_empty() { url } else { text };
+                html.push_str(&format!("<p><a href=\"{}\">{}</a></p>\n", escape_html(url), escape_html(visible)));
+            },
+        }
+    }
+    html
+}
+
 /// Options for the parser. We may one day have these. 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Options {
     strict: bool,
+
+    /// See [`Self::close_unclosed_fences`].
+    close_unclosed_fences: bool,
+
+    /// See [`Self::collapse_blank_lines`].
+    collapse_blank_lines: bool,
+
+    /// See [`Self::numbered_lists`].
+    numbered_lists: bool,
 }
 
+/// The (0-based, end-exclusive) range of source lines that a [`Block`] came from.
+pub type Span = Range<usize>;
+
 const CODE_GUARD: &str = "```";
 const BLOCK_QUOTE: &str = ">";
 
 impl Options {
+    /// By the spec, everything after a code fence that's never closed is code.
+    /// Instead, guess where a stray fence should have ended: before the first link line after it.
+    pub fn close_unclosed_fences(mut self, close: bool) -> Self {
+        self.close_unclosed_fences = close;
+        self
+    }
+
+    /// Keep at most one blank line in a row. Some authors use runs of them for spacing, which adds up to big gaps.
+    pub fn collapse_blank_lines(mut self, collapse: bool) -> Self {
+        self.collapse_blank_lines = collapse;
+        self
+    }
+
+    /// Gemtext only has `*` lists, but many authors write `1.`-style ones, too.
+    /// Parse those as [`Block::OrderedListItem`]s, instead of plain text.
+    pub fn numbered_lists(mut self, numbered: bool) -> Self {
+        self.numbered_lists = numbered;
+        self
+    }
+
     pub fn parse(&self, value: &str) -> Result<Vec<Block>, String> {
+        self.parse_with_spans(value).map(|(blocks, _)| blocks)
+    }
+
+    /// Like [`Self::parse`], but also returns the source lines of each block, so that they can be mapped back to
+    /// the source. `spans[i]` is the span of `blocks[i]`.
+    pub fn parse_with_spans(&self, value: &str) -> Result<(Vec<Block>, Vec<Span>), String> {
         let mut code: Option<CodeFence> = None;
         let mut quote: Option<Vec<String>> = None;
         let mut blocks = Vec::new();
-        for line in value.lines() {
+        let mut spans = Vec::new();
+        let mut code_start = 0;
+        let mut quote_start = 0;
+        for (line_num, line) in strip_bom(value).lines().enumerate() {
+            if code.is_none() && !line.starts_with(BLOCK_QUOTE) && let Some(quote) = quote.take() {
+                blocks.push(self.quote(quote));
+                spans.push(quote_start..line_num);
+            }
             if let Some(meta) = line.strip_prefix(CODE_GUARD) {
                 let meta = meta.trim();
                 if let Some(existing) = code.take() {
                     if !meta.is_empty() && self.strict {
-                        return Err(format!("Found end code guard with meta: {meta}"))
+                        return Err(format!("Line {}: Found end code guard with meta: {meta}", line_num + 1))
                     }
                     blocks.push(Block::CodeFence{
                         meta: existing.meta,
                         lines: existing.lines
                     });
+                    spans.push(code_start..line_num + 1);
                     continue;
                 }
                 // else: starting new block:
+                code_start = line_num;
                 code = Some(CodeFence{meta: String::from(meta), lines: Vec::new()});
                 continue;
             }
@@ -87,19 +267,18 @@ impl Options {
                 continue;
             }
             if let Some(text) = line.strip_prefix(BLOCK_QUOTE) {
-                let text = text.trim().to_string();
+                let text = text.to_string();
                 if let Some(quote) = &mut quote {
                     quote.push(text)
                 } else {
+                    quote_start = line_num;
                     quote = Some(vec![text]);
                 }
                 continue
             }
-            if let Some(quote) = quote.take() {
-                blocks.push(Block::BlockQuote{
-                    lines: quote.into_iter().map(|it| Block::Text(it)).collect()
-                })
-            }
+
+            // Everything else is a single line:
+            spans.push(line_num..line_num + 1);
 
             if let Some(HeaderLine{level, text}) = HeaderLine::parse(line) {
                 blocks.push(Block::Heading { level, text });
@@ -116,24 +295,141 @@ impl Options {
                 continue;
             }
 
+            if self.numbered_lists && let Some(OrderedListItem{num, text}) = OrderedListItem::parse(line) {
+                blocks.push(Block::OrderedListItem { num, text });
+                continue;
+            }
+
             blocks.push(Block::Text(line.into()));
 
         } // lines
 
         // Don't forget unclosed blocks!
-        if let Some(CodeFence{meta, lines}) = code {
-            blocks.push(Block::CodeFence { meta, lines })
+        let line_count = value.lines().count();
+        if let Some(CodeFence{meta, mut lines}) = code {
+            let source: Vec<&str> = strip_bom(value).lines().collect();
+            let fence_end = self.close_unclosed_fences
+                .then(|| probable_fence_end(&source[code_start + 1..]))
+                .flatten();
+            match fence_end {
+                None => {
+                    blocks.push(Block::CodeFence { meta, lines });
+                    spans.push(code_start..line_count);
+                },
+                Some(end) => {
+                    lines.truncate(end);
+                    blocks.push(Block::CodeFence { meta, lines });
+                    let rest_start = code_start + 1 + end;
+                    spans.push(code_start..rest_start);
+                    let (rest, rest_spans) = self.parse_with_spans(&source[rest_start..].join("\n"))?;
+                    blocks.extend(rest);
+                    spans.extend(rest_spans.into_iter().map(|it| it.start + rest_start..it.end + rest_start));
+                },
+            }
         }
         if let Some(quote) = quote {
-            blocks.push(Block::BlockQuote { 
-                lines: quote.into_iter().map(|it| Block::Text(it)).collect()
-             })
+            blocks.push(self.quote(quote));
+            spans.push(quote_start..line_count);
         }
 
-        Ok(blocks)
+        if self.collapse_blank_lines {
+            let mut pairs: Vec<(Block, Span)> = blocks.into_iter().zip(spans).collect();
+            let is_blank = |(block, _): &(Block, Span)| matches!(block, Block::Text(text) if text.trim().is_empty());
+            collapse_blank_runs(&mut pairs, is_blank, |(_, kept), (_, next)| kept.end = next.end);
+            (blocks, spans) = pairs.into_iter().unzip();
+        }
+
+        Ok((blocks, spans))
+    }
+}
+
+impl Options {
+    /// A [`Block::BlockQuote`] of `lines`, which have had their `>` removed.
+    fn quote(&self, lines: Vec<String>) -> Block {
+        if self.strict {
+            return Block::BlockQuote {
+                lines: lines.into_iter().map(|it| Block::Text(it.trim().to_string())).collect()
+            };
+        }
+        // Quotes are usually written `> text`. Keep any further indentation, since it matters in code fences:
+        let mut text = lines.iter()
+            .map(|it| it.strip_prefix(' ').unwrap_or(it))
+            .collect::<Vec<_>>()
+            .join("\n");
+        // So that a blank last line isn't dropped, like the strict parser keeps it:
+        text.push('\n');
+        let lines = match self.parse(&text) {
+            Ok(lines) => lines,
+            Err(_) => lines.into_iter().map(|it| Block::Text(it.trim().to_string())).collect(),
+        };
+        Block::BlockQuote { lines }
     }
 }
 
+/// Parses gemtext as it arrives, a chunk at a time, so that long documents can be shown before they finish loading.
+/// All the blocks returned by [`Self::push`] and [`Self::finish`] are the same as [`Options::parse`] gives for the
+/// whole text.
+#[derive(Debug)]
+pub struct StreamParser {
+    options: Options,
+
+    /// Text we haven't parsed yet, since the blocks in it may not be complete.
+    pending: Vec<u8>,
+
+    /// How much of `pending` we've already checked for a place to split it.
+    scanned: usize,
+
+    /// Whether `pending[..scanned]` ends inside a code fence.
+    in_fence: bool,
+}
+
+impl StreamParser {
+    pub fn new(options: Options) -> Self {
+        Self { options, pending: Vec::new(), scanned: 0, in_fence: false }
+    }
+
+    /// Add the next `chunk` of the text, and return any blocks that it completed.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Block>, String> {
+        self.pending.extend_from_slice(chunk);
+
+        // We can split before any whole line that can't continue the block before it, or be merged with it.
+        // That's any line outside of a code fence, except for quotes and blank lines (which may be collapsed).
+        let mut split = 0;
+        while let Some(len) = self.pending[self.scanned..].iter().position(|it| *it == b'\n') {
+            let line = &self.pending[self.scanned..self.scanned + len];
+            let blank = line.iter().all(u8::is_ascii_whitespace);
+            if !self.in_fence && !line.starts_with(BLOCK_QUOTE.as_bytes()) && !blank {
+                split = self.scanned;
+            }
+            if line.starts_with(CODE_GUARD.as_bytes()) {
+                self.in_fence = !self.in_fence;
+            }
+            self.scanned += len + 1;
+        }
+        if split == 0 {
+            return Ok(Vec::new());
+        }
+
+        let rest = self.pending.split_off(split);
+        let done = std::mem::replace(&mut self.pending, rest);
+        self.scanned -= split;
+        self.options.parse(&String::from_utf8_lossy(&done))
+    }
+
+    /// Parse the rest of the text, after the last chunk.
+    pub fn finish(self) -> Result<Vec<Block>, String> {
+        self.options.parse(&String::from_utf8_lossy(&self.pending))
+    }
+}
+
+/// For [`Options::close_unclosed_fences`]: the index in `lines` (which follow an unclosed fence) where the fence
+/// probably should have ended. Gemtext links don't appear in code, so we end it at the blank line before the first one.
+fn probable_fence_end(lines: &[&str]) -> Option<usize> {
+    let link = lines.iter().position(|it| it.starts_with("=>"))?;
+    let blank = lines[..link].iter().rposition(|it| it.trim().is_empty());
+    Some(blank.unwrap_or(link))
+}
+
 struct CodeFence {
     meta: String,
     lines: Vec<String>,
@@ -205,4 +501,23 @@ impl ListItem {
         return Some(Self{text})
     }
 
-}
\ No newline at end of file
+}
+
+struct OrderedListItem {
+    num: u32,
+    text: String,
+}
+
+impl OrderedListItem {
+    fn parse(value: &str) -> Option<Self> {
+        static RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(
+            r#"^\s?(?P<num>\d{1,9})[.)]\s+(?P<text>.+?)\s*$"#
+        ).unwrap());
+
+        let caps = RE.captures(value)?;
+        Some(Self {
+            num: caps["num"].parse().ok()?,
+            text: caps["text"].into(),
+        })
+    }
+}
@@ -0,0 +1,264 @@
+#![cfg(test)]
+
+use crate::gemtext::{serialize, to_html, Block, Options, StreamParser};
+use indoc::indoc;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn spans() {
+    let text = indoc! {"
+        # Title
+        Some text.
+        ```rust
+        fn main() {}
+        ```
+        > Quoted
+        > lines
+        => gemini://example.com Link
+        ```
+        unclosed
+    "};
+
+    let (blocks, spans) = Options::default().parse_with_spans(text).unwrap();
+    assert_eq!(blocks.len(), spans.len());
+    assert_eq!(spans, vec![0..1, 1..2, 2..5, 5..7, 7..8, 8..10]);
+    assert!(matches!(blocks[2], Block::CodeFence{..}));
+    assert!(matches!(blocks[3], Block::BlockQuote{..}));
+}
+
+#[test]
+fn strict_errors_have_line_numbers() {
+    let text = "Text\n```\ncode\n``` oops\n";
+    let options = Options { strict: true, ..Options::default() };
+    let err = options.parse(text).unwrap_err();
+    assert_eq!(err, "Line 4: Found end code guard with meta: oops");
+}
+
+#[test]
+fn detects_art() {
+    let fence = |meta: &str, text: &str| Block::CodeFence {
+        meta: meta.into(),
+        lines: text.lines().map(String::from).collect(),
+    };
+
+    let art = indoc! {r"
+         /\_/\
+        ( o.o )
+         > ^ <
+    "};
+    assert!(fence("", art).is_art());
+    assert!(fence("ASCII-art cat", "cat").is_art());
+
+    let code = indoc! {"
+        fn main() {
+            println!(\"Hello, world!\");
+        }
+    "};
+    assert!(!fence("rust", code).is_art());
+    assert!(!fence("party", code).is_art());
+    assert!(!Block::Text("-=-=-=-".into()).is_art());
+}
+
+#[test]
+fn leading_bom_is_ignored() {
+    let text = "# Title\n```\ncode\n```\n";
+    let with_bom = format!("\u{FEFF}{text}");
+    let options = Options::default();
+    assert_eq!(options.parse_with_spans(&with_bom).unwrap(), options.parse_with_spans(text).unwrap());
+    assert!(matches!(options.parse(&with_bom).unwrap()[0], Block::Heading { level: 1, .. }));
+}
+
+#[test]
+fn closes_unclosed_fences() {
+    let text = indoc! {"
+        Intro
+        ```
+        code
+
+        more code
+
+        ## Links
+        => gemini://example.com Example
+    "};
+    let blocks = Options::default().parse(text).unwrap();
+    assert_eq!(blocks.len(), 2, "Spec: everything after the fence is code");
+
+    let (blocks, spans) = Options::default().close_unclosed_fences(true).parse_with_spans(text).unwrap();
+    assert_eq!(blocks, vec![
+        Block::Text("Intro".into()),
+        Block::CodeFence { meta: String::new(), lines: vec!["code".into(), String::new(), "more code".into()] },
+        Block::Text(String::new()),
+        Block::Heading { level: 2, text: "Links".into() },
+        Block::Link { url: "gemini://example.com".into(), text: "Example".into() },
+    ]);
+    assert_eq!(spans, vec![0..1, 1..5, 5..6, 6..7, 7..8]);
+}
+
+#[test]
+fn collapses_blank_lines() {
+    let text = "One\n\n\n\nTwo\n   \n\n* Three\n";
+    assert_eq!(Options::default().parse(text).unwrap().len(), 8);
+
+    let (blocks, spans) = Options::default().collapse_blank_lines(true).parse_with_spans(text).unwrap();
+    assert_eq!(blocks, vec![
+        Block::Text("One".into()),
+        Block::Text(String::new()),
+        Block::Text("Two".into()),
+        Block::Text("   ".into()),
+        Block::ListItem { text: "Three".into() },
+    ]);
+    assert_eq!(spans, vec![0..1, 1..4, 4..5, 5..7, 7..8]);
+}
+
+#[test]
+fn numbered_lists() {
+    let text = indoc! {"
+        1. One
+        2) Two
+        10.Ten?
+        * Bullet
+    "};
+    let blocks = Options::default().parse(text).unwrap();
+    assert_eq!(blocks[0], Block::Text("1. One".into()), "Spec: not a list item");
+
+    let blocks = Options::default().numbered_lists(true).parse(text).unwrap();
+    assert_eq!(blocks, vec![
+        Block::OrderedListItem { num: 1, text: "One".into() },
+        Block::OrderedListItem { num: 2, text: "Two".into() },
+        Block::Text("10.Ten?".into()),
+        Block::ListItem { text: "Bullet".into() },
+    ]);
+}
+
+#[test]
+fn quoted_gemtext() {
+    let text = indoc! {"
+        > ## Quoted heading
+        > => gemini://example.com Quoted link
+        >> Nested  
+    "};
+    let blocks = Options::default().parse(text).unwrap();
+    assert_eq!(blocks, vec![Block::BlockQuote { lines: vec![
+        Block::Heading { level: 2, text: "Quoted heading".into() },
+        Block::Link { url: "gemini://example.com".into(), text: "Quoted link".into() },
+        Block::BlockQuote { lines: vec![Block::Text("Nested  ".into())] },
+    ]}]);
+
+    let strict = Options { strict: true, ..Options::default() };
+    let blocks = strict.parse(text).unwrap();
+    assert_eq!(blocks, vec![Block::BlockQuote { lines: vec![
+        Block::Text("## Quoted heading".into()),
+        Block::Text("=> gemini://example.com Quoted link".into()),
+        Block::Text("> Nested".into()),
+    ]}]);
+}
+
+#[test]
+fn serialize_round_trips() {
+    let lines = [
+        "# Title", "### Sub", "Text", "", "  indented", "* Item", "1. First", "12) Twelfth",
+        "=> gemini://example.com", "=> /relative Some link", "> Quoted", "> => gemini://example.com Quoted link",
+        ">> Nested", ">", "```art", "  code", "```", "#nope",
+    ];
+    let options = [
+        Options::default(),
+        Options::default().numbered_lists(true),
+        Options { strict: true, ..Options::default() },
+    ];
+    // Every pair and triple of lines, including unclosed fences and quotes at the end:
+    let mut docs = Vec::new();
+    for a in lines {
+        for b in lines {
+            docs.push(format!("{a}\n{b}\n"));
+            for c in lines {
+                docs.push(format!("{a}\n{b}\n{c}"));
+            }
+        }
+    }
+    for options in &options {
+        for doc in &docs {
+            let Ok(blocks) = options.parse(doc) else {
+                continue;
+            };
+            let text = serialize(&blocks);
+            assert_eq!(options.parse(&text).as_ref(), Ok(&blocks), "{doc:?} serialized as {text:?}");
+        }
+    }
+}
+
+#[test]
+fn html() {
+    let text = indoc! {"
+        # Tom & Jerry
+        * One
+        * Two
+        2. Second
+        3. Third
+        > Quote
+        ```art
+         <*>
+        ```
+        => gemini://example.com/?a=1&b=2
+    "};
+    let blocks = Options::default().numbered_lists(true).parse(text).unwrap();
+    assert_eq!(to_html(&blocks), indoc! {r#"
+        <h1>Tom &amp; Jerry</h1>
+        <ul>
+        <li>One</li>
+        <li>Two</li>
+        </ul>
+        <ol start="2">
+        <li>Second</li>
+        <li>Third</li>
+        </ol>
+        <blockquote>
+        <p>Quote</p>
+        </blockquote>
+        <pre aria-label="art"> &lt;*&gt;</pre>
+        <p><a href="gemini://example.com/?a=1&amp;b=2">gemini://example.com/?a=1&amp;b=2</a></p>
+    "#});
+}
+
+#[test]
+fn stream_parser() {
+    let text = indoc! {"
+        \u{FEFF}# Title
+
+
+        Some text.
+        > A quote
+        > that continues
+        >
+        ```code
+        fn main() {
+
+            println!(\"> not a quote\");
+        }
+        ```
+        * List
+        1. Numbered
+        => gemini://example.com/ Link
+
+        ```unclosed
+        more code
+
+        => /link
+        Trailing line without a newline"};
+    let options = || Options::default()
+        .close_unclosed_fences(true)
+        .collapse_blank_lines(true)
+        .numbered_lists(true);
+    let expected = options().parse(text).unwrap();
+
+    for chunk_size in [1, 2, 3, 7, 16, 64, text.len()] {
+        let mut parser = StreamParser::new(options());
+        let mut blocks = Vec::new();
+        for chunk in text.as_bytes().chunks(chunk_size) {
+            blocks.extend(parser.push(chunk).unwrap());
+        }
+        // Everything up to the unclosed fence can be shown before the end:
+        assert!(blocks.len() >= 9, "chunk size {chunk_size}: only {} blocks before the end", blocks.len());
+        blocks.extend(parser.finish().unwrap());
+        assert_eq!(blocks, expected, "chunk size {chunk_size}");
+    }
+}
@@ -1,8 +1,12 @@
-use eframe::{egui::{self, vec2, Color32, FontId, Frame, Link, RichText, Sense, TextStyle, Ui, UiBuilder, Vec2}, epaint::MarginF32};
+use std::collections::HashMap;
 
-use crate::{browser::widgets::DocWidget, gemtext::Block};
+use eframe::{egui::{self, load::Bytes, vec2, Align, Button, CollapsingHeader, Color32, FontId, Frame, Id, Image, Link, Modal, Rect, RichText, Sense, TextStyle, Ui, UiBuilder, Vec2}, epaint::MarginF32};
+
+use crate::{browser::{fonts::strict_mono, widgets::{code, find::Find, hints::ShownLinks, limit::BlockLimit, link_menu::LinkContextMenu, preview::LinkPreviews, image_error, DocWidget, Heading, ImageData}}, gemtext::{self, Block}};
 use crate::browser::widgets::DocumentResponse as Response;
 
+mod gemtext_widget_test;
+
 #[derive(Default, Debug)]
 pub struct GemtextWidget {
     blocks: Vec<Block>,
@@ -12,8 +16,61 @@ pub struct GemtextWidget {
     justify: bool,
 
     link_clicked: Option<String>, // "url", but may not parse as such.
+    load_image: Option<String>,
+
+    /// Inline thumbnails for image links, keyed by link URL.
+    thumbnails: HashMap<String, Thumbnail>,
+
+    report_block_clicks: bool,
+    block_clicked: Option<usize>,
+
+    /// Most links on this page are images. See [`Self::set_gallery`].
+    gallery_available: bool,
+    /// Show runs of image links as a grid of thumbnails.
+    gallery: bool,
+    /// The gallery image (by link URL) being shown full-size.
+    enlarged: Option<String>,
+
+    /// Show runs of (non-image) links as a wrapped row, like a menu.
+    compact_links: bool,
+
+    /// See [`Self::collapse_captioned`].
+    collapse_captioned: bool,
+
+    /// Highlights matches for [`DocWidget::find`].
+    find: Find,
+
+    previews: LinkPreviews,
+    shown_links: ShownLinks,
+    context_menu: LinkContextMenu,
+
+    limit: BlockLimit,
+
+    /// The index of a heading's block to scroll to. See [`DocWidget::scroll_to_heading`].
+    scroll_to_block: Option<usize>,
 }
 
+/// State of the inline thumbnail for an image link.
+#[derive(Debug)]
+enum Thumbnail {
+    Loading,
+    Shown(ImageData),
+    Hidden(ImageData),
+    Failed(String),
+}
+
+/// Max height of inline thumbnails, in points.
+const THUMBNAIL_HEIGHT: f32 = 300.0;
+
+/// Max size of thumbnails in gallery mode, in points.
+const GALLERY_TILE: f32 = 160.0;
+
+/// Fewer image links than this isn't much of a gallery.
+const GALLERY_MIN_IMAGES: usize = 3;
+
+/// Fewer consecutive links than this are shown as usual, even with [`GemtextWidget::compact_links`].
+const MENU_MIN_LINKS: usize = 3;
+
 impl DocWidget for GemtextWidget {
     fn ui(&mut self, ui: &mut Ui) -> Response {
     // Assuming we're in a top-down layout, because that's all that really makes sense:
@@ -30,95 +87,527 @@ impl DocWidget for GemtextWidget {
 
     Response {
         link_clicked: self.link_clicked.take(),
+        load_image: self.load_image.take(),
+        block_clicked: self.block_clicked.take(),
+        link_hovered: self.previews.take_hovered(),
+        links: self.shown_links.take(),
+        link_action: self.context_menu.take(),
     }
 }
 
+    fn image_loaded(&mut self, link: &str, image: Result<ImageData, String>) {
+        let thumbnail = match image {
+            Ok(image) => Thumbnail::Shown(image),
+            Err(err) => Thumbnail::Failed(err),
+        };
+        self.thumbnails.insert(link.to_string(), thumbnail);
+    }
+
+    fn link_preview(&mut self, link: &str, title: Option<String>) {
+        self.previews.set_title(link, title);
+    }
+
+    fn gallery_available(&self) -> bool {
+        self.gallery_available
+    }
+
+    fn set_gallery(&mut self, gallery: bool) {
+        self.gallery = gallery && self.gallery_available;
+    }
+
+    fn set_block_limit(&mut self, step: usize) {
+        self.limit = BlockLimit::new(step);
+    }
+
+    fn append_gemtext(&mut self, blocks: Vec<Block>) {
+        self.append_blocks(blocks);
+    }
+
+    fn find(&mut self, query: &str, case_sensitive: bool) -> usize {
+        self.find = Find::new(query, case_sensitive);
+        if !query.is_empty() {
+            // So that we can scroll to every match:
+            self.limit.show_all();
+        }
+        self.count_matches(&self.blocks)
+    }
+
+    fn find_select(&mut self, index: usize) {
+        self.find.select(index);
+    }
+
+    fn outline(&self) -> Vec<Heading> {
+        self.blocks.iter()
+            .filter_map(|block| match block {
+                Block::Heading { level, text } => Some(Heading { level: *level, text: text.trim().to_string() }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn scroll_to_heading(&mut self, index: usize) {
+        let block = self.blocks.iter()
+            .enumerate()
+            .filter(|(_, block)| matches!(block, Block::Heading { .. }))
+            .nth(index)
+            .map(|(block, _)| block);
+        if let Some(block) = block && block >= self.limit.shown(self.blocks.len()) {
+            self.limit.show_all();
+        }
+        self.scroll_to_block = block;
+    }
+
+    /// The first H1. Or, if there isn't one, the first heading.
+    fn title(&self) -> Option<String> {
+        let heading = |top: bool| self.blocks.iter().find_map(|block| match block {
+            Block::Heading { level, text } if !top || *level == 1 => Some(text.trim().to_string()),
+            _ => None,
+        });
+        heading(true).or_else(|| heading(false))
+    }
+
+    fn to_html(&self) -> Option<String> {
+        Some(gemtext::to_html(&self.blocks))
+    }
+
+    #[cfg(feature = "tts")]
+    fn readable_text(&self) -> Vec<String> {
+        self.blocks.iter()
+            .filter_map(|block| match block {
+                Block::Heading { text, .. } | Block::Text(text) | Block::ListItem { text } | Block::OrderedListItem { text, .. } => Some(text.clone()),
+                Block::Link { text, .. } => Some(text.clone()),
+                Block::BlockQuote { lines } => Some(readable_quote(lines)),
+                Block::CodeFence { .. } => None,
+            })
+            .filter(|it| !it.trim().is_empty())
+            .collect()
+    }
 }
 
 impl GemtextWidget {
 
     fn render(&mut self, ui: &mut Ui) {
+        // Take the blocks so that we can mutate self while rendering them:
+        let blocks = std::mem::take(&mut self.blocks);
+        let clicked_pos = if self.report_block_clicks { clicked_pos(ui) } else { None };
+        self.find.begin();
         let mut line_num: u32 = 0;
-        for block in &self.blocks {
+        let mut gallery = Vec::new();
+        // Consecutive links, and the index of the first one:
+        let mut menu = Vec::new();
+        let mut menu_start = 0;
+        let shown = self.limit.shown(blocks.len());
+        for (index, block) in blocks[..shown].iter().enumerate() {
             line_num += 1;
-            match block {
-                Block::Heading { level, text } => {
-                    let is_title = line_num == 1 && *level == 1;
-                    let style = if is_title { Style::title() } else { Style::heading(*level) };
-                    let rt = RichText::new(text).text_style(style).strong();
-                    if is_title {
-                        ui.vertical_centered(|ui| {
-                            ui.label(rt);
-                        });
-                    } else {
-                        ui.label(rt);
-                    }
-                },
-                Block::Text(text) => {
-                    ui.label(text);
-                },
-                Block::ListItem { text } => {
-                    ui.horizontal_top(|ui| {
-                        ui.label(" • ");
-                        ui.vertical(|ui| {
-                            ui.label(text);
-                        })
-                    });
-                },
-                Block::BlockQuote { lines } => {
-                    block_quote(ui, lines);
-                },
-                Block::CodeFence { meta: _, lines } => {
-                    for line in lines {
-                        // ui.monospace(line);
-                        let rt = RichText::new(line).text_style(Style::mono());
-                        ui.label(rt);
-                    }
-                },
-                Block::Link { url, text } => {
-                    let visible = if text.is_empty() { url } else { text };
-                    let link = Link::new(visible);
-                    let response = ui.add(link);
-                    if response.clicked() {
-                        self.link_clicked = Some(url.clone());
+            if self.gallery && let Block::Link { url, text } = block && is_image_link(url) {
+                gallery.push((url, text));
+                continue;
+            }
+            self.gallery_grid(ui, &mut gallery);
+
+            if self.compact_links && let Block::Link { url, text } = block && !is_image_link(url) {
+                if menu.is_empty() {
+                    menu_start = index;
+                }
+                menu.push((url, text));
+                continue;
+            }
+            self.link_menu(ui, &mut menu, menu_start, clicked_pos);
+
+            let top = ui.cursor().top();
+            self.render_block(ui, block, line_num);
+            if let Some(pos) = clicked_pos && top <= pos.y && pos.y < ui.cursor().top() {
+                self.block_clicked = Some(index);
+            }
+            if self.scroll_to_block == Some(index) {
+                self.scroll_to_block = None;
+                let rect = Rect::from_x_y_ranges(ui.max_rect().x_range(), top..=ui.cursor().top());
+                ui.scroll_to_rect(rect, Some(Align::TOP));
+            }
+        }
+        self.gallery_grid(ui, &mut gallery);
+        self.link_menu(ui, &mut menu, menu_start, clicked_pos);
+        self.limit.show_more_ui(ui, blocks.len());
+        self.enlarged_image(ui);
+        self.blocks = blocks;
+    }
+
+    /// Lays out a run of links as a wrapped row, if there are enough of them to be a menu. Empties `links`.
+    /// `first_index` is the block index of the first link, for [`Self::report_block_clicks`].
+    fn link_menu(&mut self, ui: &mut Ui, links: &mut Vec<(&String, &String)>, first_index: usize, clicked_pos: Option<egui::Pos2>) {
+        if links.is_empty() {
+            return;
+        }
+        if links.len() < MENU_MIN_LINKS {
+            for (offset, (url, text)) in links.drain(..).enumerate() {
+                let top = ui.cursor().top();
+                self.link(ui, url, text);
+                if let Some(pos) = clicked_pos && top <= pos.y && pos.y < ui.cursor().top() {
+                    self.block_clicked = Some(first_index + offset);
+                }
+            }
+            return;
+        }
+
+        let response = ui.horizontal_wrapped(|ui| {
+            for (index, (url, text)) in links.drain(..).enumerate() {
+                if index > 0 {
+                    ui.label(RichText::new(" · ").weak());
+                }
+                self.link(ui, url, text);
+            }
+        }).response;
+        if let Some(pos) = clicked_pos && response.rect.contains(pos) {
+            self.block_clicked = Some(first_index);
+        }
+    }
+
+    /// Lays out a run of image links as a grid of thumbnails. Empties `links`.
+    fn gallery_grid(&mut self, ui: &mut Ui, links: &mut Vec<(&String, &String)>) {
+        if links.is_empty() {
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing = Vec2::splat(4.0);
+            for (url, text) in links.drain(..) {
+                self.gallery_tile(ui, url, text);
+            }
+        });
+    }
+
+    fn gallery_tile(&mut self, ui: &mut Ui, url: &str, text: &str) {
+        let size = Vec2::splat(GALLERY_TILE);
+        let hover = if text.is_empty() { url } else { text };
+        match self.thumbnails.get(url) {
+            Some(Thumbnail::Shown(image) | Thumbnail::Hidden(image)) => {
+                let image = Image::from_bytes(image.uri.clone(), Bytes::Shared(image.bytes.clone()))
+                    .max_size(size)
+                    .sense(Sense::click());
+                if let Some(err) = image_error(ui, &image) {
+                    self.thumbnails.insert(url.to_string(), Thumbnail::Failed(err));
+                    return;
+                }
+                let response = ui.add(image).on_hover_text(hover);
+                if response.clicked() {
+                    self.enlarged = Some(url.to_string());
+                }
+            },
+            Some(Thumbnail::Failed(err)) => {
+                let err = err.clone();
+                ui.allocate_ui(size, |ui| {
+                    if ui.link(hover).on_hover_text(err).clicked() {
+                        self.link_clicked = Some(url.to_string());
                     }
-                    response.on_hover_ui(|ui| {
-                        ui.monospace(url);
+                });
+            },
+            Some(Thumbnail::Loading) | None => {
+                // We can only request one image per frame, so others wait their turn:
+                if self.load_image.is_none() && !self.thumbnails.contains_key(url) {
+                    self.load_image = Some(url.to_string());
+                    self.thumbnails.insert(url.to_string(), Thumbnail::Loading);
+                }
+                ui.ctx().request_repaint();
+                ui.allocate_ui(size, |ui| {
+                    ui.spinner().on_hover_text(hover);
+                });
+            },
+        }
+    }
+
+    /// Shows the clicked gallery image, as large as will fit, until dismissed.
+    fn enlarged_image(&mut self, ui: &mut Ui) {
+        let Some(url) = &self.enlarged else {
+            return;
+        };
+        let Some(Thumbnail::Shown(image) | Thumbnail::Hidden(image)) = self.thumbnails.get(url) else {
+            self.enlarged = None;
+            return;
+        };
+        let max_size = ui.ctx().screen_rect().size() * 0.9;
+        let modal = Modal::new(Id::new("gallery enlarged")).show(ui.ctx(), |ui| {
+            let image = Image::from_bytes(image.uri.clone(), Bytes::Shared(image.bytes.clone()))
+                .fit_to_original_size(1.0)
+                .max_size(max_size)
+                .sense(Sense::click());
+            ui.add(image).clicked()
+        });
+        if modal.inner || modal.should_close() {
+            self.enlarged = None;
+        }
+    }
+
+    fn render_block(&mut self, ui: &mut Ui, block: &Block, line_num: u32) {
+        match block {
+            Block::Heading { level, text } => {
+                let is_title = line_num == 1 && *level == 1;
+                let style = if is_title { Style::title() } else { Style::heading(*level) };
+                let rt = RichText::new(text).text_style(style).strong();
+                if is_title {
+                    ui.vertical_centered(|ui| {
+                        self.find.label(ui, rt);
                     });
-                },
+                } else {
+                    self.find.label(ui, rt);
+                }
+            },
+            Block::Text(text) => {
+                self.find.label(ui, text);
+            },
+            Block::ListItem { text } => {
+                hanging_indent(ui, &mut self.find, " • ", text);
+            },
+            Block::OrderedListItem { num, text } => {
+                hanging_indent(ui, &mut self.find, &format!(" {num}. "), text);
+            },
+            Block::BlockQuote { lines } => {
+                self.block_quote(ui, lines);
+            },
+            Block::CodeFence { meta, lines } => {
+                let alt = meta.trim();
+                if alt.is_empty() {
+                    self.code_fence(ui, block, meta, lines);
+                    return;
+                }
+                // Alt text describes the block. (ex: what some ASCII art depicts.)
+                let caption = RichText::new(alt).italics();
+                if !self.collapse_captioned {
+                    ui.label(caption.small().weak());
+                    self.code_fence(ui, block, meta, lines);
+                    return;
+                }
+                let has_match = self.find.is_searching() && lines.iter().any(|it| self.find.count(it) > 0);
+                CollapsingHeader::new(caption)
+                    .id_salt(lines.as_ptr())
+                    .open(has_match.then_some(true))
+                    .show(ui, |ui| self.code_fence(ui, block, meta, lines));
+            },
+            Block::Link { url, text } => {
+                if is_image_link(url) {
+                    self.image_link(ui, url, text);
+                    return;
+                }
+                self.link(ui, url, text);
+            },
+        }
+    }
+
+    fn link(&mut self, ui: &mut Ui, url: &String, text: &str) {
+        let visible = if text.is_empty() { url } else { text };
+        let link = Link::new(self.find.highlight(ui, visible));
+        let response = ui.add(link);
+        self.find.shown(&response);
+        if response.clicked() {
+            self.link_clicked = Some(url.clone());
+        }
+        self.shown_links.add(&response, url);
+        self.context_menu.ui(&response, url);
+        self.previews.hover_ui(response, url);
+    }
+
+    /// How many matches for [`Self::find`] are shown in `blocks`.
+    fn count_matches(&self, blocks: &[Block]) -> usize {
+        blocks.iter()
+            .map(|block| match block {
+                Block::Heading { text, .. } | Block::Text(text) | Block::ListItem { text } | Block::OrderedListItem { text, .. } => self.find.count(text),
+                Block::BlockQuote { lines } => self.count_matches(lines),
+                Block::CodeFence { lines, .. } => lines.iter().map(|it| self.find.count(it)).sum(),
+                // Gallery tiles don't show their text:
+                Block::Link { url, .. } if self.gallery && is_image_link(url) => 0,
+                Block::Link { url, text } => self.find.count(if text.is_empty() { url } else { text }),
+            })
+            .sum()
+    }
+
+    /// Quoted blocks, indented, with a line down their left side.
+    fn block_quote(&mut self, ui: &mut Ui, lines: &[Block]) {
+        let builder = UiBuilder::new();
+        let row_height = ui.text_style_height(&TextStyle::Body);
+        let left_margin = MarginF32{ left: row_height / 2.0, ..Default::default() };
+        let response = ui.scope_builder(builder, |ui| {
+            let frame = Frame::new()
+                .outer_margin(left_margin);
+            frame.show(ui, |ui| {
+                for line in lines {
+                    // 0, since nothing in a quote is the document's title:
+                    self.render_block(ui, line, 0);
+                }
+            });
+
+        });
+        let rect = response.response.rect;
+        ui.painter().line_segment(
+            [rect.left_top(), rect.left_bottom()],
+            (1.0, ui.visuals().weak_text_color()),
+        );
+    }
+
+    fn code_fence(&mut self, ui: &mut Ui, block: &Block, meta: &str, lines: &[String]) {
+        let is_art = block.is_art();
+        let text = lines.join("\n");
+        // Wrapping would garble art:
+        let wrap = !is_art && code::toolbar(ui, meta, &text);
+        let style = if is_art { Style::art() } else { Style::mono() };
+        code::body(ui, &text, wrap, |ui| {
+            for line in lines {
+                let rt = RichText::new(line).text_style(style.clone());
+                self.find.label(ui, rt);
             }
+        });
+    }
+
+    /// Like a normal link, but with a button to toggle an inline thumbnail beneath it.
+    fn image_link(&mut self, ui: &mut Ui, url: &String, text: &str) {
+        let visible = if text.is_empty() { url.as_str() } else { text };
+        ui.horizontal_wrapped(|ui| {
+            let response = ui.add(Link::new(self.find.highlight(ui, visible)));
+            self.find.shown(&response);
+            if response.clicked() {
+                self.link_clicked = Some(url.clone());
+            }
+            self.shown_links.add(&response, url);
+            self.context_menu.ui(&response, url);
+            self.previews.hover_ui(response, url);
+
+            let thumbnail = self.thumbnails.get(url);
+            let loading = matches!(thumbnail, Some(Thumbnail::Loading));
+            let label = match thumbnail {
+                Some(Thumbnail::Shown(_)) => "hide",
+                Some(Thumbnail::Loading) => "loading…",
+                _ => "show",
+            };
+            ui.add_space(ui.spacing().icon_spacing);
+            let button = ui.add_enabled(!loading, Button::new(label).small());
+            if button.clicked() {
+                self.toggle_thumbnail(url);
+            }
+        });
+
+        match self.thumbnails.get(url) {
+            Some(Thumbnail::Shown(image)) => {
+                let image = Image::from_bytes(image.uri.clone(), Bytes::Shared(image.bytes.clone()))
+                    .fit_to_original_size(1.0)
+                    .max_height(THUMBNAIL_HEIGHT)
+                    .max_width(ui.available_width());
+                match image_error(ui, &image) {
+                    None => {
+                        ui.add(image);
+                    },
+                    Some(err) => {
+                        self.thumbnails.insert(url.to_string(), Thumbnail::Failed(err));
+                    },
+                }
+            },
+            Some(Thumbnail::Failed(err)) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Couldn't load image: {err}"));
+            },
+            Some(Thumbnail::Loading) | Some(Thumbnail::Hidden(_)) | None => {},
         }
     }
 
+    fn toggle_thumbnail(&mut self, url: &str) {
+        let next = match self.thumbnails.remove(url) {
+            Some(Thumbnail::Shown(image)) => Thumbnail::Hidden(image),
+            Some(Thumbnail::Hidden(image)) => Thumbnail::Shown(image),
+            Some(Thumbnail::Loading) => Thumbnail::Loading,
+            None | Some(Thumbnail::Failed(_)) => {
+                self.load_image = Some(url.to_string());
+                Thumbnail::Loading
+            },
+        };
+        self.thumbnails.insert(url.to_string(), next);
+    }
+
+    /// Show runs of links as a compact, wrapped row. Many capsules end with a dense link footer.
+    pub fn compact_links(mut self, compact: bool) -> Self {
+        self.compact_links = compact;
+        self
+    }
+
+    /// Show preformatted blocks that have alt text collapsed, under their alt text.
+    pub fn collapse_captioned(mut self, collapse: bool) -> Self {
+        self.collapse_captioned = collapse;
+        self
+    }
+
+    /// Report which block the user clicks on, in [`Response::block_clicked`].
+    pub fn report_block_clicks(mut self, report: bool) -> Self {
+        self.report_block_clicks = report;
+        self
+    }
+
     pub fn set_blocks(&mut self, blocks: Vec<Block>) {
+        self.gallery_available = is_gallery(&blocks);
+        self.gallery &= self.gallery_available;
+        self.enlarged = None;
         self.blocks = blocks;
+        self.thumbnails.clear();
+    }
+
+    /// Add `blocks` to the end of the document. Unlike [`Self::set_blocks`], keeps the state of the ones already shown.
+    pub fn append_blocks(&mut self, blocks: Vec<Block>) {
+        self.blocks.extend(blocks);
+        self.gallery_available = is_gallery(&self.blocks);
     }
 }
 
 
-fn block_quote(ui: &mut Ui, lines: &Vec<Block>) {
-    let builder = UiBuilder::new();
-    let row_height = ui.text_style_height(&TextStyle::Body);
-    let left_margin = MarginF32{ left: row_height / 2.0, ..Default::default() };
-    let response = ui.scope_builder(builder, |ui| {
-        let frame = Frame::new()
-            .outer_margin(left_margin);
-        frame.show(ui, |ui| {
-            for line in lines {
-                if let Block::Text(line) = line {
-                    ui.label(line);
-                }
-            }
-        });
+/// Where the primary pointer button was clicked this frame, if it was within `ui`.
+fn clicked_pos(ui: &Ui) -> Option<egui::Pos2> {
+    let pos = ui.input(|i| {
+        if i.pointer.primary_clicked() { i.pointer.interact_pos() } else { None }
+    })?;
+    ui.clip_rect().contains(pos).then_some(pos)
+}
 
+/// Whether a page is mostly image links, so would look good as a gallery.
+fn is_gallery(blocks: &[Block]) -> bool {
+    let links = blocks.iter().filter_map(|it| match it {
+        Block::Link { url, .. } => Some(url),
+        _ => None,
+    });
+    let (images, total) = links.fold((0, 0), |(images, total), url| {
+        (images + usize::from(is_image_link(url)), total + 1)
     });
-    let rect = response.response.rect;
-    ui.painter().line_segment(
-        [rect.left_top(), rect.left_bottom()],
-        (1.0, ui.visuals().weak_text_color()),
-    );
+    images >= GALLERY_MIN_IMAGES && images * 2 > total
 }
 
+/// Guess from the URL's extension whether a link points to an image.
+fn is_image_link(url: &str) -> bool {
+    // Ignore any query/fragment:
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    mime_guess::from_path(path)
+        .first()
+        .map(|it| it.type_() == mime::IMAGE)
+        .unwrap_or(false)
+}
+
+/// Shows `marker` (ex: a bullet) with `text` beside it.
+/// Wrapped lines are indented to line up with the start of the text, not the marker.
+fn hanging_indent(ui: &mut Ui, find: &mut Find, marker: &str, text: &str) {
+    let font = TextStyle::Body.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let marker = ui.painter().layout_no_wrap(marker.into(), font, color);
+    let indent = MarginF32 { left: marker.size().x, ..Default::default() };
+    let response = Frame::new()
+        .inner_margin(indent)
+        .show(ui, |ui| {
+            find.label(ui, text);
+        });
+    ui.painter().galley(response.response.rect.left_top(), marker, color);
+}
+
+/// The text of a quote, as one string.
+#[cfg(feature = "tts")]
+fn readable_quote(lines: &[Block]) -> String {
+    lines.iter()
+        .filter_map(|it| match it {
+            Block::Heading { text, .. } | Block::Text(text) | Block::ListItem { text } | Block::OrderedListItem { text, .. } | Block::Link { text, .. } => Some(text.clone()),
+            Block::BlockQuote { lines } => Some(readable_quote(lines)),
+            Block::CodeFence { .. } => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
 pub struct Style;
 
@@ -139,20 +628,34 @@ impl Style {
     pub fn h3() -> TextStyle { Self::named("H3") }
     pub fn mono() -> TextStyle { Self::named("gemtext-mono") }
 
+    /// Like mono, but without any fallback fonts that might break alignment. See [`Block::is_art`].
+    pub fn art() -> TextStyle { Self::named("gemtext-art") }
+
     /// The first H1 in a Gemtext is the page Title:
     pub fn title() -> TextStyle { Self::named("Title") }
 
     fn named(name: &str) -> TextStyle { TextStyle::Name(name.into()) }
 
     pub fn config(ctx: &egui::Context) {
-        use egui::FontFamily::{Proportional, Monospace};
         let body_size = ctx.style().text_styles.get(&TextStyle::Body).expect("TextStyle::Body should always be present").size;
+        Self::set_body_size(ctx, body_size);
+    }
+
+    /// Scale egui's built-in text styles so that body text is `body_size` points, and size ours (ex: headings) to match.
+    pub fn set_body_size(ctx: &egui::Context, body_size: f32) {
+        use egui::FontFamily::{Proportional, Monospace};
+        let defaults = egui::Style::default().text_styles;
+        let scale = body_size / defaults.get(&TextStyle::Body).map_or(body_size, |it| it.size);
         ctx.all_styles_mut(|style| {
-            style.text_styles.entry(Self::title()).or_insert(FontId::new(body_size * 2.0, Proportional));
-            style.text_styles.entry(Self::h1()).or_insert(FontId::new(body_size * 2.0, Proportional));
-            style.text_styles.entry(Self::h2()).or_insert(FontId::new(body_size * 1.5, Proportional));
-            style.text_styles.entry(Self::h3()).or_insert(FontId::new(body_size * 1.2, Proportional));            
-            style.text_styles.entry(Self::mono()).or_insert(FontId::new(body_size * 0.8, Monospace));            
+            for (text_style, font) in &defaults {
+                style.text_styles.insert(text_style.clone(), FontId::new(font.size * scale, font.family.clone()));
+            }
+            style.text_styles.insert(Self::title(), FontId::new(body_size * 2.0, Proportional));
+            style.text_styles.insert(Self::h1(), FontId::new(body_size * 2.0, Proportional));
+            style.text_styles.insert(Self::h2(), FontId::new(body_size * 1.5, Proportional));
+            style.text_styles.insert(Self::h3(), FontId::new(body_size * 1.2, Proportional));
+            style.text_styles.insert(Self::mono(), FontId::new(body_size * 0.8, Monospace));
+            style.text_styles.insert(Self::art(), FontId::new(body_size * 0.8, strict_mono()));
         });
     }
 }
\ No newline at end of file
@@ -0,0 +1,58 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+
+use crate::{browser::widgets::{DocWidget as _, ImageData}, gemtext_widget::{is_image_link, GemtextWidget, Thumbnail}};
+
+const IMAGE: &str = "gemini://example.com/cat.png";
+
+fn image() -> ImageData {
+    ImageData { uri: "bytes://cat.png".into(), bytes: Arc::from(&b"png"[..]) }
+}
+
+#[test]
+fn image_links() {
+    assert!(is_image_link("cat.png"));
+    assert!(is_image_link("gemini://example.com/cat.JPG?size=large#top"));
+    assert!(!is_image_link("gemini://example.com/page.gmi"));
+    assert!(!is_image_link("gemini://example.com/"));
+}
+
+#[test]
+fn thumbnail_toggle() {
+    let mut doc = GemtextWidget::default();
+
+    // The first toggle asks for the image:
+    doc.toggle_thumbnail(IMAGE);
+    assert_eq!(doc.load_image.take().as_deref(), Some(IMAGE));
+    assert!(matches!(doc.thumbnails.get(IMAGE), Some(Thumbnail::Loading)));
+
+    // Toggling while it loads doesn't ask again:
+    doc.toggle_thumbnail(IMAGE);
+    assert_eq!(doc.load_image, None);
+
+    doc.image_loaded(IMAGE, Ok(image()));
+    assert!(matches!(doc.thumbnails.get(IMAGE), Some(Thumbnail::Shown(_))));
+
+    // Then it's hidden and shown again, without reloading:
+    doc.toggle_thumbnail(IMAGE);
+    assert!(matches!(doc.thumbnails.get(IMAGE), Some(Thumbnail::Hidden(_))));
+    doc.toggle_thumbnail(IMAGE);
+    assert!(matches!(doc.thumbnails.get(IMAGE), Some(Thumbnail::Shown(_))));
+    assert_eq!(doc.load_image, None);
+
+    // A new page forgets them:
+    doc.set_blocks(Vec::new());
+    assert!(doc.thumbnails.is_empty());
+}
+
+#[test]
+fn failed_thumbnail_retries() {
+    let mut doc = GemtextWidget::default();
+    doc.image_loaded(IMAGE, Err("not found".into()));
+    assert!(matches!(doc.thumbnails.get(IMAGE), Some(Thumbnail::Failed(_))));
+
+    doc.toggle_thumbnail(IMAGE);
+    assert_eq!(doc.load_image.as_deref(), Some(IMAGE));
+    assert!(matches!(doc.thumbnails.get(IMAGE), Some(Thumbnail::Loading)));
+}
@@ -6,8 +6,9 @@ mod widgets;
 mod svg;
 mod util;
 
-use std::error::Error;
+use std::{error::Error, path::PathBuf};
 
+use browser::config::ConfigFile;
 use clap::{builder::{styling::{Color, RgbColor, Style, Styles}}, Parser as _};
 
 
@@ -17,7 +18,11 @@ use clap::{builder::{styling::{Color, RgbColor, Style, Styles}}, Parser as _};
 /// egemi, an egui gemini/web browser.
 struct Cli {
     /// A URL to browse.
-    url: Option<String>
+    url: Option<String>,
+
+    /// Read and save settings in this TOML file. [default: egemi.toml in your config directory]
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -42,14 +47,23 @@ type DynResult<T = ()> = Result<T,Box<dyn Error>>;
 
 fn main() -> DynResult {
     let cli = Cli::parse();
-    let url = cli.url.unwrap_or("about:egemi".into());
-    
-    if url == "editor:" {
+    if cli.url.as_deref() == Some("editor:") {
         editor::main()?;
         return Ok(());
     }
 
-    browser::main(url)?;
+    // A config the user asked for has to work. Otherwise, a broken one shouldn't stop egemi from starting:
+    let config = match cli.config {
+        Some(path) => Some(ConfigFile::load(path)?),
+        None => ConfigFile::default_path().and_then(|path| match ConfigFile::load(path) {
+            Ok(config) => Some(config),
+            Err(err) => {
+                eprintln!("Warning: {err}. Using default settings.");
+                None
+            },
+        }),
+    };
+    browser::main(cli.url, config)?;
     Ok(())
 }
 
@@ -1,6 +1,10 @@
 use std::fmt::Display;
 use std::fmt::Write as _;
 
+use url::Url;
+
+mod util_test;
+
 /// Like Rust's built-in Join, but works on things that impl Display.
 pub trait DisplayJoin {
     /// Join an iterable of Displays.
@@ -26,4 +30,132 @@ impl <T, D> DisplayJoin for T
 }
 
 
+/// Remove a leading UTF-8 byte order mark, which would otherwise hide the syntax of a document's first line.
+pub fn strip_bom(text: &str) -> &str {
+    text.strip_prefix('\u{FEFF}').unwrap_or(text)
+}
+
+/// Replace each run of whitespace (including newlines) with a single space, and trim the ends.
+/// For putting untrusted text on a single gemtext line.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Escape `text` for use in HTML text or (quoted) attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Push `item` onto `list`, then drop the oldest (first) items so that at most `max` remain.
+pub fn push_bounded<T>(list: &mut Vec<T>, item: T, max: usize) {
+    list.push(item);
+    trim_oldest(list, max);
+}
+
+/// Drop the oldest (first) items in `list` so that at most `max` remain.
+pub fn trim_oldest<T>(list: &mut Vec<T>, max: usize) {
+    if list.len() > max {
+        list.drain(..list.len() - max);
+    }
+}
+
+/// Collapses each run of consecutive blank items into one, like HTML collapses whitespace.
+/// The first item of each run is kept, and `merge` folds each of the others into it. (ex: to extend its span.)
+pub fn collapse_blank_runs<T>(items: &mut Vec<T>, is_blank: impl Fn(&T) -> bool, mut merge: impl FnMut(&mut T, &T)) {
+    items.dedup_by(|next, kept| {
+        let collapse = is_blank(kept) && is_blank(next);
+        if collapse {
+            merge(kept, next);
+        }
+        collapse
+    });
+}
+
+/// The default port for schemes that [`Url`] doesn't know about.
+const DEFAULT_PORTS: &[(&str, u16)] = &[("gemini", 1965)];
+
+/// The port used when a `scheme` URL doesn't give one, for schemes that [`Url`] doesn't know about.
+pub fn default_port(scheme: &str) -> Option<u16> {
+    DEFAULT_PORTS.iter().find(|(it, _)| *it == scheme).map(|(_, port)| *port)
+}
+
+/// A canonical form of `url`, for comparing URLs and keying things by them.
+/// URLs that refer to the same resource, but were written differently, normalize to the same string:
+///
+/// * Hosts are lowercased, and default ports are removed.
+/// * `.` and `..` path segments are resolved, and an empty path becomes `/`.
+/// * Fragments are dropped, since they're only a position within the resource.
+///
+/// Other trailing slashes are kept, since servers may treat `/dir` and `/dir/` differently.
+/// Strings that don't parse as URLs are only trimmed.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    parsed.set_fragment(None);
+    if parsed.cannot_be_a_base() {
+        // ex: about: and data: URLs, which have no host or hierarchical path.
+        return parsed.into();
+    }
 
+    if let Some(host) = parsed.host_str() && host.chars().any(|c| c.is_ascii_uppercase()) {
+        let host = host.to_ascii_lowercase();
+        let _ = parsed.set_host(Some(&host));
+    }
+    if parsed.port().is_some() && parsed.port() == default_port(parsed.scheme()) {
+        let _ = parsed.set_port(None);
+    }
+
+    let path = remove_dot_segments(parsed.path());
+    if path.is_empty() && parsed.has_host() {
+        parsed.set_path("/");
+    } else if path != parsed.path() {
+        parsed.set_path(&path);
+    }
+    parsed.into()
+}
+
+/// Resolves `.` and `..` segments, as in [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986#section-5.2.4).
+/// [`Url`] already does this for http(s) and file URLs, but not for others, like gemini.
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut parts = path.split('/').peekable();
+    // An absolute path starts with an empty segment:
+    if parts.peek() == Some(&"") {
+        parts.next();
+        segments.push("");
+    }
+    let mut trailing_slash = false;
+    for part in parts {
+        trailing_slash = false;
+        match part {
+            "." => trailing_slash = true,
+            ".." => {
+                if segments.len() > 1 || segments.first().is_some_and(|it| !it.is_empty()) {
+                    segments.pop();
+                }
+                trailing_slash = true;
+            },
+            part => segments.push(part),
+        }
+    }
+    if trailing_slash {
+        segments.push("");
+    }
+    if segments == [""] {
+        return "/".to_string();
+    }
+    segments.join("/")
+}
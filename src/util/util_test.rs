@@ -0,0 +1,64 @@
+#![cfg(test)]
+
+use crate::util::{collapse_blank_runs, escape_html, normalize_url};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn normalizes_urls() {
+    let same = |a: &str, b: &str| assert_eq!(normalize_url(a), normalize_url(b), "{a} vs. {b}");
+
+    // Hosts:
+    same("gemini://Example.COM/page", "gemini://example.com/page");
+    same("https://Example.COM/page", "https://example.com/page");
+
+    // Default ports:
+    same("gemini://example.com:1965/", "gemini://example.com/");
+    same("https://example.com:443/", "https://example.com/");
+    same("http://example.com:80/", "http://example.com/");
+
+    // Dot segments:
+    same("gemini://example.com/a/./b/../c", "gemini://example.com/a/c");
+    same("gemini://example.com/a/b/..", "gemini://example.com/a/");
+    same("gemini://example.com/../../a", "gemini://example.com/a");
+    same("https://example.com/a/../b", "https://example.com/b");
+
+    // Empty paths and fragments:
+    same("gemini://example.com", "gemini://example.com/");
+    same("gemini://example.com/page#section", "gemini://example.com/page");
+
+    // Whitespace around the URL:
+    same("  gemini://example.com/\n", "gemini://example.com/");
+}
+
+#[test]
+fn keeps_differences() {
+    let differ = |a: &str, b: &str| assert!(normalize_url(a) != normalize_url(b), "{a} vs. {b}");
+
+    differ("gemini://example.com/dir", "gemini://example.com/dir/");
+    differ("gemini://example.com/Page", "gemini://example.com/page");
+    differ("gemini://example.com:1966/", "gemini://example.com/");
+    differ("gemini://example.com/?q=1", "gemini://example.com/");
+    differ("gemini://example.com/", "https://example.com/");
+}
+
+#[test]
+fn leaves_other_urls_alone() {
+    assert_eq!(normalize_url("about:egemi"), "about:egemi");
+    assert_eq!(normalize_url("data:text/plain,Hi"), "data:text/plain,Hi");
+    assert_eq!(normalize_url(" not a url "), "not a url");
+    assert_eq!(normalize_url("gemini://example.com/a%20b?q=x#y"), "gemini://example.com/a%20b?q=x");
+}
+
+#[test]
+fn collapses_blank_runs() {
+    let mut items = vec!["a", "", "", "b", "", "c", "", ""];
+    let mut merged = 0;
+    collapse_blank_runs(&mut items, |it| it.is_empty(), |_, _| merged += 1);
+    assert_eq!(items, vec!["a", "", "b", "", "c", ""]);
+    assert_eq!(merged, 2);
+}
+
+#[test]
+fn escapes_html() {
+    assert_eq!(escape_html(r#"<a href="x">Tom & Jerry's</a>"#), "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;");
+}
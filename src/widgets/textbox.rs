@@ -9,6 +9,8 @@ pub struct TextBox<'a> {
     value: &'a mut String,
     last_out: Option<TextEditOutput>,
     enabled: bool,
+    password: bool,
+    char_limit: usize,
 }
 
 
@@ -18,6 +20,8 @@ impl <'a> TextBox<'a> {
             value: buffer,
             last_out: None,
             enabled: true,
+            password: false,
+            char_limit: usize::MAX,
         }
     }
 
@@ -26,6 +30,22 @@ impl <'a> TextBox<'a> {
         self
     }
 
+    /// Mask the input, like a password field.
+    pub fn password(mut self, password: bool) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Don't allow typing more than `limit` characters.
+    pub fn char_limit(mut self, limit: usize) -> Self {
+        self.char_limit = limit;
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        self.value
+    }
+
     pub fn select_all(&self, ui: &egui::Ui) {
         // This feels like such a hack!
 
@@ -50,6 +70,10 @@ impl <'a> TextBox<'a> {
         out.response.lost_focus()
     }
     
+    pub fn middle_clicked(&self) -> bool {
+        self.last_out.as_ref().is_some_and(|it| it.response.middle_clicked())
+    }
+
     /// Note egui causes lost_focus when enter is pressed, so make sure to check this
     /// condition before lost_focus().
     pub(crate) fn enter_pressed(&self, ui: &egui::Ui) -> bool {
@@ -60,6 +84,26 @@ impl <'a> TextBox<'a> {
         })
     }
     
+    /// Up or Down, if pressed while the box has focus.
+    pub(crate) fn arrow_pressed(&self, ui: &egui::Ui) -> Option<Key> {
+        let out = self.last_out.as_ref()?;
+        if !out.response.has_focus() { return None }
+
+        ui.input(|i| [Key::ArrowUp, Key::ArrowDown].into_iter().find(|key| i.key_pressed(*key)))
+    }
+
+    /// Replaces the text, and puts the cursor at its end.
+    pub(crate) fn set_text(&mut self, ui: &egui::Ui, text: &str) {
+        *self.value = text.to_string();
+        let Some(output) = &self.last_out else { return };
+
+        let end = CCursor::new(self.value.chars().count());
+        let mut state = output.state.clone();
+        state.cursor.set_char_range(Some(CCursorRange::one(end)));
+        state.store(ui.ctx(), output.response.id);
+        ui.ctx().request_repaint();
+    }
+
     pub(crate) fn request_focus(&self) {
         let Some(out) = &self.last_out else { return };
         out.response.request_focus();
@@ -72,7 +116,10 @@ impl <'a> Widget for &mut TextBox<'a> {
     /// So we save it for later use.
     fn ui(self, ui: &mut Ui) -> egui::Response {
         let response = ui.add_enabled_ui(self.enabled, |ui| {
-            let out = TE::singleline(self.value).show(ui);
+            let out = TE::singleline(self.value)
+                .password(self.password)
+                .char_limit(self.char_limit)
+                .show(ui);
             let response = out.response.clone();
             self.last_out = Some(out);
             response